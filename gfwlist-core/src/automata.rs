@@ -0,0 +1,253 @@
+use alloc::collections::BTreeMap;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::ops::Range;
+
+use aho_corasick::{AhoCorasick, BuildError};
+
+use crate::glob::Glob;
+use crate::{PatternError, separator};
+
+/// The outcome of matching a haystack against [`Automata`], with the rule of the matching pattern.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Verdict {
+    /// A negative pattern matched
+    Allowed(usize),
+    /// A positive pattern matched, and no negative pattern did
+    Blocked(usize),
+    /// No pattern matched
+    NoMatch,
+}
+
+/// The patterns of one side, positive or negative.
+#[derive(Debug, Clone, Default)]
+struct Side<A> {
+    automaton: A,
+    /// The rule of each pattern
+    rules: Vec<usize>,
+    /// Wildcard patterns by their index in the automaton
+    globs: BTreeMap<usize, Glob>,
+}
+
+impl Side<Vec<Vec<u8>>> {
+    fn push(&mut self, needles: Vec<Vec<u8>>, glob: Option<Glob>, rule: usize) {
+        self.globs.extend(glob.map(|glob| (self.automaton.len(), glob)));
+        self.rules.extend(needles.iter().map(|_| rule));
+        self.automaton.extend(needles);
+    }
+
+    fn build(self) -> Result<Side<AhoCorasick>, BuildError> {
+        Ok(Side {
+            automaton: AhoCorasick::new(&self.automaton)?,
+            rules: self.rules,
+            globs: self.globs,
+        })
+    }
+}
+
+impl Side<AhoCorasick> {
+    /// Iterates over the rules and ranges of the patterns matching a haystack, verifying wildcard patterns.
+    fn matches<'a>(&'a self, haystack: &'a [u8]) -> impl Iterator<Item = (usize, Range<usize>)> + 'a {
+        (self.automaton.find_overlapping_iter(haystack)).filter_map(move |match_| {
+            let pattern = match_.pattern().as_usize();
+            let range = match self.globs.get(&pattern) {
+                Some(glob) => glob.find(haystack)?,
+                None => match_.range(),
+            };
+            Some((self.rules[pattern], range))
+        })
+    }
+
+    /// Returns the rule of the first pattern matching a haystack.
+    fn find(&self, haystack: &[u8]) -> Option<usize> {
+        match self.globs.is_empty() {
+            true => (self.automaton.find(haystack)).map(|match_| self.rules[match_.pattern().as_usize()]),
+            false => self.matches(haystack).next().map(|(rule, _)| rule),
+        }
+    }
+}
+
+/// Collects encoded patterns for [`Automata`].
+#[derive(Debug, Clone, Default)]
+pub struct AutomataBuilder {
+    positive: Side<Vec<Vec<u8>>>,
+    negative: Side<Vec<Vec<u8>>>,
+}
+
+impl AutomataBuilder {
+    /// Creates a builder without patterns.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Checks that an encoded pattern can be added, without adding it.
+    pub fn check_pattern(needle: &[u8]) -> Result<(), PatternError> {
+        match Glob::compile(needle)? {
+            Some(_) => Ok(()),
+            None => separator::expand(needle.to_vec()).map(|_| ()),
+        }
+    }
+
+    /// Adds an encoded pattern of a rule, with wildcards and separator placeholders.
+    ///
+    /// A pattern with separator placeholders in its path is expanded into several patterns for the same rule.
+    pub fn add_pattern(&mut self, needle: Vec<u8>, negative: bool, rule: usize) -> Result<&mut Self, PatternError> {
+        let (needles, glob) = match Glob::compile(&needle)? {
+            Some((literal, glob)) => (vec![literal], Some(glob)),
+            None => (separator::expand(needle)?, None),
+        };
+        self.side(negative).push(needles, glob, rule);
+        Ok(self)
+    }
+
+    /// Adds a literal that is searched for as it is, such as a fragment of a regular expression.
+    pub fn add_literal(&mut self, literal: Vec<u8>, negative: bool, rule: usize) -> &mut Self {
+        self.side(negative).push(vec![literal], None, rule);
+        self
+    }
+
+    fn side(&mut self, negative: bool) -> &mut Side<Vec<Vec<u8>>> {
+        match negative {
+            true => &mut self.negative,
+            false => &mut self.positive,
+        }
+    }
+
+    /// Iterates over the patterns added so far, with whether they are negative, including wildcards.
+    pub fn patterns(&self) -> impl Iterator<Item = (bool, &[u8])> {
+        [(false, &self.positive), (true, &self.negative)]
+            .into_iter()
+            .flat_map(|(negative, side)| {
+                (side.automaton.iter().enumerate()).map(move |(index, needle)| {
+                    let needle = side.globs.get(&index).map_or(&needle[..], Glob::pattern);
+                    (negative, needle)
+                })
+            })
+    }
+
+    /// Returns the total length of the literals searched for, which bounds the size of the automata.
+    pub fn literal_len(&self) -> usize {
+        (self.positive.automaton.iter())
+            .chain(&self.negative.automaton)
+            .map(Vec::len)
+            .sum()
+    }
+
+    /// Compiles the patterns into [`Automata`].
+    pub fn build(self) -> Result<Automata, BuildError> {
+        Ok(Automata {
+            positive: self.positive.build()?,
+            negative: self.negative.build()?,
+        })
+    }
+}
+
+/// Aho-Corasick automata over the encoded patterns of positive and negative rules.
+///
+/// Negative patterns take precedence over positive ones.
+#[derive(Debug, Clone)]
+pub struct Automata {
+    positive: Side<AhoCorasick>,
+    negative: Side<AhoCorasick>,
+}
+
+impl Automata {
+    /// Matches an encoded haystack, checking the negative patterns first.
+    pub fn find(&self, haystack: &[u8]) -> Verdict {
+        if let Some(rule) = self.negative.find(haystack) {
+            return Verdict::Allowed(rule);
+        }
+        if let Some(rule) = self.positive.find(haystack) {
+            return Verdict::Blocked(rule);
+        }
+        Verdict::NoMatch
+    }
+
+    /// Iterates over the rules and matched ranges of the negative or positive patterns matching an encoded haystack.
+    ///
+    /// A rule is yielded once for each of its patterns that matches.
+    pub fn matches<'a>(
+        &'a self,
+        negative: bool,
+        haystack: &'a [u8],
+    ) -> impl Iterator<Item = (usize, Range<usize>)> + 'a {
+        match negative {
+            true => self.negative.matches(haystack),
+            false => self.positive.matches(haystack),
+        }
+    }
+
+    /// Returns the number of patterns, after the expansion of separator placeholders.
+    pub fn len(&self) -> usize {
+        self.positive.rules.len() + self.negative.rules.len()
+    }
+
+    /// Returns whether there are no patterns.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns the rule of each negative or positive pattern, in the order the patterns were added.
+    pub fn pattern_rules(&self, negative: bool) -> &[usize] {
+        match negative {
+            true => &self.negative.rules,
+            false => &self.positive.rules,
+        }
+    }
+
+    /// Takes over the patterns of one side from other automata, renumbering their rules, instead of the patterns added
+    /// for that side.
+    ///
+    /// This spares rebuilding an automaton whose patterns did not change when the rules around them moved.
+    pub fn reuse_side(&mut self, negative: bool, from: &Automata, map_rule: impl Fn(usize) -> usize) {
+        let mut side = match negative {
+            true => from.negative.clone(),
+            false => from.positive.clone(),
+        };
+        side.rules.iter_mut().for_each(|rule| *rule = map_rule(*rule));
+        match negative {
+            true => self.negative = side,
+            false => self.positive = side,
+        }
+    }
+
+    /// Returns an estimate of the heap memory used by the automata, in bytes.
+    pub fn memory_usage(&self) -> usize {
+        [&self.positive, &self.negative]
+            .iter()
+            .map(|side| {
+                side.automaton.memory_usage()
+                    + side.rules.len() * size_of::<usize>()
+                    + (side.globs.values())
+                        .map(|glob| size_of::<(usize, Glob)>() + glob.memory_usage())
+                        .sum::<usize>()
+            })
+            .sum()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn negative_precedence() {
+        let mut builder = AutomataBuilder::new();
+        builder.add_pattern(b".a.com\x03/".to_vec(), false, 0).unwrap();
+        builder.add_pattern(b".b.a.com\x03/x^".to_vec(), true, 1).unwrap();
+        builder.add_pattern(b".a.com\x03/*/ads/".to_vec(), false, 2).unwrap();
+        builder.add_literal(b"video".to_vec(), false, 3);
+        assert_eq!(builder.patterns().filter(|(negative, _)| *negative).count(), 19);
+        assert!(AutomataBuilder::check_pattern(b"^a^b^").is_err());
+        let automata = builder.build().unwrap();
+        assert_eq!(automata.find(b"\x02.b.a.com\x03/x?y/"), Verdict::Blocked(0));
+        assert_eq!(automata.find(b"\x02.b.a.com\x03/x:y/"), Verdict::Allowed(1));
+        assert_eq!(automata.find(b"\x02.video.com\x03/"), Verdict::Blocked(3));
+        let rules: Vec<_> = automata.matches(false, b"\x02.a.com\x03/p/ads/").collect();
+        assert_eq!(rules, [(0, 1..9), (2, 1..15)]);
+        let mut patched = AutomataBuilder::new().build().unwrap();
+        patched.reuse_side(false, &automata, |rule| rule + 10);
+        assert_eq!(patched.pattern_rules(false)[..2], [10, 12]);
+        assert_eq!(patched.find(b"\x02.b.a.com\x03/x:y/"), Verdict::Blocked(10));
+    }
+}
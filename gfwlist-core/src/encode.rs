@@ -0,0 +1,184 @@
+//! The byte encoding of URLs and patterns.
+//!
+//! A URL is encoded as its scheme, host and path, each preceded by a marker byte. Hosts get a leading `.` and paths a
+//! trailing `/`, so that `.example.com` matches the subdomains of `example.com` and `/path/` matches whole path
+//! segments. The query follows the terminated path as is. Patterns are encoded the same way, except that they may
+//! leave out components and that their paths are only terminated if they do not end with a separator placeholder or a
+//! wildcard, or if a query follows, which is matched as a prefix.
+//!
+//! | URL or pattern | Encoding |
+//! |---|---|
+//! | `http://www.example.com/a` | `\x01http\x02.www.example.com\x03/a/` |
+//! | `example.com` | `.example.com\x03/` |
+//! | `example.com/a^` | `.example.com\x03/a^` |
+//! | `http://example.com/search?q=1` | `\x01http\x02.example.com\x03/search/?q=1` |
+//! | `example.com/search?q=` | `.example.com\x03/search/?q=` |
+
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use crate::WILDCARD;
+use crate::separator::PLACEHOLDER;
+
+/// Marker byte for the beginning of a URL scheme
+pub const BEGIN_OF_SCHEME: u8 = 0x01;
+/// Marker byte for the beginning of a host
+pub const BEGIN_OF_HOST: u8 = 0x02;
+/// Marker byte for the beginning of a path
+pub const BEGIN_OF_PATH: u8 = 0x03;
+/// Delimiter byte for host components
+pub const HOST_DELIMITER: u8 = b'.';
+/// Delimiter byte for path components
+pub const PATH_DELIMITER: u8 = b'/';
+/// Delimiter byte between the path and the query
+pub const QUERY_DELIMITER: u8 = b'?';
+
+/// Appends a host with a leading delimiter.
+pub fn append_host(acc: &mut Vec<u8>, host: &[u8]) {
+    if !host.starts_with(&[HOST_DELIMITER]) {
+        acc.push(HOST_DELIMITER);
+    }
+    acc.extend(host);
+}
+
+/// Appends a path with a trailing delimiter.
+pub fn append_path(acc: &mut Vec<u8>, path: &[u8]) {
+    acc.extend(path);
+    if !path.ends_with(&[PATH_DELIMITER]) {
+        acc.push(PATH_DELIMITER);
+    }
+}
+
+/// Appends a path that may be followed by a query, like `/search?q=1`, where the path is terminated as in
+/// [`append_path`] and the query follows as is.
+pub fn append_path_query(acc: &mut Vec<u8>, input: &[u8]) {
+    match input.iter().position(|&b| b == QUERY_DELIMITER) {
+        Some(pos) => {
+            append_path(acc, &input[..pos]);
+            acc.extend(&input[pos..]);
+        }
+        None => append_path(acc, input),
+    }
+}
+
+/// Appends the path of a pattern, which is not terminated if it ends with a separator placeholder or a wildcard.
+///
+/// A query following the path is matched as a prefix of the query of URLs, see [`append_path_query`].
+pub fn append_pattern_path(acc: &mut Vec<u8>, path: &[u8]) {
+    match path.last() {
+        _ if path.contains(&QUERY_DELIMITER) => append_path_query(acc, path),
+        Some(&(PLACEHOLDER | WILDCARD)) => acc.extend(path),
+        _ => append_path(acc, path),
+    }
+}
+
+/// Appends a pattern like `example.com/path` as a host followed by a path.
+///
+/// The host must end at the first `/` or `?`, see [`normalize_host_path`](crate::separator::normalize_host_path).
+pub fn append_host_path(acc: &mut Vec<u8>, input: &[u8]) {
+    let pos = (input.iter())
+        .position(|&b| b == PATH_DELIMITER || b == QUERY_DELIMITER)
+        .unwrap_or(input.len());
+    append_host(acc, &input[..pos]);
+    acc.push(BEGIN_OF_PATH);
+    append_pattern_path(acc, &input[pos..]);
+}
+
+/// Appends the haystack of a URL given by its parts, which are expected in lowercase except for the path and its
+/// optional query.
+pub fn append_parts(acc: &mut Vec<u8>, scheme: &str, host: &str, path: &str) {
+    acc.push(BEGIN_OF_SCHEME);
+    acc.extend(scheme.as_bytes());
+    acc.push(BEGIN_OF_HOST);
+    append_host(acc, host.as_bytes());
+    acc.push(BEGIN_OF_PATH);
+    append_path_query(acc, path.as_bytes());
+}
+
+/// Appends the haystack of a bare host name, which matches rules without a scheme or path.
+pub fn append_bare_host(acc: &mut Vec<u8>, host: &str) {
+    acc.push(BEGIN_OF_HOST);
+    append_host(acc, host.as_bytes());
+    acc.push(BEGIN_OF_PATH);
+    append_path(acc, b"/");
+}
+
+/// Appends a parsed URL under the given scheme name, which is usually its canonical scheme.
+///
+/// With `pattern` set, the URL is encoded as a pattern: its path is left out if it is `/`, the URL did not end with a
+/// slash, as told by `trailing_slash`, and it has no query, and it is only terminated as described in
+/// [`append_pattern_path`]. The query of the URL, if any, follows the path.
+#[cfg(feature = "url")]
+pub fn append_url(
+    acc: &mut Vec<u8>,
+    url: &url::Url,
+    scheme: &str,
+    pattern: bool,
+    trailing_slash: bool,
+) -> Result<(), url::ParseError> {
+    let host = url.host_str().ok_or(url::ParseError::EmptyHost)?;
+    acc.push(BEGIN_OF_SCHEME);
+    acc.extend(scheme.as_bytes());
+    acc.push(BEGIN_OF_HOST);
+    append_host(acc, host.as_bytes());
+    let path = url.path();
+    let query = url.query();
+    if !pattern || path != "/" || trailing_slash || query.is_some() {
+        acc.push(BEGIN_OF_PATH);
+        match pattern && query.is_none() {
+            false => append_path(acc, path.as_bytes()),
+            true => append_pattern_path(acc, path.as_bytes()),
+        }
+    }
+    if let Some(query) = query {
+        acc.push(QUERY_DELIMITER);
+        acc.extend(query.as_bytes());
+    }
+    Ok(())
+}
+
+/// Renders part of an encoded haystack or pattern for display, replacing the marker bytes.
+pub fn display(input: &[u8]) -> String {
+    let mut output = String::new();
+    let mut after_host_marker = false;
+    for chunk in input.split_inclusive(|&b| b < 0x04) {
+        let (mut text, marker) = match chunk.split_last() {
+            Some((&last, text)) if last < 0x04 => (text, Some(last)),
+            _ => (chunk, None),
+        };
+        // hosts are encoded with a leading delimiter
+        if after_host_marker && text.first() == Some(&HOST_DELIMITER) {
+            text = &text[1..];
+        }
+        output.push_str(&String::from_utf8_lossy(text));
+        after_host_marker = marker == Some(BEGIN_OF_HOST);
+        if after_host_marker {
+            output.push_str("://");
+        }
+    }
+    output
+}
+
+#[cfg(test)]
+mod test {
+    use alloc::vec;
+
+    use super::*;
+
+    #[test]
+    fn parts_and_patterns() {
+        let mut haystack = vec![];
+        append_parts(&mut haystack, "http", "www.example.com", "/a");
+        assert_eq!(haystack, b"\x01http\x02.www.example.com\x03/a/");
+        let mut needle = vec![];
+        append_host_path(&mut needle, b"example.com/a^");
+        assert_eq!(needle, b".example.com\x03/a^");
+        assert_eq!(display(&haystack), "http://www.example.com/a/");
+        let mut haystack = vec![];
+        append_parts(&mut haystack, "http", "example.com", "/search?q=a/b");
+        assert_eq!(haystack, b"\x01http\x02.example.com\x03/search/?q=a/b");
+        let mut needle = vec![];
+        append_host_path(&mut needle, b"example.com?q=");
+        assert_eq!(needle, b".example.com\x03/?q=");
+    }
+}
@@ -0,0 +1,91 @@
+//! `*` wildcards inside patterns.
+//!
+//! A wildcard matches any sequence of characters, including none. Patterns with wildcards stay on the automata: the
+//! longest literal segment of the encoded pattern is searched for like any other pattern, and each match is verified
+//! against the whole pattern. Separator placeholders in such patterns match any separator instead of being expanded
+//! into several patterns.
+
+use alloc::vec::Vec;
+use core::ops::Range;
+
+use crate::separator::{PLACEHOLDER, SEPARATORS};
+use crate::{PatternError, WILDCARD};
+
+/// An encoded pattern with wildcards, verified after its literal segment matched.
+#[derive(Debug, Clone)]
+pub(crate) struct Glob {
+    /// The encoded pattern, including wildcards and placeholders
+    pattern: Vec<u8>,
+}
+
+impl Glob {
+    /// Compiles an encoded pattern, returning its longest literal segment and the glob to verify matches of it with,
+    /// or `None` if the pattern has no wildcards.
+    pub(crate) fn compile(needle: &[u8]) -> Result<Option<(Vec<u8>, Glob)>, PatternError> {
+        if !needle.contains(&WILDCARD) {
+            return Ok(None);
+        }
+        let literal = (needle.split(|&byte| byte == WILDCARD || byte == PLACEHOLDER))
+            .max_by_key(|segment| segment.len())
+            .filter(|segment| !segment.is_empty())
+            .ok_or(PatternError::NoLiteral)?;
+        let glob = Glob {
+            pattern: needle.to_vec(),
+        };
+        Ok(Some((literal.to_vec(), glob)))
+    }
+
+    /// Returns the encoded pattern, including wildcards.
+    pub(crate) fn pattern(&self) -> &[u8] {
+        &self.pattern
+    }
+
+    /// Returns the range of the leftmost match in an encoded haystack, ending as early as possible.
+    pub(crate) fn find(&self, haystack: &[u8]) -> Option<Range<usize>> {
+        let mut segments = self.pattern.split(|&byte| byte == WILDCARD);
+        let first = segments.next().unwrap_or_default();
+        (0..=haystack.len()).find_map(|start| {
+            if !segment_matches(first, haystack.get(start..)?) {
+                return None;
+            }
+            // the leftmost match of each further segment leaves the most room for the ones after it
+            let mut end = start + first.len();
+            for segment in segments.clone() {
+                end += (0..=haystack.len() - end).find(|&skip| segment_matches(segment, &haystack[end + skip..]))?;
+                end += segment.len();
+            }
+            Some(start..end)
+        })
+    }
+
+    /// Returns an estimate of the heap memory used by the glob, in bytes.
+    pub(crate) fn memory_usage(&self) -> usize {
+        self.pattern.len()
+    }
+}
+
+/// Returns whether a segment without wildcards matches at the start of the haystack.
+fn segment_matches(segment: &[u8], haystack: &[u8]) -> bool {
+    segment.len() <= haystack.len()
+        && (segment.iter().zip(haystack)).all(|(&expected, byte)| match expected {
+            PLACEHOLDER => SEPARATORS.contains(byte),
+            _ => expected == *byte,
+        })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn longest_segment() {
+        let (literal, glob) = Glob::compile(b"\x01http\x02.*.example.com\x03/ads^*").unwrap().unwrap();
+        assert_eq!(literal, b".example.com\x03/ads");
+        let haystack = b"\x01http\x02.cdn.example.com\x03/ads/banner.png/";
+        assert_eq!(glob.find(haystack), Some(0..28));
+        assert!(glob.find(b"\x01http\x02.example.com\x03/ads/").is_none());
+        assert!(glob.find(b"\x01http\x02.cdn.example.com\x03/adserver/").is_none());
+        assert!(Glob::compile(b"abc").unwrap().is_none());
+        assert_eq!(Glob::compile(b"*^*").unwrap_err(), PatternError::NoLiteral);
+    }
+}
@@ -0,0 +1,72 @@
+//! The encoding and matching core of [gfwlist](https://docs.rs/gfwlist).
+//!
+//! This crate builds with `no_std` and `alloc`, for embedded routers and other targets without the standard library.
+//! It turns URLs and rule patterns into encoded haystacks and needles, see [`encode`], and matches them with
+//! Aho-Corasick automata, see [`Automata`]. Parsing lists and regular expression rules are left to the `gfwlist`
+//! crate.
+//!
+//! # Features
+//!
+//! - `std` (default): enables the standard library support of `aho-corasick`.
+//! - `url`: enables [`encode::append_url`] for parsed [`url::Url`]s, which requires `std`.
+//!
+//! # Examples
+//!
+//! ```
+//! use gfwlist_core::{AutomataBuilder, Verdict, encode};
+//!
+//! let mut builder = AutomataBuilder::new();
+//! let mut needle = vec![];
+//! encode::append_host_path(&mut needle, b"example.com");
+//! builder.add_pattern(needle, false, 0).unwrap();
+//! let mut needle = vec![];
+//! encode::append_host_path(&mut needle, b"www.example.com");
+//! builder.add_pattern(needle, true, 1).unwrap();
+//! let automata = builder.build().unwrap();
+//!
+//! let mut haystack = vec![];
+//! encode::append_parts(&mut haystack, "http", "a.example.com", "/");
+//! assert_eq!(automata.find(&haystack), Verdict::Blocked(0));
+//! haystack.clear();
+//! encode::append_parts(&mut haystack, "http", "www.example.com", "/");
+//! assert_eq!(automata.find(&haystack), Verdict::Allowed(1));
+//! ```
+
+#![no_std]
+
+extern crate alloc;
+#[cfg(feature = "std")]
+extern crate std;
+
+use core::fmt;
+
+mod automata;
+pub mod encode;
+mod glob;
+pub mod separator;
+
+pub use automata::{Automata, AutomataBuilder, Verdict};
+
+/// The wildcard `*`, which matches any sequence of characters inside a pattern
+pub const WILDCARD: u8 = b'*';
+
+/// Errors that can occur when adding an encoded pattern to an [`AutomataBuilder`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum PatternError {
+    /// The pattern has more separator placeholders than can be expanded
+    TooManyPlaceholders,
+    /// The pattern has wildcards but no literal text to search for
+    NoLiteral,
+}
+
+impl fmt::Display for PatternError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PatternError::TooManyPlaceholders => write!(f, "too many separator placeholders in pattern"),
+            PatternError::NoLiteral => write!(f, "pattern has no literal text"),
+        }
+    }
+}
+
+impl core::error::Error for PatternError {}
@@ -0,0 +1,81 @@
+//! The `^` separator placeholder of Adblock Plus.
+//!
+//! `^` matches a single separator character, which is anything but a letter, a digit or one of `_-.%`, or the end of
+//! the address. In encoded haystacks a host is always followed by the path, which starts with `/`, and the path always
+//! ends with `/`. So a `^` that ends the host stands for `/`, and a `^` in the path is expanded into one pattern for
+//! each separator that can occur in an encoded path, where `/` also covers the end of the address.
+
+use alloc::borrow::Cow;
+use alloc::vec::Vec;
+use alloc::{format, vec};
+
+use crate::PatternError;
+
+/// The separator placeholder
+pub const PLACEHOLDER: u8 = b'^';
+
+/// The separators that can occur in paths after URL normalization
+pub const SEPARATORS: &[u8] = b"/!$&'()*+,:;=@[]^|~";
+
+/// The maximum number of placeholders in the path of a pattern, each of which multiplies the number of patterns
+const MAX_PLACEHOLDERS: usize = 2;
+
+/// Replaces a `^` that ends the host of a domain or host pattern like `example.com^path` with `/`.
+pub fn normalize_host_path(input: &str) -> Cow<'_, str> {
+    match input.find(['/', '^', '?']) {
+        Some(pos) if input.as_bytes()[pos] == PLACEHOLDER => {
+            Cow::Owned(format!("{}/{}", &input[..pos], &input[pos + 1..]))
+        }
+        _ => Cow::Borrowed(input),
+    }
+}
+
+/// Replaces a `^` that ends the host of a URL pattern like `http://example.com^path` with `/`.
+pub fn normalize_url(input: &str) -> Cow<'_, str> {
+    let Some(start) = input.find("://").map(|pos| pos + 3) else {
+        return Cow::Borrowed(input);
+    };
+    match normalize_host_path(&input[start..]) {
+        Cow::Owned(rest) => Cow::Owned(format!("{}{rest}", &input[..start])),
+        Cow::Borrowed(_) => Cow::Borrowed(input),
+    }
+}
+
+/// Expands the placeholders in the path of an encoded pattern into one pattern per combination of separators.
+pub(crate) fn expand(needle: Vec<u8>) -> Result<Vec<Vec<u8>>, PatternError> {
+    let placeholders = needle.iter().filter(|&&byte| byte == PLACEHOLDER).count();
+    if placeholders > MAX_PLACEHOLDERS {
+        return Err(PatternError::TooManyPlaceholders);
+    }
+    let mut needles = vec![needle];
+    for _ in 0..placeholders {
+        needles = (needles.into_iter())
+            .flat_map(|needle| {
+                let pos = needle.iter().position(|&byte| byte == PLACEHOLDER).unwrap_or_default();
+                SEPARATORS.iter().map(move |&separator| {
+                    let mut needle = needle.clone();
+                    needle[pos] = separator;
+                    needle
+                })
+            })
+            .collect();
+    }
+    Ok(needles)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn host_end_and_path() {
+        assert_eq!(normalize_host_path("example.com^"), "example.com/");
+        assert_eq!(normalize_host_path("example.com/a^b"), "example.com/a^b");
+        assert_eq!(normalize_url("http://example.com^ad^"), "http://example.com/ad^");
+        assert_eq!(normalize_url("http://example.com/^"), "http://example.com/^");
+        let needles = expand(b"/a^b^".to_vec()).unwrap();
+        assert_eq!(needles.len(), SEPARATORS.len() * SEPARATORS.len());
+        assert!(needles.contains(&b"/a/b/".to_vec()) && needles.contains(&b"/a;b=".to_vec()));
+        assert!(expand(b"^a^b^".to_vec()).is_err());
+    }
+}
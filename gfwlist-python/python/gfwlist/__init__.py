@@ -0,0 +1,26 @@
+"""A GFW list parser and matcher."""
+
+from ._gfwlist import (
+    GfwList,
+    GfwListBuildError,
+    GfwListSyntaxError,
+    GfwListUrlError,
+    debug_build,
+    decode_any,
+    lint,
+)
+
+if debug_build:
+    # checks that the bindings agree with the Rust core
+    from ._golden import check_sample
+
+    check_sample()
+
+__all__ = [
+    "GfwList",
+    "GfwListBuildError",
+    "GfwListSyntaxError",
+    "GfwListUrlError",
+    "decode_any",
+    "lint",
+]
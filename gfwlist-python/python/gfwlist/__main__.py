@@ -0,0 +1,145 @@
+"""Command line interface, run as `python -m gfwlist` or `gfwlist`."""
+
+import argparse
+import sys
+
+from ._gfwlist import GfwList, GfwListBuildError, GfwListSyntaxError, GfwListUrlError, decode_any, lint
+
+SOURCE_FORMATS = [
+    "auto",
+    "autoproxy",
+    "domains",
+    "clash",
+    "hosts",
+    "dnsmasq",
+    "domain-list",
+    "surge",
+    "adguard-home",
+    "quantumult-x",
+]
+EXPORT_FORMATS = [
+    "acl",
+    "adblock",
+    "direct-list",
+    "direct-clash",
+    "direct-sing-box",
+    "mosdns",
+    "smartdns",
+    "sing-box",
+    "switchy-omega",
+    "xray",
+]
+
+
+def read_text(path):
+    if path == "-":
+        return sys.stdin.read()
+    with open(path, encoding="utf-8") as file:
+        return file.read()
+
+
+def read_bytes(path):
+    if path == "-":
+        return sys.stdin.buffer.read()
+    with open(path, "rb") as file:
+        return file.read()
+
+
+def load(path, source_format="autoproxy"):
+    if source_format == "auto":
+        return GfwList.from_any(read_bytes(path))
+    if source_format == "autoproxy" and path != "-":
+        return GfwList.from_file(path)
+    return GfwList.from_source(read_text(path), source_format)
+
+
+def convert(args):
+    output = load(args.input, args.source).export(
+        args.to,
+        proxy=args.proxy,
+        direct=args.direct,
+        only_tags=args.only_tags,
+        exclude_tags=args.exclude_tags,
+        only_suffixes=args.only_suffixes,
+        exclude_suffixes=args.exclude_suffixes,
+    )
+    if args.output == "-":
+        sys.stdout.write(output)
+    else:
+        with open(args.output, "w", encoding="utf-8") as file:
+            file.write(output)
+    return 0
+
+
+def test(args):
+    gfw = load(args.list, args.source)
+    for url in args.urls:
+        try:
+            blocked_by = gfw.test(url)
+            allowed_by = gfw.allowed_by(url)
+        except GfwListUrlError as error:
+            print(f"{url}\terror: {error}")
+            continue
+        if blocked_by is not None:
+            print(f"{url}\tblocked by {blocked_by}")
+        elif allowed_by is not None:
+            print(f"{url}\tallowed by {allowed_by}")
+        else:
+            print(f"{url}\tno match")
+            for rule, reason in gfw.near_misses(url):
+                print(f"\tnear miss {rule}: {reason}")
+    return 0
+
+
+def lint_command(args):
+    lints = lint(decode_any(read_bytes(args.list)))
+    for record in lints:
+        print(f"{args.list}:{record['line']}: {record['message']}")
+    return 1 if lints else 0
+
+
+def parser():
+    parser = argparse.ArgumentParser(prog="gfwlist", description="Convert, test and lint GFW lists.")
+    commands = parser.add_subparsers(dest="command", required=True)
+
+    command = commands.add_parser("convert", help="convert a list into the format of another tool")
+    command.add_argument("input", help="the list to convert, or - for stdin")
+    command.add_argument("--from", dest="source", choices=SOURCE_FORMATS, default="autoproxy")
+    command.add_argument("--to", required=True, choices=EXPORT_FORMATS)
+    command.add_argument("--proxy", default="proxy", help="the proxy outbound, profile or server group")
+    command.add_argument("--direct", default="direct", help="the direct outbound or profile")
+    command.add_argument("--only-tag", dest="only_tags", action="append", help="only export rules with this tag")
+    command.add_argument("--exclude-tag", dest="exclude_tags", action="append", help="leave out rules with this tag")
+    command.add_argument(
+        "--only-suffix", dest="only_suffixes", action="append", help="only export rules for this domain suffix"
+    )
+    command.add_argument(
+        "--exclude-suffix", dest="exclude_suffixes", action="append", help="leave out rules for this domain suffix"
+    )
+    command.add_argument("-o", "--output", default="-", help="the output file, or - for stdout")
+    command.set_defaults(run=convert)
+
+    command = commands.add_parser("test", help="print the rules deciding each URL")
+    command.add_argument("list", help="the list to test against, or - for stdin")
+    command.add_argument("--from", dest="source", choices=SOURCE_FORMATS, default="autoproxy")
+    command.add_argument("urls", nargs="+", metavar="url")
+    command.set_defaults(run=test)
+
+    command = commands.add_parser("lint", help="report mistakes in a list")
+    command.add_argument("list", help="the list to check, or - for stdin")
+    command.set_defaults(run=lint_command)
+
+    return parser
+
+
+def main(argv=None):
+    args = parser().parse_args(argv)
+    try:
+        return args.run(args)
+    except (OSError, GfwListSyntaxError, GfwListBuildError, ValueError) as error:
+        print(f"gfwlist: {error}", file=sys.stderr)
+        return 2
+
+
+if __name__ == "__main__":
+    sys.exit(main())
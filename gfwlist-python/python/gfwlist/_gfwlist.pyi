@@ -0,0 +1,283 @@
+from typing import Literal, TypedDict
+
+class GfwListSyntaxError(ValueError):
+    pass
+
+class GfwListBuildError(RuntimeError):
+    pass
+
+class GfwListUrlError(ValueError):
+    pass
+
+class HitCount(TypedDict):
+    rule: str
+    kind: Literal["positive", "negative", "regex"]
+    count: int
+    last_hit: float | None
+
+class SectionStats(TypedDict):
+    section: str | None
+    rules: int
+    hits: int
+    enabled: bool
+
+debug_build: bool
+"""Whether the extension was compiled without optimizations, which makes importing the package check golden vectors"""
+
+class Lint(TypedDict):
+    line: int
+    text: str
+    message: str
+
+def lint(rules_text: str) -> list[Lint]:
+    """
+    Check a GFW list for invalid, duplicated, redundant and shadowed rules
+
+    Args:
+        rules_text (str): The text content of the GFW list rules
+
+    Returns:
+        list[Lint]: One record per problem, with the 1-based line number and the text of the line
+    """
+    ...
+
+def decode_any(data: bytes) -> str:
+    """
+    Decode a list in any supported encoding into its text, as `GfwList.from_any` does before detecting its format
+
+    Args:
+        data (bytes): The content of the list, which may be base64-encoded like the official gfwlist.txt
+
+    Returns:
+        str: The text of the list
+
+    Raises:
+        GfwListBuildError: If the content cannot be decoded
+    """
+    ...
+
+class GfwList:
+    def __init__(self, rules_text: str) -> None:
+        """
+        Create a new GfwList instance from rules text
+        
+        Args:
+            rules_text (str): The text content of the GFW list rules
+        
+        Returns:
+            GfwList: A new GfwList instance
+        
+        Raises:
+            GfwListSyntaxError: If there's a syntax error in the rules
+            GfwListBuildError: If there's an error building the pattern matching engine
+        """
+        ...
+    @staticmethod
+    def from_file(path: str) -> GfwList:
+        """
+        Create a new GfwList instance from the file at a path
+
+        Lists encoded in base64 like the official `gfwlist.txt` are decoded.
+
+        Args:
+            path (str): The path of the list
+
+        Returns:
+            GfwList: A new GfwList instance
+
+        Raises:
+            GfwListSyntaxError: If there's a syntax error in the rules
+            GfwListBuildError: If the file cannot be read or the pattern matching engine cannot be built
+        """
+        ...
+    @staticmethod
+    def from_any(data: bytes) -> GfwList:
+        """
+        Create a new GfwList instance from a list in any supported format
+
+        Base64-encoded lists are decoded, and the format is detected among AutoProxy, Clash, hosts, dnsmasq and
+        domain-list. Entries that cannot be represented as GFW list rules are skipped.
+
+        Args:
+            data (bytes): The content of the list, encoded in UTF-8, UTF-16 with a byte order mark or GBK
+
+        Returns:
+            GfwList: A new GfwList instance
+
+        Raises:
+            GfwListSyntaxError: If there's a syntax error in the rules
+            GfwListBuildError: If there's an error building the pattern matching engine
+        """
+        ...
+    @staticmethod
+    def from_source(
+        text: str,
+        format: Literal[
+            "autoproxy", "domains", "clash", "hosts", "dnsmasq", "domain-list", "surge", "adguard-home", "quantumult-x"
+        ],
+    ) -> GfwList:
+        """
+        Create a new GfwList instance from a list in another format
+
+        Entries that cannot be represented as GFW list rules are skipped.
+
+        Args:
+            text (str): The content of the list
+            format (str): The format of the list
+
+        Returns:
+            GfwList: A new GfwList instance
+
+        Raises:
+            ValueError: If the format is unknown
+            GfwListSyntaxError: If there's a syntax error in the rules
+            GfwListBuildError: If there's an error building the pattern matching engine
+        """
+        ...
+    def export(
+        self,
+        format: Literal[
+            "acl",
+            "adblock",
+            "direct-list",
+            "direct-clash",
+            "direct-sing-box",
+            "mosdns",
+            "smartdns",
+            "sing-box",
+            "switchy-omega",
+            "xray",
+        ],
+        proxy: str = "proxy",
+        direct: str = "direct",
+        *,
+        only_tags: list[str] | None = None,
+        exclude_tags: list[str] | None = None,
+        only_suffixes: list[str] | None = None,
+        exclude_suffixes: list[str] | None = None,
+    ) -> str:
+        """
+        Render the rules in the format of another tool
+
+        The filters narrow down the exported rules, so that one list can generate several tailored files. The tags of
+        a rule are its section and the name of its source, and a suffix such as `.cn` covers a domain and its
+        subdomains.
+
+        The `direct-*` formats only export the whitelist (@@) rules that hold for whole hosts, as a standalone domain
+        list in domain-list, Clash rule provider or sing-box rule set syntax, to be matched before the proxy list.
+
+        Args:
+            format (str): The target format
+            proxy (str): The name of the proxy outbound, profile or server group, where the format needs one
+            direct (str): The name of the direct outbound or profile, where the format needs one
+            only_tags (list[str] | None): If given, only rules with one of these tags are exported
+            exclude_tags (list[str] | None): Rules with one of these tags are left out
+            only_suffixes (list[str] | None): If given, only rules whose host is covered by one of these suffixes are
+                exported
+            exclude_suffixes (list[str] | None): Rules whose host is covered by one of these suffixes are left out
+
+        Returns:
+            str: The rendered configuration
+
+        Raises:
+            ValueError: If the format is unknown
+        """
+        ...
+    def test(self, url: str) -> str | None:
+        """
+        Test if a URL matches any rule in the GfwList
+
+        Args:
+            url (str): The URL to test
+
+        Returns:
+            Optional[str]: The matching rule if found, otherwise None
+
+        Raises:
+            GfwListUrlError: If the URL is invalid or cannot be parsed
+        """
+        ...
+    def allowed_by(self, url: str) -> str | None:
+        """
+        Get the whitelist (@@) rule that allows a URL
+
+        Args:
+            url (str): The URL to test
+
+        Returns:
+            Optional[str]: The matching whitelist rule if found, otherwise None
+
+        Raises:
+            GfwListUrlError: If the URL is invalid or cannot be parsed
+        """
+        ...
+    def near_misses(self, url: str) -> list[tuple[str, str]]:
+        """
+        Explain why the rules about the registrable domain of a URL do not match it
+
+        Host, domain and URL rules that do not match are returned with the first component that prevents the match,
+        such as the scheme, the host boundary or the path, nearest misses first.
+
+        Args:
+            url (str): The URL to test
+
+        Returns:
+            list[tuple[str, str]]: The rules that do not match, with the reason
+
+        Raises:
+            GfwListUrlError: If the URL is invalid or cannot be parsed
+        """
+        ...
+    def golden_vectors(self) -> str:
+        """
+        Produce golden test vectors derived from the rules of the list
+
+        Each vector is a URL derived from a rule, with the decision of the whole list, so that bindings can check that
+        they agree with the Rust core.
+
+        Returns:
+            str: A JSON array of objects with the `url`, the `decision` ("blocked", "allowed" or "no match") and the
+                deciding `rule` or null
+
+        Raises:
+            ValueError: If a rule matches none of the URLs derived from it, or the list does not match such a URL with
+                the rule
+        """
+        ...
+    def set_hit_counting(self, enabled: bool) -> None:
+        """
+        Enable or disable counting how often each rule decides a match
+
+        Enabling hit counting starts all counters from zero.
+
+        Args:
+            enabled (bool): Whether to count hits
+        """
+        ...
+    def hit_counts(self) -> list[HitCount]:
+        """
+        Get the hit counter of each rule, in source order
+
+        The records can be passed to `pandas.DataFrame` as they are. `last_hit` is a Unix timestamp in seconds,
+        which `pandas.to_datetime(df["last_hit"], unit="s")` converts.
+
+        Returns:
+            list[HitCount]: One record per rule, or an empty list if hit counting is disabled
+        """
+        ...
+    def section_stats(self) -> list[SectionStats]:
+        """
+        Get the statistics of each section, in the order the sections appear in the list
+
+        Returns:
+            list[SectionStats]: One record per section, where `hits` is zero if hit counting is disabled
+        """
+        ...
+    def __len__(self) -> int:
+        """
+        Get the number of rules in the GfwList
+
+        Returns:
+            int: The number of rules
+        """
+        ...
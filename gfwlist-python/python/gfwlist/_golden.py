@@ -0,0 +1,38 @@
+"""Golden test vectors replayed at import time in debug builds, see `GfwList.golden_vectors`."""
+
+import json
+
+from ._gfwlist import GfwList
+
+# covers each kind of rule, so that every conversion of the bindings is exercised
+SAMPLE_LIST = """\
+||blocked-site.com
+@@||cdn.blocked-site.com
+|https://secure.example.com/login
+example.org/path?q=
+||example.net:8443^
+/^https?:\\/\\/[^\\/]+\\.video\\.com/
+@@IP-CIDR,10.0.0.0/8
+||bücher.de
+"""
+
+
+def check(gfw):
+    """Replays the golden vectors of a list through the Python API, raising AssertionError on the first mismatch."""
+    for vector in json.loads(gfw.golden_vectors()):
+        url = vector["url"]
+        blocked_by = gfw.test(url)
+        allowed_by = gfw.allowed_by(url)
+        if blocked_by is not None:
+            actual = ("blocked", blocked_by)
+        elif allowed_by is not None:
+            actual = ("allowed", allowed_by)
+        else:
+            actual = ("no match", None)
+        expected = (vector["decision"], vector["rule"])
+        if actual != expected:
+            raise AssertionError(f"golden vector {url}: expected {expected}, got {actual}")
+
+
+def check_sample():
+    check(GfwList(SAMPLE_LIST))
@@ -1,18 +1,27 @@
-use gfwlist::{BuildError, GfwList};
+use std::time::UNIX_EPOCH;
+
+use gfwlist::format::direct_list::{self, Syntax};
+use gfwlist::format::{ExportFilter, acl, adblock, filtered, mosdns, sing_box, smartdns, switchy_omega, xray};
+use gfwlist::source::{Source, SourceFormat, compile};
+use gfwlist::{BuildError, GfwList, Rule, RuleKind};
 use pyo3::create_exception;
 use pyo3::exceptions::{PyRuntimeError, PyValueError};
 use pyo3::prelude::*;
+use pyo3::types::PyDict;
 
 #[pymodule]
-#[pyo3(name = "gfwlist")]
+#[pyo3(name = "_gfwlist")]
 fn pygfwlist(py: Python, m: &Bound<'_, PyModule>) -> PyResult<()> {
     m.add_class::<PyGfwList>()?;
+    m.add_function(wrap_pyfunction!(lint, m)?)?;
+    m.add_function(wrap_pyfunction!(decode_any, m)?)?;
 
     m.add("GfwListSyntaxError", py.get_type::<GfwListSyntaxError>())?;
     m.add("GfwListBuildError", py.get_type::<GfwListBuildError>())?;
     m.add("GfwListUrlError", py.get_type::<GfwListUrlError>())?;
 
     m.add("__doc__", "A GFW list parser and matcher.")?;
+    m.add("debug_build", cfg!(debug_assertions))?;
 
     Ok(())
 }
@@ -21,6 +30,50 @@ create_exception!(pygfwlist, GfwListSyntaxError, PyValueError);
 create_exception!(pygfwlist, GfwListBuildError, PyRuntimeError);
 create_exception!(pygfwlist, GfwListUrlError, PyValueError);
 
+fn build_error(err: BuildError) -> PyErr {
+    match err {
+        BuildError::Syntax { line, error, .. } => {
+            GfwListSyntaxError::new_err(format!("Invalid rule syntax at line {line}: {error}"))
+        }
+        err @ BuildError::Source(..) => {
+            let mut inner = &err;
+            while let BuildError::Source(_, error) = inner {
+                inner = error;
+            }
+            match inner {
+                BuildError::Syntax { .. } => GfwListSyntaxError::new_err(format!("Invalid rule syntax: {err}")),
+                _ => GfwListBuildError::new_err(err.to_string()),
+            }
+        }
+        BuildError::AhoCorasick(err) => GfwListBuildError::new_err(format!("Failed to build pattern matcher: {err}")),
+        BuildError::Cancelled => GfwListBuildError::new_err("Build cancelled"),
+        err @ (BuildError::Io(_)
+        | BuildError::IncludeCycle(_)
+        | BuildError::IncludeOutsideRoot(_)
+        | BuildError::Limit { .. }
+        | BuildError::Expectation { .. }
+        | BuildError::Checksum { .. }) => GfwListBuildError::new_err(err.to_string()),
+    }
+}
+
+#[pyfunction]
+fn decode_any(data: &[u8]) -> PyResult<String> {
+    GfwList::decode_any(data).map_err(build_error)
+}
+
+#[pyfunction]
+fn lint<'py>(py: Python<'py>, rules_text: &str) -> PyResult<Vec<Bound<'py, PyDict>>> {
+    (GfwList::lint(rules_text).into_iter())
+        .map(|lint| {
+            let record = PyDict::new(py);
+            record.set_item("line", lint.line)?;
+            record.set_item("text", lint.text)?;
+            record.set_item("message", lint.kind.to_string())?;
+            Ok(record)
+        })
+        .collect()
+}
+
 #[pyclass(name = "GfwList")]
 struct PyGfwList {
     inner: GfwList,
@@ -30,15 +83,89 @@ struct PyGfwList {
 impl PyGfwList {
     #[new]
     fn new(rules_text: &str) -> PyResult<Self> {
-        match GfwList::from(rules_text) {
-            Ok(gfw) => Ok(PyGfwList { inner: gfw }),
-            Err(err) => match err {
-                BuildError::Syntax(rule, _) => Err(GfwListSyntaxError::new_err(format!("Invalid rule syntax: {rule}"))),
-                BuildError::AhoCorasick(err) => Err(GfwListBuildError::new_err(format!(
-                    "Failed to build pattern matcher: {err}",
-                ))),
-            },
-        }
+        GfwList::from(rules_text)
+            .map(|inner| PyGfwList { inner })
+            .map_err(build_error)
+    }
+
+    #[staticmethod]
+    fn from_file(path: &str) -> PyResult<Self> {
+        GfwList::from_file(path)
+            .map(|inner| PyGfwList { inner })
+            .map_err(build_error)
+    }
+
+    #[staticmethod]
+    fn from_any(data: &[u8]) -> PyResult<Self> {
+        GfwList::from_any(data)
+            .map(|inner| PyGfwList { inner })
+            .map_err(build_error)
+    }
+
+    #[staticmethod]
+    fn from_source(text: &str, format: &str) -> PyResult<Self> {
+        let format = match format {
+            "autoproxy" => SourceFormat::AutoProxy,
+            "domains" => SourceFormat::Domains,
+            "clash" => SourceFormat::ClashProvider,
+            "hosts" => SourceFormat::Hosts,
+            "dnsmasq" => SourceFormat::Dnsmasq,
+            "domain-list" => SourceFormat::DomainList,
+            "surge" => SourceFormat::Surge,
+            "adguard-home" => SourceFormat::AdGuardHome,
+            "quantumult-x" => SourceFormat::QuantumultX,
+            _ => return Err(PyValueError::new_err(format!("Unknown source format: {format}"))),
+        };
+        compile(&[Source::new("input", format, text)])
+            .map(|inner| PyGfwList { inner })
+            .map_err(build_error)
+    }
+
+    #[pyo3(signature = (
+        format,
+        proxy = "proxy",
+        direct = "direct",
+        *,
+        only_tags = None,
+        exclude_tags = None,
+        only_suffixes = None,
+        exclude_suffixes = None,
+    ))]
+    #[allow(clippy::too_many_arguments)]
+    fn export(
+        &self,
+        format: &str,
+        proxy: &str,
+        direct: &str,
+        only_tags: Option<Vec<String>>,
+        exclude_tags: Option<Vec<String>>,
+        only_suffixes: Option<Vec<String>>,
+        exclude_suffixes: Option<Vec<String>>,
+    ) -> PyResult<String> {
+        let filter = ExportFilter {
+            only_tags: only_tags.unwrap_or_default(),
+            exclude_tags: exclude_tags.unwrap_or_default(),
+            only_suffixes: only_suffixes.unwrap_or_default(),
+            exclude_suffixes: exclude_suffixes.unwrap_or_default(),
+        };
+        let filtered_list = match filter == ExportFilter::default() {
+            true => None,
+            false => Some(filtered(&self.inner, &filter).map_err(build_error)?),
+        };
+        let list = filtered_list.as_ref().unwrap_or(&self.inner);
+        Ok(match format {
+            "acl" => acl::export(list),
+            "adblock" => adblock::export(list),
+            "direct-list" => direct_list::export(list, Syntax::DomainList),
+            "direct-clash" => direct_list::export(list, Syntax::Clash),
+            "direct-sing-box" => direct_list::export(list, Syntax::SingBox),
+            "mosdns" => mosdns::export(list, false),
+            "smartdns" => smartdns::export(list, proxy),
+            "sing-box" => sing_box::export(list, proxy, direct),
+            "switchy-omega" => switchy_omega::export(list, proxy, direct),
+            "xray" => xray::export(list, proxy, direct),
+            _ => return Err(PyValueError::new_err(format!("Unknown export format: {format}"))),
+        })
     }
 
     fn test(&self, url: &str) -> PyResult<Option<&str>> {
@@ -48,6 +175,65 @@ impl PyGfwList {
         }
     }
 
+    fn allowed_by(&self, url: &str) -> PyResult<Option<&str>> {
+        match self.inner.decide(url) {
+            Ok(decision) => Ok(decision.allowed_by().map(Rule::text)),
+            Err(err) => Err(GfwListUrlError::new_err(format!("Invalid URL: {err}"))),
+        }
+    }
+
+    fn near_misses(&self, url: &str) -> PyResult<Vec<(&str, String)>> {
+        match self.inner.near_misses(url) {
+            Ok(misses) => Ok(misses
+                .iter()
+                .map(|miss| (miss.rule.text(), miss.mismatch.to_string()))
+                .collect()),
+            Err(err) => Err(GfwListUrlError::new_err(format!("Invalid URL: {err}"))),
+        }
+    }
+
+    fn golden_vectors(&self) -> PyResult<String> {
+        (self.inner.golden_vectors()).map_err(|err| PyValueError::new_err(format!("Invalid golden vectors: {err}")))
+    }
+
+    fn set_hit_counting(&mut self, enabled: bool) {
+        self.inner.set_hit_counting(enabled);
+    }
+
+    fn hit_counts<'py>(&self, py: Python<'py>) -> PyResult<Vec<Bound<'py, PyDict>>> {
+        (self.inner.hit_counts().zip(self.inner.last_hits()))
+            .map(|((rule, count), (_, last_hit))| {
+                let kind = match rule.kind() {
+                    RuleKind::Positive => "positive",
+                    RuleKind::Negative => "negative",
+                    RuleKind::Regex => "regex",
+                };
+                let last_hit = last_hit
+                    .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+                    .map(|since| since.as_secs_f64());
+                let record = PyDict::new(py);
+                record.set_item("rule", rule.text())?;
+                record.set_item("kind", kind)?;
+                record.set_item("count", count)?;
+                record.set_item("last_hit", last_hit)?;
+                Ok(record)
+            })
+            .collect()
+    }
+
+    fn section_stats<'py>(&self, py: Python<'py>) -> PyResult<Vec<Bound<'py, PyDict>>> {
+        (self.inner.section_stats().into_iter())
+            .map(|stats| {
+                let record = PyDict::new(py);
+                record.set_item("section", stats.name)?;
+                record.set_item("rules", stats.rules)?;
+                record.set_item("hits", stats.hits)?;
+                record.set_item("enabled", stats.enabled)?;
+                Ok(record)
+            })
+            .collect()
+    }
+
     fn __repr__(&self) -> PyResult<String> {
         Ok(format!("GfwList(rules_count={})", self.inner.len()))
     }
@@ -0,0 +1,66 @@
+//! Base64 decoding for lists distributed in encoded form, and encoding for checksums.
+
+/// Decodes standard base64, ignoring whitespace and line breaks. Padding is optional.
+pub(crate) fn decode(input: &str) -> Option<Vec<u8>> {
+    let mut output = Vec::with_capacity(input.len() / 4 * 3);
+    let mut buffer = 0u32;
+    let mut bits = 0;
+    let mut padding = false;
+    for byte in input.bytes().filter(|byte| !byte.is_ascii_whitespace()) {
+        let value = match byte {
+            b'A'..=b'Z' => byte - b'A',
+            b'a'..=b'z' => byte - b'a' + 26,
+            b'0'..=b'9' => byte - b'0' + 52,
+            b'+' => 62,
+            b'/' => 63,
+            b'=' => {
+                padding = true;
+                continue;
+            }
+            _ => return None,
+        };
+        if padding {
+            return None;
+        }
+        buffer = buffer << 6 | u32::from(value);
+        bits += 6;
+        if bits >= 8 {
+            bits -= 8;
+            output.push((buffer >> bits) as u8);
+        }
+    }
+    // a single leftover character cannot encode a byte
+    (bits < 6).then_some(output)
+}
+
+/// The characters of standard base64
+const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+/// Encodes standard base64 without padding.
+pub(crate) fn encode(input: &[u8]) -> String {
+    let mut output = String::with_capacity(input.len().div_ceil(3) * 4);
+    for chunk in input.chunks(3) {
+        let buffer = chunk.iter().fold(0u32, |buffer, byte| buffer << 8 | u32::from(*byte)) << (8 * (3 - chunk.len()));
+        for index in 0..=chunk.len() {
+            output.push(ALPHABET[(buffer >> (18 - 6 * index) & 63) as usize] as char);
+        }
+    }
+    output
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn decode_padding() {
+        assert_eq!(decode("fHxleGFtcGxlLmNvbQ==").unwrap(), b"||example.com");
+        assert_eq!(decode("fHxl\neGFt").unwrap(), b"||exam");
+        assert_eq!(decode("YQ").unwrap(), b"a");
+        assert!(decode("Y").is_none());
+        assert!(decode("YQ==YQ").is_none());
+        assert!(decode("a*").is_none());
+        assert_eq!(encode(b"||example.com"), "fHxleGFtcGxlLmNvbQ");
+        assert_eq!(encode(b"abc"), "YWJj");
+    }
+}
@@ -0,0 +1,1125 @@
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, OnceLock};
+use std::{fmt, fs};
+
+use gfwlist_core::{AutomataBuilder, encode};
+use regex::Regex;
+
+use crate::condition::Conditions;
+use crate::expect::Expectation;
+use crate::ip::Network;
+use crate::literal::{RegexPrefilter, required_literals};
+use crate::metadata::ListMetadata;
+use crate::patch::Reuse;
+use crate::rule::{move_to_arena, parse_line, parse_lines, section_header};
+use crate::stats::rules_memory_usage;
+use crate::{
+    BuildError, Diagnostic, GfwList, Origin, Pattern, Rule, RuleOptions, RuleParser, SchemeMap, SyntaxError,
+    append_pattern, checksum, numbered_byte_lines,
+};
+
+/// Logs the line numbers and reasons of skipped lines in order, up to [`GfwListBuilder::MAX_LOGGED_LINES`].
+#[cfg(feature = "log")]
+fn log_skipped(lines: impl Iterator<Item = (usize, String)>) {
+    let mut lines: Vec<_> = lines.collect();
+    lines.sort_by_key(|(line, _)| *line);
+    let mut remaining = 0;
+    for (index, (line, reason)) in lines.into_iter().enumerate() {
+        match index < GfwListBuilder::MAX_LOGGED_LINES {
+            true => log::warn!("skipped line {line}: {reason}"),
+            false => remaining += 1,
+        }
+    }
+    if remaining > 0 {
+        log::warn!("skipped {remaining} more lines");
+    }
+}
+
+/// A handle to cancel builds in progress.
+///
+/// Clones of a handle share their state, so a handle can be given to a builder with
+/// [`GfwListBuilder::cancel_on`] and cancelled from another thread, for example when a newer update supersedes the
+/// list being built. A cancelled build fails with [`BuildError::Cancelled`].
+///
+/// # Examples
+///
+/// ```
+/// # use gfwlist::{BuildError, BuildHandle, GfwListBuilder};
+/// let handle = BuildHandle::new();
+/// let mut builder = GfwListBuilder::new();
+/// builder.cancel_on(&handle).add_rules("||blocked-site.com").unwrap();
+/// handle.cancel();
+/// assert!(matches!(builder.build(), Err(BuildError::Cancelled)));
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct BuildHandle {
+    cancelled: Arc<AtomicBool>,
+}
+
+impl BuildHandle {
+    /// Creates a handle that is not cancelled.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Cancels all builds using this handle.
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    /// Returns whether the handle has been cancelled.
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+}
+
+/// Resolves `!#include` directives, registered with [`GfwListBuilder::include_resolver`].
+///
+/// The resolver decides how included lists are fetched, such as from a bundled archive or over the network, and which
+/// targets are allowed at all. Closures taking the name of the including list and the target are resolvers as well.
+pub trait IncludeResolver: Send + Sync {
+    /// Returns the name and the content of the list that `target` refers to.
+    ///
+    /// `from` is the name of the including list, as returned by an earlier call or the path given to
+    /// [`add_file`](GfwListBuilder::add_file), or `None` for lists added with [`add_rules`](GfwListBuilder::add_rules).
+    /// Names identify lists in errors and when detecting include cycles.
+    fn resolve(&self, from: Option<&str>, target: &str) -> Result<(String, String), BuildError>;
+}
+
+impl<F> IncludeResolver for F
+where
+    F: Fn(Option<&str>, &str) -> Result<(String, String), BuildError> + Send + Sync,
+{
+    fn resolve(&self, from: Option<&str>, target: &str) -> Result<(String, String), BuildError> {
+        self(from, target)
+    }
+}
+
+impl fmt::Debug for dyn IncludeResolver {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("IncludeResolver")
+    }
+}
+
+/// How a builder handles regular expression rules.
+///
+/// Compiling regular expressions is the most expensive part of building and matching. Minimal deployments can
+/// choose another policy to consume upstream lists that contain a handful of regex rules.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum RegexPolicy {
+    /// Compile regular expressions and test them against the full URL
+    #[default]
+    Compile,
+    /// Reject regex rules with [`SyntaxError::RegexDisabled`]
+    Reject,
+    /// Leave regex rules out of the list, see [`GfwListBuilder::skipped`]
+    Skip,
+    /// Replace each regex rule with the longest literal fragment that all its matches contain, which is matched like
+    /// a substring of the host or the path. Regex rules without a fragment of at least
+    /// [`MIN_FRAGMENT_LEN`](Self::MIN_FRAGMENT_LEN) bytes are skipped.
+    ///
+    /// Approximated rules match more URLs than the original ones, and are overridden by negative rules.
+    Approximate,
+}
+
+/// How a builder handles `! Checksum:` comments, which hold the base64-encoded MD5 digest of the list as computed by
+/// Adblock Plus tooling.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum ChecksumPolicy {
+    /// Treat checksum comments like other comments
+    #[default]
+    Ignore,
+    /// Fail with [`BuildError::Checksum`] if a list does not match its checksum comment
+    Verify,
+    /// Like [`Verify`](Self::Verify), but also fail if a list has no checksum comment
+    Require,
+}
+
+impl RegexPolicy {
+    /// The minimum length of a literal fragment used by [`RegexPolicy::Approximate`]
+    pub const MIN_FRAGMENT_LEN: usize = 4;
+}
+
+/// Resource limits enforced by a builder, for lists supplied by untrusted users.
+///
+/// Limits are checked while [`add_rules`](GfwListBuilder::add_rules) reads the input, so oversized lists are
+/// rejected early, and again by [`build`](GfwListBuilder::build). Exceeding a limit fails with
+/// [`BuildError::Limit`].
+///
+/// # Examples
+///
+/// ```
+/// # use gfwlist::{BuildError, GfwListBuilder, Limits, Resource};
+/// let mut builder = GfwListBuilder::new();
+/// builder.limits(Limits {
+///     max_regexes: Some(1),
+///     ..Limits::default()
+/// });
+/// let result = builder.add_rules("/a/\n||example.com\n/b/");
+/// assert!(matches!(result, Err(BuildError::Limit { resource: Resource::Regexes, limit: 1 })));
+/// ```
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct Limits {
+    /// The maximum number of rules
+    pub max_rules: Option<usize>,
+    /// The maximum number of compiled regular expressions
+    pub max_regexes: Option<usize>,
+    /// The maximum estimated memory usage in bytes, see [`GfwList::memory_usage`]
+    pub max_memory: Option<usize>,
+    /// The maximum length of a line in bytes. Longer lines are rejected before they are parsed, with
+    /// [`SyntaxError::LineTooLong`] at their line, and skipped by
+    /// [`add_rules_lenient`](GfwListBuilder::add_rules_lenient)
+    pub max_line_length: Option<usize>,
+}
+
+/// A resource restricted by [`Limits`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Resource {
+    /// The number of rules
+    Rules,
+    /// The number of compiled regular expressions
+    Regexes,
+    /// The estimated memory usage in bytes
+    Memory,
+}
+
+impl fmt::Display for Resource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Resource::Rules => "rules",
+            Resource::Regexes => "regexes",
+            Resource::Memory => "bytes of memory",
+        })
+    }
+}
+
+impl Limits {
+    fn check_line(&self, line: &str) -> Result<(), SyntaxError> {
+        match self.max_line_length {
+            Some(limit) if line.len() > limit => Err(SyntaxError::LineTooLong {
+                length: line.len(),
+                limit,
+            }),
+            _ => Ok(()),
+        }
+    }
+
+    fn check(&self, resource: Resource, usage: usize) -> Result<(), BuildError> {
+        let limit = match resource {
+            Resource::Rules => self.max_rules,
+            Resource::Regexes => self.max_regexes,
+            Resource::Memory => self.max_memory,
+        };
+        match limit {
+            Some(limit) if usage > limit => Err(BuildError::Limit { resource, limit }),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// `GfwListBuilder` assembles rules from several sources before compiling them into a [`GfwList`].
+///
+/// Rules are parsed and encoded as they are added, so syntax errors are reported by the `add_*` methods. The
+/// Aho-Corasick automata are only built once, by [`GfwListBuilder::build`].
+///
+/// # Examples
+///
+/// ```
+/// # use gfwlist::GfwListBuilder;
+/// let remote_list = "||blocked-site.com\n@@||exception.com";
+/// let mut builder = GfwListBuilder::new();
+/// builder.add_rules(remote_list).unwrap().add_rule("||user-site.com").unwrap();
+/// let gfw = builder.build().unwrap();
+/// assert_eq!(gfw.test("http://user-site.com").unwrap(), Some("||user-site.com"));
+/// assert_eq!(gfw.len(), 3);
+/// ```
+#[derive(Debug, Default)]
+pub struct GfwListBuilder {
+    rules: Vec<Rule>,
+    automata: AutomataBuilder,
+    regex_patterns: Vec<(Regex, usize)>,
+    /// The number of negative regexes, which precede the positive ones in `regex_patterns`
+    negative_regexes: usize,
+    ip_rules: Vec<(Network, usize)>,
+    /// The number of negative IP rules, which precede the positive ones in `ip_rules`
+    negative_ip_rules: usize,
+    disabled_sections: HashSet<String>,
+    handle: Option<BuildHandle>,
+    regex_policy: RegexPolicy,
+    regex_prefilter: bool,
+    ignore_query: bool,
+    skipped: Vec<Rule>,
+    expectations: Vec<Expectation>,
+    metadata: ListMetadata,
+    verify_expectations: bool,
+    limits: Limits,
+    schemes: SchemeMap,
+    include_root: Option<PathBuf>,
+    include_resolver: Option<Arc<dyn IncludeResolver>>,
+    checksum_policy: ChecksumPolicy,
+    defined: HashSet<String>,
+    parsers: Vec<Arc<dyn RuleParser>>,
+    /// The structures of the list being patched, see [`GfwList::commit`]
+    pub(crate) reuse: Option<Reuse>,
+}
+
+impl GfwListBuilder {
+    /// The number of skipped lines logged by each call of [`add_rules_lenient`](Self::add_rules_lenient), after which
+    /// only the number of the remaining ones is logged
+    pub const MAX_LOGGED_LINES: usize = 20;
+
+    /// Creates an empty builder.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Makes the build fail with [`BuildError::Cancelled`] once the handle is cancelled.
+    ///
+    /// The handle is checked for every line added by [`add_rules`](Self::add_rules) and before and after each
+    /// automaton is built.
+    pub fn cancel_on(&mut self, handle: &BuildHandle) -> &mut Self {
+        self.handle = Some(handle.clone());
+        self
+    }
+
+    fn check_cancelled(&self) -> Result<(), BuildError> {
+        match &self.handle {
+            Some(handle) if handle.is_cancelled() => Err(BuildError::Cancelled),
+            _ => Ok(()),
+        }
+    }
+
+    /// Sets the schemes that are matched as equivalent, see [`SchemeMap`].
+    ///
+    /// This only affects rules added afterwards.
+    pub fn scheme_map(&mut self, schemes: SchemeMap) -> &mut Self {
+        self.schemes = schemes;
+        self
+    }
+
+    /// Sets the resource limits of the list.
+    pub fn limits(&mut self, limits: Limits) -> &mut Self {
+        self.limits = limits;
+        self
+    }
+
+    fn check_limits(&self) -> Result<(), BuildError> {
+        self.limits.check(Resource::Rules, self.rules.len())?;
+        self.limits.check(Resource::Regexes, self.regex_patterns.len())?;
+        if self.limits.max_memory.is_some() {
+            let usage = rules_memory_usage(&self.rules) + self.automata.literal_len();
+            self.limits.check(Resource::Memory, usage)?;
+        }
+        Ok(())
+    }
+
+    /// Sets how regex rules added afterwards are handled.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use gfwlist::{GfwListBuilder, RegexPolicy};
+    /// let mut builder = GfwListBuilder::new();
+    /// builder.regex_policy(RegexPolicy::Approximate);
+    /// builder.add_rules("/^https?:\\/\\/[^\\/]+\\.blogspot\\.com/\n/a|b/").unwrap();
+    /// assert_eq!(builder.skipped().len(), 1);
+    /// let gfw = builder.build().unwrap();
+    /// assert!(gfw.test("https://foo.blogspot.com/").unwrap().is_some());
+    /// ```
+    pub fn regex_policy(&mut self, policy: RegexPolicy) -> &mut Self {
+        self.regex_policy = policy;
+        self
+    }
+
+    /// Sets how checksum comments are handled. Defaults to [`ChecksumPolicy::Ignore`].
+    ///
+    /// The checksum is verified separately for each call to [`add_rules`](Self::add_rules) and for each file added
+    /// with [`add_file`](Self::add_file), including files it includes.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use gfwlist::{BuildError, ChecksumPolicy, GfwListBuilder};
+    /// let mut builder = GfwListBuilder::new();
+    /// builder.checksum_policy(ChecksumPolicy::Verify);
+    /// builder.add_rules("[AutoProxy 0.2.9]\n! Checksum: vCkiHPnn/VD8ZJlzTuGmVA\n||blocked-site.com\n").unwrap();
+    /// let error = builder.add_rules("! Checksum: vCkiHPnn/VD8ZJlzTuGmVA\n||other-site.com\n").unwrap_err();
+    /// assert!(matches!(error, BuildError::Checksum { .. }));
+    /// ```
+    pub fn checksum_policy(&mut self, policy: ChecksumPolicy) -> &mut Self {
+        self.checksum_policy = policy;
+        self
+    }
+
+    /// Enables a prefilter that only runs a regular expression if the URL contains a literal fragment required by it.
+    ///
+    /// This speeds up lists with many regex rules, at the cost of a larger compiled list. Decisions are unchanged.
+    pub fn regex_prefilter(&mut self, enabled: bool) -> &mut Self {
+        self.regex_prefilter = enabled;
+        self
+    }
+
+    /// Keeps the query strings of URLs out of matching, for deployments that must not inspect them.
+    ///
+    /// Query strings are matched by default, so that rules like `||example.com/search?q=` take effect. With this
+    /// enabled, regex rules are tested against URLs without their query and fragment, and other rules only see the
+    /// path, so that rules constraining the query never match.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use gfwlist::GfwListBuilder;
+    /// let mut builder = GfwListBuilder::new();
+    /// builder.ignore_query(true).add_rules("||example.com/search?q=\n/[?&]token=/").unwrap();
+    /// let gfw = builder.build().unwrap();
+    /// assert_eq!(gfw.test("https://example.com/search?q=tiananmen").unwrap(), None);
+    /// assert_eq!(gfw.test("https://example.org/?token=1").unwrap(), None);
+    /// ```
+    pub fn ignore_query(&mut self, enabled: bool) -> &mut Self {
+        self.ignore_query = enabled;
+        self
+    }
+
+    /// Returns the regex rules that were left out because of the [`RegexPolicy`].
+    pub fn skipped(&self) -> &[Rule] {
+        &self.skipped
+    }
+
+    /// Makes the build fail with [`BuildError::Expectation`] if an expectation embedded in the added rules is not met.
+    ///
+    /// See [`expect`](crate::expect) for the annotation syntax.
+    pub fn verify_expectations(&mut self, enabled: bool) -> &mut Self {
+        self.verify_expectations = enabled;
+        self
+    }
+
+    /// Disables a section of the list, see [`Rule::section`].
+    ///
+    /// Rules of a disabled section are kept in the compiled list, but never match. This only affects rules added
+    /// afterwards.
+    pub fn disable_section(&mut self, name: impl Into<String>) -> &mut Self {
+        self.disabled_sections.insert(name.into());
+        self
+    }
+
+    /// Registers a parser for another line syntax, which is tried before the AutoProxy syntax for all lines added
+    /// afterwards, see [`RuleParser`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use gfwlist::{GfwListBuilder, Pattern, Rule};
+    /// let mut builder = GfwListBuilder::new();
+    /// // CSV rows like `proxy,example.com`
+    /// builder.add_parser(|line: &str| {
+    ///     let (action, domain) = line.split_once(',')?;
+    ///     Some(Ok(Rule::new(action == "direct", Pattern::Domain(domain.into()))))
+    /// });
+    /// builder.add_rules("proxy,blocked-site.com\ndirect,cdn.blocked-site.com\n||other-site.com").unwrap();
+    /// let gfw = builder.build().unwrap();
+    /// assert_eq!(gfw.test("http://www.blocked-site.com").unwrap(), Some("proxy,blocked-site.com"));
+    /// assert_eq!(gfw.test("http://cdn.blocked-site.com").unwrap(), None);
+    /// assert!(gfw.test("http://other-site.com").unwrap().is_some());
+    /// ```
+    pub fn add_parser(&mut self, parser: impl RuleParser + 'static) -> &mut Self {
+        self.parsers.push(Arc::new(parser));
+        self
+    }
+
+    /// Adds a single line of a GFW list. Empty lines and comments are ignored.
+    pub fn add_rule(&mut self, line: &str) -> Result<&mut Self, SyntaxError> {
+        self.limits.check_line(line)?;
+        match parse_line(&self.parsers, line)? {
+            Some(rule) => self.add_parsed(rule),
+            None => Ok(self),
+        }
+    }
+
+    /// Adds all lines of a GFW list.
+    ///
+    /// Line numbers in errors refer to `input`. Lines between `!#include-base64-begin` and `!#include-base64-end`
+    /// are decoded as a base64-encoded list, whose rules are reported at the line of the begin directive.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use gfwlist::GfwList;
+    /// let input = "||user-site.com\n!#include-base64-begin\nfHxibG9ja2VkLXNpdGUuY29t\n!#include-base64-end";
+    /// let gfw = GfwList::from(input).unwrap();
+    /// assert_eq!(gfw.test("http://blocked-site.com").unwrap(), Some("||blocked-site.com"));
+    /// ```
+    pub fn add_rules(&mut self, input: &str) -> Result<&mut Self, BuildError> {
+        self.add_lines(input, None, &mut vec![])?;
+        Ok(self)
+    }
+
+    /// Adds all lines of a large list given as bytes, such as a merge of many upstream lists.
+    ///
+    /// This bulk path finds line breaks over the raw bytes, with SIMD if the `memchr` feature is enabled, reserves room
+    /// for all rules upfront and skips comments without decoding them. Only rules and section headers are read:
+    /// directives such as `!#if` and `!#include`, inline base64 chunks, expectations and metadata are treated as
+    /// comments, so use [`add_rules`](Self::add_rules) for lists that rely on them. A line that is not valid UTF-8
+    /// fails with [`BuildError::Io`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use gfwlist::GfwListBuilder;
+    /// let mut builder = GfwListBuilder::new();
+    /// builder.add_bulk(b"! merged list\r\n||blocked-site.com\r\n!---- Extra ----\r\n||other-site.com").unwrap();
+    /// let gfw = builder.build().unwrap();
+    /// let rule = gfw.decide("http://other-site.com").unwrap().rule().unwrap().clone();
+    /// assert_eq!((rule.origin().unwrap().line, rule.section()), (4, Some("Extra")));
+    /// ```
+    pub fn add_bulk(&mut self, input: &[u8]) -> Result<&mut Self, BuildError> {
+        self.rules.reserve(numbered_byte_lines(input).count());
+        let parsers = self.parsers.clone();
+        let mut section: Option<Arc<str>> = None;
+        for (line, offset, bytes) in numbered_byte_lines(input) {
+            self.check_cancelled()?;
+            let is_header = bytes.starts_with(b"!---") || bytes.starts_with(b"!###") || bytes.starts_with(b"!===");
+            if bytes.is_empty() || (bytes[0] == b'!' && !is_header) {
+                continue;
+            }
+            let error = |error| BuildError::Syntax { line, offset, error };
+            if let Some(limit) = self.limits.max_line_length
+                && bytes.len() > limit
+            {
+                let length = bytes.len();
+                return Err(error(SyntaxError::LineTooLong { length, limit }));
+            }
+            let text = std::str::from_utf8(bytes).map_err(|error| {
+                BuildError::Io(std::io::Error::new(
+                    std::io::ErrorKind::InvalidData,
+                    format!("line {line} is not valid UTF-8: {error}"),
+                ))
+            })?;
+            if is_header {
+                section = section_header(text).map(Arc::from).or(section);
+                continue;
+            }
+            if let Some(mut rule) = parse_line(&parsers, text).map_err(error)? {
+                rule = rule.with_origin(Origin::line(line));
+                if let Some(section) = &section {
+                    rule = rule.with_section(section.clone());
+                }
+                self.add_parsed(rule).map_err(error)?;
+                self.check_limits()?;
+            }
+        }
+        Ok(self)
+    }
+
+    /// Defines a symbol for `!#if` directives.
+    ///
+    /// Lines between `!#if condition` and `!#else` or `!#endif` are only added if the condition holds. Conditions
+    /// combine symbols with `!`, `&&`, `||` and parentheses, and undefined symbols are false. Blocks may be nested, but
+    /// must be closed in the file they are opened in.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use gfwlist::GfwListBuilder;
+    /// let mut builder = GfwListBuilder::new();
+    /// builder.define("router");
+    /// builder.add_rules("!#if router && !ipv6\n||router-only.com\n!#else\n||desktop-only.com\n!#endif").unwrap();
+    /// let gfw = builder.build().unwrap();
+    /// assert!(gfw.test("http://router-only.com").unwrap().is_some());
+    /// assert!(gfw.test("http://desktop-only.com").unwrap().is_none());
+    /// ```
+    pub fn define(&mut self, symbol: impl Into<String>) -> &mut Self {
+        self.defined.insert(symbol.into());
+        self
+    }
+
+    /// Sets the directory that files included with `!#include` must be in, see [`add_file`](Self::add_file).
+    pub fn include_root(&mut self, root: impl Into<PathBuf>) -> &mut Self {
+        self.include_root = Some(root.into());
+        self
+    }
+
+    /// Resolves `!#include` directives with a custom resolver instead of reading files.
+    ///
+    /// Includes are then followed in lists added with [`add_rules`](Self::add_rules) as well, and the
+    /// [include root](Self::include_root) is left to the resolver to enforce. Conditional blocks must still be closed
+    /// in the list they are opened in.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use gfwlist::{BuildError, GfwListBuilder};
+    /// let mut builder = GfwListBuilder::new();
+    /// builder.include_resolver(|_: Option<&str>, target: &str| match target {
+    ///     "ads.txt" => Ok((target.to_string(), "||ads.example.com\n!#include extra.txt".to_string())),
+    ///     "extra.txt" => Ok((target.to_string(), "!#if router\n||router.example.com\n!#endif".to_string())),
+    ///     _ => Err(BuildError::Io(std::io::ErrorKind::NotFound.into())),
+    /// });
+    /// builder.define("router").add_rules("||example.org\n!#include ads.txt").unwrap();
+    /// let gfw = builder.build().unwrap();
+    /// assert!(gfw.test("http://ads.example.com").unwrap().is_some());
+    /// assert!(gfw.test("http://router.example.com").unwrap().is_some());
+    /// ```
+    pub fn include_resolver(&mut self, resolver: impl IncludeResolver + 'static) -> &mut Self {
+        self.include_resolver = Some(Arc::new(resolver));
+        self
+    }
+
+    /// Adds all lines of a GFW list file, following `!#include path/to/other.txt` directives.
+    ///
+    /// Included paths are relative to the including file and must be in the [include root](Self::include_root), which
+    /// defaults to the directory of `path`, unless an [include resolver](Self::include_resolver) is set. Errors in
+    /// included files are wrapped in [`BuildError::Source`] with the path of the file. Including a file that is
+    /// already being included fails with [`BuildError::IncludeCycle`].
+    ///
+    /// Without an include resolver, [`add_rules`](Self::add_rules) treats `!#include` directives as comments, since it
+    /// has no path to resolve them against.
+    pub fn add_file(&mut self, path: impl AsRef<Path>) -> Result<&mut Self, BuildError> {
+        let input = fs::read_to_string(&path)?;
+        self.add_file_content(path.as_ref(), &input)?;
+        Ok(self)
+    }
+
+    /// Adds the content of a file read by the caller, see [`add_file`](Self::add_file).
+    pub(crate) fn add_file_content(&mut self, path: &Path, input: &str) -> Result<(), BuildError> {
+        let path = path.canonicalize()?;
+        let root = match &self.include_root {
+            Some(root) => root.canonicalize()?,
+            None => path.parent().unwrap_or(Path::new("/")).to_path_buf(),
+        };
+        let mut stack = vec![path];
+        self.add_lines(input, Some(&root), &mut stack)
+    }
+
+    /// Adds the lines of a list, resolving includes against the stack of lists being included.
+    ///
+    /// Without an include resolver, files are included from the root, or not at all if there is none.
+    fn add_lines(&mut self, input: &str, root: Option<&Path>, stack: &mut Vec<PathBuf>) -> Result<(), BuildError> {
+        self.verify_checksum(input)?;
+        let mut conditions = Conditions::default();
+        let parsers = self.parsers.clone();
+        for (line, offset, text, result) in parse_lines(input, &parsers, self.limits.max_line_length) {
+            self.check_cancelled()?;
+            let error = |error| BuildError::Syntax { line, offset, error };
+            if let Err(too_long @ SyntaxError::LineTooLong { .. }) = result {
+                return Err(error(too_long));
+            }
+            if conditions
+                .directive((line, offset), &text, &self.defined)
+                .map_err(error)?
+                || !conditions.is_active()
+            {
+                continue;
+            }
+            if let Some(target) = text.strip_prefix("!#include ") {
+                if let Some(resolver) = self.include_resolver.clone() {
+                    let from = stack.last().map(|name| name.to_string_lossy().into_owned());
+                    let (name, input) = (resolver.resolve(from.as_deref(), target.trim()))
+                        .map_err(|error| BuildError::Source(target.trim().to_string(), Box::new(error)))?;
+                    self.include_lines(root, stack, PathBuf::from(name), &input)?;
+                    continue;
+                }
+                if let Some(root) = root {
+                    self.include(root, stack, target.trim())?;
+                    continue;
+                }
+            }
+            self.metadata.parse_line(&text);
+            self.expectations.extend(Expectation::parse(line, &text));
+            if let Some(rule) = result.map_err(error)? {
+                self.add_parsed(rule).map_err(error)?;
+                self.check_limits()?;
+            }
+        }
+        match conditions.unclosed() {
+            Some((line, offset)) => Err(BuildError::Syntax {
+                line,
+                offset,
+                error: SyntaxError::Directive,
+            }),
+            None => Ok(()),
+        }
+    }
+
+    fn verify_checksum(&self, input: &str) -> Result<(), BuildError> {
+        let declared = match self.checksum_policy {
+            ChecksumPolicy::Ignore => return Ok(()),
+            ChecksumPolicy::Verify => match checksum::declared(input) {
+                Some(declared) => Some(declared),
+                None => return Ok(()),
+            },
+            ChecksumPolicy::Require => checksum::declared(input),
+        };
+        let actual = checksum::compute(input);
+        match declared {
+            Some(declared) if declared.trim_end_matches('=') == actual => Ok(()),
+            declared => Err(BuildError::Checksum {
+                declared: declared.map(str::to_string),
+                actual,
+            }),
+        }
+    }
+
+    fn include(&mut self, root: &Path, stack: &mut Vec<PathBuf>, target: &str) -> Result<(), BuildError> {
+        let dir = stack.last().and_then(|path| path.parent()).unwrap_or(root);
+        let path = dir.join(target);
+        let wrap = |error| BuildError::Source(path.display().to_string(), Box::new(error));
+        let path = path.canonicalize().map_err(|error| wrap(BuildError::Io(error)))?;
+        if !path.starts_with(root) {
+            return Err(BuildError::IncludeOutsideRoot(path));
+        }
+        let input = fs::read_to_string(&path).map_err(|error| wrap(BuildError::Io(error)))?;
+        self.include_lines(Some(root), stack, path, &input)
+    }
+
+    /// Adds the lines of an included list with the given name.
+    fn include_lines(
+        &mut self,
+        root: Option<&Path>,
+        stack: &mut Vec<PathBuf>,
+        path: PathBuf,
+        input: &str,
+    ) -> Result<(), BuildError> {
+        if stack.contains(&path) {
+            return Err(BuildError::IncludeCycle(path));
+        }
+        stack.push(path);
+        let result = self.add_lines(input, root, stack);
+        let path = stack.pop().unwrap_or_default();
+        result.map_err(|error| match error {
+            // report cycles and cancellation at the top level
+            error @ (BuildError::IncludeCycle(_) | BuildError::IncludeOutsideRoot(_) | BuildError::Cancelled) => error,
+            error => BuildError::Source(path.display().to_string(), Box::new(error)),
+        })
+    }
+
+    /// Adds all valid lines of a GFW list, returning a [`Diagnostic`] for every invalid line.
+    ///
+    /// `!#if` blocks are evaluated like in [`add_rules`](Self::add_rules), where malformed directives and an `!#if`
+    /// that is never closed are reported as diagnostics.
+    ///
+    /// Stops early if the build is cancelled, see [`cancel_on`](Self::cancel_on). With the `log` feature, invalid lines
+    /// and regex rules skipped by the [`RegexPolicy`] are also logged as warnings, up to
+    /// [`MAX_LOGGED_LINES`](Self::MAX_LOGGED_LINES).
+    pub fn add_rules_lenient(&mut self, input: &str) -> Vec<Diagnostic> {
+        #[cfg(feature = "log")]
+        let skipped = self.skipped.len();
+        let mut diagnostics = vec![];
+        let mut conditions = Conditions::default();
+        let parsers = self.parsers.clone();
+        for (line, offset, text, result) in parse_lines(input, &parsers, self.limits.max_line_length) {
+            if self.check_cancelled().is_err() {
+                break;
+            }
+            if let Err(error @ SyntaxError::LineTooLong { limit, .. }) = result {
+                // keep the diagnostics of oversized lines small
+                let text = text[..text.floor_char_boundary(limit)].to_string();
+                diagnostics.push(Diagnostic {
+                    line,
+                    offset,
+                    text,
+                    error,
+                });
+                continue;
+            }
+            match conditions.directive((line, offset), &text, &self.defined) {
+                Ok(false) if conditions.is_active() => {}
+                Ok(_) => continue,
+                Err(error) => {
+                    diagnostics.push(Diagnostic {
+                        line,
+                        offset,
+                        text: text.to_string(),
+                        error,
+                    });
+                    continue;
+                }
+            }
+            self.expectations.extend(Expectation::parse(line, &text));
+            let result = result.and_then(|rule| match rule {
+                Some(rule) => self.add_parsed(rule).map(|_| ()),
+                None => Ok(()),
+            });
+            if let Err(error) = result {
+                diagnostics.push(Diagnostic {
+                    line,
+                    offset,
+                    text: text.to_string(),
+                    error,
+                });
+            }
+        }
+        if let Some((line, offset)) = conditions.unclosed() {
+            let text = input.get(offset..).and_then(|rest| rest.lines().next());
+            diagnostics.push(Diagnostic {
+                line,
+                offset,
+                text: text.unwrap_or_default().to_string(),
+                error: SyntaxError::Directive,
+            });
+        }
+        #[cfg(feature = "log")]
+        log_skipped(
+            (diagnostics
+                .iter()
+                .map(|diagnostic| (diagnostic.line, diagnostic.error.to_string())))
+            .chain(self.skipped[skipped..].iter().map(|rule| {
+                let line = rule.origin().map_or(0, |origin| origin.line);
+                (line, "regex rule skipped by the regex policy".to_string())
+            })),
+        );
+        diagnostics
+    }
+
+    /// Adds an already parsed rule.
+    pub fn add_parsed(&mut self, rule: Rule) -> Result<&mut Self, SyntaxError> {
+        let rule_index = self.rules.len();
+        let disabled = rule
+            .section()
+            .is_some_and(|section| self.disabled_sections.contains(section));
+        if let Pattern::Regex(source) = rule.pattern() {
+            match self.regex_policy {
+                RegexPolicy::Compile => {
+                    let regex = match self.reuse.as_ref().and_then(|reuse| reuse.regex(source)) {
+                        Some(regex) => regex,
+                        None => Regex::new(source).map_err(SyntaxError::Regex)?,
+                    };
+                    // negative regexes come first, so that they take precedence
+                    if !disabled {
+                        if rule.is_negative() {
+                            self.regex_patterns.insert(self.negative_regexes, (regex, rule_index));
+                            self.negative_regexes += 1;
+                        } else {
+                            self.regex_patterns.push((regex, rule_index));
+                        }
+                    }
+                }
+                RegexPolicy::Reject => return Err(SyntaxError::RegexDisabled),
+                RegexPolicy::Skip => {
+                    self.skipped.push(rule);
+                    return Ok(self);
+                }
+                RegexPolicy::Approximate => {
+                    let literals = required_literals(source).map_err(SyntaxError::Regex)?;
+                    // fragments must not span the scheme, host and path components of the haystack
+                    let fragment = (literals.iter())
+                        .flat_map(|literal| literal.split([':', '/']))
+                        .max_by_key(|fragment| fragment.len())
+                        .filter(|fragment| fragment.len() >= RegexPolicy::MIN_FRAGMENT_LEN);
+                    let Some(fragment) = fragment else {
+                        self.skipped.push(rule);
+                        return Ok(self);
+                    };
+                    if !disabled {
+                        if let Some(reuse) = &mut self.reuse {
+                            let literal = fragment.as_bytes().to_vec();
+                            (reuse.add(&mut self.automata, literal, true, &rule, rule_index))
+                                .map_err(|_| SyntaxError::Rule)?;
+                        } else {
+                            (self.automata).add_literal(fragment.as_bytes().to_vec(), rule.is_negative(), rule_index);
+                        }
+                    }
+                }
+            }
+            self.rules.push(rule);
+            return Ok(self);
+        }
+        if let Pattern::Cidr(network) = rule.pattern() {
+            let network = Network::parse(network).ok_or(SyntaxError::Rule)?;
+            if !disabled {
+                if rule.is_negative() {
+                    self.ip_rules.insert(self.negative_ip_rules, (network, rule_index));
+                    self.negative_ip_rules += 1;
+                } else {
+                    self.ip_rules.push((network, rule_index));
+                }
+            }
+            self.rules.push(rule);
+            return Ok(self);
+        }
+        let mut needle: Vec<u8> = vec![];
+        append_pattern(&mut needle, rule.pattern(), &self.schemes).map_err(SyntaxError::Url)?;
+        if disabled {
+            // validated, but never matched
+            AutomataBuilder::check_pattern(&needle).map_err(|_| SyntaxError::Rule)?;
+        } else if let Some(reuse) = &mut self.reuse {
+            (reuse.add(&mut self.automata, needle, false, &rule, rule_index)).map_err(|_| SyntaxError::Rule)?;
+        } else {
+            (self.automata)
+                .add_pattern(needle, rule.is_negative(), rule_index)
+                .map_err(|_| SyntaxError::Rule)?;
+        }
+        self.rules.push(rule);
+        Ok(self)
+    }
+
+    /// Renders the patterns compiled so far, see [`GfwList::expand_rule`].
+    pub(crate) fn compiled_patterns(&self) -> Vec<String> {
+        let mut output: Vec<String> = (self.regex_patterns.iter())
+            .map(|(regex, index)| {
+                let prefix = if self.rules[*index].is_negative() { "@@" } else { "" };
+                format!("{prefix}/{}/", regex.as_str())
+            })
+            .collect();
+        for (negative, needle) in self.automata.patterns() {
+            let prefix = if negative { "@@" } else { "" };
+            output.push(format!("{prefix}{}", encode::display(needle)));
+        }
+        output
+    }
+
+    /// Returns the number of rules added so far.
+    pub fn len(&self) -> usize {
+        self.rules.len()
+    }
+
+    /// Checks if no rules have been added so far.
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+
+    /// Compiles the added rules into a [`GfwList`] behind an [`Arc`], see [`GfwList::build_shared`].
+    pub fn build_shared(self) -> Result<Arc<GfwList>, BuildError> {
+        self.build().map(Arc::new)
+    }
+
+    /// Compiles the added rules into a [`GfwList`].
+    pub fn build(mut self) -> Result<GfwList, BuildError> {
+        self.check_cancelled()?;
+        if let Some(reuse) = &mut self.reuse {
+            reuse.finish(&mut self.automata);
+        }
+        self.check_limits()?;
+        let mut automata = std::mem::take(&mut self.automata)
+            .build()
+            .map_err(BuildError::AhoCorasick)?;
+        if let Some(reuse) = &self.reuse {
+            reuse.apply(&mut automata);
+        }
+        self.check_cancelled()?;
+        let regex_prefilter = match self.regex_prefilter {
+            true => Some(RegexPrefilter::new(self.regex_patterns.iter().map(|(regex, _)| regex))?),
+            false => None,
+        };
+        move_to_arena(&mut self.rules);
+        let important_rules = (self.rules.iter().enumerate())
+            .filter(|(_, rule)| rule.options().is_some_and(|options| options.important))
+            .map(|(index, _)| index)
+            .collect();
+        let contextual_rules = (self.rules.iter().enumerate())
+            .filter(|(_, rule)| rule.options().is_some_and(RuleOptions::needs_context))
+            .map(|(index, _)| index)
+            .collect();
+        let rule_ports = (self.rules.iter().enumerate())
+            .filter_map(|(index, rule)| Some((index, rule.pattern().port()?)))
+            .collect();
+        let list = GfwList {
+            automata,
+            rules: self.rules,
+            regex_patterns: self.regex_patterns,
+            regex_prefilter,
+            important_rules,
+            contextual_rules,
+            ip_rules: self.ip_rules,
+            rule_ports,
+            staged: None,
+            disabled_sections: self.disabled_sections,
+            regex_policy: self.regex_policy,
+            ignore_query: self.ignore_query,
+            hits: None,
+            expectations: self.expectations,
+            metadata: self.metadata,
+            schemes: self.schemes,
+            domain_index: OnceLock::new(),
+        };
+        self.limits.check(Resource::Memory, list.memory_usage())?;
+        if self.verify_expectations
+            && let Some(failure) = list.verify_expectations().first()
+        {
+            return Err(BuildError::Expectation {
+                line: failure.line,
+                url: failure.url.clone(),
+            });
+        }
+        Ok(list)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn add_rules_errors() {
+        let mut builder = GfwListBuilder::new();
+        builder.add_rules("||example.com").unwrap();
+        assert!(matches!(
+            builder.add_rules("! comment\n/abc"),
+            Err(BuildError::Syntax {
+                line: 2,
+                offset: 10,
+                error: SyntaxError::Rule,
+            })
+        ));
+        assert!(matches!(builder.add_rule("/(/"), Err(SyntaxError::Regex(_))));
+        assert!(matches!(builder.add_rule("|not a url"), Err(SyntaxError::Url(_))));
+        assert_eq!(builder.len(), 1);
+    }
+
+    #[test]
+    fn lenient_conditions() {
+        let mut builder = GfwListBuilder::new();
+        let diagnostics = builder.add_rules_lenient("!#if adguard\n||a.com\n!#endif\n!#else\n!#if (b\n||b.com\n!#if c");
+        let lines: Vec<_> = (diagnostics.iter())
+            .map(|diagnostic| (diagnostic.line, diagnostic.text.as_str()))
+            .collect();
+        assert_eq!(lines, [(4, "!#else"), (5, "!#if (b"), (7, "!#if c")]);
+        let gfw = builder.build().unwrap();
+        assert!(gfw.test("http://a.com").unwrap().is_none());
+        assert!(gfw.test("http://b.com").unwrap().is_some());
+        assert!(GfwList::lint("!#if a\n||a.com\n!#else\n||a.com\n!#endif").is_empty());
+    }
+
+    #[test]
+    fn add_bulk() {
+        let mut builder = GfwListBuilder::new();
+        builder
+            .add_bulk(b"\n!#if never\n||a.com\n!#endif\n\n@@||b.a.com\n")
+            .unwrap();
+        assert_eq!(builder.len(), 2);
+        assert!(matches!(
+            builder.add_bulk(b"||c.com\n/abc"),
+            Err(BuildError::Syntax { line: 2, offset: 8, .. })
+        ));
+        let error = builder.add_bulk(b"! \xff\n||d.com\n||\xff.com").unwrap_err();
+        assert!(matches!(error, BuildError::Io(error) if error.kind() == std::io::ErrorKind::InvalidData));
+        assert_eq!(builder.len(), 4);
+    }
+
+    #[test]
+    fn limits() {
+        let limits = |limits| {
+            let mut builder = GfwListBuilder::new();
+            builder
+                .limits(limits)
+                .add_rule("||a.com")
+                .unwrap()
+                .add_rule("||b.com")
+                .unwrap();
+            builder.build()
+        };
+        let max_rules = Limits {
+            max_rules: Some(1),
+            ..Limits::default()
+        };
+        assert!(matches!(
+            limits(max_rules),
+            Err(BuildError::Limit {
+                resource: Resource::Rules,
+                ..
+            })
+        ));
+        let max_memory = Limits {
+            max_memory: Some(64),
+            ..Limits::default()
+        };
+        assert!(matches!(
+            limits(max_memory),
+            Err(BuildError::Limit {
+                resource: Resource::Memory,
+                ..
+            })
+        ));
+        assert!(limits(Limits::default()).is_ok());
+    }
+
+    #[test]
+    fn long_lines() {
+        let limits = Limits {
+            max_line_length: Some(64),
+            ..Limits::default()
+        };
+        let huge = format!("||{}.com", "a".repeat(1 << 22));
+        let input = format!("||a.com\n{huge}\n||b.com\n/{}/", "é".repeat(40));
+        let mut builder = GfwListBuilder::new();
+        builder.limits(limits);
+        assert!(matches!(
+            builder.add_rules(&input),
+            Err(BuildError::Syntax {
+                line: 2,
+                offset: 8,
+                error: SyntaxError::LineTooLong { limit: 64, .. },
+            })
+        ));
+        assert!(matches!(builder.add_rule(&huge), Err(SyntaxError::LineTooLong { .. })));
+        assert!(builder.add_bulk(huge.as_bytes()).is_err());
+        let mut builder = GfwListBuilder::new();
+        let diagnostics = builder.limits(limits).add_rules_lenient(&input);
+        assert_eq!(
+            diagnostics.iter().map(|diagnostic| diagnostic.line).collect::<Vec<_>>(),
+            [2, 4]
+        );
+        assert_eq!((diagnostics[0].text.len(), diagnostics[1].text.len()), (64, 63));
+        assert_eq!(builder.len(), 2);
+        // lines decoded from inline base64 chunks are limited as well
+        let encoded = crate::base64::encode(huge.as_bytes());
+        let input = format!("!#include-base64-begin\n{encoded}\n!#include-base64-end");
+        assert_eq!(GfwListBuilder::new().limits(limits).add_rules_lenient(&input).len(), 1);
+    }
+
+    #[test]
+    fn includes() {
+        let dir = std::env::temp_dir().join(format!("gfwlist-includes-{}", std::process::id()));
+        fs::create_dir_all(dir.join("user")).unwrap();
+        fs::write(dir.join("main.txt"), "||a.com\n!#include user/rules.txt").unwrap();
+        fs::write(dir.join("user/rules.txt"), "||b.com\n!#include ../other.txt").unwrap();
+        fs::write(dir.join("other.txt"), "@@||c.b.com").unwrap();
+        let gfw = GfwList::from_file(dir.join("main.txt")).unwrap();
+        assert_eq!(gfw.len(), 3);
+
+        fs::write(dir.join("other.txt"), "!#include main.txt").unwrap();
+        let error = GfwList::from_file(dir.join("main.txt")).unwrap_err();
+        assert!(matches!(error, BuildError::IncludeCycle(path) if path.ends_with("main.txt")));
+
+        let mut builder = GfwListBuilder::new();
+        builder.include_root(dir.join("user"));
+        let error = builder.add_file(dir.join("user/rules.txt")).unwrap_err();
+        assert!(matches!(error, BuildError::IncludeOutsideRoot(_)));
+
+        fs::write(dir.join("other.txt"), "/(/").unwrap();
+        let error = GfwList::from_file(dir.join("main.txt")).unwrap_err();
+        assert!(matches!(&error, BuildError::Source(path, _) if path.ends_with("rules.txt")));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn include_resolver() {
+        let mut builder = GfwListBuilder::new();
+        builder.include_resolver(|from: Option<&str>, target: &str| {
+            let name = format!("{}/{target}", from.unwrap_or("lists"));
+            match target {
+                "a.txt" => Ok((name, "||a.com\n!#include b.txt".to_string())),
+                "b.txt" => Ok(("lists/a.txt".to_string(), "!#include a.txt".to_string())),
+                _ => Err(BuildError::Io(std::io::ErrorKind::NotFound.into())),
+            }
+        });
+        let error = builder.add_rules("!#include a.txt").unwrap_err();
+        assert!(matches!(error, BuildError::IncludeCycle(path) if path.ends_with("lists/a.txt")));
+        let error = builder.add_rules("||c.com\n!#include missing.txt").unwrap_err();
+        assert!(
+            matches!(error, BuildError::Source(name, error) if name == "missing.txt" && matches!(*error, BuildError::Io(_)))
+        );
+    }
+
+    #[test]
+    fn cancelled() {
+        let handle = BuildHandle::new();
+        let mut builder = GfwListBuilder::new();
+        builder.cancel_on(&handle);
+        handle.clone().cancel();
+        assert!(matches!(builder.add_rules("||example.com"), Err(BuildError::Cancelled)));
+        assert!(builder.add_rules_lenient("/abc").is_empty());
+        assert!(builder.is_empty());
+    }
+}
@@ -0,0 +1,403 @@
+//! Decision caching for repeated lookups.
+//!
+//! [`CachedGfwList`] memoizes decisions per registrable domain, so that `a.example.com` and `b.example.com` share one
+//! cache entry. This is only correct when every host under the domain gets the same decision, so the rules are
+//! indexed when the cache is created and domains affected by path, port, scheme or exact-host rules are never cached.
+//! Regular expression rules may depend on any part of the URL and are always evaluated before the cache is consulted.
+
+use std::collections::{HashMap, HashSet};
+use std::sync::{PoisonError, RwLock};
+
+use aho_corasick::{AhoCorasick, Anchored, Input, StartKind};
+use url::Url;
+
+use crate::{BuildError, Decision, GfwList, Pattern, RegexPolicy, Rule, SyntaxError, Verdict};
+
+/// Second-level labels under which country-code domains are registered, such as `example.com.cn` or `example.co.uk`.
+const SECOND_LEVEL_LABELS: [&str; 7] = ["ac", "co", "com", "edu", "gov", "net", "org"];
+
+/// Approximates the registrable domain of a host without a public suffix list.
+///
+/// The registrable domain consists of the last two labels, or the last three if the host ends with a two-letter
+/// country code preceded by a common second-level label. IP addresses are returned as is.
+pub(crate) fn registrable_domain(host: &str) -> &str {
+    if host.starts_with('[') || host.parse::<std::net::IpAddr>().is_ok() {
+        return host;
+    }
+    let mut labels = host.rsplit('.');
+    let tld = labels.next().unwrap_or_default();
+    let sld = labels.next();
+    let count = match sld {
+        Some(sld) if tld.len() == 2 && SECOND_LEVEL_LABELS.contains(&sld) => 3,
+        _ => 2,
+    };
+    match host.rmatch_indices('.').nth(count - 1) {
+        Some((pos, _)) => &host[pos + 1..],
+        None => host,
+    }
+}
+
+/// Iterates over a host and all its parent domains, such as `a.example.com`, `example.com` and `com`.
+fn host_suffixes(host: &str) -> impl Iterator<Item = &str> {
+    std::iter::once(host).chain(host.match_indices('.').map(|(pos, _)| &host[pos + 1..]))
+}
+
+/// Which registrable domains can be cached, computed from the rules of a list.
+#[derive(Debug)]
+struct CacheIndex {
+    /// Set when a rule has no host, so that no domain can be cached safely
+    disabled: bool,
+    /// Registrable domains with rules that do not apply uniformly to all their hosts
+    non_uniform: HashSet<String>,
+    /// Hosts of path or port rules that also apply to their subdomains
+    partial_domains: HashSet<String>,
+    /// Host prefixes of URL rules, which depend on the scheme and are not anchored at the end of the host, unless the
+    /// cache is disabled
+    url_hosts: Option<AhoCorasick>,
+}
+
+impl CacheIndex {
+    fn new(list: &GfwList) -> Self {
+        let mut disabled = false;
+        let mut non_uniform = HashSet::new();
+        let mut partial_domains = HashSet::new();
+        let mut url_hosts = vec![];
+        for rule in list.iter_rules() {
+            let pattern = rule.pattern();
+            match pattern {
+                // IP rules only match hosts that are IP addresses, which are their own registrable domain
+                Pattern::Cidr(_) => continue,
+                Pattern::Regex(_) => {
+                    // approximated regex rules are matched by the automata on any part of the URL
+                    disabled |= list.regex_policy == RegexPolicy::Approximate;
+                    continue;
+                }
+                Pattern::Domain(_) | Pattern::Host(_) | Pattern::Url(_) => {}
+            }
+            // wildcards in the host may match any domain
+            let Some(host) = pattern.host().filter(|host| !host.is_empty() && !host.contains('*')) else {
+                disabled = true;
+                continue;
+            };
+            if let Pattern::Url(_) = pattern {
+                url_hosts.push(host);
+                continue;
+            }
+            let domain = registrable_domain(&host).to_string();
+            let partial = pattern.path().is_some() || pattern.port().is_some();
+            let subdomains = matches!(pattern, Pattern::Domain(_));
+            if partial || !subdomains || host != domain {
+                non_uniform.insert(domain);
+            }
+            if partial && subdomains {
+                partial_domains.insert(host);
+            }
+        }
+        // an index that cannot be built disables the cache instead of failing the list
+        let url_hosts = AhoCorasick::builder()
+            .start_kind(StartKind::Anchored)
+            .build(url_hosts)
+            .ok();
+        CacheIndex {
+            disabled: disabled || url_hosts.is_none(),
+            non_uniform,
+            partial_domains,
+            url_hosts,
+        }
+    }
+
+    fn is_cacheable(&self, host: &str) -> bool {
+        let Some(url_hosts) = self.url_hosts.as_ref().filter(|_| !self.disabled) else {
+            return false;
+        };
+        if url_hosts.is_match(Input::new(host).anchored(Anchored::Yes)) {
+            return false;
+        }
+        let domain = registrable_domain(host);
+        !self.non_uniform.contains(domain) && !host_suffixes(domain).any(|suffix| self.partial_domains.contains(suffix))
+    }
+}
+
+/// `CachedGfwList` wraps a [`GfwList`] with a cache of decisions keyed by registrable domain.
+///
+/// The cache holds at most `capacity` entries and is cleared when it is full. Decisions are always the same as those
+/// of [`GfwList::test`].
+///
+/// # Examples
+///
+/// ```
+/// # use gfwlist::{GfwList, cache::CachedGfwList};
+/// let gfw = GfwList::from("||blocked-site.com\n||example.com/private").unwrap();
+/// let cached = CachedGfwList::new(gfw, 1024);
+/// assert_eq!(cached.test("http://www.blocked-site.com/").unwrap(), Some("||blocked-site.com"));
+/// assert_eq!(cached.test("http://blocked-site.com/page").unwrap(), Some("||blocked-site.com"));
+/// assert_eq!(cached.len(), 1);
+///
+/// // domains with path rules are not cached
+/// assert_eq!(cached.test("http://example.com/private").unwrap(), Some("||example.com/private"));
+/// assert_eq!(cached.test("http://example.com/public").unwrap(), None);
+/// assert_eq!(cached.len(), 1);
+/// ```
+#[derive(Debug)]
+pub struct CachedGfwList {
+    list: GfwList,
+    index: CacheIndex,
+    cache: RwLock<HashMap<String, Verdict>>,
+    capacity: usize,
+}
+
+impl CachedGfwList {
+    /// Wraps a list, indexing its rules to find which domains can be cached.
+    pub fn new(list: GfwList, capacity: usize) -> Self {
+        CachedGfwList {
+            index: CacheIndex::new(&list),
+            list,
+            cache: RwLock::new(HashMap::new()),
+            capacity,
+        }
+    }
+
+    /// Returns the wrapped list.
+    pub fn list(&self) -> &GfwList {
+        &self.list
+    }
+
+    /// Unwraps the list, dropping the cache.
+    pub fn into_inner(self) -> GfwList {
+        self.list
+    }
+
+    /// Tests whether a URL matches any rule, see [`GfwList::test`].
+    pub fn test(&self, input: &str) -> Result<Option<&str>, url::ParseError> {
+        Ok(self.decide(input)?.blocked_by().map(Rule::text))
+    }
+
+    /// Decides whether a URL is blocked, see [`GfwList::decide`].
+    pub fn decide(&self, input: &str) -> Result<Decision<'_>, url::ParseError> {
+        let url = Url::parse(input);
+        if let Some(verdict) = self.list.test_important(input, url.as_ref().ok())? {
+            return Ok(verdict.resolve(&self.list));
+        }
+        if let Some(verdict) = self.list.test_regex(input) {
+            return Ok(verdict.resolve(&self.list));
+        }
+        let url = url?;
+        let host = url.host_str().ok_or(url::ParseError::EmptyHost)?;
+        if !self.index.is_cacheable(host) {
+            return Ok(self.list.test_automata(&url)?.resolve(&self.list));
+        }
+        let key = registrable_domain(host);
+        let cached = self
+            .cache
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .get(key)
+            .copied();
+        let decision = match cached {
+            Some(decision) => decision,
+            None => {
+                let decision = self.list.test_automata(&url)?;
+                let mut cache = self.cache.write().unwrap_or_else(PoisonError::into_inner);
+                if cache.len() >= self.capacity {
+                    cache.clear();
+                }
+                cache.insert(key.to_string(), decision);
+                decision
+            }
+        };
+        Ok(decision.resolve(&self.list))
+    }
+
+    /// Returns whether decisions for a host are cached.
+    pub fn is_cacheable(&self, host: &str) -> bool {
+        self.index.is_cacheable(host)
+    }
+
+    /// Returns the number of cached decisions.
+    pub fn len(&self) -> usize {
+        self.cache.read().unwrap_or_else(PoisonError::into_inner).len()
+    }
+
+    /// Checks if the cache is empty.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Removes all cached decisions.
+    pub fn clear(&self) {
+        self.cache.write().unwrap_or_else(PoisonError::into_inner).clear();
+    }
+
+    /// Stages a rule to be added by the next [`commit`](Self::commit), see [`GfwList::add_rule`].
+    pub fn add_rule(&mut self, line: &str) -> Result<(), SyntaxError> {
+        self.list.add_rule(line)
+    }
+
+    /// Stages the removal of all rules with the given text, see [`GfwList::remove_rule`].
+    pub fn remove_rule(&mut self, text: &str) -> bool {
+        self.list.remove_rule(text)
+    }
+
+    /// Applies the staged changes to the list, see [`GfwList::commit`].
+    ///
+    /// Only the cached decisions of domains overlapping the hosts of added or removed rules are invalidated, so that
+    /// small edits keep the cache warm.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use gfwlist::{GfwList, cache::CachedGfwList};
+    /// let mut cached = CachedGfwList::new(GfwList::from("||blocked-site.com").unwrap(), 1024);
+    /// cached.test("http://blocked-site.com/").unwrap();
+    /// cached.test("http://example.com/").unwrap();
+    /// cached.add_rule("@@||www.blocked-site.com").unwrap();
+    /// cached.commit().unwrap();
+    /// assert_eq!(cached.len(), 1);
+    /// assert_eq!(cached.test("http://www.blocked-site.com/").unwrap(), None);
+    /// ```
+    pub fn commit(&mut self) -> Result<(), BuildError> {
+        if !self.list.has_staged_changes() {
+            return self.list.commit();
+        }
+        let old_rules: Vec<Rule> = self.list.iter_rules().cloned().collect();
+        self.list.commit()?;
+        self.index = CacheIndex::new(&self.list);
+        let cache = self.cache.get_mut().unwrap_or_else(PoisonError::into_inner);
+
+        // collect the hosts of changed rules, giving up on rules without a host
+        let old_set: HashSet<&Rule> = old_rules.iter().collect();
+        let new_set: HashSet<&Rule> = self.list.iter_rules().collect();
+        let mut hosts = vec![];
+        for rule in old_set.symmetric_difference(&new_set) {
+            if matches!(rule.pattern(), Pattern::Regex(_)) {
+                // regex rules are evaluated before the cache is consulted
+                continue;
+            }
+            match rule.pattern().host().filter(|host| !host.is_empty()) {
+                Some(host) => hosts.push(host.to_ascii_lowercase()),
+                None => {
+                    cache.clear();
+                    return Ok(());
+                }
+            }
+        }
+        if self.index.disabled {
+            cache.clear();
+            return Ok(());
+        }
+
+        // cached verdicts refer to rules by index, which changes with the rules before them
+        let mut new_indices = HashMap::new();
+        for (index, rule) in self.list.iter_rules().enumerate() {
+            new_indices.entry(rule).or_insert(index);
+        }
+        let overlaps = |domain: &str, host: &str| {
+            let is_subdomain = |a: &str, b: &str| a.strip_suffix(b).is_some_and(|rest| rest.ends_with('.'));
+            domain == host || is_subdomain(domain, host) || is_subdomain(host, domain)
+        };
+        cache.retain(|domain, verdict| {
+            if hosts.iter().any(|host| overlaps(domain, host)) {
+                return false;
+            }
+            let remapped = match *verdict {
+                Verdict::Blocked(index) => new_indices.get(&old_rules[index]).map(|&index| Verdict::Blocked(index)),
+                Verdict::Allowed(index) => new_indices.get(&old_rules[index]).map(|&index| Verdict::Allowed(index)),
+                Verdict::NoMatch => Some(Verdict::NoMatch),
+            };
+            match remapped {
+                Some(remapped) => {
+                    *verdict = remapped;
+                    true
+                }
+                None => false,
+            }
+        });
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn registrable_domains() {
+        assert_eq!(registrable_domain("www.example.com"), "example.com");
+        assert_eq!(registrable_domain("a.b.example.co.uk"), "example.co.uk");
+        assert_eq!(registrable_domain("com"), "com");
+        assert_eq!(registrable_domain("1.2.3.4"), "1.2.3.4");
+    }
+
+    #[test]
+    fn same_decisions() {
+        let gfw = GfwList::from(
+            "||example.com\n@@||direct.example.com\nexample.org\n||path.net/a\n|http://prefix.io\n||net/b\n/regex\\.info/",
+        )
+        .unwrap();
+        let cached = CachedGfwList::new(
+            GfwList::from(&gfw.iter_rules().map(|r| r.text()).collect::<Vec<_>>().join("\n")).unwrap(),
+            16,
+        );
+        for url in [
+            "http://example.com/",
+            "http://www.example.com/",
+            "http://direct.example.com/",
+            "http://example.org/",
+            "http://www.example.org/",
+            "http://path.net/a",
+            "http://www.path.net/b",
+            "http://other.net/b",
+            "http://prefix.io.cn/",
+            "https://prefix.io/",
+            "http://regex.info/",
+        ] {
+            // test twice to hit the cache
+            assert_eq!(cached.test(url).unwrap(), gfw.test(url).unwrap(), "{url}");
+            assert_eq!(cached.test(url).unwrap(), gfw.test(url).unwrap(), "{url}");
+        }
+        assert!(!cached.is_cacheable("www.example.com"));
+        assert!(!cached.is_cacheable("other.net"));
+        assert!(cached.is_cacheable("example.info"));
+    }
+
+    #[test]
+    fn incremental_invalidation() {
+        let mut cached = CachedGfwList::new(GfwList::from("||a.com\n||b.com\n||c.com").unwrap(), 16);
+        for url in ["http://a.com/", "http://b.com/", "http://c.com/", "http://d.com/"] {
+            cached.test(url).unwrap();
+        }
+        assert!(cached.remove_rule("||a.com"));
+        cached.add_rule("||www.d.com").unwrap();
+        cached.commit().unwrap();
+        assert_eq!(cached.len(), 2);
+        assert_eq!(cached.test("http://c.com/").unwrap(), Some("||c.com"));
+        assert_eq!(cached.test("http://a.com/").unwrap(), None);
+        assert_eq!(cached.test("http://www.d.com/").unwrap(), Some("||www.d.com"));
+    }
+
+    #[test]
+    fn port_rules() {
+        let source = "||example.net:8443^\n||com:8080";
+        let gfw = GfwList::from(source).unwrap();
+        for urls in [
+            [
+                "http://example.net:8443/",
+                "http://example.net/",
+                "http://a.com:8080/",
+                "http://a.com/",
+            ],
+            [
+                "http://example.net/",
+                "http://example.net:8443/",
+                "http://a.com/",
+                "http://a.com:8080/",
+            ],
+        ] {
+            let cached = CachedGfwList::new(GfwList::from(source).unwrap(), 16);
+            for url in urls {
+                assert_eq!(cached.test(url).unwrap(), gfw.test(url).unwrap(), "{url}");
+            }
+        }
+        assert!(!CachedGfwList::new(gfw, 16).is_cacheable("www.example.net"));
+    }
+}
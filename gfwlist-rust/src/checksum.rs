@@ -0,0 +1,101 @@
+//! `! Checksum:` comments as embedded by Adblock Plus list tooling.
+//!
+//! The checksum is the base64-encoded MD5 digest, without padding, of the list with the checksum line removed,
+//! carriage returns removed and consecutive line breaks collapsed into one.
+
+use crate::base64;
+
+/// Returns the checksum declared in the input, if any.
+pub(crate) fn declared(input: &str) -> Option<&str> {
+    input.lines().find_map(checksum_line)
+}
+
+/// Computes the checksum of the input, ignoring its checksum line.
+pub(crate) fn compute(input: &str) -> String {
+    let mut lines = input.split_inclusive('\n');
+    let mut data = String::with_capacity(input.len());
+    for line in lines.by_ref() {
+        if checksum_line(line).is_some() {
+            break;
+        }
+        data.push_str(line);
+    }
+    lines.for_each(|line| data.push_str(line));
+    data.retain(|c| c != '\r');
+    let mut previous = None;
+    data.retain(|c| {
+        let duplicate = c == '\n' && previous == Some('\n');
+        previous = Some(c);
+        !duplicate
+    });
+    base64::encode(&md5(data.as_bytes()))
+}
+
+/// Returns the value of a line like `! Checksum: FM7GyTqYKuCGXtkW+9g/Lw`.
+fn checksum_line(line: &str) -> Option<&str> {
+    let rest = line.trim_start().strip_prefix('!')?.trim_start();
+    let keyword = rest
+        .get(..8)
+        .filter(|keyword| keyword.eq_ignore_ascii_case("checksum"))?;
+    let value = rest[keyword.len()..].trim_start_matches(|c: char| c.is_whitespace() || c == '-' || c == ':');
+    let end = value
+        .find(|c: char| !(c.is_ascii_alphanumeric() || matches!(c, '_' | '+' | '/' | '=')))
+        .unwrap_or(value.len());
+    Some(&value[..end]).filter(|value| !value.is_empty())
+}
+
+/// Computes the MD5 digest of the data.
+fn md5(data: &[u8]) -> [u8; 16] {
+    const SHIFTS: [u32; 16] = [7, 12, 17, 22, 5, 9, 14, 20, 4, 11, 16, 23, 6, 10, 15, 21];
+    let constants: [u32; 64] = std::array::from_fn(|i| ((i as f64 + 1.0).sin().abs() * 4294967296.0) as u32);
+    let mut state: [u32; 4] = [0x67452301, 0xefcdab89, 0x98badcfe, 0x10325476];
+
+    let mut message = data.to_vec();
+    message.push(0x80);
+    while message.len() % 64 != 56 {
+        message.push(0);
+    }
+    message.extend_from_slice(&((data.len() as u64).wrapping_mul(8)).to_le_bytes());
+
+    for block in message.chunks(64) {
+        let words: [u32; 16] = std::array::from_fn(|i| u32::from_le_bytes(block[i * 4..i * 4 + 4].try_into().unwrap()));
+        let [mut a, mut b, mut c, mut d] = state;
+        for i in 0..64 {
+            let (f, g) = match i / 16 {
+                0 => ((b & c) | (!b & d), i),
+                1 => ((d & b) | (!d & c), (5 * i + 1) % 16),
+                2 => (b ^ c ^ d, (3 * i + 5) % 16),
+                _ => (c ^ (b | !d), (7 * i) % 16),
+            };
+            let rotated = (a.wrapping_add(f).wrapping_add(constants[i]).wrapping_add(words[g]))
+                .rotate_left(SHIFTS[i / 16 * 4 + i % 4]);
+            (a, b, c, d) = (d, b.wrapping_add(rotated), b, c);
+        }
+        for (state, value) in state.iter_mut().zip([a, b, c, d]) {
+            *state = state.wrapping_add(value);
+        }
+    }
+    let mut digest = [0; 16];
+    for (chunk, value) in digest.chunks_mut(4).zip(state) {
+        chunk.copy_from_slice(&value.to_le_bytes());
+    }
+    digest
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn normalized_digest() {
+        assert_eq!(base64::encode(&md5(b"")), "1B2M2Y8AsgTpgAmY7PhCfg");
+        assert_eq!(
+            base64::encode(&md5(b"The quick brown fox jumps over the lazy dog")),
+            "nhB9nTcrtoJr2B01QqQZ1g"
+        );
+        let list = "[Adblock]\r\n! Checksum: abc\n||a.com\n\n\n||b.com\n";
+        assert_eq!(declared(list), Some("abc"));
+        assert_eq!(compute(list), compute("[Adblock]\n||a.com\n||b.com\n"));
+        assert_eq!(compute(list), base64::encode(&md5(b"[Adblock]\n||a.com\n||b.com\n")));
+    }
+}
@@ -0,0 +1,165 @@
+//! AdGuard-style `!#if` conditional directives.
+
+use std::collections::HashSet;
+
+use crate::SyntaxError;
+
+/// The directive starting a conditional block
+const IF: &str = "!#if";
+/// The directive switching to the other branch of a conditional block
+const ELSE: &str = "!#else";
+/// The directive ending a conditional block
+const ENDIF: &str = "!#endif";
+/// The deepest nesting of parentheses in a condition, so that crafted lines cannot overflow the stack
+const MAX_DEPTH: usize = 64;
+
+/// Tracks the open conditional blocks of a list while its lines are read in order.
+#[derive(Debug, Default)]
+pub(crate) struct Conditions {
+    /// The line and offset of each open `!#if`, whether its current branch is taken, and whether `!#else` was seen
+    open: Vec<((usize, usize), bool, bool)>,
+}
+
+impl Conditions {
+    /// Handles a line, returning whether it is a directive.
+    pub(crate) fn directive(
+        &mut self,
+        position: (usize, usize),
+        text: &str,
+        defined: &HashSet<String>,
+    ) -> Result<bool, SyntaxError> {
+        let text = text.trim_end();
+        if let Some(condition) = text.strip_prefix(IF).filter(|rest| rest.starts_with([' ', '\t'])) {
+            let value = self.is_active() && evaluate(condition, defined)?;
+            self.open.push((position, value, false));
+        } else if text == ELSE {
+            let (last, enclosing) = self.open.split_last_mut().ok_or(SyntaxError::Directive)?;
+            if last.2 {
+                return Err(SyntaxError::Directive);
+            }
+            last.1 = !last.1 && enclosing.iter().all(|(_, value, _)| *value);
+            last.2 = true;
+        } else if text == ENDIF {
+            self.open.pop().ok_or(SyntaxError::Directive)?;
+        } else {
+            return Ok(false);
+        }
+        Ok(true)
+    }
+
+    /// Returns whether lines at the current position are included.
+    pub(crate) fn is_active(&self) -> bool {
+        self.open.iter().all(|(_, value, _)| *value)
+    }
+
+    /// Returns the line and offset of the first `!#if` that is not closed at the end of the input.
+    pub(crate) fn unclosed(&self) -> Option<(usize, usize)> {
+        self.open.first().map(|(position, _, _)| *position)
+    }
+}
+
+/// Evaluates a condition such as `router && !(windows || mac)`.
+fn evaluate(condition: &str, defined: &HashSet<String>) -> Result<bool, SyntaxError> {
+    let mut tokens = tokenize(condition)?.into_iter().peekable();
+    let value = or(&mut tokens, defined, 0)?;
+    match tokens.next() {
+        None => Ok(value),
+        Some(_) => Err(SyntaxError::Directive),
+    }
+}
+
+type Tokens<'a> = std::iter::Peekable<std::vec::IntoIter<&'a str>>;
+
+fn tokenize(condition: &str) -> Result<Vec<&str>, SyntaxError> {
+    let mut tokens = vec![];
+    let mut rest = condition.trim_start();
+    while !rest.is_empty() {
+        let len = match rest {
+            _ if rest.starts_with("&&") || rest.starts_with("||") => 2,
+            _ if rest.starts_with(['!', '(', ')']) => 1,
+            _ => rest
+                .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+                .unwrap_or(rest.len()),
+        };
+        if len == 0 {
+            return Err(SyntaxError::Directive);
+        }
+        tokens.push(&rest[..len]);
+        rest = rest[len..].trim_start();
+    }
+    Ok(tokens)
+}
+
+fn or(tokens: &mut Tokens, defined: &HashSet<String>, depth: usize) -> Result<bool, SyntaxError> {
+    let mut value = and(tokens, defined, depth)?;
+    while tokens.next_if_eq(&"||").is_some() {
+        value |= and(tokens, defined, depth)?;
+    }
+    Ok(value)
+}
+
+fn and(tokens: &mut Tokens, defined: &HashSet<String>, depth: usize) -> Result<bool, SyntaxError> {
+    let mut value = not(tokens, defined, depth)?;
+    while tokens.next_if_eq(&"&&").is_some() {
+        value &= not(tokens, defined, depth)?;
+    }
+    Ok(value)
+}
+
+fn not(tokens: &mut Tokens, defined: &HashSet<String>, depth: usize) -> Result<bool, SyntaxError> {
+    let mut negated = false;
+    while tokens.next_if_eq(&"!").is_some() {
+        negated = !negated;
+    }
+    let value = match tokens.next().ok_or(SyntaxError::Directive)? {
+        "(" if depth < MAX_DEPTH => {
+            let value = or(tokens, defined, depth + 1)?;
+            tokens.next_if_eq(&")").ok_or(SyntaxError::Directive)?;
+            value
+        }
+        "(" | "&&" | "||" | ")" => return Err(SyntaxError::Directive),
+        symbol => defined.contains(symbol),
+    };
+    Ok(value != negated)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn nested_blocks() {
+        let defined = HashSet::from(["router".to_string()]);
+        assert!(evaluate("router && !(windows || mac)", &defined).unwrap());
+        assert!(matches!(evaluate("router &&", &defined), Err(SyntaxError::Directive)));
+        assert!(evaluate(&format!("{}router", "!".repeat(500_000)), &defined).unwrap());
+        let nested = format!("{}router{}", "(".repeat(MAX_DEPTH), ")".repeat(MAX_DEPTH));
+        assert!(evaluate(&nested, &defined).unwrap());
+        let nested = format!("{}router{}", "(".repeat(500_000), ")".repeat(500_000));
+        assert!(matches!(evaluate(&nested, &defined), Err(SyntaxError::Directive)));
+        let input = format!("!#if {}a\n||a.com\n!#endif", "(".repeat(500_000));
+        assert!(crate::GfwList::from(&input).is_err());
+        let mut conditions = Conditions::default();
+        let mut active = vec![];
+        for (line, text) in [
+            "!#if windows",
+            "!#if router",
+            "a",
+            "!#else",
+            "b",
+            "!#endif",
+            "!#else",
+            "c",
+            "!#endif",
+        ]
+        .into_iter()
+        .enumerate()
+        {
+            if !conditions.directive((line + 1, 0), text, &defined).unwrap() && conditions.is_active() {
+                active.push(text);
+            }
+        }
+        assert_eq!(active, ["c"]);
+        assert_eq!(conditions.unclosed(), None);
+    }
+}
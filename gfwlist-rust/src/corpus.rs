@@ -0,0 +1,131 @@
+//! Representative URLs for validating rules.
+//!
+//! [`generate`] produces URLs around the edges of a rule: subdomains and label boundaries of its host, other
+//! schemes, ports, and paths just inside and outside its path prefix. The URLs are classified by compiling the rule on
+//! its own, so the corpus always agrees with the matcher and shows list authors what a rule actually covers.
+
+use url::Url;
+
+use crate::{GfwList, Pattern, Rule};
+
+/// URLs that a rule does and does not match, returned by [`generate`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Corpus {
+    /// URLs matched by the rule
+    pub matching: Vec<String>,
+    /// Similar URLs not matched by the rule
+    pub non_matching: Vec<String>,
+}
+
+/// Generates representative URLs for a rule.
+///
+/// Regex rules and invalid rules produce an empty corpus, since nothing useful can be derived from them.
+///
+/// # Examples
+///
+/// ```
+/// # use gfwlist::{Rule, corpus};
+/// let corpus = corpus::generate(&Rule::parse("||example.com/video").unwrap().unwrap());
+/// assert!(corpus.matching.contains(&"https://www.example.com/video/sub".to_string()));
+/// assert!(corpus.non_matching.contains(&"http://xexample.com/video".to_string()));
+/// assert!(corpus.non_matching.contains(&"http://example.com/videox".to_string()));
+/// ```
+pub fn generate(rule: &Rule) -> Corpus {
+    let (schemes, host, path) = match rule.pattern() {
+        Pattern::Regex(_) => return Corpus::default(),
+        Pattern::Url(url) => {
+            let Ok(url) = Url::parse(url) else {
+                return Corpus::default();
+            };
+            let Some(host) = url.host_str() else {
+                return Corpus::default();
+            };
+            (vec![url.scheme().to_string()], host.to_string(), rule.pattern().path())
+        }
+        pattern => match pattern.host() {
+            Some(host) if !host.is_empty() => (vec![], host, pattern.path()),
+            _ => return Corpus::default(),
+        },
+    };
+    let Ok(list) = GfwList::from_rules([rule.clone()]) else {
+        return Corpus::default();
+    };
+
+    let mut candidates: Vec<String> = vec![];
+    let mut schemes = schemes;
+    for scheme in ["http", "https", "ws"] {
+        if !schemes.iter().any(|s| s == scheme) {
+            schemes.push(scheme.to_string());
+        }
+    }
+    let port = (rule.pattern().port())
+        .map(|port| format!(":{port}"))
+        .unwrap_or_default();
+    let mut hosts = [
+        host.clone(),
+        format!("www.{host}"),
+        format!("a.b.{host}"),
+        format!("x{host}"),
+        format!("{host}.example"),
+    ]
+    .map(|host| format!("{host}{port}"))
+    .to_vec();
+    // port rules do not match the host on the default port of the scheme
+    if !port.is_empty() {
+        hosts.push(host.clone());
+    }
+    let path = path.unwrap_or_default();
+    let paths = if path.is_empty() || path == "/" {
+        vec!["/".to_string(), "/any/path".to_string()]
+    } else {
+        let trimmed = path.trim_end_matches('/');
+        vec![
+            path.clone(),
+            format!("{trimmed}/sub"),
+            format!("{trimmed}x"),
+            "/".to_string(),
+        ]
+    };
+    for scheme in &schemes {
+        for host in &hosts {
+            for path in &paths {
+                let candidate = format!("{scheme}://{host}{path}");
+                if !candidates.contains(&candidate) {
+                    candidates.push(candidate);
+                }
+            }
+        }
+    }
+
+    let mut corpus = Corpus::default();
+    for candidate in candidates {
+        match list.decide(&candidate) {
+            Ok(decision) if decision.rule().is_some() => corpus.matching.push(candidate),
+            Ok(_) => corpus.non_matching.push(candidate),
+            Err(_) => {}
+        }
+    }
+    corpus
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn url_and_host_rules() {
+        let corpus = generate(&Rule::parse("|https://example.com").unwrap().unwrap());
+        assert!(corpus.matching.contains(&"https://example.com.example/".to_string()));
+        assert!(corpus.non_matching.contains(&"http://example.com/".to_string()));
+
+        let corpus = generate(&Rule::parse("@@example.com").unwrap().unwrap());
+        assert!(corpus.matching.contains(&"ws://example.com/any/path".to_string()));
+        assert!(corpus.non_matching.contains(&"http://www.example.com/".to_string()));
+
+        assert_eq!(generate(&Rule::parse("/regex/").unwrap().unwrap()), Corpus::default());
+
+        let corpus = generate(&Rule::parse("||example.com:8443^").unwrap().unwrap());
+        assert!(corpus.matching.contains(&"http://www.example.com:8443/".to_string()));
+        assert!(corpus.non_matching.contains(&"http://example.com/".to_string()));
+    }
+}
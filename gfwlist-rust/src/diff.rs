@@ -0,0 +1,87 @@
+//! Differences between two versions of a list.
+//!
+//! Rules are compared by their [`RuleId`](crate::RuleId), so rewriting `.example.com` as `||example.com` or moving a
+//! rule to another line is not a change. A rule whose polarity is flipped, such as `||example.com` becoming
+//! `@@||example.com`, is reported as changed rather than as a removal and an addition.
+
+use std::collections::{HashMap, HashSet};
+
+use crate::{GfwList, Rule, RuleId};
+
+/// The differences between two lists, returned by [`GfwList::diff`].
+///
+/// Each set keeps the order of the rules in their list.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Diff<'a> {
+    /// Rules of the new list that are not in the old list
+    pub added: Vec<&'a Rule>,
+    /// Rules of the old list that are not in the new list
+    pub removed: Vec<&'a Rule>,
+    /// Rules of the old list whose polarity is flipped in the new list, together with their new version
+    pub changed: Vec<(&'a Rule, &'a Rule)>,
+}
+
+impl Diff<'_> {
+    /// Returns whether the lists have the same rules.
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+    }
+}
+
+/// Identifies the pattern of a rule regardless of its polarity.
+fn pattern_id(rule: &Rule) -> RuleId {
+    rule.clone().with_negative(false).id()
+}
+
+impl GfwList {
+    /// Compares two versions of a list.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use gfwlist::GfwList;
+    /// let old = GfwList::from(".example.com\n||example.org\n||example.net").unwrap();
+    /// let new = GfwList::from("||example.com\n@@||example.org\n||example.io").unwrap();
+    /// let diff = GfwList::diff(&old, &new);
+    /// assert_eq!(diff.added.iter().map(|rule| rule.text()).collect::<Vec<_>>(), ["||example.io"]);
+    /// assert_eq!(diff.removed.iter().map(|rule| rule.text()).collect::<Vec<_>>(), ["||example.net"]);
+    /// assert_eq!(diff.changed[0].1.text(), "@@||example.org");
+    /// ```
+    pub fn diff<'a>(old: &'a GfwList, new: &'a GfwList) -> Diff<'a> {
+        let old_ids: HashSet<RuleId> = old.iter_rules().map(Rule::id).collect();
+        let new_ids: HashSet<RuleId> = new.iter_rules().map(Rule::id).collect();
+        let mut removed: Vec<&Rule> = old.iter_rules().filter(|rule| !new_ids.contains(&rule.id())).collect();
+        let mut added: Vec<&Rule> = new.iter_rules().filter(|rule| !old_ids.contains(&rule.id())).collect();
+
+        // pair removed and added rules with the same pattern
+        let added_patterns: HashMap<RuleId, &Rule> = added.iter().map(|rule| (pattern_id(rule), *rule)).collect();
+        let mut changed = vec![];
+        removed.retain(|rule| match added_patterns.get(&pattern_id(rule)) {
+            Some(new_rule) => {
+                changed.push((*rule, *new_rule));
+                false
+            }
+            None => true,
+        });
+        let changed_ids: HashSet<RuleId> = changed.iter().map(|(_, rule)| rule.id()).collect();
+        added.retain(|rule| !changed_ids.contains(&rule.id()));
+        Diff {
+            added,
+            removed,
+            changed,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn reordered_lists_are_equal() {
+        let old = GfwList::from("||a.com\n@@||b.com\n/c/").unwrap();
+        let new = GfwList::from("/c/\n@@.B.com\n||a.com").unwrap();
+        assert!(GfwList::diff(&old, &new).is_empty());
+        assert!(!GfwList::diff(&old, &GfwList::from("").unwrap()).is_empty());
+    }
+}
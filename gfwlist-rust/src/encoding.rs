@@ -0,0 +1,60 @@
+//! Lists saved in legacy encodings, enabled by the `encoding` feature.
+//!
+//! Some lists maintained in China are saved in GBK, usually because of Chinese comments, and Windows tools may save
+//! lists in UTF-16. [`GfwList::from_bytes`] detects these encodings and transcodes the list before parsing it.
+
+use std::borrow::Cow;
+
+use encoding_rs::{Encoding, GB18030};
+
+use crate::{BuildError, GfwList};
+
+/// Decodes a list into text.
+///
+/// A byte order mark selects UTF-8 or UTF-16, input without one is kept if it is valid UTF-8, and anything else is
+/// decoded as GB18030, a superset of GBK. Invalid sequences are replaced with U+FFFD.
+pub(crate) fn decode_text(input: &[u8]) -> Cow<'_, str> {
+    if let Some((encoding, bom_len)) = Encoding::for_bom(input) {
+        return encoding.decode_without_bom_handling(&input[bom_len..]).0;
+    }
+    match std::str::from_utf8(input) {
+        Ok(text) => Cow::Borrowed(text),
+        Err(_) => GB18030.decode_without_bom_handling(input).0,
+    }
+}
+
+impl GfwList {
+    /// Constructs a new `GfwList` from a list in UTF-8, UTF-16 with a byte order mark, or GBK.
+    ///
+    /// Lists that are not valid UTF-8 and have no byte order mark are decoded as GB18030, a superset of GBK. Invalid
+    /// sequences are replaced with U+FFFD, which only affects the rules and comments containing them. Lists encoded
+    /// like the official `gfwlist.txt` are decoded, see [`GfwList::from_base64`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use gfwlist::GfwList;
+    /// // "! 中文\n||blocked-site.com" in GBK
+    /// let input = b"! \xd6\xd0\xce\xc4\n||blocked-site.com";
+    /// let gfw_list = GfwList::from_bytes(input).unwrap();
+    /// assert_eq!(gfw_list.test("http://blocked-site.com").unwrap(), Some("||blocked-site.com"));
+    /// ```
+    pub fn from_bytes(input: &[u8]) -> Result<Self, BuildError> {
+        GfwList::from_downloaded(&decode_text(input))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn detect_encodings() {
+        assert_eq!(decode_text(b"\xef\xbb\xbf||a.com"), "||a.com");
+        assert_eq!(decode_text(b"\xff\xfe|\0|\0a\0"), "||a");
+        assert_eq!(decode_text("! 中文".as_bytes()), "! 中文");
+        assert_eq!(decode_text(b"! \xd6\xd0\xce\xc4"), "! 中文");
+        let gfw = GfwList::from_any(b"# \xd6\xd0\xce\xc4\nserver=/example.cn/114.114.114.114").unwrap();
+        assert!(gfw.test("http://www.example.cn").unwrap().is_some());
+    }
+}
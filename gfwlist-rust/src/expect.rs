@@ -0,0 +1,91 @@
+//! Executable expectations embedded in lists.
+//!
+//! List maintainers can annotate rules with comments asserting how a URL is decided:
+//!
+//! ```text
+//! ||blocked-site.com
+//! !#expect-block: http://www.blocked-site.com/
+//! @@||exception.blocked-site.com
+//! !#expect-allow: http://exception.blocked-site.com/
+//! ```
+//!
+//! `expect-block` asserts that the URL is blocked, and `expect-allow` that it is not. Since the annotations are
+//! comments, other tools ignore them. They are checked by [`GfwList::verify_expectations`], or at build time with
+//! [`GfwListBuilder::verify_expectations`](crate::GfwListBuilder::verify_expectations).
+
+use crate::GfwList;
+
+/// An assertion embedded in a list with `!#expect-block:` or `!#expect-allow:`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Expectation {
+    /// The 1-based line number of the annotation
+    pub line: usize,
+    /// The URL to test
+    pub url: String,
+    /// Whether the URL is expected to be blocked
+    pub blocked: bool,
+}
+
+impl Expectation {
+    /// Parses an annotation comment at a line.
+    pub(crate) fn parse(line: usize, text: &str) -> Option<Self> {
+        let (blocked, url) = if let Some(url) = text.strip_prefix("!#expect-block:") {
+            (true, url)
+        } else {
+            (false, text.strip_prefix("!#expect-allow:")?)
+        };
+        Some(Expectation {
+            line,
+            url: url.trim().to_string(),
+            blocked,
+        })
+    }
+
+    /// Returns whether the list decides the URL as expected. Invalid URLs never meet the expectation.
+    pub fn is_met(&self, list: &GfwList) -> bool {
+        list.decide(&self.url)
+            .is_ok_and(|decision| decision.is_blocked() == self.blocked)
+    }
+}
+
+impl GfwList {
+    /// Returns the expectations embedded in the list.
+    pub fn expectations(&self) -> &[Expectation] {
+        &self.expectations
+    }
+
+    /// Checks the expectations embedded in the list, returning those that are not met.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use gfwlist::GfwList;
+    /// let gfw_list = GfwList::from("||example.com\n!#expect-block: http://www.example.com/\n\
+    ///                               !#expect-allow: http://example.com/").unwrap();
+    /// let failures = gfw_list.verify_expectations();
+    /// assert_eq!(failures.len(), 1);
+    /// assert_eq!(failures[0].line, 3);
+    /// ```
+    pub fn verify_expectations(&self) -> Vec<&Expectation> {
+        (self.expectations.iter())
+            .filter(|expectation| !expectation.is_met(self))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{BuildError, GfwListBuilder};
+
+    #[test]
+    fn build_time_verification() {
+        let mut builder = GfwListBuilder::new();
+        builder.verify_expectations(true);
+        builder
+            .add_rules("||a.com\n!#expect-allow: http://b.com/\n!#expect-allow: http://a.com/")
+            .unwrap();
+        assert!(matches!(builder.build(), Err(BuildError::Expectation { line: 3, .. })));
+        assert_eq!(Expectation::parse(1, "! comment"), None);
+    }
+}
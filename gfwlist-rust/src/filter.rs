@@ -0,0 +1,201 @@
+//! Compact pre-screening filters for edge components.
+//!
+//! A [`BloomFilter`] holds the registrable domains of the positive rules of a list. Components that cannot run the
+//! full matcher, such as eBPF programs or nginx modules, can consult it first and skip the full matcher for hosts
+//! that are definitely not listed.
+//!
+//! # Layout
+//!
+//! The filter is a bit array of `m` bits probed by `k` hash functions. A domain is hashed with 64-bit FNV-1a over its
+//! lowercase bytes; with `h1` the low and `h2` the high 32 bits of that hash, the probed bits are
+//! `(h1 + i * h2) mod m` for `i` in `0..k`, computed with wrapping 64-bit arithmetic. [`BloomFilter::to_bytes`]
+//! serializes the filter as the magic `GFWB`, a version byte (`1`), `k` as one byte, `m` as a little-endian `u64`
+//! and the bit array, where bit `j` is bit `j % 8` of byte `j / 8`.
+
+use crate::cache::registrable_domain;
+use crate::{GfwList, Pattern};
+
+const MAGIC: &[u8; 4] = b"GFWB";
+const VERSION: u8 = 1;
+const HEADER_LEN: usize = 14;
+
+/// A Bloom filter of registrable domains.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BloomFilter {
+    bits: Vec<u8>,
+    num_bits: u64,
+    num_hashes: u8,
+    complete: bool,
+}
+
+pub(crate) fn fnv1a(input: &[u8]) -> u64 {
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for &byte in input {
+        hash ^= byte.to_ascii_lowercase() as u64;
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+    hash
+}
+
+impl BloomFilter {
+    /// Creates an empty filter sized for `capacity` domains at the given false-positive rate.
+    ///
+    /// The rate is clamped to the range from `1e-9` to `0.5`.
+    pub fn new(capacity: usize, false_positive_rate: f64) -> Self {
+        let rate = false_positive_rate.clamp(1e-9, 0.5);
+        let ln2 = std::f64::consts::LN_2;
+        let num_bits = (-(capacity.max(1) as f64) * rate.ln() / (ln2 * ln2)).ceil().max(64.0) as u64;
+        let num_hashes = ((num_bits as f64 / capacity.max(1) as f64) * ln2)
+            .round()
+            .clamp(1.0, 32.0) as u8;
+        BloomFilter {
+            bits: vec![0; num_bits.div_ceil(8) as usize],
+            num_bits,
+            num_hashes,
+            complete: true,
+        }
+    }
+
+    fn positions(&self, domain: &str) -> impl Iterator<Item = u64> + use<> {
+        let hash = fnv1a(domain.as_bytes());
+        let (h1, h2) = (hash & 0xffff_ffff, hash >> 32);
+        let num_bits = self.num_bits;
+        (0..self.num_hashes as u64).map(move |i| h1.wrapping_add(i.wrapping_mul(h2)) % num_bits)
+    }
+
+    /// Inserts a domain.
+    pub fn insert(&mut self, domain: &str) {
+        for pos in self.positions(domain) {
+            self.bits[(pos / 8) as usize] |= 1 << (pos % 8);
+        }
+    }
+
+    /// Returns whether a domain may have been inserted. There are no false negatives.
+    pub fn contains(&self, domain: &str) -> bool {
+        self.positions(domain)
+            .all(|pos| self.bits[(pos / 8) as usize] & (1 << (pos % 8)) != 0)
+    }
+
+    /// Returns whether the registrable domain of a host may have been inserted.
+    pub fn contains_host(&self, host: &str) -> bool {
+        self.contains(registrable_domain(host))
+    }
+
+    /// Returns whether every positive rule of the source list is covered.
+    ///
+    /// Regular expressions, rules without a host and rules covering whole top-level domains cannot be represented.
+    /// URL rules are represented by their host, although they also match longer hosts such as `example.com.cn` for
+    /// `|http://example.com`. If the filter is not complete, a negative answer does not rule out a match.
+    pub fn is_complete(&self) -> bool {
+        self.complete
+    }
+
+    /// Returns the number of bits `m`.
+    pub fn num_bits(&self) -> u64 {
+        self.num_bits
+    }
+
+    /// Returns the number of hash functions `k`.
+    pub fn num_hashes(&self) -> u8 {
+        self.num_hashes
+    }
+
+    /// Serializes the filter in the layout documented in the [module docs](self).
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut output = Vec::with_capacity(HEADER_LEN + self.bits.len());
+        output.extend(MAGIC);
+        output.push(VERSION);
+        output.push(self.num_hashes);
+        output.extend(self.num_bits.to_le_bytes());
+        output.extend(&self.bits);
+        output
+    }
+
+    /// Deserializes a filter produced by [`BloomFilter::to_bytes`], returning `None` if the input is malformed.
+    ///
+    /// The completeness flag is not serialized, so the result is assumed to be complete.
+    pub fn from_bytes(input: &[u8]) -> Option<Self> {
+        let header = input.get(..HEADER_LEN)?;
+        if &header[..4] != MAGIC || header[4] != VERSION || header[5] == 0 {
+            return None;
+        }
+        let num_bits = u64::from_le_bytes(header[6..].try_into().ok()?);
+        let bits = &input[HEADER_LEN..];
+        if num_bits == 0 || bits.len() as u64 != num_bits.div_ceil(8) {
+            return None;
+        }
+        Some(BloomFilter {
+            bits: bits.to_vec(),
+            num_bits,
+            num_hashes: header[5],
+            complete: true,
+        })
+    }
+}
+
+impl GfwList {
+    /// Builds a Bloom filter of the registrable domains of the positive rules.
+    ///
+    /// `false_positive_rate` is the probability that [`BloomFilter::contains`] returns `true` for a domain that was
+    /// not inserted, such as `0.01` for 1%.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use gfwlist::GfwList;
+    /// let gfw = GfwList::from("||blocked-site.com\n||www.blocked.org\n@@||exception.com").unwrap();
+    /// let filter = gfw.to_bloom_filter(0.001);
+    /// assert!(filter.contains_host("www.blocked-site.com"));
+    /// assert!(filter.contains_host("cdn.blocked.org"));
+    /// assert!(filter.is_complete());
+    /// ```
+    pub fn to_bloom_filter(&self, false_positive_rate: f64) -> BloomFilter {
+        let mut domains = vec![];
+        let mut complete = true;
+        for rule in self.iter_rules().filter(|rule| !rule.is_negative()) {
+            let pattern = rule.pattern();
+            match pattern.host().filter(|host| !host.is_empty() && host.contains('.')) {
+                Some(host) if !matches!(pattern, Pattern::Regex(_)) => {
+                    complete &= !matches!(pattern, Pattern::Url(_));
+                    domains.push(registrable_domain(&host).to_string());
+                }
+                _ => complete = false,
+            }
+        }
+        let mut filter = BloomFilter::new(domains.len(), false_positive_rate);
+        for domain in &domains {
+            filter.insert(domain);
+        }
+        filter.complete = complete;
+        filter
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn false_positive_rate() {
+        let domains: Vec<String> = (0..1000).map(|i| format!("domain{i}.com")).collect();
+        let mut filter = BloomFilter::new(domains.len(), 0.01);
+        for domain in &domains {
+            filter.insert(domain);
+        }
+        assert!(domains.iter().all(|domain| filter.contains(domain)));
+        let false_positives = (0..10000).filter(|i| filter.contains(&format!("other{i}.org"))).count();
+        assert!(false_positives < 300, "{false_positives}");
+    }
+
+    #[test]
+    fn bytes_round_trip() {
+        let gfw = GfwList::from("||example.com\n|http://example.org\n/regex/").unwrap();
+        let filter = gfw.to_bloom_filter(0.01);
+        assert!(!filter.is_complete());
+        let bytes = filter.to_bytes();
+        assert_eq!(&bytes[..4], b"GFWB");
+        let decoded = BloomFilter::from_bytes(&bytes).unwrap();
+        assert!(decoded.contains("example.com") && decoded.contains("example.org"));
+        assert_eq!(BloomFilter::from_bytes(&bytes[..bytes.len() - 1]), None);
+    }
+}
@@ -0,0 +1,186 @@
+//! The ACL format of [shadowsocks-rust](https://github.com/shadowsocks/shadowsocks-rust).
+//!
+//! An ACL file consists of sections such as `[proxy_list]` and `[bypass_list]`, each containing one entry per line.
+//! An entry is either an IP network, `||domain` (the domain and its subdomains), `|domain` (exactly the domain) or a
+//! regular expression tested against the host.
+//!
+//! ACLs only see hosts, so the conversion works at host level:
+//!
+//! - Path constraints are dropped when exporting, and `|http://` rules are reduced to their exact host.
+//! - GFW list regular expressions are tested against full URLs and are not exported.
+//! - IP rules become IP networks, and IP networks become IP rules.
+//! - When importing, host regexes are only kept if they spell out a plain domain such as `(^|\.)example\.com$`.
+
+use super::{Fidelity, Import, Report, cidr, unescape_domain};
+use crate::{GfwList, Origin, Pattern, Rule};
+
+/// Renders the rules of a [`GfwList`] as a shadowsocks-rust ACL.
+///
+/// The ACL bypasses everything by default, proxies the positive rules and bypasses the negative rules.
+///
+/// # Examples
+///
+/// ```
+/// # use gfwlist::{GfwList, format::acl};
+/// let gfw = GfwList::from("||blocked-site.com\n@@||exception.com").unwrap();
+/// assert_eq!(
+///     acl::export(&gfw),
+///     "[bypass_all]\n\n[proxy_list]\n||blocked-site.com\n\n[bypass_list]\n||exception.com\n",
+/// );
+/// ```
+pub fn export(list: &GfwList) -> String {
+    let mut proxy_list = String::new();
+    let mut bypass_list = String::new();
+    for rule in list.iter_rules() {
+        let Some(entry) = entry(rule.pattern()) else {
+            continue;
+        };
+        let output = if rule.is_negative() {
+            &mut bypass_list
+        } else {
+            &mut proxy_list
+        };
+        output.push_str(&entry);
+        output.push('\n');
+    }
+    format!("[bypass_all]\n\n[proxy_list]\n{proxy_list}\n[bypass_list]\n{bypass_list}")
+}
+
+fn entry(pattern: &Pattern) -> Option<String> {
+    if let Pattern::Cidr(network) = pattern {
+        return Some(network.clone());
+    }
+    let host = pattern.host().filter(|host| !host.is_empty())?;
+    match pattern {
+        Pattern::Domain(_) => Some(format!("||{host}")),
+        Pattern::Host(_) | Pattern::Url(_) => Some(format!("|{host}")),
+        Pattern::Regex(_) | Pattern::Cidr(_) => None,
+    }
+}
+
+/// Parses a shadowsocks-rust ACL into rules.
+///
+/// Entries of `[proxy_list]` (or `[black_list]`) become positive rules, and entries of `[bypass_list]` (or
+/// `[white_list]`) become negative rules. IP networks become IP rules. Entries of other sections and regexes that do
+/// not describe a plain domain are reported as dropped.
+///
+/// # Examples
+///
+/// ```
+/// # use gfwlist::format::acl;
+/// let import = acl::import("[proxy_list]\n(^|\\.)example\\.com$\n8.8.8.8/32\n[bypass_list]\n|direct.example.com\n[x]\n1.1");
+/// let rules: Vec<_> = import.rules.iter().map(|rule| rule.text()).collect();
+/// assert_eq!(rules, ["||example.com", "IP-CIDR,8.8.8.8/32", "@@direct.example.com"]);
+/// assert_eq!(import.dropped, [(7, "1.1".to_string())]);
+/// ```
+pub fn import(input: &str) -> Import {
+    let mut import = Import::default();
+    let mut negative = None;
+    for (line_index, line) in input.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        if line.starts_with('[') && line.ends_with(']') {
+            negative = match line {
+                "[proxy_list]" | "[black_list]" => Some(false),
+                "[bypass_list]" | "[white_list]" => Some(true),
+                _ => None,
+            };
+            continue;
+        }
+        match negative.zip(parse_entry(line)) {
+            Some((negative, pattern)) => import
+                .rules
+                .push(Rule::new(negative, pattern).with_origin(Origin::line(line_index + 1))),
+            None => import.dropped.push((line_index + 1, line.to_string())),
+        }
+    }
+    import
+}
+
+fn parse_entry(line: &str) -> Option<Pattern> {
+    if let Some(domain) = line.strip_prefix("||") {
+        return Some(Pattern::Domain(domain.to_string()));
+    }
+    if let Some(domain) = line.strip_prefix('|') {
+        return Some(Pattern::Host(domain.to_string()));
+    }
+    if let Some(pattern) = cidr(line) {
+        return Some(pattern);
+    }
+    let body = line.strip_suffix('$')?;
+    if let Some(body) = body.strip_prefix("(^|\\.)").or_else(|| body.strip_prefix("^(.*\\.)?")) {
+        return unescape_domain(body).map(Pattern::Domain);
+    }
+    unescape_domain(body.strip_prefix('^')?).map(Pattern::Host)
+}
+
+/// Reports how the rules of a [`GfwList`] survive a round trip through [`export`] and [`import`].
+///
+/// # Examples
+///
+/// ```
+/// # use gfwlist::{GfwList, format::acl};
+/// let gfw = GfwList::from("||example.com\n|http://example.org\n/regex/").unwrap();
+/// let report = acl::fidelity(&gfw);
+/// assert_eq!(report.lossless.len(), 1);
+/// assert_eq!(report.approximated.len(), 1);
+/// assert_eq!(report.dropped.len(), 1);
+/// ```
+pub fn fidelity(list: &GfwList) -> Report {
+    Report::new(list, |rule| match entry(rule.pattern()) {
+        None => Fidelity::Dropped,
+        Some(_) if rule.pattern().path().is_some() || matches!(rule.pattern(), Pattern::Url(_)) => {
+            Fidelity::Approximated
+        }
+        Some(_) => Fidelity::Lossless,
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn export_approximations() {
+        let gfw = GfwList::from("|http://example.com/page\nexample.org/path\n/regex/\n@@.example.net").unwrap();
+        assert_eq!(
+            export(&gfw),
+            "[bypass_all]\n\n[proxy_list]\n|example.com\n|example.org\n\n[bypass_list]\n||example.net\n",
+        );
+    }
+
+    #[test]
+    fn import_round_trip() {
+        let gfw = GfwList::from("||example.com\nwww.example.org\n@@||direct.example.com").unwrap();
+        let rules = import(&export(&gfw)).rules;
+        assert_eq!(GfwList::from_rules(rules).unwrap().iter_rules().count(), 3);
+
+        let import = import("[outbound_block_list]\n||ads.com\n[proxy_list]\n^www\\.example\\.org$\n.*foo.*\n");
+        assert_eq!(
+            import.rules,
+            [Rule::new(false, Pattern::Host("www.example.org".into()))]
+        );
+        assert_eq!(
+            import.dropped,
+            [(2, "||ads.com".to_string()), (5, ".*foo.*".to_string())]
+        );
+    }
+
+    #[test]
+    fn fidelity_round_trip() {
+        let gfw = GfwList::from("||example.com\n@@www.example.com\n||example.org/path\n|https://example.net").unwrap();
+        let report = fidelity(&gfw);
+        let imported = import(&export(&gfw)).rules;
+        for rule in &report.lossless {
+            assert!(
+                imported
+                    .iter()
+                    .any(|r| r.is_negative() == rule.is_negative() && r.pattern() == rule.pattern())
+            );
+        }
+        assert_eq!(report.approximated.len(), 2);
+        assert!(report.dropped.is_empty());
+    }
+}
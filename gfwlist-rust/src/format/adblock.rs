@@ -0,0 +1,134 @@
+//! Network filters of [adblock-rust](https://github.com/brave/adblock-rust) in the Adblock Plus syntax.
+//!
+//! GFW lists descend from the Adblock Plus syntax, so most rules carry over unchanged:
+//!
+//! - `||example.com` becomes `||example.com^`, where `^` ends the host.
+//! - `|http://example.com/path` and `/regex/` are kept as they are.
+//! - `example.com` matches exactly one host, which Adblock Plus filters cannot express, so it is approximated by
+//!   `||example.com^`, which also covers subdomains.
+//!
+//! The importer accepts filter lists such as EasyList. Cosmetic filters and filters with `$options` have no GFW list
+//! equivalent and are dropped.
+
+use super::{Fidelity, Import, Report};
+use crate::{GfwList, Origin, Pattern, Rule};
+
+/// Renders the rules of a [`GfwList`] as an Adblock Plus filter list, one network filter per line.
+///
+/// # Examples
+///
+/// ```
+/// # use gfwlist::{GfwList, format::adblock};
+/// let gfw = GfwList::from("||blocked-site.com\n|http://blocked.com/page\n/ads?\\./\n@@||exception.com").unwrap();
+/// assert_eq!(
+///     adblock::export(&gfw),
+///     "||blocked-site.com^\n|http://blocked.com/page\n/ads?\\./\n@@||exception.com^\n",
+/// );
+/// ```
+pub fn export(list: &GfwList) -> String {
+    let mut output = String::new();
+    for rule in list.iter_rules() {
+        let Some(filter) = filter(rule.pattern()) else {
+            continue;
+        };
+        if rule.is_negative() {
+            output.push_str("@@");
+        }
+        output.push_str(&filter);
+        output.push('\n');
+    }
+    output
+}
+
+fn filter(pattern: &Pattern) -> Option<String> {
+    match pattern {
+        Pattern::Cidr(_) => None,
+        Pattern::Regex(regex) => Some(format!("/{regex}/")),
+        Pattern::Url(url) => Some(format!("|{url}")),
+        Pattern::Domain(_) | Pattern::Host(_) => {
+            let host = pattern.host().filter(|host| !host.is_empty())?;
+            Some(match pattern.path() {
+                Some(path) => format!("||{host}{path}"),
+                None => format!("||{host}^"),
+            })
+        }
+    }
+}
+
+/// Parses an Adblock Plus filter list into rules.
+///
+/// Exception filters starting with `@@` become negative rules. Comments and the `[Adblock Plus]` header are skipped,
+/// and filters without a GFW list equivalent are reported as dropped.
+///
+/// # Examples
+///
+/// ```
+/// # use gfwlist::format::adblock;
+/// let import = adblock::import("[Adblock Plus 2.0]\n||ads.com^\n@@||ok.ads.com^$script\nexample.org##.ad\n");
+/// let rules: Vec<_> = import.rules.iter().map(|rule| rule.text()).collect();
+/// assert_eq!(rules, ["||ads.com"]);
+/// assert_eq!(import.dropped[0], (3, "@@||ok.ads.com^$script".to_string()));
+/// assert_eq!(import.dropped[1], (4, "example.org##.ad".to_string()));
+/// ```
+pub fn import(input: &str) -> Import {
+    let mut import = Import::default();
+    for (line_index, line) in input.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('!') || (line.starts_with('[') && line.ends_with(']')) {
+            continue;
+        }
+        let (negative, filter) = match line.strip_prefix("@@") {
+            Some(filter) => (true, filter),
+            None => (false, line),
+        };
+        match parse_filter(filter) {
+            Some(pattern) => import
+                .rules
+                .push(Rule::new(negative, pattern).with_origin(Origin::line(line_index + 1))),
+            None => import.dropped.push((line_index + 1, line.to_string())),
+        }
+    }
+    import
+}
+
+fn parse_filter(filter: &str) -> Option<Pattern> {
+    if let Some(regex) = filter.strip_prefix('/').and_then(|s| s.strip_suffix('/')) {
+        return Some(Pattern::Regex(regex.to_string()));
+    }
+    // cosmetic filters and options
+    if filter.contains('#') || filter.contains('$') {
+        return None;
+    }
+    let plain = |s: &str| !s.is_empty() && !s.contains('|');
+    if let Some(rest) = filter.strip_prefix("||") {
+        let domain = rest.strip_suffix('^').unwrap_or(rest);
+        return plain(domain).then(|| Pattern::Domain(domain.to_string()));
+    }
+    let url = filter.strip_prefix('|')?;
+    (plain(url) && url.contains("://")).then(|| Pattern::Url(url.to_string()))
+}
+
+/// Reports how the rules of a [`GfwList`] survive a round trip through [`export`] and [`import`].
+pub fn fidelity(list: &GfwList) -> Report {
+    Report::new(list, |rule| match (rule.pattern(), filter(rule.pattern())) {
+        (_, None) => Fidelity::Dropped,
+        (Pattern::Host(_), _) => Fidelity::Approximated,
+        _ => Fidelity::Lossless,
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn round_trip() {
+        let gfw = GfwList::from("||a.com\n||b.com/path\n|https://c.com/\n/d\\.com/\n@@||e.a.com\nf.com").unwrap();
+        let import = import(&export(&gfw));
+        assert!(import.dropped.is_empty());
+        let report = fidelity(&gfw);
+        assert_eq!(report.lossless, import.rules[..5]);
+        assert_eq!(report.approximated, [Rule::new(false, Pattern::Host("f.com".into()))]);
+        assert_eq!(import.rules[5], Rule::new(false, Pattern::Domain("f.com".into())));
+    }
+}
@@ -0,0 +1,169 @@
+//! DNS filtering rules of [AdGuard Home](https://github.com/AdguardTeam/AdGuardHome/wiki/Hosts-Blocklists).
+//!
+//! AdGuard Home reads a dialect of the Adblock Plus syntax that is matched against host names rather than URLs,
+//! together with lines in the format of the hosts file. Rules map onto GFW list rules as follows:
+//!
+//! - `||example.com^` becomes `||example.com`, which also covers subdomains.
+//! - `|example.com^` becomes `example.com`, which matches exactly the host.
+//! - `example` and `example.com^` match host names containing or ending with them, and become regular expressions over
+//!   the host.
+//! - `/regex/` is matched against the host name, and becomes a regular expression over the host like the regexes of
+//!   [`domain_list`](super::domain_list).
+//! - `0.0.0.0 example.com` blocks exactly the host, see [`hosts`](super::hosts).
+//!
+//! Exceptions start with `@@` as usual. The `$important` and `$dnstype` modifiers are kept as the
+//! [options](crate::Rule::options) of the rules. `$important` rules take precedence over all others, and `$dnstype`
+//! is evaluated when testing with a [`RequestContext`](crate::RequestContext), without which such rules never match.
+//! Rules with other modifiers, such as `$client` or `$dnsrewrite`, cannot be represented and are dropped. Lines
+//! starting with `!` or `#` are comments.
+
+use std::net::IpAddr;
+
+use super::{Import, domain_list, hosts};
+use crate::request::{RuleOptions, split_options};
+use crate::{Origin, Pattern, Rule};
+
+/// Parses an AdGuard Home blocklist into rules.
+///
+/// # Examples
+///
+/// ```
+/// # use gfwlist::format::adguard_home;
+/// # use gfwlist::{GfwList, RequestContext, TestOptions};
+/// let import = adguard_home::import("\
+/// ! Title: DNS blocklist
+/// ||ads.example.com^$important
+/// @@||example.com^
+/// ||example.org^$dnstype=AAAA
+/// 0.0.0.0 tracker.example.net
+/// ||example.net^$client=192.168.0.1
+/// ");
+/// assert_eq!(import.dropped, [(6, "||example.net^$client=192.168.0.1".to_string())]);
+/// let gfw_list = GfwList::from_rules(import.rules).unwrap();
+/// let options = |dns_type: &str| TestOptions {
+///     default_scheme: Some("dns".into()),
+///     context: Some(RequestContext {
+///         dns_type: Some(dns_type.into()),
+///         ..Default::default()
+///     }),
+///     ..Default::default()
+/// };
+/// let test = |host, dns_type| gfw_list.test_with_options(host, &options(dns_type)).unwrap();
+/// assert_eq!(test("ads.example.com", "A"), Some("||ads.example.com$important"));
+/// assert_eq!(test("www.example.org", "A"), None);
+/// assert_eq!(test("www.example.org", "AAAA"), Some("||example.org$dnstype=AAAA"));
+/// assert_eq!(test("tracker.example.net", "A"), Some("tracker.example.net"));
+/// ```
+pub fn import(input: &str) -> Import {
+    let mut import = Import::default();
+    for (line_index, line) in input.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with(['!', '#']) || (line.starts_with('[') && line.ends_with(']')) {
+            continue;
+        }
+        let line_number = line_index + 1;
+        // lines in the format of the hosts file start with an address
+        let first = line.split_whitespace().next().unwrap_or_default();
+        if first.parse::<IpAddr>().is_ok() {
+            let hosts = hosts::import(line);
+            (import.rules).extend(
+                hosts
+                    .rules
+                    .into_iter()
+                    .map(|rule| rule.with_origin(Origin::line(line_number))),
+            );
+            (import.dropped).extend(hosts.dropped.into_iter().map(|(_, entry)| (line_number, entry)));
+            continue;
+        }
+        match parse_rule(line) {
+            Some(rule) => import.rules.push(rule.with_origin(Origin::line(line_number))),
+            None => import.dropped.push((line_number, line.to_string())),
+        }
+    }
+    import
+}
+
+fn parse_rule(line: &str) -> Option<Rule> {
+    let (body, options) = split_options(line);
+    let (negative, body) = match body.strip_prefix("@@") {
+        Some(body) => (true, body),
+        None => (false, body),
+    };
+    if let Some(options) = &options {
+        let supported = RuleOptions {
+            important: options.important,
+            dns_types: options.dns_types.clone(),
+            excluded_dns_types: options.excluded_dns_types.clone(),
+            ..RuleOptions::default()
+        };
+        if *options != supported {
+            return None;
+        }
+    }
+    let rule = Rule::new(negative, parse_pattern(body)?);
+    match options {
+        Some(options) => Rule::parse(&format!("{}${options}", rule.text())).ok().flatten(),
+        None => Some(rule),
+    }
+}
+
+fn parse_pattern(body: &str) -> Option<Pattern> {
+    if let Some(regex) = body.strip_prefix('/').and_then(|body| body.strip_suffix('/')) {
+        return domain_list::pattern("regexp", regex);
+    }
+    // `Some(true)` for `||`, which starts at a label, and `Some(false)` for `|`, which starts at the host
+    let (start, rest) = match (body.strip_prefix("||"), body.strip_prefix('|')) {
+        (Some(rest), _) => (Some(true), rest),
+        (None, Some(rest)) => (Some(false), rest),
+        (None, None) => (None, body),
+    };
+    let (host, ends) = match rest.strip_suffix(['^', '|']) {
+        Some(host) => (host, true),
+        None => (rest, false),
+    };
+    let is_host =
+        !host.is_empty() && (host.chars()).all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '.' | '_' | '*'));
+    if !is_host {
+        return None;
+    }
+    let host = host.to_ascii_lowercase();
+    match start {
+        Some(true) => Some(Pattern::Domain(host)),
+        Some(false) if ends => Some(Pattern::Host(host)),
+        _ => {
+            let regex = regex::escape(&host).replace("\\*", ".*");
+            let (start, end) = (if start.is_some() { "^" } else { "" }, if ends { "$" } else { "" });
+            domain_list::pattern("regexp", &format!("{start}{regex}{end}"))
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{GfwList, RequestContext, TestOptions};
+
+    #[test]
+    fn important_exceptions() {
+        let input = "@@||example.com^\n||ads.example.com^$important\n@@|cdn.ads.example.com^$important\n";
+        let import = import(&format!("{input}analytics^\n|tracker\n/x\\d+$/$bad"));
+        assert_eq!(import.dropped, [(6, "/x\\d+$/$bad".to_string())]);
+        let gfw = GfwList::from_rules(import.rules).unwrap();
+        let options = TestOptions {
+            default_scheme: Some("dns".into()),
+            context: Some(RequestContext::default()),
+            ..Default::default()
+        };
+        let test = |host| gfw.test_with_options(host, &options).unwrap();
+        assert_eq!(test("ads.example.com"), Some("||ads.example.com$important"));
+        assert_eq!(
+            gfw.test("dns://ads.example.com").unwrap(),
+            Some("||ads.example.com$important")
+        );
+        assert_eq!(test("cdn.ads.example.com"), None);
+        assert!(test("myanalytics").is_some());
+        assert!(test("analytics.com").is_none());
+        assert!(test("tracker.example.org").is_some());
+        assert!(test("ads.tracker.org").is_none());
+    }
+}
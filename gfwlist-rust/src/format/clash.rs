@@ -0,0 +1,148 @@
+//! Rule providers and rules of [Clash](https://wiki.metacubex.one/).
+//!
+//! A rule provider is a YAML file with a `payload` list, or a text file with one entry per line. Entries of
+//! `classical` providers have the form `TYPE,value`, while entries of `domain` providers are bare domains, where
+//! `+.example.com` also covers subdomains. The `rules` list of a Clash configuration is read as well, where entries
+//! have the form `TYPE,value,policy`. The types map onto rules as follows:
+//!
+//! - `DOMAIN,example.com` becomes `example.com`.
+//! - `DOMAIN-SUFFIX,example.com` becomes `||example.com`.
+//! - `DOMAIN-KEYWORD,example` becomes a regular expression matching URLs whose host contains `example`.
+//! - `DOMAIN-REGEX,^ads\.` becomes a regular expression matching URLs whose host matches it, as for `regexp:` entries
+//!   of [`domain_list`](super::domain_list).
+//! - `IP-CIDR,8.8.8.0/24` and `IP-CIDR6,2001:db8::/32` become IP rules, see [`Pattern::Cidr`].
+//!
+//! Rules with the `DIRECT` policy become negative rules, and all other rules positive ones. `GEOIP`, `MATCH` and all
+//! other types cannot be represented and are dropped.
+
+use super::{Import, cidr, domain_list, host_keyword};
+use crate::{Origin, Pattern, Rule};
+
+/// Parses a Clash rule provider or configuration into rules.
+///
+/// # Examples
+///
+/// ```
+/// # use gfwlist::format::clash;
+/// let import = clash::import("\
+/// payload:
+///   - DOMAIN-SUFFIX,google.com
+///   - 'DOMAIN,www.example.com'
+///   - IP-CIDR,8.8.8.8/32,no-resolve
+///   - GEOIP,CN
+/// ");
+/// let rules: Vec<_> = import.rules.iter().map(|rule| rule.text()).collect();
+/// assert_eq!(rules, ["||google.com", "www.example.com", "IP-CIDR,8.8.8.8/32"]);
+/// assert_eq!(import.dropped, [(5, "GEOIP,CN".to_string())]);
+///
+/// let import = clash::import("\
+/// rules:
+///   - DOMAIN-SUFFIX,google.com,Proxy
+///   - DOMAIN-SUFFIX,cn,DIRECT
+///   - MATCH,Proxy
+/// ");
+/// let rules: Vec<_> = import.rules.iter().map(|rule| rule.text()).collect();
+/// assert_eq!(rules, ["||google.com", "@@||cn"]);
+/// ```
+pub fn import(input: &str) -> Import {
+    let mut import = Import::default();
+    let mut in_payload = false;
+    for (line_index, line) in input.lines().enumerate() {
+        let trimmed = line.trim();
+        if trimmed.is_empty() || trimmed.starts_with('#') {
+            continue;
+        }
+        let entry = if line.starts_with([' ', '\t', '-']) {
+            match trimmed.strip_prefix('-').filter(|_| in_payload) {
+                Some(entry) => entry.trim(),
+                None => continue,
+            }
+        } else if trimmed.ends_with(':') || trimmed.contains(": ") {
+            in_payload = matches!(trimmed, "payload:" | "rules:");
+            continue;
+        } else {
+            // an entry of a text provider
+            trimmed
+        };
+        let entry = unquote(entry);
+        match parse_entry(entry) {
+            Some((negative, pattern)) => import
+                .rules
+                .push(Rule::new(negative, pattern).with_origin(Origin::line(line_index + 1))),
+            None => import.dropped.push((line_index + 1, entry.to_string())),
+        }
+    }
+    import
+}
+
+fn unquote(input: &str) -> &str {
+    for quote in ['\'', '"'] {
+        if let Some(inner) = input.strip_prefix(quote).and_then(|s| s.strip_suffix(quote)) {
+            return inner;
+        }
+    }
+    input
+}
+
+/// Parses an entry into a pattern and whether it is negative.
+fn parse_entry(entry: &str) -> Option<(bool, Pattern)> {
+    let mut fields = entry.split(',').map(str::trim);
+    let kind = fields.next()?;
+    let Some(value) = fields.next() else {
+        // domain providers list bare domains
+        if let Some(domain) = kind.strip_prefix("+.") {
+            return Some((false, Pattern::Domain(domain.to_string())));
+        }
+        let is_domain = kind.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '.');
+        return Some((false, Pattern::Host(kind.to_string()))).filter(|_| is_domain && !kind.is_empty());
+    };
+    let negative = fields.next() == Some("DIRECT");
+    let pattern = match kind {
+        "DOMAIN" => Pattern::Host(value.to_string()),
+        "DOMAIN-SUFFIX" => Pattern::Domain(value.to_string()),
+        "DOMAIN-KEYWORD" => host_keyword(value),
+        "DOMAIN-REGEX" => domain_list::pattern("regexp", value)?,
+        "IP-CIDR" | "IP-CIDR6" => cidr(value)?,
+        _ => return None,
+    };
+    Some((negative, pattern))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::GfwList;
+
+    #[test]
+    fn import_keyword() {
+        let import = import("payload:\n  - DOMAIN-KEYWORD,google\n  - GEOIP,CN\n");
+        assert_eq!(import.dropped, [(3, "GEOIP,CN".to_string())]);
+        let gfw = GfwList::from_rules(import.rules).unwrap();
+        assert!(gfw.test("https://www.google.co.jp/search").unwrap().is_some());
+        assert!(gfw.test("https://example.com/google").unwrap().is_none());
+    }
+
+    #[test]
+    fn import_domain_provider() {
+        let import = import("# comment\npayload:\n- '+.example.com'\n- \"example.org\"\nother: 1\n- ignored.com\n");
+        assert_eq!(
+            import.rules,
+            [
+                Rule::new(false, Pattern::Domain("example.com".into())),
+                Rule::new(false, Pattern::Host("example.org".into())),
+            ],
+        );
+        assert!(import.dropped.is_empty());
+    }
+
+    #[test]
+    fn import_text_provider() {
+        let import =
+            import("# text\nDOMAIN-REGEX,^ads\\d\\.\nPROCESS-NAME,curl\n+.example.com\nIP-CIDR6,2001:db8::/32\n");
+        assert_eq!(import.dropped, [(3, "PROCESS-NAME,curl".to_string())]);
+        let gfw = GfwList::from_rules(import.rules).unwrap();
+        assert!(gfw.test("https://ads1.example.org/").unwrap().is_some());
+        assert!(gfw.test("https://www.example.com/").unwrap().is_some());
+        assert!(gfw.test("https://[2001:db8::1]/").unwrap().is_some());
+    }
+}
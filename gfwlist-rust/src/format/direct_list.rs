@@ -0,0 +1,138 @@
+//! Standalone direct lists of the negative (`@@`) rules, for the "direct" rule sets of Clash and sing-box.
+//!
+//! When a list is deployed as domain sets, such as through DNS or rule sets, its exceptions cannot be expressed inline.
+//! Instead, the negative rules are exported as a separate domain list, which the configuration matches before the
+//! proxy list. Exceptions keep their meaning only if they hold for every URL on their hosts: an exception with a path,
+//! port or scheme would send all traffic of its hosts direct, so it is left out. Wildcard hosts are left out as well.
+//! Domains are emitted in the order of the list, without duplicates.
+
+use std::collections::HashSet;
+
+use super::{Fidelity, Report, json_array};
+use crate::{GfwList, Pattern, Rule};
+
+/// The syntax of a direct list.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum Syntax {
+    /// A [domain-list-community](super::domain_list) list with `domain:` and `full:` entries, also read by MosDNS
+    #[default]
+    DomainList,
+    /// A Clash rule provider with `domain` behavior, where `+.example.com` covers subdomains
+    Clash,
+    /// A sing-box rule set in source format, with `domain` and `domain_suffix` fields
+    SingBox,
+}
+
+/// Returns the host of an exception that holds for every URL on it, and whether it covers subdomains.
+fn exception(rule: &Rule) -> Option<(String, bool)> {
+    let pattern = rule.pattern();
+    if !rule.is_negative() || pattern.path().is_some() || pattern.port().is_some() {
+        return None;
+    }
+    let host = pattern.host().filter(|host| !host.is_empty() && !host.contains('*'))?;
+    match pattern {
+        Pattern::Domain(_) => Some((host, true)),
+        Pattern::Host(_) => Some((host, false)),
+        _ => None,
+    }
+}
+
+/// Renders the exceptions of a [`GfwList`] as a standalone direct list.
+///
+/// # Examples
+///
+/// ```
+/// # use gfwlist::{GfwList, format::direct_list::{self, Syntax}};
+/// let gfw = GfwList::from("||google.com\n@@||cn.google.com\n@@www.google.cn\n@@||google.com/ncr").unwrap();
+/// assert_eq!(direct_list::export(&gfw, Syntax::DomainList), "domain:cn.google.com\nfull:www.google.cn\n");
+/// assert_eq!(
+///     direct_list::export(&gfw, Syntax::Clash),
+///     "payload:\n  - '+.cn.google.com'\n  - 'www.google.cn'\n",
+/// );
+/// assert_eq!(
+///     direct_list::export(&gfw, Syntax::SingBox),
+///     r#"{
+///   "version": 1,
+///   "rules": [
+///     {
+///       "domain": ["www.google.cn"],
+///       "domain_suffix": ["cn.google.com"]
+///     }
+///   ]
+/// }
+/// "#,
+/// );
+/// ```
+pub fn export(list: &GfwList, syntax: Syntax) -> String {
+    let mut seen = HashSet::new();
+    let exceptions: Vec<(String, bool)> = (list.iter_rules())
+        .filter_map(exception)
+        .filter(|exception| seen.insert(exception.clone()))
+        .collect();
+    match syntax {
+        Syntax::DomainList => exceptions
+            .iter()
+            .map(|(host, subdomains)| match subdomains {
+                true => format!("domain:{host}\n"),
+                false => format!("full:{host}\n"),
+            })
+            .collect(),
+        Syntax::Clash => {
+            let mut output = String::from("payload:\n");
+            for (host, subdomains) in &exceptions {
+                let prefix = if *subdomains { "+." } else { "" };
+                output.push_str(&format!("  - '{prefix}{host}'\n"));
+            }
+            output
+        }
+        Syntax::SingBox => {
+            let hosts = |subdomains: bool| {
+                (exceptions.iter())
+                    .filter(move |exception| exception.1 == subdomains)
+                    .map(|(host, _)| host)
+            };
+            let mut fields = vec![];
+            for (key, subdomains) in [("domain", false), ("domain_suffix", true)] {
+                if hosts(subdomains).next().is_some() {
+                    fields.push(format!("      \"{key}\": {}", json_array(hosts(subdomains))));
+                }
+            }
+            let rules = match fields.is_empty() {
+                true => "[]".to_string(),
+                false => format!("[\n    {{\n{}\n    }}\n  ]", fields.join(",\n")),
+            };
+            format!("{{\n  \"version\": 1,\n  \"rules\": {rules}\n}}\n")
+        }
+    }
+}
+
+/// Reports how the rules of a [`GfwList`] are converted by [`export`]. Positive rules are always dropped.
+pub fn fidelity(list: &GfwList) -> Report {
+    Report::new(list, |rule| match exception(rule) {
+        Some(_) => Fidelity::Lossless,
+        None => Fidelity::Dropped,
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn exceptions() {
+        let gfw = GfwList::from(
+            "@@||a.com\n@@|http://b.com\n@@||c.com:8080\n@@*.d.com\n/e/\n@@||a.com\n@@/f/\n@@IP-CIDR,10.0.0.0/8",
+        )
+        .unwrap();
+        assert_eq!(export(&gfw, Syntax::DomainList), "domain:a.com\n");
+        let report = fidelity(&gfw);
+        assert_eq!(report.lossless.len(), 2);
+        assert_eq!(report.dropped.len(), 6);
+        let gfw = GfwList::from("||a.com").unwrap();
+        assert_eq!(export(&gfw, Syntax::Clash), "payload:\n");
+        assert_eq!(
+            export(&gfw, Syntax::SingBox),
+            "{\n  \"version\": 1,\n  \"rules\": []\n}\n"
+        );
+    }
+}
@@ -0,0 +1,90 @@
+//! Domain lists kept as [dnsmasq](https://thekelleys.org.uk/dnsmasq/doc.html) configuration fragments.
+//!
+//! Lists like `accelerated-domains.china.conf` route each domain through a directive such as
+//! `server=/example.com/114.114.114.114`, `address=/example.com/0.0.0.0` or `ipset=/example.com/gfwlist`. A directive
+//! may list several domains between slashes, and dnsmasq matches each of them together with its subdomains, so every
+//! domain becomes a domain rule like `||example.com`.
+//!
+//! The upstream, address or set after the domains is not kept: the polarity of the rules is chosen when loading the
+//! list, for example with [`Source::negative`](crate::source::Source::negative). A domain listed by several
+//! directives is only imported once. Other directives are skipped.
+
+use std::collections::HashSet;
+
+use super::Import;
+use crate::{Origin, Pattern, Rule};
+
+/// The directives that apply to the domains between their slashes
+const DOMAIN_DIRECTIVES: &[&str] = &["server", "local", "address", "ipset", "nftset"];
+
+/// Parses a dnsmasq configuration fragment into positive domain rules.
+///
+/// # Examples
+///
+/// ```
+/// # use gfwlist::format::dnsmasq;
+/// let import = dnsmasq::import("\
+/// # China domains
+/// server=/example.cn/example.com.cn/114.114.114.114
+/// ipset=/example.cn/chnroute
+/// address=/#/0.0.0.0
+/// cache-size=1000
+/// ");
+/// let rules: Vec<_> = import.rules.iter().map(|rule| rule.text()).collect();
+/// assert_eq!(rules, ["||example.cn", "||example.com.cn"]);
+/// assert_eq!(import.dropped, [(4, "#".to_string())]);
+/// ```
+pub fn import(input: &str) -> Import {
+    let mut import = Import::default();
+    let mut seen = HashSet::new();
+    for (line_index, line) in input.lines().enumerate() {
+        let line = line.trim();
+        let Some((directive, value)) = line.split_once('=') else {
+            continue;
+        };
+        if !DOMAIN_DIRECTIVES.contains(&directive.trim()) {
+            continue;
+        }
+        // `server=8.8.8.8` sets an upstream for all domains
+        let Some(value) = value.trim().strip_prefix('/') else {
+            continue;
+        };
+        let mut domains: Vec<&str> = value.split('/').collect();
+        // the upstream, address or set after the last slash
+        domains.pop();
+        for domain in domains {
+            let domain = domain.trim_matches('.').to_ascii_lowercase();
+            if domain.is_empty() || !seen.insert(domain.clone()) {
+                continue;
+            }
+            match is_domain(&domain) {
+                true => import
+                    .rules
+                    .push(Rule::new(false, Pattern::Domain(domain)).with_origin(Origin::line(line_index + 1))),
+                false => import.dropped.push((line_index + 1, domain)),
+            }
+        }
+    }
+    import
+}
+
+fn is_domain(domain: &str) -> bool {
+    domain
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '.' | '_'))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::GfwList;
+
+    #[test]
+    fn subdomains() {
+        let import = import("server=/Example.COM./\nlocal=/example.com/\nserver=8.8.8.8\nnftset=/a b/4#inet#fw#set\n");
+        assert_eq!(import.dropped, [(4, "a b".to_string())]);
+        let gfw = GfwList::from_rules(import.rules).unwrap();
+        assert_eq!(gfw.len(), 1);
+        assert!(gfw.test("http://cdn.example.com/").unwrap().is_some());
+    }
+}
@@ -0,0 +1,124 @@
+//! The text format of [domain-list-community](https://github.com/v2fly/domain-list-community), the source of the
+//! `geosite` lists of V2Ray and Xray.
+//!
+//! Each line holds one entry, optionally followed by attributes such as `@ads` and `#` comments. The prefixes map onto
+//! rules as follows:
+//!
+//! - `domain:example.com` and bare `example.com` become `||example.com`.
+//! - `full:www.example.com` becomes `www.example.com`.
+//! - `keyword:example` becomes a regular expression matching URLs whose host contains `example`.
+//! - `regexp:\.example\.com$` becomes a regular expression matching URLs whose host matches it. Unanchored regexes may
+//!   also match in the path.
+//!
+//! `include:` lines refer to other lists by name and are dropped, as are regexes that do not compile.
+
+use super::{Import, host_keyword};
+use crate::{Origin, Pattern, Rule};
+
+/// Parses a domain list into positive rules, keeping the entries accepted by an attribute filter.
+///
+/// Each item of the filter is an attribute such as `cn`, which entries must have, or a negated attribute such as
+/// `!cn`, which entries must not have, like `geosite:google@cn` in V2Ray routing. Entries rejected by the filter are
+/// skipped rather than dropped.
+///
+/// # Examples
+///
+/// ```
+/// # use gfwlist::format::domain_list;
+/// let input = "\
+/// google.com # search
+/// full:www.google.cn @cn
+/// domain:googleadservices.com @ads
+/// keyword:google-analytics @ads
+/// include:youtube
+/// ";
+/// let texts = |filter: &[&str]| -> Vec<String> {
+///     let import = domain_list::import(input, filter);
+///     import.rules.iter().map(|rule| rule.text().to_string()).collect()
+/// };
+/// assert_eq!(texts(&["!ads"]), ["||google.com", "www.google.cn"]);
+/// assert_eq!(texts(&["cn"]), ["www.google.cn"]);
+/// assert_eq!(domain_list::import(input, &[]).dropped, [(5, "include:youtube".to_string())]);
+/// ```
+pub fn import(input: &str, filter: &[&str]) -> Import {
+    let mut import = Import::default();
+    for (line_index, line) in input.lines().enumerate() {
+        let line = line.split('#').next().unwrap_or_default();
+        let mut fields = line.split_whitespace();
+        let Some(entry) = fields.next() else {
+            continue;
+        };
+        // `&affiliation` fields add the entry to other lists and are not attributes
+        let attributes: Vec<&str> = fields.filter_map(|field| field.strip_prefix('@')).collect();
+        if !accepts(filter, &attributes) {
+            continue;
+        }
+        match parse_entry(entry) {
+            Some(pattern) => import
+                .rules
+                .push(Rule::new(false, pattern).with_origin(Origin::line(line_index + 1))),
+            None => import.dropped.push((line_index + 1, entry.to_string())),
+        }
+    }
+    import
+}
+
+/// Returns whether an entry with the given attributes passes the filter.
+pub(super) fn accepts(filter: &[&str], attributes: &[&str]) -> bool {
+    filter.iter().all(|item| match item.strip_prefix('!') {
+        Some(attribute) => !attributes.contains(&attribute),
+        None => attributes.contains(item),
+    })
+}
+
+fn parse_entry(entry: &str) -> Option<Pattern> {
+    let (kind, value) = entry.split_once(':').unwrap_or(("domain", entry));
+    pattern(kind, value)
+}
+
+/// Converts an entry given by its prefix, such as `domain` or `regexp`, and its value into a pattern.
+pub(super) fn pattern(kind: &str, value: &str) -> Option<Pattern> {
+    let is_domain =
+        !value.is_empty() && (value.chars()).all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '.' | '_'));
+    match kind {
+        "domain" if is_domain => Some(Pattern::Domain(value.to_ascii_lowercase())),
+        "full" if is_domain => Some(Pattern::Host(value.to_ascii_lowercase())),
+        "keyword" if !value.is_empty() => Some(host_keyword(value)),
+        "regexp" => host_regex(value),
+        _ => None,
+    }
+}
+
+/// Converts a regular expression over hosts into one over URLs, returning `None` if it does not compile.
+fn host_regex(regex: &str) -> Option<Pattern> {
+    let (prefix, regex) = match regex.strip_prefix('^') {
+        Some(regex) => ("^[^:]+:\\/\\/", regex),
+        None => ("^[^:]+:\\/\\/[^\\/]*", regex),
+    };
+    let (regex, suffix) = match regex.strip_suffix('$').filter(|regex| !regex.ends_with('\\')) {
+        Some(regex) => (regex, "(?:[:\\/]|$)"),
+        None => (regex, ""),
+    };
+    let pattern = format!("{prefix}(?:{regex}){suffix}");
+    regex::Regex::new(&pattern).ok()?;
+    Some(Pattern::Regex(pattern))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::GfwList;
+
+    #[test]
+    fn host_regexes() {
+        let import = import(
+            "regexp:^ads\\d+\\.example\\.com$ @ads &other\nregexp:( @ads\n",
+            &["ads", "!cn"],
+        );
+        assert_eq!(import.dropped, [(2, "regexp:(".to_string())]);
+        let gfw = GfwList::from_rules(import.rules).unwrap();
+        assert!(gfw.test("https://ads12.example.com/banner").unwrap().is_some());
+        assert!(gfw.test("https://ads12.example.com.cn/").unwrap().is_none());
+        assert!(gfw.test("https://cdn.ads1.example.com/").unwrap().is_none());
+    }
+}
@@ -0,0 +1,124 @@
+//! Domain hash maps for eBPF programs.
+//!
+//! Kernel-level DNS or SNI filters cannot run the full matcher, but can look up hashes in a `BPF_MAP_TYPE_HASH` map
+//! with `u64` keys and `u32` values. Domains are matched at host level as described in [`DomainSets`], and host
+//! regexes are not exported.
+//!
+//! A key is the 64-bit FNV-1a hash of the lowercase domain, as used by [`BloomFilter`](crate::filter::BloomFilter).
+//! A value combines [`FLAG_BLOCK`], [`FLAG_SUBDOMAINS`] and [`FLAG_EXACT_ALLOWED`]. To classify a name, a program
+//! hashes the name and each of its parent domains, from the most specific one, and uses the first entry that applies:
+//! an entry for a parent domain only applies if it has [`FLAG_SUBDOMAINS`].
+
+use std::collections::HashMap;
+
+use super::{DomainSets, Report, domain_fidelity};
+use crate::GfwList;
+use crate::filter::fnv1a;
+
+/// Set for domains of positive rules, unset for domains of negative rules.
+pub const FLAG_BLOCK: u32 = 1;
+/// Set if the entry also applies to subdomains.
+pub const FLAG_SUBDOMAINS: u32 = 2;
+/// Set if the domain itself is allowed while its subdomains are blocked, as with `||example.com` and
+/// `@@example.com`. [`FLAG_BLOCK`] then only applies to subdomains.
+pub const FLAG_EXACT_ALLOWED: u32 = 4;
+
+/// Returns the map entries for the rules of a [`GfwList`].
+///
+/// Entries for negative rules come first. If a domain occurs in several rules, negative rules take precedence,
+/// followed by entries covering subdomains. A negative rule for the domain itself does not hide a positive rule for
+/// its subdomains, which are combined into a single entry with [`FLAG_EXACT_ALLOWED`].
+///
+/// # Examples
+///
+/// ```
+/// # use gfwlist::{GfwList, format::ebpf};
+/// let gfw = GfwList::from("||blocked-site.com\n@@exception.com").unwrap();
+/// let entries = ebpf::entries(&gfw);
+/// assert_eq!(entries[0].1, 0);
+/// assert_eq!(entries[1].1, ebpf::FLAG_BLOCK | ebpf::FLAG_SUBDOMAINS);
+/// ```
+pub fn entries(list: &GfwList) -> Vec<(u64, u32)> {
+    let mut entries: Vec<(u64, u32)> = vec![];
+    let mut indices = HashMap::new();
+    for negative in [true, false] {
+        let sets = DomainSets::from_list(list, negative);
+        let block = if negative { 0 } else { FLAG_BLOCK };
+        let domains = (sets.suffixes.iter().map(|domain| (domain, block | FLAG_SUBDOMAINS)))
+            .chain(sets.full.iter().map(|domain| (domain, block)));
+        for (domain, value) in domains {
+            let key = fnv1a(domain.as_bytes());
+            match indices.get(&key) {
+                None => {
+                    indices.insert(key, entries.len());
+                    entries.push((key, value));
+                }
+                Some(&index) if entries[index].1 == 0 && value == FLAG_BLOCK | FLAG_SUBDOMAINS => {
+                    entries[index].1 = value | FLAG_EXACT_ALLOWED;
+                }
+                Some(_) => {}
+            }
+        }
+    }
+    entries
+}
+
+/// Serializes the map entries as consecutive 12-byte records of a little-endian `u64` key and `u32` value.
+pub fn export(list: &GfwList) -> Vec<u8> {
+    let mut output = vec![];
+    for (key, value) in entries(list) {
+        output.extend(key.to_le_bytes());
+        output.extend(value.to_le_bytes());
+    }
+    output
+}
+
+/// Renders a `bpftool batch file` script loading the entries into the map pinned at `map_path`.
+///
+/// # Examples
+///
+/// ```
+/// # use gfwlist::{GfwList, format::ebpf};
+/// let gfw = GfwList::from("||blocked-site.com").unwrap();
+/// let script = ebpf::bpftool_batch(&gfw, "/sys/fs/bpf/gfwlist");
+/// assert!(script.starts_with("map update pinned /sys/fs/bpf/gfwlist key hex "));
+/// assert!(script.ends_with(" value hex 03 00 00 00\n"));
+/// ```
+pub fn bpftool_batch(list: &GfwList, map_path: &str) -> String {
+    let hex = |bytes: &[u8]| bytes.iter().map(|b| format!("{b:02x}")).collect::<Vec<_>>().join(" ");
+    let mut output = String::new();
+    for (key, value) in entries(list) {
+        output.push_str(&format!(
+            "map update pinned {map_path} key hex {} value hex {}\n",
+            hex(&key.to_le_bytes()),
+            hex(&value.to_le_bytes()),
+        ));
+    }
+    output
+}
+
+/// Reports how the rules of a [`GfwList`] are converted by [`export`].
+pub fn fidelity(list: &GfwList) -> Report {
+    Report::new(list, |rule| domain_fidelity(rule.pattern(), true, false))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn negative_precedence() {
+        let gfw = GfwList::from("||example.com\n@@||example.com\nexample.org").unwrap();
+        let entries = entries(&gfw);
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0], (fnv1a(b"example.com"), FLAG_SUBDOMAINS));
+        assert_eq!(entries[1], (fnv1a(b"example.org"), FLAG_BLOCK));
+        assert_eq!(export(&gfw).len(), 24);
+
+        let gfw = GfwList::from("||blocked.com\n@@blocked.com").unwrap();
+        let value = FLAG_BLOCK | FLAG_SUBDOMAINS | FLAG_EXACT_ALLOWED;
+        assert_eq!(super::entries(&gfw), [(fnv1a(b"blocked.com"), value)]);
+        assert!(gfw.test("http://www.blocked.com/").unwrap().is_some());
+        assert!(gfw.test("http://blocked.com/").unwrap().is_none());
+    }
+}
@@ -0,0 +1,245 @@
+//! The `geosite.dat` files of V2Ray and Xray, which bundle the lists of
+//! [domain-list-community](https://github.com/v2fly/domain-list-community) as protocol buffers.
+//!
+//! A file holds one category per list, such as `google` or `cn`. Categories are selected like in V2Ray routing, as
+//! `geosite:google` or `google`, optionally followed by attribute filters such as `@cn` or `@!ads`, see
+//! [`domain_list::import`](super::domain_list::import). Entries map onto rules as in
+//! [`domain_list`](super::domain_list), and entries of the same category are numbered from 1 in their origins and in
+//! [`Import::dropped`].
+
+use std::sync::Arc;
+
+use thiserror::Error;
+
+use super::Import;
+use super::domain_list::{accepts, pattern};
+use crate::{Origin, Rule};
+
+/// An error reading a `geosite.dat` file.
+#[derive(Debug, Error, Clone, PartialEq, Eq)]
+pub enum GeositeError {
+    /// The data is not a valid list of categories
+    #[error("malformed geosite data")]
+    Malformed,
+    /// A selected category is not in the file
+    #[error("unknown geosite category: {0}")]
+    UnknownCategory(String),
+}
+
+/// A field of a protocol buffer message.
+enum Value<'a> {
+    Varint(u64),
+    Bytes(&'a [u8]),
+    Fixed,
+}
+
+/// Reads the fields of a protocol buffer message.
+struct Fields<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> Fields<'a> {
+    fn varint(&mut self) -> Result<u64, GeositeError> {
+        let mut value = 0u64;
+        for shift in (0..64).step_by(7) {
+            let (&byte, rest) = self.data.split_first().ok_or(GeositeError::Malformed)?;
+            self.data = rest;
+            value |= u64::from(byte & 0x7f) << shift;
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+        Err(GeositeError::Malformed)
+    }
+
+    fn skip(&mut self, len: usize) -> Result<&'a [u8], GeositeError> {
+        if len > self.data.len() {
+            return Err(GeositeError::Malformed);
+        }
+        let (bytes, rest) = self.data.split_at(len);
+        self.data = rest;
+        Ok(bytes)
+    }
+}
+
+impl<'a> Iterator for Fields<'a> {
+    type Item = Result<(u64, Value<'a>), GeositeError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.data.is_empty() {
+            return None;
+        }
+        let mut field = || {
+            let key = self.varint()?;
+            let value = match key & 7 {
+                0 => Value::Varint(self.varint()?),
+                1 => self.skip(8).map(|_| Value::Fixed)?,
+                2 => {
+                    let len = usize::try_from(self.varint()?).map_err(|_| GeositeError::Malformed)?;
+                    Value::Bytes(self.skip(len)?)
+                }
+                5 => self.skip(4).map(|_| Value::Fixed)?,
+                _ => return Err(GeositeError::Malformed),
+            };
+            Ok((key >> 3, value))
+        };
+        let result = field();
+        if result.is_err() {
+            self.data = &[];
+        }
+        Some(result)
+    }
+}
+
+fn fields(data: &[u8]) -> Fields<'_> {
+    Fields { data }
+}
+
+fn string(bytes: &[u8]) -> Result<&str, GeositeError> {
+    std::str::from_utf8(bytes).map_err(|_| GeositeError::Malformed)
+}
+
+/// A `GeoSite` message: a category code and its `Domain` messages.
+fn categories_of(data: &[u8]) -> impl Iterator<Item = Result<(String, Vec<&[u8]>), GeositeError>> {
+    fields(data).filter_map(|field| {
+        let site = match field {
+            Ok((1, Value::Bytes(site))) => site,
+            Ok(_) => return None,
+            Err(error) => return Some(Err(error)),
+        };
+        let mut code = String::new();
+        let mut domains = vec![];
+        for field in fields(site) {
+            match field {
+                Ok((1, Value::Bytes(bytes))) => match string(bytes) {
+                    Ok(text) => code = text.to_ascii_lowercase(),
+                    Err(error) => return Some(Err(error)),
+                },
+                Ok((2, Value::Bytes(domain))) => domains.push(domain),
+                Ok(_) => {}
+                Err(error) => return Some(Err(error)),
+            }
+        }
+        Some(Ok((code, domains)))
+    })
+}
+
+/// Returns the entry of a `Domain` message as a domain-list-community prefix and value, with its attributes.
+fn entry(domain: &[u8]) -> Result<(&'static str, &str, Vec<&str>), GeositeError> {
+    let mut kind = "keyword";
+    let mut value = "";
+    let mut attributes = vec![];
+    for field in fields(domain) {
+        match field? {
+            (1, Value::Varint(type_)) => {
+                kind = match type_ {
+                    0 => "keyword",
+                    1 => "regexp",
+                    2 => "domain",
+                    3 => "full",
+                    _ => "unknown",
+                }
+            }
+            (2, Value::Bytes(bytes)) => value = string(bytes)?,
+            (3, Value::Bytes(attribute)) => {
+                for field in fields(attribute) {
+                    if let (1, Value::Bytes(key)) = field? {
+                        attributes.push(string(key)?);
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    Ok((kind, value, attributes))
+}
+
+/// Returns the codes of the categories in a `geosite.dat` file, in lowercase.
+pub fn categories(data: &[u8]) -> Result<Vec<String>, GeositeError> {
+    categories_of(data)
+        .map(|category| category.map(|(code, _)| code))
+        .collect()
+}
+
+/// Converts the selected categories of a `geosite.dat` file into positive rules, in the order of the selectors.
+///
+/// # Examples
+///
+/// ```
+/// # use gfwlist::GfwList;
+/// # use gfwlist::format::geosite;
+/// # fn message(field: u8, bytes: &[u8]) -> Vec<u8> {
+/// #     [&[field << 3 | 2, bytes.len() as u8][..], bytes].concat()
+/// # }
+/// # let domain = |type_: u8, value: &str, attribute: Option<&str>| {
+/// #     let attribute = attribute.map(|key| message(3, &message(1, key.as_bytes()))).unwrap_or_default();
+/// #     message(2, &[&[0x08, type_][..], &message(2, value.as_bytes()), &attribute].concat())
+/// # };
+/// # let site = |code: &str, domains: &[Vec<u8>]| {
+/// #     message(1, &[message(1, code.as_bytes()), domains.concat()].concat())
+/// # };
+/// # let data = [
+/// #     site("GOOGLE", &[domain(2, "google.com", None), domain(3, "www.google.cn", Some("cn"))]),
+/// #     site("CN", &[domain(2, "cn", None)]),
+/// # ]
+/// # .concat();
+/// // `data` holds the categories `google` and `cn`
+/// assert_eq!(geosite::categories(&data).unwrap(), ["google", "cn"]);
+/// let import = geosite::import(&data, &["geosite:google@!cn"]).unwrap();
+/// let gfw_list = GfwList::from_rules(import.rules).unwrap();
+/// assert!(gfw_list.test("https://mail.google.com").unwrap().is_some());
+/// assert!(gfw_list.test("https://www.google.cn").unwrap().is_none());
+/// assert!(geosite::import(&data, &["youtube"]).is_err());
+/// ```
+pub fn import(data: &[u8], selectors: &[&str]) -> Result<Import, GeositeError> {
+    let mut import = Import::default();
+    for selector in selectors {
+        let selector = selector.strip_prefix("geosite:").unwrap_or(selector);
+        let mut parts = selector.split('@');
+        let code = parts.next().unwrap_or_default().to_ascii_lowercase();
+        let filter: Vec<&str> = parts.collect();
+        let mut domains = None;
+        for category in categories_of(data) {
+            let (other, other_domains) = category?;
+            if other == code {
+                domains = Some(other_domains);
+                break;
+            }
+        }
+        let domains = domains.ok_or_else(|| GeositeError::UnknownCategory(code.clone()))?;
+        let source: Arc<str> = format!("geosite:{code}").into();
+        for (index, domain) in domains.into_iter().enumerate() {
+            let (kind, value, attributes) = entry(domain)?;
+            if !accepts(&filter, &attributes) {
+                continue;
+            }
+            let origin = Origin {
+                source: Some(source.clone()),
+                ..Origin::line(index + 1)
+            };
+            match pattern(kind, value) {
+                Some(pattern) => import.rules.push(Rule::new(false, pattern).with_origin(origin)),
+                None => import.dropped.push((index + 1, format!("{kind}:{value}"))),
+            }
+        }
+    }
+    Ok(import)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn malformed() {
+        // a `GeoSite` message claiming more bytes than there are
+        assert_eq!(categories(&[0x0a, 0x05, 0x0a]), Err(GeositeError::Malformed));
+        // a keyword entry of the category `ads`, followed by an unknown varint field
+        let data = [
+            0x0a, 0x0d, 0x0a, 0x03, b'a', b'd', b's', 0x12, 0x04, 0x12, 0x02, b'a', b'd', 0x18, 0x01,
+        ];
+        let import = import(&data, &["ADS"]).unwrap();
+        assert_eq!(import.rules[0].text(), "/^[^:]+:\\/\\/[^\\/]*ad/");
+        assert_eq!(import.rules[0].origin().unwrap().source.as_deref(), Some("geosite:ads"));
+    }
+}
@@ -0,0 +1,90 @@
+//! Blocklists in the format of the hosts file.
+//!
+//! Each line maps an address to one or more host names, such as `0.0.0.0 ads.example.com`. Blocklists map hosts to a
+//! sink address, which is `0.0.0.0`, `127.0.0.1`, `::` or `::1`. A hosts file only affects exactly the listed names,
+//! so every host becomes an exact host rule like `ads.example.com`, which does not cover subdomains.
+//!
+//! Lines mapping hosts to other addresses redirect rather than block them, and are dropped. Names of the local
+//! machine such as `localhost` are skipped.
+
+use super::Import;
+use crate::{Origin, Pattern, Rule};
+
+/// The addresses that blocklists map hosts to
+const SINK_ADDRESSES: &[&str] = &["0.0.0.0", "127.0.0.1", "::", "::1"];
+
+/// The names of the local machine found at the top of most hosts files
+const LOCAL_NAMES: &[&str] = &[
+    "localhost",
+    "localhost.localdomain",
+    "local",
+    "broadcasthost",
+    "ip6-localhost",
+    "ip6-loopback",
+    "0.0.0.0",
+];
+
+/// Parses a hosts file into positive rules.
+///
+/// # Examples
+///
+/// ```
+/// # use gfwlist::format::hosts;
+/// let import = hosts::import("\
+/// 127.0.0.1 localhost
+/// 0.0.0.0 ads.example.com tracker.example.com # trackers
+/// 203.0.113.1 redirected.example.com
+/// ");
+/// let rules: Vec<_> = import.rules.iter().map(|rule| rule.text()).collect();
+/// assert_eq!(rules, ["ads.example.com", "tracker.example.com"]);
+/// assert_eq!(import.dropped, [(3, "203.0.113.1 redirected.example.com".to_string())]);
+/// ```
+pub fn import(input: &str) -> Import {
+    let mut import = Import::default();
+    for (line_index, line) in input.lines().enumerate() {
+        let line = line.split('#').next().unwrap_or_default().trim();
+        let mut fields = line.split_whitespace();
+        let Some(address) = fields.next() else {
+            continue;
+        };
+        if !SINK_ADDRESSES.contains(&address) {
+            import.dropped.push((line_index + 1, line.to_string()));
+            continue;
+        }
+        for host in fields {
+            let host = host.trim_end_matches('.').to_ascii_lowercase();
+            if LOCAL_NAMES.contains(&host.as_str()) {
+                continue;
+            }
+            match is_host(&host) {
+                true => import
+                    .rules
+                    .push(Rule::new(false, Pattern::Host(host)).with_origin(Origin::line(line_index + 1))),
+                false => import.dropped.push((line_index + 1, host)),
+            }
+        }
+    }
+    import
+}
+
+fn is_host(host: &str) -> bool {
+    !host.is_empty()
+        && host
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '.' | '_'))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::GfwList;
+
+    #[test]
+    fn exact_hosts() {
+        let import = import("::1 ip6-localhost\n:: Ads.Example.COM.\n0.0.0.0 bad/host\n");
+        assert_eq!(import.dropped, [(3, "bad/host".to_string())]);
+        let gfw = GfwList::from_rules(import.rules).unwrap();
+        assert!(gfw.test("http://ads.example.com/").unwrap().is_some());
+        assert!(gfw.test("http://cdn.ads.example.com/").unwrap().is_none());
+    }
+}
@@ -0,0 +1,52 @@
+//! Policy configuration of [Knot Resolver](https://www.knot-resolver.cz/).
+//!
+//! Domains are matched at host level as described in [`DomainSets`]. Knot Resolver suffix policies always cover
+//! subdomains, so exact hosts are widened to their subdomains, and host regexes are not exported.
+
+use super::{DomainSets, Report, domain_fidelity};
+use crate::GfwList;
+
+/// Renders the rules of a [`GfwList`] as a Lua snippet for the Knot Resolver configuration.
+///
+/// Domains of positive rules are forwarded to `upstreams`. Domains of negative rules are added first with
+/// `policy.PASS`, so that they are resolved normally even below a forwarded domain.
+///
+/// # Examples
+///
+/// ```
+/// # use gfwlist::{GfwList, format::knot};
+/// let gfw = GfwList::from("||blocked-site.com\n@@||exception.com").unwrap();
+/// assert_eq!(
+///     knot::export(&gfw, &["8.8.8.8"]),
+///     "policy.add(policy.suffix(policy.PASS, policy.todnames({'exception.com.'})))\n\
+///      policy.add(policy.suffix(policy.FORWARD({'8.8.8.8'}), policy.todnames({'blocked-site.com.'})))\n",
+/// );
+/// ```
+pub fn export(list: &GfwList, upstreams: &[&str]) -> String {
+    let upstreams: Vec<String> = upstreams.iter().map(|upstream| lua_string(upstream)).collect();
+    let forward = format!("policy.FORWARD({{{}}})", upstreams.join(", "));
+    let mut output = String::new();
+    for (negative, action) in [(true, "policy.PASS"), (false, forward.as_str())] {
+        let domains: Vec<String> = (DomainSets::from_list(list, negative).hosts().into_iter())
+            .map(|domain| lua_string(&format!("{domain}.")))
+            .collect();
+        if domains.is_empty() {
+            continue;
+        }
+        output.push_str(&format!(
+            "policy.add(policy.suffix({action}, policy.todnames({{{}}})))\n",
+            domains.join(", "),
+        ));
+    }
+    output
+}
+
+/// Quotes a string as a single-quoted Lua string literal.
+fn lua_string(input: &str) -> String {
+    format!("'{}'", input.replace('\\', "\\\\").replace('\'', "\\'"))
+}
+
+/// Reports how the rules of a [`GfwList`] are converted by [`export`].
+pub fn fidelity(list: &GfwList) -> Report {
+    Report::new(list, |rule| domain_fidelity(rule.pattern(), false, false))
+}
@@ -0,0 +1,529 @@
+//! Conversions between GFW lists and the rule formats of other tools.
+//!
+//! Each submodule covers one foreign format. Exporters take a compiled [`GfwList`](crate::GfwList) and
+//! render the subset of its rules that the target format can express. Importers produce [`Rule`]s that can be
+//! compiled with [`GfwList::from_rules`](crate::GfwList::from_rules), together with the entries that could not
+//! be converted.
+//!
+//! # Ordering
+//!
+//! Exporters are deterministic: they emit rules in the order of the list, grouped by the sections of the target
+//! format. To get stable diffs of generated files regardless of how upstream lists are ordered, export a copy of the
+//! list sorted with [`ordered`] and [`Order::Sorted`].
+//!
+//! # Filtering
+//!
+//! To generate several tailored files from one master list, such as a lighter list for mobile devices, export copies
+//! of the list narrowed down with [`filtered`].
+
+use crate::host::is_under;
+use crate::ip::canonical_network;
+use crate::{BuildError, GfwList, Pattern, Rule};
+
+pub mod acl;
+pub mod adblock;
+pub mod adguard_home;
+pub mod clash;
+pub mod direct_list;
+pub mod dnsmasq;
+pub mod domain_list;
+pub mod ebpf;
+#[cfg(feature = "geosite")]
+pub mod geosite;
+pub mod hosts;
+pub mod knot;
+pub mod mosdns;
+pub mod privoxy;
+pub mod quantumult_x;
+pub mod sing_box;
+pub mod smartdns;
+pub mod squid;
+pub mod surge;
+pub mod switchy_omega;
+pub mod unbound;
+pub mod xray;
+
+/// The result of importing rules from a foreign format.
+#[derive(Debug, Clone, Default)]
+pub struct Import {
+    /// Rules converted from the input
+    pub rules: Vec<Rule>,
+    /// Entries that could not be represented, with their 1-based line numbers
+    pub dropped: Vec<(usize, String)>,
+}
+
+/// The order of the rules of an exported list.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum Order {
+    /// The order of the source rules
+    #[default]
+    Source,
+    /// Sorted by host with the labels reversed (so that `a.example.com` follows `example.com`), then by polarity and
+    /// rule text. Regex rules come first.
+    Sorted,
+}
+
+/// Returns a copy of the list with its rules in the given order.
+///
+/// The decisions of the list are unchanged, but [`GfwList::test`] may report a different rule when several rules
+/// match a URL.
+///
+/// # Examples
+///
+/// ```
+/// # use gfwlist::{GfwList, format::{Order, acl, ordered}};
+/// let gfw = GfwList::from("||www.example.com\n||example.org\n||example.com").unwrap();
+/// let sorted = ordered(&gfw, Order::Sorted).unwrap();
+/// assert!(acl::export(&sorted).contains("||example.com\n||www.example.com\n||example.org\n"));
+/// ```
+pub fn ordered(list: &GfwList, order: Order) -> Result<GfwList, BuildError> {
+    let mut rules: Vec<Rule> = list.iter_rules().cloned().collect();
+    if order == Order::Sorted {
+        rules.sort_by_cached_key(|rule| {
+            let host = rule.pattern().host().unwrap_or_default().to_ascii_lowercase();
+            let labels: Vec<String> = host.rsplit('.').map(str::to_string).collect();
+            (labels, rule.is_negative(), rule.text().to_string())
+        });
+    }
+    GfwList::from_rules(rules)
+}
+
+/// Selects the rules of a list to export, see [`filtered`].
+///
+/// The tags of a rule are its [section](Rule::section) and the name of its [source](crate::Origin::source). Suffixes
+/// are matched against the host of a rule, where `.cn` and `cn` both cover `cn` and its subdomains.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ExportFilter {
+    /// If not empty, only rules with one of these tags are kept
+    pub only_tags: Vec<String>,
+    /// Rules with one of these tags are left out
+    pub exclude_tags: Vec<String>,
+    /// If not empty, only rules whose host is covered by one of these suffixes are kept, which leaves out rules
+    /// without a host such as regexes
+    pub only_suffixes: Vec<String>,
+    /// Rules whose host is covered by one of these suffixes are left out
+    pub exclude_suffixes: Vec<String>,
+}
+
+impl ExportFilter {
+    /// Returns whether the filter keeps the rule.
+    pub fn accepts(&self, rule: &Rule) -> bool {
+        let host = rule.pattern().host();
+        let covered = |suffixes: &[String]| {
+            (host.as_deref()).is_some_and(|host| suffixes.iter().any(|suffix| is_under(host, suffix)))
+        };
+        (self.only_tags.is_empty() || self.only_tags.iter().any(|tag| rule.has_tag(tag)))
+            && !self.exclude_tags.iter().any(|tag| rule.has_tag(tag))
+            && (self.only_suffixes.is_empty() || covered(&self.only_suffixes))
+            && !covered(&self.exclude_suffixes)
+    }
+}
+
+/// Returns a copy of the list with only the rules kept by the filter.
+///
+/// # Examples
+///
+/// ```
+/// # use gfwlist::{GfwList, format::{ExportFilter, filtered}};
+/// let gfw = GfwList::from("||example.cn\n!---- Streaming ----\n||video.com\n||video.cn\n/ads/").unwrap();
+/// let filter = ExportFilter {
+///     exclude_tags: vec!["Streaming".into()],
+///     only_suffixes: vec![".cn".into()],
+///     ..Default::default()
+/// };
+/// let mobile = filtered(&gfw, &filter).unwrap();
+/// let rules: Vec<_> = mobile.rules().map(|(_, text)| text).collect();
+/// assert_eq!(rules, ["||example.cn"]);
+/// ```
+pub fn filtered(list: &GfwList, filter: &ExportFilter) -> Result<GfwList, BuildError> {
+    GfwList::from_rules(list.iter_rules().filter(|rule| filter.accepts(rule)).cloned())
+}
+
+/// How well a rule survives the conversion into a foreign format.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Fidelity {
+    /// The rule is converted with the same meaning, so a round trip through an importer keeps it unchanged
+    Lossless,
+    /// The rule is converted, but matches more or fewer URLs than the original
+    Approximated,
+    /// The rule cannot be expressed and is left out
+    Dropped,
+}
+
+/// The fidelity of each rule of a [`GfwList`] when converted into a foreign format.
+///
+/// Each exporting module provides a `fidelity` function producing this report, so that users know what they lose
+/// before converting a list.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Report {
+    /// Rules converted with the same meaning
+    pub lossless: Vec<Rule>,
+    /// Rules converted with a different meaning
+    pub approximated: Vec<Rule>,
+    /// Rules left out of the conversion
+    pub dropped: Vec<Rule>,
+}
+
+impl Report {
+    fn new(list: &GfwList, classify: impl Fn(&Rule) -> Fidelity) -> Self {
+        let mut report = Report::default();
+        for rule in list.iter_rules() {
+            let rules = match classify(rule) {
+                Fidelity::Lossless => &mut report.lossless,
+                Fidelity::Approximated => &mut report.approximated,
+                Fidelity::Dropped => &mut report.dropped,
+            };
+            rules.push(rule.clone());
+        }
+        report
+    }
+
+    /// Returns whether every rule is converted losslessly.
+    pub fn is_lossless(&self) -> bool {
+        self.approximated.is_empty() && self.dropped.is_empty()
+    }
+}
+
+/// Classifies a pattern for formats built on [`DomainSets`].
+///
+/// `exact_hosts` tells whether the format can match a single host without its subdomains, and `host_regexes` whether
+/// it can match host regexes.
+fn domain_fidelity(pattern: &Pattern, exact_hosts: bool, host_regexes: bool) -> Fidelity {
+    if pattern.host().is_none_or(|host| host.is_empty()) {
+        return Fidelity::Dropped;
+    }
+    match pattern {
+        Pattern::Regex(_) | Pattern::Cidr(_) => Fidelity::Dropped,
+        _ if pattern.path().is_some() => Fidelity::Approximated,
+        Pattern::Domain(_) => Fidelity::Lossless,
+        Pattern::Host(_) if exact_hosts => Fidelity::Lossless,
+        Pattern::Host(_) => Fidelity::Approximated,
+        // the scheme is dropped
+        Pattern::Url(_) if host_regexes => Fidelity::Approximated,
+        Pattern::Url(_) => Fidelity::Dropped,
+    }
+}
+
+/// The hosts covered by one polarity of a [`GfwList`], for formats that can only match domains.
+///
+/// Path constraints and schemes are dropped, and GFW list regular expressions are skipped since they are tested
+/// against full URLs. Each set keeps the order of the source rules.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DomainSets {
+    /// Domains matched together with their subdomains, from `||example.com` and `.example.com`
+    pub suffixes: Vec<String>,
+    /// Domains matched exactly, from `example.com` and URL rules with a path
+    pub full: Vec<String>,
+    /// Regular expressions matched against the host, from URL rules without a path
+    pub regexes: Vec<String>,
+}
+
+impl DomainSets {
+    /// Extracts the domain sets of the positive (or, if `negative` is set, the negative) rules of a list.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use gfwlist::{GfwList, format::DomainSets};
+    /// let gfw = GfwList::from("||example.com\nexample.org/path\n|http://example.net\n@@||direct.com").unwrap();
+    /// let sets = DomainSets::from_list(&gfw, false);
+    /// assert_eq!(sets.suffixes, ["example.com"]);
+    /// assert_eq!(sets.full, ["example.org"]);
+    /// assert_eq!(sets.regexes, ["^example\\.net"]);
+    /// ```
+    pub fn from_list(list: &GfwList, negative: bool) -> Self {
+        let mut sets = DomainSets::default();
+        for rule in list.iter_rules().filter(|rule| rule.is_negative() == negative) {
+            let pattern = rule.pattern();
+            let Some(host) = pattern.host().filter(|host| !host.is_empty()) else {
+                continue;
+            };
+            match pattern {
+                Pattern::Domain(_) => sets.suffixes.push(host),
+                Pattern::Url(_) if pattern.path().is_none() => sets.regexes.push(format!("^{}", regex::escape(&host))),
+                Pattern::Host(_) | Pattern::Url(_) => sets.full.push(host),
+                Pattern::Regex(_) | Pattern::Cidr(_) => {}
+            }
+        }
+        sets
+    }
+
+    /// Returns the domains of [`suffixes`](Self::suffixes) and [`full`](Self::full) without duplicates, for formats
+    /// that cannot match exactly one host.
+    pub fn hosts(&self) -> Vec<&str> {
+        let mut seen = std::collections::HashSet::new();
+        (self.suffixes.iter().chain(&self.full))
+            .map(String::as_str)
+            .filter(|host| seen.insert(*host))
+            .collect()
+    }
+
+    /// Returns whether all sets are empty.
+    pub fn is_empty(&self) -> bool {
+        self.suffixes.is_empty() && self.full.is_empty() && self.regexes.is_empty()
+    }
+}
+
+/// Returns a regular expression matching URLs whose host contains a keyword.
+fn host_keyword(keyword: &str) -> Pattern {
+    Pattern::Regex(format!("^[^:]+:\\/\\/[^\\/]*{}", regex::escape(keyword)))
+}
+
+/// Quotes a string as a JSON string literal.
+pub(crate) fn json_string(input: &str) -> String {
+    let mut output = String::with_capacity(input.len() + 2);
+    output.push('"');
+    for c in input.chars() {
+        match c {
+            '"' => output.push_str("\\\""),
+            '\\' => output.push_str("\\\\"),
+            '\n' => output.push_str("\\n"),
+            '\r' => output.push_str("\\r"),
+            '\t' => output.push_str("\\t"),
+            c if (c as u32) < 0x20 => output.push_str(&format!("\\u{:04x}", c as u32)),
+            c => output.push(c),
+        }
+    }
+    output.push('"');
+    output
+}
+
+/// Renders a list of JSON objects, each given as its `(key, value)` members, indented by two spaces.
+pub(crate) fn json_objects(objects: &[Vec<(&str, String)>]) -> String {
+    let objects: Vec<String> = objects
+        .iter()
+        .map(|members| {
+            let members: Vec<String> = members
+                .iter()
+                .map(|(key, value)| format!("    {}: {value}", json_string(key)))
+                .collect();
+            format!("  {{\n{}\n  }}", members.join(",\n"))
+        })
+        .collect();
+    if objects.is_empty() {
+        return "[]\n".to_string();
+    }
+    format!("[\n{}\n]\n", objects.join(",\n"))
+}
+
+/// Renders a list of strings as a single-line JSON array.
+fn json_array<S: AsRef<str>>(items: impl IntoIterator<Item = S>) -> String {
+    let items: Vec<String> = items.into_iter().map(|item| json_string(item.as_ref())).collect();
+    format!("[{}]", items.join(", "))
+}
+
+/// Converts an escaped domain regex such as `example\.com` back into a plain domain.
+fn unescape_domain(input: &str) -> Option<String> {
+    let mut output = String::with_capacity(input.len());
+    let mut chars = input.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => match chars.next()? {
+                '.' => output.push('.'),
+                '-' => output.push('-'),
+                _ => return None,
+            },
+            'a'..='z' | 'A'..='Z' | '0'..='9' | '-' => output.push(c),
+            _ => return None,
+        }
+    }
+    Some(output).filter(|s| !s.is_empty())
+}
+
+/// Converts an IP address or a CIDR block into an IP pattern.
+fn cidr(input: &str) -> Option<Pattern> {
+    canonical_network(input).map(Pattern::Cidr)
+}
+
+/// A parsed JSON value. Objects remember the 1-based line they start on, for the line numbers of [`Import`].
+#[derive(Debug, Clone, PartialEq)]
+enum Json {
+    Null,
+    Bool(bool),
+    Number(f64),
+    String(String),
+    Array(Vec<Json>),
+    Object(usize, Vec<(String, Json)>),
+}
+
+impl Json {
+    /// Parses a JSON document, returning the line of the first error on failure.
+    fn parse(input: &str) -> Result<Json, usize> {
+        let mut parser = JsonParser {
+            chars: input.chars().peekable(),
+            line: 1,
+            depth: 0,
+        };
+        let value = parser.value().ok_or(parser.line)?;
+        match parser.skip_whitespace() {
+            None => Ok(value),
+            Some(_) => Err(parser.line),
+        }
+    }
+
+    /// Returns the member of an object with the given key.
+    fn get(&self, key: &str) -> Option<&Json> {
+        match self {
+            Json::Object(_, members) => members.iter().find(|(k, _)| k == key).map(|(_, value)| value),
+            _ => None,
+        }
+    }
+
+    fn as_str(&self) -> Option<&str> {
+        match self {
+            Json::String(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    /// Visits the value and all values nested in it, parents first.
+    fn walk<'a>(&'a self, visit: &mut impl FnMut(&'a Json)) {
+        visit(self);
+        match self {
+            Json::Array(items) => items.iter().for_each(|item| item.walk(visit)),
+            Json::Object(_, members) => members.iter().for_each(|(_, value)| value.walk(visit)),
+            _ => {}
+        }
+    }
+}
+
+/// The deepest nesting of arrays and objects in a JSON document, so that crafted files cannot overflow the stack.
+const MAX_JSON_DEPTH: usize = 128;
+
+struct JsonParser<'a> {
+    chars: std::iter::Peekable<std::str::Chars<'a>>,
+    line: usize,
+    /// The number of arrays and objects enclosing the current value
+    depth: usize,
+}
+
+impl JsonParser<'_> {
+    fn skip_whitespace(&mut self) -> Option<char> {
+        while let Some(&c) = self.chars.peek() {
+            match c {
+                '\n' => self.line += 1,
+                ' ' | '\t' | '\r' => {}
+                _ => return Some(c),
+            }
+            self.chars.next();
+        }
+        None
+    }
+
+    fn expect(&mut self, expected: char) -> Option<()> {
+        (self.skip_whitespace()? == expected)
+            .then(|| self.chars.next())
+            .map(|_| ())
+    }
+
+    fn value(&mut self) -> Option<Json> {
+        if self.depth == MAX_JSON_DEPTH {
+            return None;
+        }
+        self.depth += 1;
+        let value = self.nested_value();
+        self.depth -= 1;
+        value
+    }
+
+    /// Parses a value one level deeper, see [`value`](Self::value).
+    fn nested_value(&mut self) -> Option<Json> {
+        match self.skip_whitespace()? {
+            '{' => {
+                let line = self.line;
+                self.chars.next();
+                let mut members = vec![];
+                if self.skip_whitespace()? == '}' {
+                    self.chars.next();
+                    return Some(Json::Object(line, members));
+                }
+                loop {
+                    self.expect('"')?;
+                    let key = self.string()?;
+                    self.expect(':')?;
+                    members.push((key, self.value()?));
+                    match self.skip_whitespace()? {
+                        ',' => self.chars.next(),
+                        '}' => break,
+                        _ => return None,
+                    };
+                }
+                self.chars.next();
+                Some(Json::Object(line, members))
+            }
+            '[' => {
+                self.chars.next();
+                let mut items = vec![];
+                if self.skip_whitespace()? == ']' {
+                    self.chars.next();
+                    return Some(Json::Array(items));
+                }
+                loop {
+                    items.push(self.value()?);
+                    match self.skip_whitespace()? {
+                        ',' => self.chars.next(),
+                        ']' => break,
+                        _ => return None,
+                    };
+                }
+                self.chars.next();
+                Some(Json::Array(items))
+            }
+            '"' => {
+                self.chars.next();
+                self.string().map(Json::String)
+            }
+            _ => {
+                let mut token = String::new();
+                while let Some(&c) = self
+                    .chars
+                    .peek()
+                    .filter(|c| c.is_ascii_alphanumeric() || "+-.".contains(**c))
+                {
+                    token.push(c);
+                    self.chars.next();
+                }
+                match token.as_str() {
+                    "null" => Some(Json::Null),
+                    "true" => Some(Json::Bool(true)),
+                    "false" => Some(Json::Bool(false)),
+                    _ => token.parse().ok().map(Json::Number),
+                }
+            }
+        }
+    }
+
+    /// Parses the rest of a string after its opening quote.
+    fn string(&mut self) -> Option<String> {
+        let mut output = String::new();
+        loop {
+            match self.chars.next()? {
+                '"' => return Some(output),
+                '\\' => match self.chars.next()? {
+                    'n' => output.push('\n'),
+                    'r' => output.push('\r'),
+                    't' => output.push('\t'),
+                    'b' => output.push('\u{8}'),
+                    'f' => output.push('\u{c}'),
+                    'u' => {
+                        let mut code = self.hex4()?;
+                        if (0xd800..0xdc00).contains(&code) {
+                            self.expect('\\')?;
+                            self.expect('u')?;
+                            code = 0x10000 + ((code - 0xd800) << 10) + (self.hex4()?.checked_sub(0xdc00)? & 0x3ff);
+                        }
+                        output.push(char::from_u32(code)?);
+                    }
+                    c => output.push(c),
+                },
+                '\n' => return None,
+                c => output.push(c),
+            }
+        }
+    }
+
+    fn hex4(&mut self) -> Option<u32> {
+        let digits: String = (0..4).map(|_| self.chars.next()).collect::<Option<_>>()?;
+        u32::from_str_radix(&digits, 16).ok()
+    }
+}
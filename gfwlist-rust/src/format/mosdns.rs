@@ -0,0 +1,39 @@
+//! Domain set files of [MosDNS](https://github.com/IrineSistiana/mosdns).
+//!
+//! A domain set file holds one domain matcher per line, using the `domain:`, `full:` and `regexp:` prefixes. Domains
+//! are matched at host level as described in [`DomainSets`]. A MosDNS domain set has no polarity, so the positive and
+//! negative rules are exported as separate files.
+
+use super::{DomainSets, Report, domain_fidelity};
+use crate::GfwList;
+
+/// Renders the domains of the positive (or, if `negative` is set, the negative) rules of a [`GfwList`] as a MosDNS
+/// domain set file.
+///
+/// # Examples
+///
+/// ```
+/// # use gfwlist::{GfwList, format::mosdns};
+/// let gfw = GfwList::from("||blocked-site.com\n|http://blocked.com\n@@exception.com").unwrap();
+/// assert_eq!(mosdns::export(&gfw, false), "domain:blocked-site.com\nregexp:^blocked\\.com\n");
+/// assert_eq!(mosdns::export(&gfw, true), "full:exception.com\n");
+/// ```
+pub fn export(list: &GfwList, negative: bool) -> String {
+    let sets = DomainSets::from_list(list, negative);
+    let mut output = String::new();
+    for (prefix, values) in [
+        ("domain", &sets.suffixes),
+        ("full", &sets.full),
+        ("regexp", &sets.regexes),
+    ] {
+        for value in values {
+            output.push_str(&format!("{prefix}:{value}\n"));
+        }
+    }
+    output
+}
+
+/// Reports how the rules of a [`GfwList`] are converted by [`export`].
+pub fn fidelity(list: &GfwList) -> Report {
+    Report::new(list, |rule| domain_fidelity(rule.pattern(), true, true))
+}
@@ -0,0 +1,88 @@
+//! Action files of [Privoxy](https://www.privoxy.org/).
+//!
+//! The classic deployment puts Privoxy in front of a SOCKS proxy and forwards only the listed hosts to it. The
+//! exported action file contains one `forward-override` section for the positive rules and one for the negative rules,
+//! which come last so that Privoxy lets them take precedence.
+//!
+//! Privoxy patterns consist of a host and an optional path:
+//!
+//! - `||example.com` becomes `.example.com`, which also covers subdomains.
+//! - `example.com` and `|http://example.com` become the exact host `example.com`; the scheme is not preserved.
+//! - Paths are exported as prefixes, so `/page` also covers `/pages`.
+//! - GFW list regular expressions are tested against full URLs and are not exported.
+
+use super::{Fidelity, Report};
+use crate::{GfwList, Pattern};
+
+/// Renders the rules of a [`GfwList`] as a Privoxy action file forwarding matched hosts to a SOCKS5 proxy.
+///
+/// # Examples
+///
+/// ```
+/// # use gfwlist::{GfwList, format::privoxy};
+/// let gfw = GfwList::from("||blocked-site.com\n@@||exception.com").unwrap();
+/// assert_eq!(
+///     privoxy::export(&gfw, "127.0.0.1:1080"),
+///     "{+forward-override{forward-socks5 127.0.0.1:1080 .}}\n.blocked-site.com\n\n\
+///      {+forward-override{forward .}}\n.exception.com\n",
+/// );
+/// ```
+pub fn export(list: &GfwList, socks_addr: &str) -> String {
+    let mut proxy_list = String::new();
+    let mut direct_list = String::new();
+    for rule in list.iter_rules() {
+        let Some(entry) = entry(rule.pattern()) else {
+            continue;
+        };
+        let output = if rule.is_negative() {
+            &mut direct_list
+        } else {
+            &mut proxy_list
+        };
+        output.push_str(&entry);
+        output.push('\n');
+    }
+    format!(
+        "{{+forward-override{{forward-socks5 {socks_addr} .}}}}\n{proxy_list}\n{{+forward-override{{forward \
+         .}}}}\n{direct_list}"
+    )
+}
+
+fn entry(pattern: &Pattern) -> Option<String> {
+    let host = pattern.host().filter(|host| !host.is_empty())?;
+    let mut entry = match pattern {
+        Pattern::Domain(_) => format!(".{host}"),
+        Pattern::Host(_) | Pattern::Url(_) => host,
+        Pattern::Regex(_) | Pattern::Cidr(_) => return None,
+    };
+    if let Some(path) = pattern.path() {
+        entry.push_str(&regex::escape(&path));
+    }
+    Some(entry)
+}
+
+/// Reports how the rules of a [`GfwList`] are converted by [`export`].
+pub fn fidelity(list: &GfwList) -> Report {
+    Report::new(list, |rule| match entry(rule.pattern()) {
+        None => Fidelity::Dropped,
+        Some(_) if rule.pattern().path().is_some() || matches!(rule.pattern(), Pattern::Url(_)) => {
+            Fidelity::Approximated
+        }
+        Some(_) => Fidelity::Lossless,
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn export_paths() {
+        let gfw = GfwList::from("|http://example.com/a.b\nexample.org/path\n/regex/\n@@.example.net").unwrap();
+        assert_eq!(
+            export(&gfw, "127.0.0.1:1080"),
+            "{+forward-override{forward-socks5 127.0.0.1:1080 \
+             .}}\nexample.com/a\\.b\nexample.org/path\n\n{+forward-override{forward .}}\n.example.net\n",
+        );
+    }
+}
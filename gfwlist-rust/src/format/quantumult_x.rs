@@ -0,0 +1,117 @@
+//! Filter resources of [Quantumult X](https://github.com/crossutility/Quantumult-X).
+//!
+//! A filter has one rule per line of the form `type, value, policy`, where the type is case-insensitive and the policy
+//! may be omitted in filter resources. The `[filter_local]` section of a configuration is read as well. The types map
+//! onto rules as follows:
+//!
+//! - `host, example.com` becomes `example.com`.
+//! - `host-suffix, example.com` becomes `||example.com`.
+//! - `host-keyword, example` becomes a regular expression matching URLs whose host contains `example`.
+//! - `url-regex, ^https?://example\.com/ads` becomes the same regular expression, since both are tested against the
+//!   full URL.
+//! - `ip-cidr, 8.8.8.0/24` and `ip6-cidr, 2001:db8::/32` become IP rules, see [`Pattern::Cidr`].
+//!
+//! Rules with the `direct` policy become negative rules, and all other rules positive ones. `geoip`, `final` and all
+//! other types cannot be represented and are dropped. Lines starting with `#`, `;` or `//` are comments.
+
+use super::surge::{escape_slashes, is_policy};
+use super::{Import, cidr, host_keyword};
+use crate::{Origin, Pattern, Rule};
+
+/// Parses a Quantumult X filter resource or configuration into rules.
+///
+/// # Examples
+///
+/// ```
+/// # use gfwlist::format::quantumult_x;
+/// let import = quantumult_x::import("\
+/// [general]
+/// network_check_url = http://www.google.com/generate_204
+/// [filter_local]
+/// host-suffix, google.com, proxy
+/// HOST, www.example.cn, DIRECT
+/// url-regex, ^http://ads\\.example\\.com/, reject
+/// ip-cidr, 10.0.0.0/8, direct
+/// final, proxy
+/// ");
+/// let rules: Vec<_> = import.rules.iter().map(|rule| rule.text()).collect();
+/// assert_eq!(rules[..3], ["||google.com", "@@www.example.cn", "/^http:\\/\\/ads\\.example\\.com\\//"]);
+/// assert_eq!(rules[3], "@@IP-CIDR,10.0.0.0/8");
+/// assert_eq!(import.dropped, [(8, "final, proxy".to_string())]);
+/// ```
+pub fn import(input: &str) -> Import {
+    let mut import = Import::default();
+    let mut in_filters = true;
+    for (line_index, line) in input.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with(['#', ';']) || line.starts_with("//") {
+            continue;
+        }
+        if let Some(section) = line.strip_prefix('[').and_then(|line| line.strip_suffix(']')) {
+            in_filters = section.trim().eq_ignore_ascii_case("filter_local");
+            continue;
+        }
+        if !in_filters {
+            continue;
+        }
+        match parse_entry(line) {
+            Some((negative, pattern)) => import
+                .rules
+                .push(Rule::new(negative, pattern).with_origin(Origin::line(line_index + 1))),
+            None => import.dropped.push((line_index + 1, line.to_string())),
+        }
+    }
+    import
+}
+
+/// Parses an entry into a pattern and whether it is negative.
+fn parse_entry(entry: &str) -> Option<(bool, Pattern)> {
+    let (kind, rest) = entry.split_once(',')?;
+    let kind = kind.trim().to_ascii_lowercase();
+    let (value, policy) = match kind.as_str() {
+        // regexes may contain commas, so only a last field that looks like a policy name is split off
+        "url-regex" => match rest.rsplit_once(',') {
+            Some((value, policy)) if is_policy(policy.trim()) => (value, Some(policy.trim())),
+            _ => (rest, None),
+        },
+        _ => {
+            let mut fields = rest.split(',');
+            (fields.next().unwrap_or_default(), fields.next().map(str::trim))
+        }
+    };
+    let negative = policy.is_some_and(|policy| policy.eq_ignore_ascii_case("direct"));
+    let value = value.trim();
+    if value.is_empty() {
+        return None;
+    }
+    let pattern = match kind.as_str() {
+        "host" => Pattern::Host(value.to_ascii_lowercase()),
+        "host-suffix" => Pattern::Domain(value.to_ascii_lowercase()),
+        "host-keyword" => host_keyword(value),
+        "url-regex" => {
+            regex::Regex::new(value).ok()?;
+            Pattern::Regex(escape_slashes(value))
+        }
+        "ip-cidr" | "ip6-cidr" => cidr(value)?,
+        _ => return None,
+    };
+    Some((negative, pattern))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::GfwList;
+
+    #[test]
+    fn filter_resources() {
+        let import = import("# list\nHOST-KEYWORD,ads\nhost-suffix,example.org\nuser-agent,App*\nurl-regex,(\n");
+        assert_eq!(import.dropped.len(), 2);
+        let gfw = GfwList::from_rules(import.rules).unwrap();
+        assert!(gfw.test("https://cdn.example.org/").unwrap().is_some());
+        assert!(gfw.test("https://ads.example.com/").unwrap().is_some());
+        assert!(gfw.test("https://example.com/ads").unwrap().is_none());
+        let import = super::import("url-regex, ^https?://a{1,3}\\.com, Direct");
+        assert_eq!(import.rules[0].to_string(), "@@/^https?:\\/\\/a{1,3}\\.com/");
+    }
+}
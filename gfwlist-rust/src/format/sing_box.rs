@@ -0,0 +1,63 @@
+//! Route rules of [sing-box](https://sing-box.sagernet.org/).
+//!
+//! The export is the content of the `route.rules` array. Domains are matched at host level as described in
+//! [`DomainSets`], using the `domain`, `domain_suffix` and `domain_regex` fields. sing-box uses the first matching
+//! rule, so the negative rules are emitted before the positive rules.
+
+use super::{DomainSets, Report, domain_fidelity, json_array, json_objects, json_string};
+use crate::GfwList;
+
+/// Renders the rules of a [`GfwList`] as sing-box route rules.
+///
+/// Negative rules are routed to `direct_tag` and positive rules to `proxy_tag`. Empty fields and rules are omitted.
+///
+/// # Examples
+///
+/// ```
+/// # use gfwlist::{GfwList, format::sing_box};
+/// let gfw = GfwList::from("||blocked-site.com\n|http://blocked.com\n@@exception.com").unwrap();
+/// assert_eq!(
+///     sing_box::export(&gfw, "proxy", "direct"),
+///     r#"[
+///   {
+///     "domain": ["exception.com"],
+///     "outbound": "direct"
+///   },
+///   {
+///     "domain_suffix": ["blocked-site.com"],
+///     "domain_regex": ["^blocked\\.com"],
+///     "outbound": "proxy"
+///   }
+/// ]
+/// "#,
+/// );
+/// ```
+pub fn export(list: &GfwList, proxy_tag: &str, direct_tag: &str) -> String {
+    let rules: Vec<_> = [(true, direct_tag), (false, proxy_tag)]
+        .into_iter()
+        .filter_map(|(negative, tag)| {
+            let sets = DomainSets::from_list(list, negative);
+            if sets.is_empty() {
+                return None;
+            }
+            let mut members = vec![];
+            for (key, values) in [
+                ("domain", &sets.full),
+                ("domain_suffix", &sets.suffixes),
+                ("domain_regex", &sets.regexes),
+            ] {
+                if !values.is_empty() {
+                    members.push((key, json_array(values)));
+                }
+            }
+            members.push(("outbound", json_string(tag)));
+            Some(members)
+        })
+        .collect();
+    json_objects(&rules)
+}
+
+/// Reports how the rules of a [`GfwList`] are converted by [`export`].
+pub fn fidelity(list: &GfwList) -> Report {
+    Report::new(list, |rule| domain_fidelity(rule.pattern(), true, true))
+}
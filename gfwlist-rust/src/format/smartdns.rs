@@ -0,0 +1,38 @@
+//! Configuration of [SmartDNS](https://github.com/pymumu/smartdns).
+//!
+//! Domains are matched at host level as described in [`DomainSets`]. SmartDNS `nameserver` rules always cover
+//! subdomains, so exact hosts are widened to their subdomains, and host regexes are not exported.
+
+use super::{DomainSets, Report, domain_fidelity};
+use crate::GfwList;
+
+/// Renders the rules of a [`GfwList`] as SmartDNS `nameserver` lines.
+///
+/// Domains of positive rules are resolved by the server group `group`. Domains of negative rules use the special group
+/// `-`, which makes SmartDNS fall back to its default servers.
+///
+/// # Examples
+///
+/// ```
+/// # use gfwlist::{GfwList, format::smartdns};
+/// let gfw = GfwList::from("||blocked-site.com\nblocked-host.com\n@@||exception.com").unwrap();
+/// assert_eq!(
+///     smartdns::export(&gfw, "oversea"),
+///     "nameserver /blocked-site.com/oversea\nnameserver /blocked-host.com/oversea\nnameserver /exception.com/-\n",
+/// );
+/// ```
+pub fn export(list: &GfwList, group: &str) -> String {
+    let mut output = String::new();
+    for (negative, group) in [(false, group), (true, "-")] {
+        let sets = DomainSets::from_list(list, negative);
+        for domain in sets.hosts() {
+            output.push_str(&format!("nameserver /{domain}/{group}\n"));
+        }
+    }
+    output
+}
+
+/// Reports how the rules of a [`GfwList`] are converted by [`export`].
+pub fn fidelity(list: &GfwList) -> Report {
+    Report::new(list, |rule| domain_fidelity(rule.pattern(), false, false))
+}
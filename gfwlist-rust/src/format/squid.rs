@@ -0,0 +1,50 @@
+//! `dstdomain` ACL files of the [Squid](https://www.squid-cache.org/) proxy.
+//!
+//! Domains are matched at host level as described in [`DomainSets`]: `||example.com` becomes `.example.com`, which
+//! also covers subdomains, and exact hosts are kept as is. Host regexes would need a separate `dstdom_regex` ACL and
+//! are not exported.
+//!
+//! A `dstdomain` ACL has no polarity, so the positive and negative rules are exported as separate files. Both files
+//! start with comments showing how to route the positive domains through a `cache_peer`, except for the negative
+//! domains.
+
+use super::{DomainSets, Report, domain_fidelity};
+use crate::GfwList;
+
+/// Renders the domains of the positive (or, if `negative` is set, the negative) rules of a [`GfwList`] as a Squid
+/// `dstdomain` ACL file.
+///
+/// The leading comments refer to the cache peer `peer` and to the ACLs `gfwlist` and `gfwlist_direct`, loaded from
+/// the positive and negative files respectively.
+///
+/// # Examples
+///
+/// ```
+/// # use gfwlist::{GfwList, format::squid};
+/// let gfw = GfwList::from("||blocked-site.com\nblocked-host.com\n@@||exception.com").unwrap();
+/// let output = squid::export(&gfw, false, "upstream");
+/// assert!(output.contains("# cache_peer_access upstream allow gfwlist\n"));
+/// assert!(output.ends_with("\n.blocked-site.com\nblocked-host.com\n"));
+/// assert!(squid::export(&gfw, true, "upstream").ends_with("\n.exception.com\n"));
+/// ```
+pub fn export(list: &GfwList, negative: bool, peer: &str) -> String {
+    let mut output = format!(
+        "# Squid dstdomain ACL generated from a GFW list.\n#\n# acl gfwlist dstdomain \"/etc/squid/gfwlist.acl\"\n# \
+         acl gfwlist_direct dstdomain \"/etc/squid/gfwlist_direct.acl\"\n# cache_peer_access {peer} deny \
+         gfwlist_direct\n# cache_peer_access {peer} allow gfwlist\n# cache_peer_access {peer} deny all\n# \
+         never_direct allow gfwlist !gfwlist_direct\n\n"
+    );
+    let sets = DomainSets::from_list(list, negative);
+    for domain in &sets.suffixes {
+        output.push_str(&format!(".{domain}\n"));
+    }
+    for domain in &sets.full {
+        output.push_str(&format!("{domain}\n"));
+    }
+    output
+}
+
+/// Reports how the rules of a [`GfwList`] are converted by [`export`].
+pub fn fidelity(list: &GfwList) -> Report {
+    Report::new(list, |rule| domain_fidelity(rule.pattern(), true, false))
+}
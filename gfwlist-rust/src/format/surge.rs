@@ -0,0 +1,139 @@
+//! Rulesets of [Surge](https://manual.nssurge.com/) and Shadowrocket.
+//!
+//! A ruleset has one rule per line of the form `TYPE,value`, while a domain set lists bare domains, where
+//! `.example.com` also covers subdomains. The `[Rule]` section of a configuration is read as well, where rules have the
+//! form `TYPE,value,policy`. The types map onto rules as follows:
+//!
+//! - `DOMAIN,example.com` becomes `example.com`.
+//! - `DOMAIN-SUFFIX,example.com` becomes `||example.com`.
+//! - `DOMAIN-KEYWORD,example` becomes a regular expression matching URLs whose host contains `example`.
+//! - `URL-REGEX,^https?://example\.com/ads` becomes the same regular expression, since both are tested against the full
+//!   URL.
+//! - `IP-CIDR,8.8.8.0/24` and `IP-CIDR6,2001:db8::/32` become IP rules, see [`Pattern::Cidr`].
+//!
+//! Rules with the `DIRECT` policy become negative rules, and all other rules positive ones. `GEOIP`, references to
+//! other rulesets, `FINAL` and all other types cannot be represented and are dropped. Lines starting with `#`, `;` or
+//! `//` are comments.
+
+use super::{Import, cidr, host_keyword};
+use crate::{Origin, Pattern, Rule};
+
+/// Parses a Surge ruleset, domain set or configuration into rules.
+///
+/// # Examples
+///
+/// ```
+/// # use gfwlist::format::surge;
+/// let import = surge::import("\
+/// [General]
+/// loglevel = notify
+/// [Rule]
+/// DOMAIN-SUFFIX,google.com,Proxy
+/// URL-REGEX,^http://ads\\.example\\.com/,REJECT
+/// DOMAIN-SUFFIX,cn,DIRECT
+/// IP-CIDR,10.0.0.0/8,DIRECT,no-resolve
+/// FINAL,Proxy
+/// ");
+/// let rules: Vec<_> = import.rules.iter().map(|rule| rule.text()).collect();
+/// assert_eq!(rules, ["||google.com", "/^http:\\/\\/ads\\.example\\.com\\//", "@@||cn", "@@IP-CIDR,10.0.0.0/8"]);
+/// assert_eq!(import.dropped, [(8, "FINAL,Proxy".to_string())]);
+/// ```
+pub fn import(input: &str) -> Import {
+    let mut import = Import::default();
+    let mut in_rules = true;
+    for (line_index, line) in input.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with(['#', ';']) || line.starts_with("//") {
+            continue;
+        }
+        if let Some(section) = line.strip_prefix('[').and_then(|line| line.strip_suffix(']')) {
+            in_rules = section.eq_ignore_ascii_case("rule");
+            continue;
+        }
+        if !in_rules {
+            continue;
+        }
+        match parse_entry(line) {
+            Some((negative, pattern)) => import
+                .rules
+                .push(Rule::new(negative, pattern).with_origin(Origin::line(line_index + 1))),
+            None => import.dropped.push((line_index + 1, line.to_string())),
+        }
+    }
+    import
+}
+
+/// Parses an entry into a pattern and whether it is negative.
+fn parse_entry(entry: &str) -> Option<(bool, Pattern)> {
+    let Some((kind, rest)) = entry.split_once(',') else {
+        // domain sets list bare domains
+        let is_domain = entry.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '.');
+        return match entry.strip_prefix('.') {
+            _ if !is_domain => None,
+            Some(domain) => Some((false, Pattern::Domain(domain.to_string()))),
+            None => Some((false, Pattern::Host(entry.to_string()))),
+        };
+    };
+    let kind = kind.trim();
+    let (value, policy) = match kind {
+        // regexes may contain commas, so only a last field that looks like a policy name is split off
+        "URL-REGEX" => match rest.rsplit_once(',') {
+            Some((value, policy)) if is_policy(policy.trim()) => (value, Some(policy.trim())),
+            _ => (rest, None),
+        },
+        _ => {
+            let mut fields = rest.split(',');
+            (fields.next().unwrap_or_default(), fields.next().map(str::trim))
+        }
+    };
+    let negative = policy == Some("DIRECT");
+    let value = value.trim().to_string();
+    let pattern = match kind {
+        "DOMAIN" => Pattern::Host(value),
+        "DOMAIN-SUFFIX" => Pattern::Domain(value),
+        "DOMAIN-KEYWORD" => host_keyword(&value),
+        "URL-REGEX" => {
+            regex::Regex::new(&value).ok()?;
+            Pattern::Regex(escape_slashes(&value))
+        }
+        "IP-CIDR" | "IP-CIDR6" => cidr(&value)?,
+        _ => return None,
+    };
+    Some((negative, pattern))
+}
+
+/// Escapes the slashes of a regular expression, as GFW list regexes are written.
+pub(super) fn escape_slashes(regex: &str) -> String {
+    let mut output = String::with_capacity(regex.len());
+    let mut escaped = false;
+    for c in regex.chars() {
+        if c == '/' && !escaped {
+            output.push('\\');
+        }
+        escaped = c == '\\' && !escaped;
+        output.push(c);
+    }
+    output
+}
+
+/// Returns whether a field looks like the name of a policy, such as `DIRECT` or `Proxy`.
+pub(super) fn is_policy(field: &str) -> bool {
+    !field.is_empty() && (field.chars()).all(|c| c.is_alphanumeric() || matches!(c, '-' | '_' | ' '))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::GfwList;
+
+    #[test]
+    fn rulesets_and_domain_sets() {
+        let import = import("// list\n.example.com\nexample.org\nRULE-SET,https://example.com/list.txt\nURL-REGEX,(\n");
+        assert_eq!(import.dropped.len(), 2);
+        let gfw = GfwList::from_rules(import.rules).unwrap();
+        assert!(gfw.test("https://cdn.example.com/").unwrap().is_some());
+        assert!(gfw.test("https://cdn.example.org/").unwrap().is_none());
+        let import = super::import("URL-REGEX,^https?://a{1,3}\\.com,DIRECT");
+        assert_eq!(import.rules[0].to_string(), "@@/^https?:\\/\\/a{1,3}\\.com/");
+    }
+}
@@ -0,0 +1,219 @@
+//! Switch rules of [SwitchyOmega](https://github.com/FelisCatus/SwitchyOmega).
+//!
+//! The export is the content of the `rules` array of a switch profile in a SwitchyOmega options backup (`.bak`).
+//! SwitchyOmega uses the first matching rule, so the negative rules are emitted before the positive rules. Patterns
+//! are converted into conditions as follows:
+//!
+//! - `||example.com` becomes the host wildcard `*.example.com`, which SwitchyOmega also matches against `example.com`
+//!   itself.
+//! - `example.com` becomes the host wildcard `example.com`.
+//! - `|http://example.com/path` becomes the URL wildcard `http://example.com/path*`.
+//! - Host patterns with a path become URL wildcards for any scheme, which only approximate the subdomains of domain
+//!   patterns.
+//! - GFW list regular expressions become URL regex conditions.
+//!
+//! The importer accepts a whole backup as well as a single `rules` array, and converts the same conditions back.
+
+use super::{Fidelity, Import, Json, Report, json_objects, json_string};
+use crate::{GfwList, Origin, Pattern, Rule};
+
+/// Renders the rules of a [`GfwList`] as SwitchyOmega switch rules.
+///
+/// Negative rules are switched to `direct_profile` and positive rules to `proxy_profile`.
+///
+/// # Examples
+///
+/// ```
+/// # use gfwlist::{GfwList, format::switchy_omega};
+/// let gfw = GfwList::from("||blocked-site.com\n/^https:\\/\\/blocked/\n@@exception.com").unwrap();
+/// assert_eq!(
+///     switchy_omega::export(&gfw, "proxy", "direct"),
+///     r#"[
+///   {
+///     "condition": {"conditionType": "HostWildcardCondition", "pattern": "exception.com"},
+///     "profileName": "direct"
+///   },
+///   {
+///     "condition": {"conditionType": "HostWildcardCondition", "pattern": "*.blocked-site.com"},
+///     "profileName": "proxy"
+///   },
+///   {
+///     "condition": {"conditionType": "UrlRegexCondition", "pattern": "^https:\\/\\/blocked"},
+///     "profileName": "proxy"
+///   }
+/// ]
+/// "#,
+/// );
+/// ```
+pub fn export(list: &GfwList, proxy_profile: &str, direct_profile: &str) -> String {
+    let mut rules = vec![];
+    for (negative, profile) in [(true, direct_profile), (false, proxy_profile)] {
+        for rule in list.iter_rules().filter(|rule| rule.is_negative() == negative) {
+            let Some((condition_type, pattern)) = condition(rule.pattern()) else {
+                continue;
+            };
+            let condition = format!(
+                "{{\"conditionType\": {}, \"pattern\": {}}}",
+                json_string(condition_type),
+                json_string(&pattern),
+            );
+            rules.push(vec![("condition", condition), ("profileName", json_string(profile))]);
+        }
+    }
+    json_objects(&rules)
+}
+
+fn condition(pattern: &Pattern) -> Option<(&'static str, String)> {
+    match pattern {
+        Pattern::Regex(regex) => Some(("UrlRegexCondition", regex.clone())),
+        Pattern::Cidr(_) => None,
+        // wildcard characters cannot be escaped
+        Pattern::Url(url) if url.contains(['*', '?', '|']) => None,
+        Pattern::Url(url) => Some(("UrlWildcardCondition", format!("{url}*"))),
+        Pattern::Domain(_) | Pattern::Host(_) => {
+            let host = pattern.host().filter(|host| !host.is_empty())?;
+            let wildcard = match pattern {
+                Pattern::Domain(_) => format!("*.{host}"),
+                _ => host,
+            };
+            match pattern.path() {
+                Some(path) => Some(("UrlWildcardCondition", format!("*://{wildcard}{path}*"))),
+                None => Some(("HostWildcardCondition", wildcard)),
+            }
+        }
+    }
+}
+
+/// Parses SwitchyOmega switch rules into GFW list rules.
+///
+/// Every rule object found in the input is converted, so both a whole options backup and a single `rules` array are
+/// accepted. Rules switching to `direct_profile` become negative rules. Conditions without a GFW list equivalent, such
+/// as keywords or IP ranges, are dropped with the line of their rule object. Invalid JSON is reported as a single
+/// dropped entry.
+///
+/// # Examples
+///
+/// ```
+/// # use gfwlist::{Pattern, Rule, format::switchy_omega};
+/// let import = switchy_omega::import(
+///     r#"{"+auto switch": {"profileType": "SwitchProfile", "rules": [
+///         {"condition": {"conditionType": "HostWildcardCondition", "pattern": "*.example.com"},
+///          "profileName": "proxy"},
+///         {"condition": {"conditionType": "KeywordCondition", "pattern": "example"}, "profileName": "proxy"}
+///     ]}}"#,
+///     "direct",
+/// );
+/// assert_eq!(import.rules, [Rule::new(false, Pattern::Domain("example.com".into()))]);
+/// assert_eq!(import.dropped, [(4, "KeywordCondition: example".to_string())]);
+/// ```
+pub fn import(input: &str, direct_profile: &str) -> Import {
+    let mut import = Import::default();
+    let json = match Json::parse(input) {
+        Ok(json) => json,
+        Err(line) => {
+            import.dropped.push((line, "invalid JSON".to_string()));
+            return import;
+        }
+    };
+    json.walk(&mut |value| {
+        let Json::Object(line, _) = value else {
+            return;
+        };
+        let Some((condition, profile)) = value
+            .get("condition")
+            .zip(value.get("profileName").and_then(Json::as_str))
+        else {
+            return;
+        };
+        let condition_type = condition
+            .get("conditionType")
+            .and_then(Json::as_str)
+            .unwrap_or_default();
+        let pattern = condition.get("pattern").and_then(Json::as_str).unwrap_or_default();
+        match parse_condition(condition_type, pattern) {
+            Some(pattern) => import
+                .rules
+                .push(Rule::new(profile == direct_profile, pattern).with_origin(Origin::line(*line))),
+            None => import.dropped.push((*line, format!("{condition_type}: {pattern}"))),
+        }
+    });
+    import
+}
+
+fn parse_condition(condition_type: &str, pattern: &str) -> Option<Pattern> {
+    let plain = |s: &str| !s.is_empty() && !s.contains(['*', '?', '|']);
+    match condition_type {
+        "UrlRegexCondition" => Some(Pattern::Regex(pattern.to_string())),
+        "HostWildcardCondition" => match pattern.strip_prefix("*.") {
+            Some(domain) if plain(domain) => Some(Pattern::Domain(domain.to_string())),
+            _ if plain(pattern) => Some(Pattern::Host(pattern.to_string())),
+            _ => None,
+        },
+        "UrlWildcardCondition" => {
+            let prefix = pattern.strip_suffix('*')?;
+            match prefix.strip_prefix("*://") {
+                Some(rest) if rest.contains('/') => match rest.strip_prefix("*.") {
+                    Some(domain) if plain(domain) => Some(Pattern::Domain(domain.to_string())),
+                    _ if plain(rest) => Some(Pattern::Host(rest.to_string())),
+                    _ => None,
+                },
+                Some(_) => None,
+                None if plain(prefix) && prefix.contains("://") => Some(Pattern::Url(prefix.to_string())),
+                None => None,
+            }
+        }
+        _ => None,
+    }
+}
+
+/// Reports how the rules of a [`GfwList`] are converted by [`export`].
+pub fn fidelity(list: &GfwList) -> Report {
+    Report::new(list, |rule| {
+        let pattern = rule.pattern();
+        match pattern {
+            Pattern::Regex(_) => Fidelity::Lossless,
+            Pattern::Url(url) if url.contains(['*', '?', '|']) => Fidelity::Dropped,
+            Pattern::Url(_) => Fidelity::Lossless,
+            _ if pattern.host().is_none_or(|host| host.is_empty()) => Fidelity::Dropped,
+            _ if pattern.path().is_some() => Fidelity::Approximated,
+            _ => Fidelity::Lossless,
+        }
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn round_trip() {
+        let gfw =
+            GfwList::from("||a.com\nb.com\n|http://c.com/page\n.d.com/path\n/^https?:\\/\\/e\\.c\"om/\n@@||f.com")
+                .unwrap();
+        let import = import(&export(&gfw, "proxy", "direct"), "direct");
+        assert!(import.dropped.is_empty());
+        let patterns: Vec<_> = import
+            .rules
+            .iter()
+            .map(|rule| (rule.is_negative(), rule.pattern()))
+            .collect();
+        assert_eq!(patterns[0], (true, &Pattern::Domain("f.com".into())));
+        assert_eq!(patterns[3], (false, &Pattern::Url("http://c.com/page".into())));
+        assert_eq!(patterns[4], (false, &Pattern::Domain("d.com/path".into())));
+        assert_eq!(patterns[5], (false, &Pattern::Regex("^https?:\\/\\/e\\.c\"om".into())));
+        assert_eq!(import.rules[1].origin(), Some(&Origin::line(6)));
+
+        let report = fidelity(&gfw);
+        assert_eq!(report.approximated.len(), 1);
+        assert!(report.dropped.is_empty());
+        assert_eq!(
+            super::import("[{\n", "direct").dropped,
+            [(2, "invalid JSON".to_string())]
+        );
+        let nested = "[".repeat(200_000);
+        assert_eq!(
+            super::import(&nested, "direct").dropped,
+            [(1, "invalid JSON".to_string())]
+        );
+    }
+}
@@ -0,0 +1,71 @@
+//! Configuration of the [Unbound](https://nlnetlabs.nl/projects/unbound/) recursive resolver.
+//!
+//! Domains are matched at host level as described in [`DomainSets`]. Unbound zones always cover subdomains, so exact
+//! hosts are widened to their subdomains, and host regexes are not exported.
+
+use super::{DomainSets, Fidelity, Report, domain_fidelity};
+use crate::GfwList;
+
+/// Renders the domains of the positive rules of a [`GfwList`] as `forward-zone` stanzas.
+///
+/// Each zone forwards to all of `upstreams`, given in Unbound syntax such as `8.8.8.8@53`. Unbound cannot exempt a
+/// subdomain from a forward zone, so negative rules are not exported; see [`local_zones`] for a configuration that
+/// honors them.
+///
+/// # Examples
+///
+/// ```
+/// # use gfwlist::{GfwList, format::unbound};
+/// let gfw = GfwList::from("||blocked-site.com\n@@||exception.com").unwrap();
+/// assert_eq!(
+///     unbound::forward_zones(&gfw, &["8.8.8.8@53"]),
+///     "forward-zone:\n    name: \"blocked-site.com.\"\n    forward-addr: 8.8.8.8@53\n",
+/// );
+/// ```
+pub fn forward_zones(list: &GfwList, upstreams: &[&str]) -> String {
+    let mut output = String::new();
+    for domain in DomainSets::from_list(list, false).hosts() {
+        output.push_str(&format!("forward-zone:\n    name: \"{domain}.\"\n"));
+        for upstream in upstreams {
+            output.push_str(&format!("    forward-addr: {upstream}\n"));
+        }
+    }
+    output
+}
+
+/// Renders the domains of a [`GfwList`] as `local-zone` lines.
+///
+/// Domains of positive rules get the zone type `zone_type`, such as `always_nxdomain` or `refuse`. Domains of
+/// negative rules get the type `transparent`, which takes precedence for their subdomains and resolves them normally.
+///
+/// # Examples
+///
+/// ```
+/// # use gfwlist::{GfwList, format::unbound};
+/// let gfw = GfwList::from("||blocked-site.com\n@@||exception.blocked-site.com").unwrap();
+/// assert_eq!(
+///     unbound::local_zones(&gfw, "always_nxdomain"),
+///     "local-zone: \"blocked-site.com.\" always_nxdomain\nlocal-zone: \"exception.blocked-site.com.\" transparent\n",
+/// );
+/// ```
+pub fn local_zones(list: &GfwList, zone_type: &str) -> String {
+    let mut output = String::new();
+    for (negative, zone_type) in [(false, zone_type), (true, "transparent")] {
+        for domain in DomainSets::from_list(list, negative).hosts() {
+            output.push_str(&format!("local-zone: \"{domain}.\" {zone_type}\n"));
+        }
+    }
+    output
+}
+
+/// Reports how the rules of a [`GfwList`] are converted by [`forward_zones`].
+///
+/// Negative rules are always dropped, since forward zones cannot express them.
+pub fn fidelity(list: &GfwList) -> Report {
+    Report::new(list, |rule| {
+        if rule.is_negative() {
+            return Fidelity::Dropped;
+        }
+        domain_fidelity(rule.pattern(), false, false)
+    })
+}
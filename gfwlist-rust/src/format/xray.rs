@@ -0,0 +1,71 @@
+//! Routing rules of [Xray](https://xtls.github.io/).
+//!
+//! The export is the content of the `routing.rules` array. Domains are matched at host level as described in
+//! [`DomainSets`], using the `domain:`, `full:` and `regexp:` prefixes of Xray domain entries. Xray uses the first
+//! matching rule, so the negative rules are emitted before the positive rules.
+
+use super::{DomainSets, Report, domain_fidelity, json_array, json_objects, json_string};
+use crate::GfwList;
+
+/// Renders the rules of a [`GfwList`] as Xray routing rules.
+///
+/// Negative rules are routed to `direct_tag` and positive rules to `proxy_tag`. Empty rules are omitted.
+///
+/// # Examples
+///
+/// ```
+/// # use gfwlist::{GfwList, format::xray};
+/// let gfw = GfwList::from("||blocked-site.com\nblocked-host.com\n@@||exception.com").unwrap();
+/// assert_eq!(
+///     xray::export(&gfw, "proxy", "direct"),
+///     r#"[
+///   {
+///     "type": "field",
+///     "domain": ["domain:exception.com"],
+///     "outboundTag": "direct"
+///   },
+///   {
+///     "type": "field",
+///     "domain": ["domain:blocked-site.com", "full:blocked-host.com"],
+///     "outboundTag": "proxy"
+///   }
+/// ]
+/// "#,
+/// );
+/// ```
+pub fn export(list: &GfwList, proxy_tag: &str, direct_tag: &str) -> String {
+    let rules: Vec<_> = [(true, direct_tag), (false, proxy_tag)]
+        .into_iter()
+        .filter_map(|(negative, tag)| {
+            let sets = DomainSets::from_list(list, negative);
+            if sets.is_empty() {
+                return None;
+            }
+            let domains = (sets.suffixes.iter().map(|s| format!("domain:{s}")))
+                .chain(sets.full.iter().map(|s| format!("full:{s}")))
+                .chain(sets.regexes.iter().map(|s| format!("regexp:{s}")));
+            Some(vec![
+                ("type", json_string("field")),
+                ("domain", json_array(domains)),
+                ("outboundTag", json_string(tag)),
+            ])
+        })
+        .collect();
+    json_objects(&rules)
+}
+
+/// Reports how the rules of a [`GfwList`] are converted by [`export`].
+pub fn fidelity(list: &GfwList) -> Report {
+    Report::new(list, |rule| domain_fidelity(rule.pattern(), true, true))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn export_empty() {
+        let gfw = GfwList::from("/regex/").unwrap();
+        assert_eq!(export(&gfw, "proxy", "direct"), "[]\n");
+    }
+}
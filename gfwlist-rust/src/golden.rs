@@ -0,0 +1,139 @@
+//! Golden test vectors, for checking that language bindings agree with this crate.
+//!
+//! [`GfwList::golden_vectors`] decides URLs derived from the rules of a list, as [`corpus`](crate::corpus) does for
+//! single rules, and renders them with the expected decisions as JSON. A binding loads the same list, replays the
+//! vectors through its own API and compares the results, which catches conversions that lose or alter information on
+//! the way through the binding. Vectors are only derived from rules that behave in the list as they do on their own,
+//! so that a vector never records a rule that cannot match as expected behavior.
+
+use std::collections::HashSet;
+
+use thiserror::Error;
+
+use crate::format::{json_objects, json_string};
+use crate::{Decision, GfwList, RuleOptions, corpus};
+
+/// An error deriving golden test vectors from a rule, see [`GfwList::golden_vectors`].
+#[derive(Debug, Error, Clone, PartialEq, Eq)]
+pub enum GoldenError {
+    /// The rule matches none of the URLs derived from it, so that it never matches
+    #[error("rule {0} matches none of the URLs derived from it")]
+    DeadRule(String),
+    /// The list does not match a URL with a rule that matches it on its own
+    #[error("rule {rule} does not match {url} in the list")]
+    Unmatched {
+        /// The text of the rule
+        rule: String,
+        /// The URL derived from the rule
+        url: String,
+    },
+    /// A URL derived from a rule cannot be parsed
+    #[error("error parsing URL {url}: {error}")]
+    Url {
+        /// The URL derived from the rule
+        url: String,
+        /// The error of the URL parser
+        error: url::ParseError,
+    },
+}
+
+impl GfwList {
+    /// Produces golden test vectors derived from the rules of the list.
+    ///
+    /// For each active rule, the first URL its [corpus](corpus::generate) matches and the first URL it does not match
+    /// are decided by the whole list. The result is a JSON array of objects with the `url`, the `decision`, which is
+    /// `"blocked"`, `"allowed"` or `"no match"`, and the text of the deciding `rule`, or `null`. URLs are unique and in
+    /// the order of the rules they are derived from. Regex and IP rules, from which no URLs are derived, and rules that
+    /// only match with a [request context](crate::RequestContext) yield no vectors.
+    ///
+    /// Fails if a rule matches none of its URLs, or if the list does not match a URL with the rule it was derived
+    /// from, such as a rule with a query in a list that [ignores queries](crate::GfwListBuilder::ignore_query).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use gfwlist::GfwList;
+    /// let gfw_list = GfwList::from("||example.com\n@@||www.example.com").unwrap();
+    /// let vectors = gfw_list.golden_vectors().unwrap();
+    /// assert!(vectors.starts_with(r#"[
+    ///   {
+    ///     "url": "http://example.com/",
+    ///     "decision": "blocked",
+    ///     "rule": "||example.com"
+    ///   },
+    ///   {
+    ///     "url": "http://xexample.com/",
+    ///     "decision": "no match",
+    ///     "rule": null
+    ///   },"#));
+    /// assert!(vectors.contains(r#""decision": "allowed""#));
+    /// ```
+    pub fn golden_vectors(&self) -> Result<String, GoldenError> {
+        let mut seen = HashSet::new();
+        let mut vectors = vec![];
+        let rules = (self.iter_rules()).filter(|rule| {
+            rule.section()
+                .is_none_or(|section| !self.disabled_sections.contains(section))
+                && !rule.options().is_some_and(RuleOptions::needs_context)
+        });
+        for rule in rules {
+            let corpus = corpus::generate(rule);
+            let matching = corpus.matching.into_iter().next();
+            if matching.is_none() && !corpus.non_matching.is_empty() {
+                return Err(GoldenError::DeadRule(rule.text().to_string()));
+            }
+            let parse_error = |url: &str| {
+                let url = url.to_string();
+                move |error| GoldenError::Url { url, error }
+            };
+            if let Some(url) = &matching {
+                let matches = self.test_all(url).map_err(parse_error(url))?;
+                if !matches.iter().any(|matched| std::ptr::eq(matched, rule)) {
+                    let rule = rule.text().to_string();
+                    return Err(GoldenError::Unmatched { rule, url: url.clone() });
+                }
+            }
+            let urls = matching.into_iter().chain(corpus.non_matching.into_iter().next());
+            for url in urls.filter(|url| seen.insert(url.clone())) {
+                let decision = self.decide(&url).map_err(parse_error(&url))?;
+                let (name, rule) = match decision {
+                    Decision::Blocked(rule) => ("blocked", json_string(rule.text())),
+                    Decision::Allowed(rule) => ("allowed", json_string(rule.text())),
+                    Decision::NoMatch => ("no match", "null".to_string()),
+                };
+                vectors.push(vec![
+                    ("url", json_string(&url)),
+                    ("decision", json_string(name)),
+                    ("rule", rule),
+                ]);
+            }
+        }
+        Ok(json_objects(&vectors))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn vectors() {
+        let mut gfw = GfwList::from("||a.com\n||c.com:8443^\n!---- Off ----\n||b.com\n/regex/").unwrap();
+        assert_eq!(gfw.golden_vectors().unwrap().matches("\"url\"").count(), 6);
+        gfw.set_section_enabled("Off", false).unwrap();
+        let vectors = gfw.golden_vectors().unwrap();
+        assert_eq!(vectors.matches("\"url\"").count(), 4);
+        assert!(!vectors.contains("b.com"));
+        assert_eq!(GfwList::from("").unwrap().golden_vectors().unwrap(), "[]\n");
+
+        let mut builder = crate::GfwListBuilder::new();
+        builder.ignore_query(true).add_rules("||a.com/search?q=").unwrap();
+        assert_eq!(
+            builder.build().unwrap().golden_vectors(),
+            Err(GoldenError::Unmatched {
+                rule: "||a.com/search?q=".into(),
+                url: "http://a.com/search?q=".into(),
+            })
+        );
+    }
+}
@@ -0,0 +1,143 @@
+//! Host-level decisions for callers that cannot see full URLs.
+
+use crate::ip::normalize_host;
+use crate::literal::required_literals;
+use crate::{Decision, GfwList, Pattern, Rule};
+
+/// The decision for every URL on a host, see [`GfwList::host_decision`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum HostDecision<'a> {
+    /// Every URL on the host gets the same decision, which may be [`Decision::NoMatch`]
+    Definite(Decision<'a>),
+    /// URLs on the host may get different decisions depending on their scheme, path or query
+    PathDependent {
+        /// The decision of the rules that only depend on the host, see [`GfwList::decide_host`]
+        host: Decision<'a>,
+        /// The rules that may change the decision for some URLs on the host
+        rules: Vec<&'a Rule>,
+    },
+}
+
+impl<'a> HostDecision<'a> {
+    /// Returns the decision if it holds for every URL on the host.
+    pub fn definite(&self) -> Option<&Decision<'a>> {
+        match self {
+            HostDecision::Definite(decision) => Some(decision),
+            HostDecision::PathDependent { .. } => None,
+        }
+    }
+
+    /// Returns whether every URL on the host is blocked.
+    pub fn is_blocked(&self) -> bool {
+        self.definite().is_some_and(Decision::is_blocked)
+    }
+}
+
+/// Returns whether a host is a domain or one of its subdomains, where a leading `.` of the domain is ignored.
+pub(crate) fn is_under(host: &str, domain: &str) -> bool {
+    let domain = domain.trim_start_matches('.');
+    (host.len() >= domain.len() && host.is_char_boundary(host.len() - domain.len()))
+        && host[host.len() - domain.len()..].eq_ignore_ascii_case(domain)
+        && (host.len() == domain.len() || host[..host.len() - domain.len()].ends_with('.'))
+}
+
+/// Returns whether a host rule with a path or a URL rule may match URLs on the host.
+fn covers_host(pattern: &Pattern, host: &str) -> bool {
+    let Some(rule_host) = pattern.host().map(|rule_host| rule_host.to_ascii_lowercase()) else {
+        return false;
+    };
+    match pattern {
+        _ if rule_host.is_empty() || rule_host.contains('*') => true,
+        Pattern::Domain(_) => host == rule_host || host.ends_with(&format!(".{rule_host}")),
+        _ => host == rule_host,
+    }
+}
+
+/// Returns whether a regular expression may match URLs on the host.
+///
+/// A regex is ruled out if it requires a literal fragment that looks like part of a domain, but the host does not
+/// contain it. Regexes that are not known to be about a host may match any host.
+fn regex_may_match_host(source: &str, host: &str) -> bool {
+    let host = format!(".{host}");
+    let Ok(literals) = required_literals(source) else {
+        return true;
+    };
+    (literals.iter())
+        .flat_map(|literal| literal.split([':', '/']))
+        .map(str::to_ascii_lowercase)
+        .filter(|fragment| fragment.contains('.'))
+        .all(|fragment| host.contains(&fragment))
+}
+
+impl GfwList {
+    /// Decides a host for callers that only see the host, such as TLS interceptors working from the SNI.
+    ///
+    /// Unlike [`decide_host`](Self::decide_host), which ignores rules that depend on more than the host, this reports
+    /// whether such rules may decide some URLs on the host differently. In that case the caller must defer to a check
+    /// of the full URL, for example at the HTTP level. The host is expected in its ASCII form.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use gfwlist::{GfwList, HostDecision};
+    /// let gfw_list = GfwList::from("||blocked-site.com\n@@||blocked-site.com/public\n||mixed.com/private").unwrap();
+    /// assert!(gfw_list.host_decision("www.blocked-site.com").definite().is_none());
+    /// assert!(gfw_list.host_decision("other.blocked-site.org").definite().is_some_and(|d| !d.is_blocked()));
+    ///
+    /// let HostDecision::PathDependent { host, rules } = gfw_list.host_decision("mixed.com") else {
+    ///     panic!();
+    /// };
+    /// assert!(!host.is_blocked());
+    /// assert_eq!(rules[0].text(), "||mixed.com/private");
+    /// ```
+    pub fn host_decision(&self, host: &str) -> HostDecision<'_> {
+        let decision = self.decide_host(host);
+        let host = host.strip_suffix('.').unwrap_or(host).to_ascii_lowercase();
+        let host = normalize_host(&host);
+        let rules: Vec<&Rule> = (self.rules.iter())
+            .filter(|rule| {
+                rule.section()
+                    .is_none_or(|section| !self.disabled_sections.contains(section))
+            })
+            .filter(|rule| match rule.pattern() {
+                Pattern::Regex(source) => regex_may_match_host(source, &host),
+                pattern @ Pattern::Url(_) => covers_host(pattern, &host),
+                pattern => pattern.path().is_some() && covers_host(pattern, &host),
+            })
+            // only rules that take precedence over the host decision can change it
+            .filter(|rule| match decision {
+                Decision::Blocked(_) => rule.is_negative(),
+                Decision::Allowed(_) => matches!(rule.pattern(), Pattern::Regex(_)),
+                Decision::NoMatch => true,
+            })
+            .collect();
+        match rules.is_empty() {
+            true => HostDecision::Definite(decision),
+            false => HostDecision::PathDependent { host: decision, rules },
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn regexes_and_urls() {
+        let gfw = GfwList::from("/^https?:\\/\\/[^\\/]+\\.google\\.com/\n/\\/video\\//\n|http://plain.com\n||ok.com")
+            .unwrap();
+        let rules = |host| match GfwList::host_decision(&gfw, host) {
+            HostDecision::Definite(_) => vec![],
+            HostDecision::PathDependent { rules, .. } => rules.into_iter().map(Rule::text).collect(),
+        };
+        assert_eq!(
+            rules("www.google.com"),
+            ["/^https?:\\/\\/[^\\/]+\\.google\\.com/", "/\\/video\\//"]
+        );
+        assert_eq!(rules("plain.com"), ["/\\/video\\//", "|http://plain.com"]);
+        let gfw = GfwList::from("||ok.com\n@@|https://ok.com/public\n@@||direct.com\nexample.com/path").unwrap();
+        assert!(gfw.host_decision("www.ok.com").is_blocked());
+        assert!(gfw.host_decision("ok.com").definite().is_none());
+        assert!(gfw.host_decision("direct.com").definite().is_some());
+    }
+}
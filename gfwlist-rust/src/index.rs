@@ -0,0 +1,135 @@
+//! An index of rules by the registrable domain they are about.
+
+use std::collections::HashMap;
+
+use crate::literal::required_literals;
+use crate::request::base_domain;
+use crate::{GfwList, Pattern, Rule};
+
+/// Rules grouped by registrable domain, built on the first call of [`GfwList::rules_for_domain`].
+#[derive(Debug, Clone, Default)]
+pub(crate) struct DomainIndex {
+    /// Rules by the registrable domains of their host, or of the domain-like literals of their regex
+    by_domain: HashMap<String, Vec<usize>>,
+    /// Rules that may match any domain, such as regexes without domain-like literals and hosts with wildcards
+    unindexed: Vec<usize>,
+}
+
+impl DomainIndex {
+    fn new(rules: &[Rule]) -> Self {
+        let mut index = DomainIndex::default();
+        for (rule_index, rule) in rules.iter().enumerate() {
+            let domains = rule_domains(rule);
+            if domains.is_empty() {
+                index.unindexed.push(rule_index);
+            }
+            for domain in domains {
+                let rules = index.by_domain.entry(base_domain(&domain).to_string()).or_default();
+                if rules.last() != Some(&rule_index) {
+                    rules.push(rule_index);
+                }
+            }
+        }
+        index
+    }
+}
+
+/// Returns the domains a rule is about, or nothing if it may match any domain.
+fn rule_domains(rule: &Rule) -> Vec<String> {
+    let domains: Vec<String> = match rule.pattern() {
+        Pattern::Regex(source) => (required_literals(source).unwrap_or_default().iter())
+            .flat_map(|literal| literal.split([':', '/']))
+            .map(|fragment| fragment.trim_matches('.').to_ascii_lowercase())
+            .filter(|fragment| fragment.contains('.'))
+            .collect(),
+        pattern => pattern
+            .host()
+            .into_iter()
+            .map(|host| host.to_ascii_lowercase())
+            .collect(),
+    };
+    (domains.into_iter())
+        .filter(|domain| !domain.is_empty() && !domain.contains('*'))
+        .collect()
+}
+
+/// Returns whether the host is the domain or one of its subdomains.
+fn is_within(host: &str, domain: &str) -> bool {
+    host.strip_suffix(domain)
+        .is_some_and(|rest| rest.is_empty() || rest.ends_with('.'))
+}
+
+/// Returns whether a rule about `rule_domain` may affect the domain or one of its subdomains.
+fn affects(rule: &Rule, rule_domain: &str, domain: &str) -> bool {
+    let covers_subdomains = matches!(rule.pattern(), Pattern::Domain(_) | Pattern::Regex(_));
+    is_within(rule_domain, domain) || (covers_subdomains && is_within(domain, rule_domain))
+}
+
+impl GfwList {
+    /// Returns the rules that may affect a domain or its subdomains, in source order.
+    ///
+    /// These are the rules on the domain and its subdomains, the domain rules covering it like `||example.com` for
+    /// `www.example.com`, the regexes whose literal fragments point at it, and the rules that may match any domain,
+    /// such as regexes without domain-like literals. Rules in disabled sections are left out. Rules are looked up by
+    /// registrable domain, approximated by the last two labels, in an index built on the first call.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use gfwlist::{GfwList, Rule};
+    /// let input = "||example.com\n@@||static.www.example.com\nmail.example.com\n||other.com\n";
+    /// let gfw_list = GfwList::from(&format!("{input}/^https?:\\/\\/[^\\/]+\\.example\\.com/")).unwrap();
+    /// let rules = |domain| gfw_list.rules_for_domain(domain).into_iter().map(Rule::text).collect::<Vec<_>>();
+    /// assert_eq!(rules("example.com").len(), 4);
+    /// assert_eq!(
+    ///     rules("www.example.com"),
+    ///     ["||example.com", "@@||static.www.example.com", "/^https?:\\/\\/[^\\/]+\\.example\\.com/"],
+    /// );
+    /// assert!(rules("example.org").is_empty());
+    /// ```
+    pub fn rules_for_domain(&self, domain: &str) -> Vec<&Rule> {
+        let domain = domain.strip_suffix('.').unwrap_or(domain).to_ascii_lowercase();
+        (self.rules_for_base_domain(&domain, true).into_iter())
+            .filter(|rule| {
+                let domains = rule_domains(rule);
+                domains.is_empty() || domains.iter().any(|rule_domain| affects(rule, rule_domain, &domain))
+            })
+            .collect()
+    }
+
+    /// Returns the active rules about any domain with the registrable domain of a lowercase domain, in source order,
+    /// optionally with the rules that may match any domain.
+    pub(crate) fn rules_for_base_domain(&self, domain: &str, unindexed: bool) -> Vec<&Rule> {
+        let index = self.domain_index.get_or_init(|| DomainIndex::new(&self.rules));
+        let unindexed = if unindexed { &index.unindexed[..] } else { &[] };
+        let mut candidates: Vec<usize> = (index.by_domain.get(base_domain(domain)).into_iter().flatten())
+            .chain(unindexed)
+            .copied()
+            .collect();
+        candidates.sort_unstable();
+        (candidates.into_iter())
+            .map(|rule_index| &self.rules[rule_index])
+            .filter(|rule| {
+                rule.section()
+                    .is_none_or(|section| !self.disabled_sections.contains(section))
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn unindexed_rules() {
+        let gfw = GfwList::from("*.example.*\n/\\/video\\//\n||a.example.com\nexample.com/ads\n@@||b.com").unwrap();
+        let rules: Vec<_> = gfw
+            .rules_for_domain("A.Example.COM.")
+            .into_iter()
+            .map(Rule::text)
+            .collect();
+        assert_eq!(rules, ["*.example.*", "/\\/video\\//", "||a.example.com"]);
+        assert_eq!(gfw.rules_for_domain("b.com").len(), 3);
+    }
+}
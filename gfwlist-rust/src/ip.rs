@@ -0,0 +1,202 @@
+//! IP and CIDR rules such as `IP-CIDR,8.8.8.0/24`, which match hosts that are IP addresses.
+//!
+//! IPv6 literal hosts are written in brackets in URLs, such as `http://[2001:db8::1]/`. Hosts of rules and hosts given
+//! to [`GfwList::decide_host`] may be written with or without brackets, and are brought into the bracketed canonical
+//! form of URLs before they are encoded, see [`normalize_host`].
+
+use std::borrow::Cow;
+use std::net::{IpAddr, Ipv6Addr};
+
+use url::{Host, Url};
+
+use crate::{Decision, GfwList, Rule, Verdict};
+
+/// A network in CIDR notation, see [`Pattern::Cidr`](crate::Pattern::Cidr).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Network {
+    addr: IpAddr,
+    prefix: u8,
+}
+
+impl Network {
+    /// Parses a network such as `8.8.8.0/24` or `2001:db8::/32`, or a bare address, which is a network of one
+    /// address. Host bits of the address are cleared.
+    pub(crate) fn parse(text: &str) -> Option<Self> {
+        let (addr, prefix) = match text.split_once('/') {
+            Some((addr, prefix)) => (addr.parse::<IpAddr>().ok()?, Some(prefix.parse::<u8>().ok()?)),
+            None => (text.parse::<IpAddr>().ok()?, None),
+        };
+        let bits = if addr.is_ipv4() { 32 } else { 128 };
+        let prefix = prefix.unwrap_or(bits);
+        if prefix > bits {
+            return None;
+        }
+        let addr = match addr {
+            IpAddr::V4(addr) => IpAddr::V4((u32::from(addr) & mask_v4(prefix)).into()),
+            IpAddr::V6(addr) => IpAddr::V6((u128::from(addr) & mask_v6(prefix)).into()),
+        };
+        Some(Network { addr, prefix })
+    }
+
+    /// Returns whether the network contains the address, where IPv4-mapped IPv6 addresses count as IPv4 addresses.
+    pub(crate) fn contains(&self, ip: IpAddr) -> bool {
+        match (self.addr, ip.to_canonical()) {
+            (IpAddr::V4(addr), IpAddr::V4(ip)) => u32::from(ip) & mask_v4(self.prefix) == u32::from(addr),
+            (IpAddr::V6(addr), IpAddr::V6(ip)) => u128::from(ip) & mask_v6(self.prefix) == u128::from(addr),
+            _ => false,
+        }
+    }
+}
+
+impl std::fmt::Display for Network {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}/{}", self.addr, self.prefix)
+    }
+}
+
+fn mask_v4(prefix: u8) -> u32 {
+    u32::MAX.checked_shl(32 - u32::from(prefix)).unwrap_or(0)
+}
+
+fn mask_v6(prefix: u8) -> u128 {
+    u128::MAX.checked_shl(128 - u32::from(prefix)).unwrap_or(0)
+}
+
+/// Returns the canonical form of a network, such as `8.8.8.0/24` for `8.8.8.8/24`, or `None` if it is invalid.
+pub(crate) fn canonical_network(text: &str) -> Option<String> {
+    Network::parse(text).map(|network| network.to_string())
+}
+
+/// Returns an IPv6 literal host, with or without brackets, in the canonical form of URLs such as `[2001:db8::1]`, an
+/// internationalized domain in its ASCII form such as `xn--bcher-kva.de`, as the URL parser does, and other hosts
+/// unchanged.
+pub(crate) fn normalize_host(host: &str) -> Cow<'_, str> {
+    if !host.is_ascii() {
+        return match url::Host::parse(host) {
+            Ok(url::Host::Domain(host)) => Cow::Owned(host),
+            _ => Cow::Borrowed(host),
+        };
+    }
+    let bare = (host.strip_prefix('['))
+        .and_then(|host| host.strip_suffix(']'))
+        .unwrap_or(host);
+    match bare.parse::<Ipv6Addr>() {
+        Ok(addr) if host != format!("[{addr}]") => Cow::Owned(format!("[{addr}]")),
+        _ => Cow::Borrowed(host),
+    }
+}
+
+/// Normalizes the host of a domain or host pattern like `2001:db8::1/path`, which ends at the first `/` or `?`, see
+/// [`normalize_host`].
+pub(crate) fn normalize_pattern_host(input: &str) -> Cow<'_, str> {
+    let (host, path) = input.split_at(input.find(['/', '?']).unwrap_or(input.len()));
+    match normalize_host(host) {
+        Cow::Owned(host) => Cow::Owned(host + path),
+        Cow::Borrowed(_) => Cow::Borrowed(input),
+    }
+}
+
+/// Returns the IP address of a host, which may be an IPv6 address in brackets like in URLs.
+pub(crate) fn host_ip(host: &str) -> Option<IpAddr> {
+    let host = host
+        .strip_prefix('[')
+        .and_then(|host| host.strip_suffix(']'))
+        .unwrap_or(host);
+    host.parse().ok()
+}
+
+/// Returns the IP address of the host of a URL, if it is one.
+pub(crate) fn url_ip(url: &Url) -> Option<IpAddr> {
+    match url.host()? {
+        Host::Ipv4(addr) => Some(addr.into()),
+        Host::Ipv6(addr) => Some(addr.into()),
+        Host::Domain(_) => None,
+    }
+}
+
+impl GfwList {
+    /// Tests whether an IP address is blocked, see [`GfwList::decide_ip`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use gfwlist::GfwList;
+    /// let gfw_list = GfwList::from("IP-CIDR,8.8.8.0/24\n@@IP-CIDR,8.8.8.8/32\nIP-CIDR6,2001:db8::/32").unwrap();
+    /// assert_eq!(gfw_list.test_ip("8.8.8.4".parse().unwrap()), Some("IP-CIDR,8.8.8.0/24"));
+    /// assert_eq!(gfw_list.test_ip("8.8.8.8".parse().unwrap()), None);
+    /// assert_eq!(gfw_list.test_ip("2001:db8::1".parse().unwrap()), Some("IP-CIDR6,2001:db8::/32"));
+    /// // IP rules also apply to URLs whose host is an IP address
+    /// assert_eq!(gfw_list.test("http://8.8.8.4/dns-query").unwrap(), Some("IP-CIDR,8.8.8.0/24"));
+    /// ```
+    pub fn test_ip(&self, ip: IpAddr) -> Option<&str> {
+        self.decide_ip(ip).blocked_by().map(Rule::text)
+    }
+
+    /// Decides whether an IP address is blocked, for callers such as firewalls that only know the address.
+    ///
+    /// The address is tested like a bare host, see [`GfwList::decide_host`], so that IP rules and host rules on the
+    /// address such as `8.8.8.8` are evaluated. IPv4-mapped IPv6 addresses match IPv4 networks.
+    pub fn decide_ip(&self, ip: IpAddr) -> Decision<'_> {
+        match ip {
+            IpAddr::V4(addr) => self.decide_host(&addr.to_string()),
+            IpAddr::V6(addr) => self.decide_host(&format!("[{addr}]")),
+        }
+    }
+
+    /// Returns the verdict of the first IP rule matching the address, where negative rules come first.
+    pub(crate) fn test_ip_rules(&self, ip: IpAddr) -> Verdict {
+        (self.ip_rules.iter())
+            .find(|(network, _)| network.contains(ip))
+            .map_or(Verdict::NoMatch, |(_, index)| self.regex_verdict(*index))
+    }
+
+    /// Combines the verdict of the automata with the IP rules matching the address of the host, if it is one.
+    ///
+    /// Negative IP rules take precedence over positive rules of the automata, and positive IP rules only apply if
+    /// nothing else matches.
+    pub(crate) fn with_ip_rules(&self, verdict: Verdict, ip: Option<IpAddr>) -> Verdict {
+        let Some(ip) = ip.filter(|_| !self.ip_rules.is_empty()) else {
+            return verdict;
+        };
+        match (verdict, self.test_ip_rules(ip)) {
+            (Verdict::Allowed(_), _) | (_, Verdict::NoMatch) => verdict,
+            (_, Verdict::Allowed(index)) => Verdict::Allowed(index),
+            (Verdict::NoMatch, ip_verdict) => ip_verdict,
+            (Verdict::Blocked(_), Verdict::Blocked(_)) => verdict,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn networks() {
+        assert_eq!(canonical_network("8.8.8.8/24").as_deref(), Some("8.8.8.0/24"));
+        assert_eq!(canonical_network("2001:DB8::1").as_deref(), Some("2001:db8::1/128"));
+        assert_eq!(canonical_network("0.0.0.0/0").as_deref(), Some("0.0.0.0/0"));
+        assert_eq!(canonical_network("8.8.8.8/33"), None);
+        assert_eq!(canonical_network("example.com/24"), None);
+        assert_eq!(normalize_host("2001:DB8:0::1"), "[2001:db8::1]");
+        assert_eq!(normalize_pattern_host("[2001:db8:0::1]/a:b"), "[2001:db8::1]/a:b");
+        assert_eq!(normalize_host("example.com"), "example.com");
+        assert_eq!(
+            normalize_pattern_host("*.Bücher.de/Straße"),
+            "*.xn--bcher-kva.de/Straße"
+        );
+        let network = Network::parse("10.0.0.0/8").unwrap();
+        assert!(network.contains("10.1.2.3".parse().unwrap()));
+        assert!(network.contains("::ffff:10.1.2.3".parse().unwrap()));
+        assert!(!network.contains("11.0.0.0".parse().unwrap()));
+        let gfw =
+            GfwList::from("||example.com\nIP-CIDR,10.0.0.0/8\n@@IP-CIDR,10.0.0.1/32,no-resolve\n10.0.0.1").unwrap();
+        assert!(matches!(gfw.decide_host("10.0.0.1"), Decision::Allowed(_)));
+        assert_eq!(gfw.test_host("[::ffff:10.0.0.2]"), Some("IP-CIDR,10.0.0.0/8"));
+        assert_eq!(
+            gfw.test_parts("https", "10.2.0.0", "/").unwrap(),
+            Some("IP-CIDR,10.0.0.0/8")
+        );
+        assert_eq!(gfw.test_host("example.com"), Some("||example.com"));
+    }
+}
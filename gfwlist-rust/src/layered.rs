@@ -0,0 +1,125 @@
+//! Stacking lists with override layers.
+//!
+//! Proxy clients usually combine an upstream list with the user's own allow and block rules, which must win over
+//! the upstream list regardless of its exceptions. Merging the lists would let an upstream `@@` rule override a user
+//! block. [`LayeredGfwList`] keeps each layer as a separate [`GfwList`] and asks them from the top down instead: the
+//! first layer with a matching rule decides.
+
+use url::Url;
+
+use crate::{Decision, GfwList, Rule};
+
+/// A base list with override layers on top, see the [module documentation](self).
+///
+/// # Examples
+///
+/// ```
+/// # use gfwlist::{GfwList, layered::LayeredGfwList};
+/// let upstream = GfwList::from("||blocked-site.com\n@@||cdn.blocked-site.com").unwrap();
+/// let user = GfwList::from("||cdn.blocked-site.com\n@@||blocked-site.com/public").unwrap();
+/// let mut layered = LayeredGfwList::new(upstream);
+/// layered.push_layer(user);
+/// assert_eq!(layered.test("http://cdn.blocked-site.com").unwrap(), Some("||cdn.blocked-site.com"));
+/// assert_eq!(layered.test("http://blocked-site.com/public").unwrap(), None);
+/// assert_eq!(layered.test("http://blocked-site.com/").unwrap(), Some("||blocked-site.com"));
+/// ```
+#[derive(Debug, Clone)]
+pub struct LayeredGfwList {
+    /// The base list followed by the layers, from the lowest to the highest precedence
+    layers: Vec<GfwList>,
+}
+
+impl LayeredGfwList {
+    /// Creates a stack with only a base list.
+    pub fn new(base: GfwList) -> Self {
+        LayeredGfwList { layers: vec![base] }
+    }
+
+    /// Adds a layer on top, which takes precedence over the base list and all previous layers.
+    pub fn push_layer(&mut self, layer: GfwList) -> &mut Self {
+        self.layers.push(layer);
+        self
+    }
+
+    /// Removes the top layer and returns it. The base list cannot be removed.
+    pub fn pop_layer(&mut self) -> Option<GfwList> {
+        if self.layers.len() > 1 { self.layers.pop() } else { None }
+    }
+
+    /// Returns the base list.
+    pub fn base(&self) -> &GfwList {
+        &self.layers[0]
+    }
+
+    /// Returns the base list mutably, for example to commit staged changes.
+    pub fn base_mut(&mut self) -> &mut GfwList {
+        &mut self.layers[0]
+    }
+
+    /// Returns the layers on top of the base list, from the lowest to the highest precedence.
+    pub fn layers(&self) -> &[GfwList] {
+        &self.layers[1..]
+    }
+
+    /// Returns the layers on top of the base list mutably.
+    pub fn layers_mut(&mut self) -> &mut [GfwList] {
+        &mut self.layers[1..]
+    }
+
+    /// Returns the decision of the highest layer that has a matching rule.
+    fn first_match<'a>(
+        &'a self,
+        mut decide: impl FnMut(&'a GfwList) -> Result<Decision<'a>, url::ParseError>,
+    ) -> Result<Decision<'a>, url::ParseError> {
+        for layer in self.layers.iter().rev() {
+            let decision = decide(layer)?;
+            if decision != Decision::NoMatch {
+                return Ok(decision);
+            }
+        }
+        Ok(Decision::NoMatch)
+    }
+
+    /// Tests whether a URL is blocked, returning the deciding rule like [`GfwList::test`].
+    pub fn test(&self, input: &str) -> Result<Option<&str>, url::ParseError> {
+        Ok(self.decide(input)?.blocked_by().map(Rule::text))
+    }
+
+    /// Decides whether a URL is blocked, asking the layers from the top down like [`GfwList::decide`].
+    ///
+    /// The URL is parsed first, so regex rules see its serialization, see [`GfwList::decide_url`].
+    pub fn decide(&self, input: &str) -> Result<Decision<'_>, url::ParseError> {
+        let url = Url::parse(input)?;
+        self.decide_url(&url)
+    }
+
+    /// Decides whether an already parsed URL is blocked, see [`GfwList::decide_url`].
+    pub fn decide_url(&self, url: &Url) -> Result<Decision<'_>, url::ParseError> {
+        self.first_match(|layer| layer.decide_url(url))
+    }
+
+    /// Decides whether a bare host name is blocked, see [`GfwList::decide_host`].
+    pub fn decide_host(&self, host: &str) -> Decision<'_> {
+        self.first_match(|layer| Ok(layer.decide_host(host)))
+            .unwrap_or(Decision::NoMatch)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn top_layer_wins() {
+        let mut layered = LayeredGfwList::new(GfwList::from("||a.com\n/b\\.com/").unwrap());
+        layered.push_layer(GfwList::from("@@||a.com").unwrap());
+        layered.push_layer(GfwList::from("||x.a.com").unwrap());
+        assert_eq!(layered.decide_host("x.a.com").rule().map(Rule::text), Some("||x.a.com"));
+        assert!(layered.decide("http://y.a.com").unwrap().allowed_by().is_some());
+        assert!(layered.decide("http://b.com").unwrap().is_blocked());
+        assert!(layered.pop_layer().is_some());
+        assert!(layered.pop_layer().is_some());
+        assert!(layered.pop_layer().is_none());
+        assert_eq!(layered.layers().len(), 0);
+    }
+}
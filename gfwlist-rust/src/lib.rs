@@ -1,32 +1,121 @@
 #![doc = include_str!("../README.md")]
 
-use aho_corasick::AhoCorasick;
+use std::borrow::Cow;
+use std::collections::HashSet;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::{Arc, OnceLock};
+use std::{fmt, fs};
+
+use gfwlist_core::{Automata, encode, separator};
 use regex::Regex;
 use thiserror::Error;
 use url::Url;
 
-mod constants {
-    /// Marker byte for the beginning of a URL scheme
-    pub const BEGIN_OF_SCHEME: u8 = 0x01;
-    /// Marker byte for the beginning of a host
-    pub const BEGIN_OF_HOST: u8 = 0x02;
-    /// Marker byte for the beginning of a path
-    pub const BEGIN_OF_PATH: u8 = 0x03;
-    /// Delimiter byte for host components
-    pub const HOST_DELIMITER: u8 = b'.';
-    /// Delimiter byte for path components
-    pub const PATH_DELIMITER: u8 = b'/';
-}
+mod base64;
+mod builder;
+pub mod cache;
+mod checksum;
+mod condition;
+pub mod corpus;
+pub mod diff;
+#[cfg(feature = "encoding")]
+mod encoding;
+pub mod expect;
+pub mod filter;
+pub mod format;
+mod golden;
+mod host;
+mod index;
+mod ip;
+pub mod layered;
+pub mod lint;
+mod literal;
+pub mod live;
+pub mod metadata;
+mod miss;
+pub mod monitor;
+pub mod optimize;
+mod options;
+#[cfg(feature = "rayon")]
+mod parallel;
+mod patch;
+mod port;
+mod request;
+pub mod risk;
+mod rule;
+mod scheme;
+pub mod source;
+pub mod stats;
+
+pub use builder::{BuildHandle, ChecksumPolicy, GfwListBuilder, IncludeResolver, Limits, RegexPolicy, Resource};
+pub use golden::GoldenError;
+pub use host::HostDecision;
+pub use miss::{Mismatch, NearMiss};
+pub use options::TestOptions;
+pub use request::{RequestContext, RuleOptions};
+pub use rule::{Confidence, Origin, Pattern, Rule, RuleId, RuleKind, RuleParser};
+pub use scheme::SchemeMap;
 
 /// Errors that can occur when building a GfwList.
+///
+/// Errors own all their data, so they can be returned from functions that own the rule text, and implement
+/// [`std::error::Error`] for use with `?` in other error types.
 #[derive(Debug, Error)]
 pub enum BuildError {
     /// Error related to syntax issues in a rule
-    #[error("syntax error at line {0}: {1}")]
-    Syntax(usize, SyntaxError),
+    #[error("syntax error at line {line}: {error}")]
+    Syntax {
+        /// The 1-based line number of the rule
+        line: usize,
+        /// The byte offset of the start of the line in the input, or 0 if the rule was not parsed from text
+        offset: usize,
+        /// The error in the rule
+        error: SyntaxError,
+    },
+    /// Error in one of several named sources
+    #[error("error in source {0}: {1}")]
+    Source(String, Box<BuildError>),
     /// Error from the Aho-Corasick algorithm during pattern compilation
     #[error("error building Aho-Corasick: {0}")]
     AhoCorasick(#[from] aho_corasick::BuildError),
+    /// The build was cancelled through a [`BuildHandle`]
+    #[error("build cancelled")]
+    Cancelled,
+    /// The list could not be read, see [`GfwList::from_file`]
+    #[error("failed to read list: {0}")]
+    Io(#[from] std::io::Error),
+    /// An `!#include` directive refers to a file that is already being included
+    #[error("include cycle through {}", .0.display())]
+    IncludeCycle(PathBuf),
+    /// An `!#include` directive refers to a file outside the include root, see [`GfwListBuilder::include_root`]
+    #[error("included file {} is outside the include root", .0.display())]
+    IncludeOutsideRoot(PathBuf),
+    /// A resource limit of the builder is exceeded, see [`Limits`]
+    #[error("limit of {limit} {resource} exceeded")]
+    Limit {
+        /// The exceeded resource
+        resource: Resource,
+        /// The configured limit
+        limit: usize,
+    },
+    /// An expectation embedded in the list is not met, see [`expect`]
+    #[error("expectation at line {line} not met for {url}")]
+    Expectation {
+        /// The 1-based line number of the expectation
+        line: usize,
+        /// The URL of the expectation
+        url: String,
+    },
+    /// The list does not match its `! Checksum:` comment, or has none although one is required, see [`ChecksumPolicy`]
+    #[error("checksum mismatch: declared {}, computed {actual}", declared.as_deref().unwrap_or("none"))]
+    Checksum {
+        /// The declared checksum, if any
+        declared: Option<String>,
+        /// The checksum computed from the list
+        actual: String,
+    },
 }
 
 /// Specific syntax errors encountered during GfwList parsing
@@ -41,6 +130,358 @@ pub enum SyntaxError {
     /// Error parsing a URL
     #[error("error parsing URL: {0}")]
     Url(url::ParseError),
+    /// A regex rule was rejected by [`RegexPolicy::Reject`]
+    #[error("regular expressions are disabled")]
+    RegexDisabled,
+    /// An inline base64 chunk could not be decoded
+    #[error("invalid base64 chunk")]
+    Base64,
+    /// An `!#if`, `!#else` or `!#endif` directive is malformed or unbalanced, see [`GfwListBuilder::define`]
+    #[error("invalid conditional directive")]
+    Directive,
+    /// A line is longer than [`Limits::max_line_length`]
+    #[error("line of {length} bytes exceeds the limit of {limit} bytes")]
+    LineTooLong {
+        /// The length of the line in bytes
+        length: usize,
+        /// The maximum length in bytes
+        limit: usize,
+    },
+}
+
+/// Errors that can occur when matching a URL with [`GfwList::test_with_options`].
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum MatchError {
+    /// The URL could not be parsed
+    #[error("error parsing URL: {0}")]
+    Url(#[from] url::ParseError),
+    /// The deadline passed before matching finished
+    #[error("deadline exceeded")]
+    Timeout,
+}
+
+impl MatchError {
+    /// Returns the stable code of the error.
+    pub fn code(&self) -> ErrorCode {
+        match self {
+            MatchError::Url(_) => ErrorCode::Url,
+            MatchError::Timeout => ErrorCode::Timeout,
+        }
+    }
+}
+
+/// Stable numeric codes of errors, for callers that cannot match on Rust error types.
+///
+/// The values never change, so they can be shared across FFI boundaries and service protocols. Codes that are not
+/// produced by this crate are reserved for embedding services.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[repr(u32)]
+pub enum ErrorCode {
+    /// A rule could not be parsed, see [`SyntaxError`]
+    Parse = 1,
+    /// A URL could not be parsed, see [`url::ParseError`]
+    Url = 2,
+    /// A size limit was exceeded, such as the capacity of the automata
+    LimitExceeded = 3,
+    /// An operation did not finish in time
+    Timeout = 4,
+    /// A list was not modified since it was last fetched
+    NotModified = 5,
+    /// Serialized data has an unsupported version
+    VersionMismatch = 6,
+    /// A build was cancelled through a [`BuildHandle`]
+    Cancelled = 7,
+    /// An expectation embedded in a list is not met
+    ExpectationFailed = 8,
+    /// A list could not be read
+    Io = 9,
+    /// A list does not match its declared checksum
+    ChecksumMismatch = 10,
+}
+
+impl ErrorCode {
+    /// Returns the numeric value of the code.
+    pub fn as_u32(self) -> u32 {
+        self as u32
+    }
+}
+
+impl BuildError {
+    /// Returns the stable code of the error.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use gfwlist::{ErrorCode, GfwList};
+    /// let error = GfwList::from("/invalid").unwrap_err();
+    /// assert_eq!(error.code(), ErrorCode::Parse);
+    /// assert_eq!(error.code().as_u32(), 1);
+    /// ```
+    pub fn code(&self) -> ErrorCode {
+        match self {
+            BuildError::Syntax { error, .. } => error.code(),
+            BuildError::Source(_, error) => error.code(),
+            BuildError::AhoCorasick(_) => ErrorCode::LimitExceeded,
+            BuildError::Cancelled => ErrorCode::Cancelled,
+            BuildError::Io(_) => ErrorCode::Io,
+            BuildError::IncludeCycle(_) | BuildError::IncludeOutsideRoot(_) => ErrorCode::Parse,
+            BuildError::Limit { .. } => ErrorCode::LimitExceeded,
+            BuildError::Expectation { .. } => ErrorCode::ExpectationFailed,
+            BuildError::Checksum { .. } => ErrorCode::ChecksumMismatch,
+        }
+    }
+}
+
+impl SyntaxError {
+    /// Returns the stable code of the error, which is always [`ErrorCode::Parse`].
+    pub fn code(&self) -> ErrorCode {
+        ErrorCode::Parse
+    }
+}
+
+/// A line that was skipped by lenient parsing.
+#[derive(Debug, Error)]
+#[error("line {line}: {error}")]
+pub struct Diagnostic {
+    /// The 1-based line number
+    pub line: usize,
+    /// The byte offset of the start of the line in the input
+    pub offset: usize,
+    /// The text of the line
+    pub text: String,
+    /// Why the line was skipped
+    pub error: SyntaxError,
+}
+
+/// The outcome of matching a URL against a [`GfwList`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Decision<'a> {
+    /// The URL is matched by a positive rule or a regex rule
+    Blocked(&'a Rule),
+    /// The URL is matched by a negative (whitelist) rule, which takes precedence over positive rules
+    Allowed(&'a Rule),
+    /// The URL is not matched by any rule
+    NoMatch,
+}
+
+impl<'a> Decision<'a> {
+    /// Returns the rule that decided the outcome, if any.
+    pub fn rule(&self) -> Option<&'a Rule> {
+        match self {
+            Decision::Blocked(rule) | Decision::Allowed(rule) => Some(rule),
+            Decision::NoMatch => None,
+        }
+    }
+
+    /// Returns the positive or regex rule that blocked the URL, if any.
+    pub fn blocked_by(&self) -> Option<&'a Rule> {
+        match self {
+            Decision::Blocked(rule) => Some(rule),
+            _ => None,
+        }
+    }
+
+    /// Returns the negative rule that allowed the URL, if any.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use gfwlist::GfwList;
+    /// let gfw_list = GfwList::from("||blocked-site.com\n@@||exception.blocked-site.com").unwrap();
+    /// let decision = gfw_list.decide("http://exception.blocked-site.com").unwrap();
+    /// assert_eq!(decision.allowed_by().map(|rule| rule.text()), Some("@@||exception.blocked-site.com"));
+    /// assert_eq!(gfw_list.decide("http://other.com").unwrap().allowed_by(), None);
+    /// ```
+    pub fn allowed_by(&self) -> Option<&'a Rule> {
+        match self {
+            Decision::Allowed(rule) => Some(rule),
+            _ => None,
+        }
+    }
+
+    /// Returns whether the URL is blocked.
+    pub fn is_blocked(&self) -> bool {
+        matches!(self, Decision::Blocked(_))
+    }
+
+    /// Returns the confidence of the deciding rule, if any, see [`Rule::confidence`].
+    ///
+    /// Cautious deployments may treat decisions of keyword rules differently, or only accept them for some domains
+    /// with [`TestOptions::keyword_suffixes`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use gfwlist::{Confidence, GfwList};
+    /// let gfw_list = GfwList::from("||google.com\n/^https?:\\/\\/[^\\/]*gstatic/").unwrap();
+    /// assert_eq!(gfw_list.decide("https://www.google.com/").unwrap().confidence(), Some(Confidence::High));
+    /// assert_eq!(gfw_list.decide("https://gstatic.cn/").unwrap().confidence(), Some(Confidence::Low));
+    /// ```
+    pub fn confidence(&self) -> Option<Confidence> {
+        self.rule().map(Rule::confidence)
+    }
+
+    /// Returns where the deciding rule comes from, if it is known.
+    ///
+    /// Rules compiled from several sources with [`source::compile`] or [`GfwListBuilder::add_source`] know the name
+    /// of their source, and rules parsed from text know their line.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use gfwlist::source::{Source, SourceFormat, compile};
+    /// let gfw_list = compile(&[
+    ///     Source::new("gfwlist.txt", SourceFormat::AutoProxy, "||blocked-site.com"),
+    ///     Source::new("corporate-list.txt", SourceFormat::AutoProxy, "! internal\n||intranet.example"),
+    /// ])
+    /// .unwrap();
+    /// let decision = gfw_list.decide("http://intranet.example").unwrap();
+    /// assert_eq!(decision.origin().unwrap().to_string(), "corporate-list.txt:2");
+    /// assert_eq!(decision.to_string(), "blocked by ||intranet.example (corporate-list.txt:2)");
+    /// ```
+    pub fn origin(&self) -> Option<&'a Origin> {
+        self.rule()?.origin()
+    }
+}
+
+/// Describes the decision and its rule, such as `blocked by ||example.com (gfwlist.txt:12)`.
+impl fmt::Display for Decision<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let rule = match self {
+            Decision::Blocked(rule) => {
+                write!(f, "blocked by {}", rule.text())?;
+                rule
+            }
+            Decision::Allowed(rule) => {
+                write!(f, "allowed by {}", rule.text())?;
+                rule
+            }
+            Decision::NoMatch => return f.write_str("not matched"),
+        };
+        match rule.origin() {
+            Some(origin) => write!(f, " ({origin})"),
+            None => Ok(()),
+        }
+    }
+}
+
+/// All rules matching a URL, returned by [`GfwList::test_all`].
+///
+/// Rules are in priority order: regex rules, then negative rules, then positive rules. Within each group, rules are
+/// ordered like the matches of the automata, so the first rule is the one that [`GfwList::decide`] reports.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Matches<'a> {
+    url: String,
+    rules: Vec<&'a Rule>,
+    /// The part of the URL matched by each rule
+    fragments: Vec<String>,
+}
+
+impl<'a> Matches<'a> {
+    /// Iterates over the matching rules in priority order.
+    pub fn iter(&self) -> impl Iterator<Item = &'a Rule> + '_ {
+        self.rules.iter().copied()
+    }
+
+    /// Returns the number of matching rules.
+    pub fn len(&self) -> usize {
+        self.rules.len()
+    }
+
+    /// Returns whether no rule matches.
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+
+    /// Returns the decision made by the rule with the highest priority.
+    pub fn decision(&self) -> Decision<'a> {
+        match self.rules.first() {
+            Some(rule) if rule.is_negative() => Decision::Allowed(rule),
+            Some(rule) => Decision::Blocked(rule),
+            None => Decision::NoMatch,
+        }
+    }
+
+    /// Renders the matches as a Graphviz DOT graph, from the URL through each rule and its matched fragment to the
+    /// decision.
+    ///
+    /// The edge from the deciding rule to the decision is bold, and the edges of overridden rules are dashed.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use gfwlist::GfwList;
+    /// let gfw_list = GfwList::from("||example.com\n@@||www.example.com").unwrap();
+    /// let dot = gfw_list.test_all("http://www.example.com/").unwrap().to_dot();
+    /// assert!(dot.starts_with("digraph explain {"));
+    /// assert!(dot.contains("rule0 -> decision [style=bold];"));
+    /// assert!(dot.contains("rule1 -> decision [style=dashed];"));
+    /// ```
+    pub fn to_dot(&self) -> String {
+        fn quote(input: &str) -> String {
+            format!("\"{}\"", input.replace('\\', "\\\\").replace('"', "\\\""))
+        }
+        let decision = match self.decision() {
+            Decision::Blocked(_) => "blocked",
+            Decision::Allowed(_) => "allowed",
+            Decision::NoMatch => "no match",
+        };
+        let mut output = String::from("digraph explain {\n  rankdir=LR;\n");
+        output.push_str(&format!("  url [label={}, shape=box];\n", quote(&self.url)));
+        output.push_str(&format!("  decision [label={}, shape=diamond];\n", quote(decision)));
+        for (index, (rule, fragment)) in self.rules.iter().zip(&self.fragments).enumerate() {
+            let style = if index == 0 { "bold" } else { "dashed" };
+            output.push_str(&format!("  rule{index} [label={}];\n", quote(rule.text())));
+            output.push_str(&format!("  url -> rule{index} [label={}];\n", quote(fragment)));
+            output.push_str(&format!("  rule{index} -> decision [style={style}];\n"));
+        }
+        if self.rules.is_empty() {
+            output.push_str("  url -> decision [style=dashed];\n");
+        }
+        output.push_str("}\n");
+        output
+    }
+}
+
+impl<'a> IntoIterator for Matches<'a> {
+    type Item = &'a Rule;
+    type IntoIter = std::vec::IntoIter<&'a Rule>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.rules.into_iter()
+    }
+}
+
+/// A [`Decision`] by rule index, which does not borrow the list.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Verdict {
+    Blocked(usize),
+    Allowed(usize),
+    NoMatch,
+}
+
+impl From<gfwlist_core::Verdict> for Verdict {
+    fn from(verdict: gfwlist_core::Verdict) -> Self {
+        match verdict {
+            gfwlist_core::Verdict::Blocked(index) => Verdict::Blocked(index),
+            gfwlist_core::Verdict::Allowed(index) => Verdict::Allowed(index),
+            gfwlist_core::Verdict::NoMatch => Verdict::NoMatch,
+        }
+    }
+}
+
+impl Verdict {
+    /// Looks up the matched rule, counting a hit for it if hit counting is enabled.
+    pub(crate) fn resolve(self, list: &GfwList) -> Decision<'_> {
+        if let Verdict::Blocked(index) | Verdict::Allowed(index) = self {
+            list.record_hit(index);
+        }
+        match self {
+            Verdict::Blocked(index) => Decision::Blocked(&list.rules[index]),
+            Verdict::Allowed(index) => Decision::Allowed(&list.rules[index]),
+            Verdict::NoMatch => Decision::NoMatch,
+        }
+    }
 }
 
 /// `GfwList` represents a compiled set of rules for matching URLs.
@@ -48,53 +489,129 @@ pub enum SyntaxError {
 /// It uses Aho-Corasick for fast pattern matching and regular expressions
 /// for more complex matching. Rules can be either positive (block) or
 /// negative (allow) patterns.
+///
+/// # Sharing between threads
+///
+/// A `GfwList` is `Send + Sync`, and all lookups take `&self`, so one list can serve any number of threads. Build it
+/// with [`GfwList::build_shared`] or [`GfwListBuilder::build_shared`] and clone the `Arc` into each thread. Subscribers
+/// that should not keep a replaced list alive, such as caches keyed by list, hold a [`Weak`](std::sync::Weak) from
+/// [`Arc::downgrade`] instead. Lists that are updated while being read are better served by
+/// [`LiveGfwList`](live::LiveGfwList).
 #[derive(Debug)]
 pub struct GfwList {
-    positive_ac: AhoCorasick,
-    negative_ac: AhoCorasick,
-    positive_rules: Vec<String>,
-    regex_patterns: Vec<(Regex, String)>,
+    automata: Automata,
+    rules: Vec<Rule>,
+    regex_patterns: Vec<(Regex, usize)>,
+    regex_prefilter: Option<literal::RegexPrefilter>,
+    /// The indices of the rules with the `$important` option, in ascending order
+    important_rules: Vec<usize>,
+    /// The indices of the rules that only match with a request context, see [`RuleOptions`], in ascending order
+    contextual_rules: Vec<usize>,
+    /// The networks of IP rules, where negative rules come first
+    ip_rules: Vec<(ip::Network, usize)>,
+    /// The ports of port-specific rules by rule index, in ascending order
+    rule_ports: Vec<(usize, u16)>,
+    staged: Option<Vec<Rule>>,
+    disabled_sections: HashSet<String>,
+    regex_policy: RegexPolicy,
+    /// Whether query strings are kept out of matching, see [`GfwListBuilder::ignore_query`]
+    ignore_query: bool,
+    hits: Option<Box<[stats::HitCounter]>>,
+    expectations: Vec<expect::Expectation>,
+    metadata: metadata::ListMetadata,
+    schemes: SchemeMap,
+    domain_index: OnceLock<index::DomainIndex>,
 }
 
-fn append_host(acc: &mut Vec<u8>, host: &[u8]) {
-    if !host.starts_with(&[constants::HOST_DELIMITER]) {
-        acc.push(constants::HOST_DELIMITER);
-    }
-    acc.extend(host);
+/// Splits the input into lines like [`str::lines`], together with their 1-based line numbers and byte offsets.
+fn numbered_lines(input: &str) -> impl Iterator<Item = (usize, usize, &str)> {
+    let mut offset = 0;
+    input.split_inclusive('\n').enumerate().map(move |(index, raw)| {
+        let start = offset;
+        offset += raw.len();
+        let line = match raw.strip_suffix('\n') {
+            Some(line) => line.strip_suffix('\r').unwrap_or(line),
+            None => raw,
+        };
+        (index + 1, start, line)
+    })
 }
 
-fn append_path(acc: &mut Vec<u8>, path: &[u8]) {
-    acc.extend(path);
-    if !path.ends_with(&[constants::PATH_DELIMITER]) {
-        acc.push(constants::PATH_DELIMITER);
-    }
+/// Splits bytes into lines like [`numbered_lines`], finding line breaks with SIMD if the `memchr` feature is enabled.
+fn numbered_byte_lines(input: &[u8]) -> impl Iterator<Item = (usize, usize, &[u8])> {
+    #[cfg(feature = "memchr")]
+    let breaks = memchr::memchr_iter(b'\n', input);
+    #[cfg(not(feature = "memchr"))]
+    let breaks = (input.iter().enumerate()).filter_map(|(pos, &byte)| (byte == b'\n').then_some(pos));
+    let last = Some(input.len()).filter(|_| !input.is_empty() && !input.ends_with(b"\n"));
+    let mut start = 0;
+    (breaks.map(|pos| pos + 1).chain(last))
+        .enumerate()
+        .map(move |(index, end)| {
+            let offset = start;
+            let raw = &input[start..end];
+            start = end;
+            let line = raw.strip_suffix(b"\n").unwrap_or(raw);
+            (index + 1, offset, line.strip_suffix(b"\r").unwrap_or(line))
+        })
 }
 
-fn append_host_path(acc: &mut Vec<u8>, input: &[u8]) {
-    let pos = input
-        .iter()
-        .position(|&b| b == constants::PATH_DELIMITER)
-        .unwrap_or(input.len());
-    append_host(acc, &input[..pos]);
-    acc.push(constants::BEGIN_OF_PATH);
-    append_path(acc, &input[pos..]);
+fn append_url<const FULL_MODE: bool>(
+    acc: &mut Vec<u8>,
+    input: &str,
+    schemes: &SchemeMap,
+) -> Result<(), url::ParseError> {
+    let url = Url::parse(input)?;
+    append_parsed_url::<FULL_MODE>(acc, &url, input.ends_with('/'), schemes)
 }
 
-fn append_url<const FULL_MODE: bool>(acc: &mut Vec<u8>, input: &str) -> Result<(), url::ParseError> {
-    let url = Url::parse(input)?;
-    let host_str = url.host_str().ok_or(url::ParseError::EmptyHost)?;
-    acc.push(constants::BEGIN_OF_SCHEME);
-    acc.extend(url.scheme().as_bytes());
-    acc.push(constants::BEGIN_OF_HOST);
-    append_host(acc, host_str.as_bytes());
-    let path = url.path();
-    if FULL_MODE || path != "/" || input.ends_with('/') {
-        acc.push(constants::BEGIN_OF_PATH);
-        append_path(acc, url.path().as_bytes());
+fn append_parsed_url<const FULL_MODE: bool>(
+    acc: &mut Vec<u8>,
+    url: &Url,
+    trailing_slash: bool,
+    schemes: &SchemeMap,
+) -> Result<(), url::ParseError> {
+    encode::append_url(acc, url, schemes.canonical(url.scheme()), !FULL_MODE, trailing_slash)
+}
+
+fn append_pattern(acc: &mut Vec<u8>, pattern: &Pattern, schemes: &SchemeMap) -> Result<(), url::ParseError> {
+    match pattern {
+        Pattern::Domain(s) => {
+            let s = separator::normalize_host_path(s);
+            let s = port::strip_port(&s);
+            encode::append_host_path(acc, ip::normalize_pattern_host(&s).as_bytes());
+        }
+        Pattern::Host(s) => {
+            let s = separator::normalize_host_path(s);
+            let s = port::strip_port(&s);
+            acc.push(encode::BEGIN_OF_HOST);
+            encode::append_host_path(acc, ip::normalize_pattern_host(&s).as_bytes());
+        }
+        Pattern::Url(s) => append_url::<false>(acc, &separator::normalize_url(s), schemes)?,
+        Pattern::Regex(_) | Pattern::Cidr(_) => unreachable!("regex and IP patterns are not encoded"),
     }
     Ok(())
 }
 
+/// Decodes a list encoded like the official `gfwlist.txt`, detected by its encoded `[AutoProxy` header.
+fn decode_downloaded(input: &str) -> Option<String> {
+    if !input.trim_start().starts_with("W0F1dG9Qcm94") {
+        return None;
+    }
+    String::from_utf8(base64::decode(input)?).ok()
+}
+
+/// Decodes a list that is entirely base64-encoded text, with or without an `[AutoProxy` header.
+fn decode_base64_list(input: &str) -> Option<String> {
+    // short inputs like `/ads/` are valid base64 by chance
+    if input.trim().len() < 16 {
+        return None;
+    }
+    let decoded = String::from_utf8(base64::decode(input)?).ok()?;
+    let text = decoded.chars().all(|c| !c.is_control() || c.is_ascii_whitespace());
+    text.then_some(decoded)
+}
+
 impl GfwList {
     /// Constructs a new `GfwList` from a string containing GFW list rules.
     ///
@@ -104,6 +621,9 @@ impl GfwList {
     /// - Negative patterns: `@@pattern` (whitelist)
     /// - Positive patterns: `pattern` (blacklist)
     /// - Patterns with different formats: `.example.com`, `||example.com`, etc.
+    /// - IP rules: `IP-CIDR,8.8.8.0/24` or `IP-CIDR6,2001:db8::/32`, matching hosts that are IP addresses
+    /// - Port-specific patterns: `|http://example.com:8080` or `||example.com:2053^`, matching URLs on that port
+    /// - Patterns with a query: `||example.com/search?q=`, matching a prefix of the query string
     ///
     /// # Examples
     ///
@@ -117,54 +637,420 @@ impl GfwList {
     /// let gfw_list = GfwList::from(list_content).unwrap();
     /// ```
     pub fn from(input: &str) -> Result<Self, BuildError> {
-        let mut positive_rules: Vec<String> = vec![];
-        let mut negative_rules: Vec<String> = vec![];
-        let mut positive_patterns: Vec<Vec<u8>> = vec![];
-        let mut negative_patterns: Vec<Vec<u8>> = vec![];
-        let mut regex_patterns: Vec<(Regex, String)> = vec![];
-        // split the source into lines
-        for (line_index, mut line_str) in input.lines().enumerate() {
-            // skip empty lines and comments
-            if line_str.is_empty() || line_str.starts_with('!') {
-                continue;
-            }
-            if line_str.starts_with('/') {
-                if line_str.len() == 1 || !line_str.ends_with('/') {
-                    return Err(BuildError::Syntax(line_index, SyntaxError::Rule));
-                }
-                let regex = Regex::new(&line_str[1..line_str.len() - 1])
-                    .map_err(|e| BuildError::Syntax(line_index, SyntaxError::Regex(e)))?;
-                regex_patterns.push((regex, line_str.to_string()));
-                continue;
+        let mut builder = GfwListBuilder::new();
+        builder.add_rules(input)?;
+        builder.build()
+    }
+
+    /// Constructs a new `GfwList`, skipping invalid lines instead of failing.
+    ///
+    /// Returns the list together with a [`Diagnostic`] for every skipped line. Only errors that are not tied to a
+    /// line, such as failing to build the automata, are returned as errors.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use gfwlist::GfwList;
+    /// let (gfw_list, diagnostics) = GfwList::from_lenient("||blocked-site.com\n/invalid\n|not a url").unwrap();
+    /// assert_eq!(gfw_list.len(), 1);
+    /// assert_eq!(diagnostics.len(), 2);
+    /// assert_eq!(diagnostics[0].line, 2);
+    /// assert_eq!(diagnostics[1].text, "|not a url");
+    /// ```
+    pub fn from_lenient(input: &str) -> Result<(Self, Vec<Diagnostic>), BuildError> {
+        let mut builder = GfwListBuilder::new();
+        let diagnostics = builder.add_rules_lenient(input);
+        Ok((builder.build()?, diagnostics))
+    }
+
+    /// Constructs a new `GfwList` from the file at a path.
+    ///
+    /// The file must be valid UTF-8. Read failures are returned as [`BuildError::Io`]. Lists encoded like the official
+    /// `gfwlist.txt` are decoded, see [`GfwList::from_base64`]. Other files may include further files from their
+    /// directory, see [`GfwListBuilder::add_file`].
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self, BuildError> {
+        let input = fs::read_to_string(&path)?;
+        if let Some(decoded) = decode_downloaded(&input) {
+            return GfwList::from(&decoded);
+        }
+        let mut builder = GfwListBuilder::new();
+        builder.add_file_content(path.as_ref(), &input)?;
+        builder.build()
+    }
+
+    /// Constructs a new `GfwList` from a reader, such as a socket or a decompressor.
+    ///
+    /// The input is read to the end and must be valid UTF-8. Read failures are returned as [`BuildError::Io`]. Lists
+    /// encoded like the official `gfwlist.txt` are decoded, see [`GfwList::from_base64`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use gfwlist::{BuildError, GfwList};
+    /// let gfw_list = GfwList::from_reader("||blocked-site.com\n".as_bytes()).unwrap();
+    /// assert_eq!(gfw_list.len(), 1);
+    /// assert!(matches!(GfwList::from_reader(&b"\xff"[..]), Err(BuildError::Io(_))));
+    /// ```
+    pub fn from_reader(mut reader: impl Read) -> Result<Self, BuildError> {
+        let mut input = String::new();
+        reader.read_to_string(&mut input)?;
+        GfwList::from_downloaded(&input)
+    }
+
+    /// Constructs a new `GfwList` from a base64-encoded list, the form in which the official `gfwlist.txt` is
+    /// distributed.
+    ///
+    /// Line breaks in the encoded input are ignored. Invalid base64 is reported as [`SyntaxError::Base64`] at line 1,
+    /// and line numbers of other errors refer to the decoded list.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use gfwlist::GfwList;
+    /// // "[AutoProxy 0.2.9]\n||blocked-site.com\n"
+    /// let gfw_list = GfwList::from_base64("W0F1dG9Qcm94eSAwLjIuOV0KfHxibG9ja2VkLXNpdGUuY29tCg==").unwrap();
+    /// assert_eq!(gfw_list.test("http://blocked-site.com").unwrap(), Some("||blocked-site.com"));
+    /// ```
+    pub fn from_base64(input: &str) -> Result<Self, BuildError> {
+        let decoded = base64::decode(input).and_then(|bytes| String::from_utf8(bytes).ok());
+        match decoded {
+            Some(decoded) => GfwList::from(&decoded),
+            None => Err(BuildError::Syntax {
+                line: 1,
+                offset: 0,
+                error: SyntaxError::Base64,
+            }),
+        }
+    }
+
+    /// Constructs a new `GfwList` from a list in any supported format, such as a list provided by a user.
+    ///
+    /// Base64-encoded lists are decoded first, then the format is guessed with [`SourceFormat::detect`]. AutoProxy
+    /// lists are parsed like with [`GfwList::from`], while other formats are imported like a [`Source`] of that
+    /// format, skipping the entries that cannot be represented. Input that is not UTF-8 fails with
+    /// [`BuildError::Io`], unless the `encoding` feature is enabled, which decodes it like [`GfwList::from_bytes`]
+    /// instead.
+    ///
+    /// [`Source`]: source::Source
+    /// [`SourceFormat::detect`]: source::SourceFormat::detect
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use gfwlist::GfwList;
+    /// let dnsmasq = GfwList::from_any(b"server=/blocked-site.com/8.8.8.8\n").unwrap();
+    /// assert_eq!(dnsmasq.test("http://www.blocked-site.com").unwrap(), Some("||blocked-site.com"));
+    /// let hosts = GfwList::from_any(b"0.0.0.0 ads.example.com").unwrap();
+    /// assert!(hosts.test("http://ads.example.com").unwrap().is_some());
+    /// // "[AutoProxy 0.2.9]\n||blocked-site.com\n"
+    /// let encoded = GfwList::from_any(b"W0F1dG9Qcm94eSAwLjIuOV0KfHxibG9ja2VkLXNpdGUuY29tCg==").unwrap();
+    /// assert_eq!(encoded.test("http://blocked-site.com").unwrap(), Some("||blocked-site.com"));
+    /// ```
+    pub fn from_any(input: &[u8]) -> Result<Self, BuildError> {
+        let input = GfwList::decode_any(input)?;
+        match source::SourceFormat::detect(&input) {
+            source::SourceFormat::AutoProxy => GfwList::from(&input),
+            format => source::compile(&[source::Source::new("input", format, &input)]),
+        }
+    }
+
+    /// Decodes a list in any supported encoding into its text, as [`GfwList::from_any`] does before guessing its
+    /// format, such as for [linting](GfwList::lint) a list provided by a user.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use gfwlist::GfwList;
+    /// let text = GfwList::decode_any(b"W0F1dG9Qcm94eSAwLjIuOV0KfHxibG9ja2VkLXNpdGUuY29tCg==").unwrap();
+    /// assert_eq!(text, "[AutoProxy 0.2.9]\n||blocked-site.com\n");
+    /// assert_eq!(GfwList::decode_any(b"\xef\xbb\xbf||example.com").unwrap(), "||example.com");
+    /// ```
+    pub fn decode_any(input: &[u8]) -> Result<String, BuildError> {
+        #[cfg(feature = "encoding")]
+        let input = encoding::decode_text(input);
+        #[cfg(not(feature = "encoding"))]
+        let input = Cow::Borrowed(
+            std::str::from_utf8(input)
+                .map_err(|error| BuildError::Io(std::io::Error::new(std::io::ErrorKind::InvalidData, error)))?,
+        );
+        let input = input.strip_prefix('\u{feff}').unwrap_or(&input);
+        Ok(decode_base64_list(input).unwrap_or_else(|| input.to_string()))
+    }
+
+    /// Constructs a new `GfwList` like [`GfwList::from`], ready to be shared between threads.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use std::sync::Arc;
+    /// # use std::thread;
+    /// # use gfwlist::GfwList;
+    /// let gfw_list = GfwList::build_shared("||blocked-site.com").unwrap();
+    /// let subscriber = Arc::downgrade(&gfw_list);
+    /// let worker = {
+    ///     let gfw_list = gfw_list.clone();
+    ///     thread::spawn(move || gfw_list.test("http://blocked-site.com").unwrap().is_some())
+    /// };
+    /// assert!(worker.join().unwrap());
+    /// // the subscriber notices once the list is dropped
+    /// drop(gfw_list);
+    /// assert!(subscriber.upgrade().is_none());
+    /// ```
+    pub fn build_shared(input: &str) -> Result<Arc<Self>, BuildError> {
+        GfwList::from(input).map(Arc::new)
+    }
+
+    /// Parses a list that may be base64-encoded, see [`decode_downloaded`].
+    fn from_downloaded(input: &str) -> Result<Self, BuildError> {
+        GfwList::from(decode_downloaded(input).as_deref().unwrap_or(input))
+    }
+
+    /// Constructs a new `GfwList` from already parsed rules.
+    ///
+    /// This is useful for rules produced by the importers in [`format`]. Line numbers in errors refer to the 1-based
+    /// position of the rule in the input.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use gfwlist::{GfwList, Pattern, Rule};
+    /// let rules = vec![Rule::new(false, Pattern::Domain("blocked-site.com".into()))];
+    /// let gfw_list = GfwList::from_rules(rules).unwrap();
+    /// assert_eq!(gfw_list.test("http://blocked-site.com/page").unwrap(), Some("||blocked-site.com"));
+    /// ```
+    pub fn from_rules(rules: impl IntoIterator<Item = Rule>) -> Result<Self, BuildError> {
+        let mut builder = GfwListBuilder::new();
+        for (index, rule) in rules.into_iter().enumerate() {
+            builder.add_parsed(rule).map_err(|error| BuildError::Syntax {
+                line: index + 1,
+                offset: 0,
+                error,
+            })?;
+        }
+        builder.build()
+    }
+
+    /// Combines the rules of several lists into a new list, rebuilding the automata.
+    ///
+    /// Rules keep their order, and duplicates are only kept at their first occurrence. The precedence is the same as
+    /// for a single list: regex rules first, then negative rules of any list, then positive rules of any list. So an
+    /// allowlist merged with a blocklist overrides it regardless of their order.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use gfwlist::GfwList;
+    /// let upstream = GfwList::from("||blocked-site.com\n||example.com").unwrap();
+    /// let personal = GfwList::from("@@||www.example.com\n||example.com").unwrap();
+    /// let merged = GfwList::merge(&[&upstream, &personal]).unwrap();
+    /// assert_eq!(merged.len(), 3);
+    /// assert_eq!(merged.test("http://www.example.com").unwrap(), None);
+    /// assert_eq!(merged.test("http://blocked-site.com").unwrap(), Some("||blocked-site.com"));
+    /// ```
+    pub fn merge(lists: &[&GfwList]) -> Result<Self, BuildError> {
+        let mut seen = HashSet::new();
+        let rules: Vec<Rule> = (lists.iter())
+            .flat_map(|list| list.rules.iter())
+            .filter(|rule| seen.insert(*rule))
+            .cloned()
+            .collect();
+        Self::from_rules(rules)
+    }
+
+    /// Combines the rules of this list with another list, see [`GfwList::merge`].
+    pub fn merged_with(&self, other: &GfwList) -> Result<Self, BuildError> {
+        Self::merge(&[self, other])
+    }
+
+    /// Stages a rule to be added by the next [`commit`](Self::commit).
+    ///
+    /// The rule is validated immediately, but matching is not affected until the changes are committed. Empty lines
+    /// and comments are ignored.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use gfwlist::GfwList;
+    /// let mut gfw_list = GfwList::from("||blocked-site.com").unwrap();
+    /// gfw_list.add_rule("||user-site.com").unwrap();
+    /// assert_eq!(gfw_list.test("http://user-site.com").unwrap(), None);
+    /// gfw_list.commit().unwrap();
+    /// assert_eq!(gfw_list.test("http://user-site.com").unwrap(), Some("||user-site.com"));
+    /// ```
+    pub fn add_rule(&mut self, line: &str) -> Result<(), SyntaxError> {
+        if let Some(rule) = self.validate_rule(line)? {
+            self.staged.get_or_insert_with(|| self.rules.clone()).push(rule);
+        }
+        Ok(())
+    }
+
+    /// Parses a line and checks that it compiles with the settings of this list.
+    pub(crate) fn validate_rule(&self, line: &str) -> Result<Option<Rule>, SyntaxError> {
+        let Some(rule) = Rule::parse(line)? else {
+            return Ok(None);
+        };
+        GfwListBuilder::new()
+            .regex_policy(self.regex_policy)
+            .add_parsed(rule.clone())?;
+        Ok(Some(rule))
+    }
+
+    /// Shows how a line is compiled with the settings of this list, for editors that preview rules.
+    ///
+    /// Returns the patterns the line is matched with, after URL normalization such as IDNA conversion and the
+    /// [scheme map](GfwListBuilder::scheme_map). Patterns are rendered like the fragments of [`Matches`]: host
+    /// patterns start with `.` if they match subdomains and with `://` if they match exactly one host. Regexes are
+    /// rendered as `/regex/`, and patterns of negative rules start with `@@`. Comments and rules skipped by the
+    /// [regex policy](RegexPolicy::Skip) yield no patterns.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use gfwlist::GfwList;
+    /// let gfw_list = GfwList::from("").unwrap();
+    /// assert_eq!(gfw_list.expand_rule("||Example.com").unwrap(), [".Example.com/"]);
+    /// assert_eq!(gfw_list.expand_rule("@@example.com/path").unwrap(), ["@@://example.com/path/"]);
+    /// assert_eq!(gfw_list.expand_rule("|https://bücher.de").unwrap(), ["https://xn--bcher-kva.de"]);
+    /// assert_eq!(gfw_list.expand_rule("||bücher.de").unwrap(), [".xn--bcher-kva.de/"]);
+    /// assert_eq!(gfw_list.expand_rule("! comment").unwrap(), Vec::<String>::new());
+    /// assert!(gfw_list.expand_rule("/(/").is_err());
+    /// ```
+    pub fn expand_rule(&self, line: &str) -> Result<Vec<String>, SyntaxError> {
+        let Some(rule) = Rule::parse(line)? else {
+            return Ok(vec![]);
+        };
+        let mut builder = GfwListBuilder::new();
+        builder.regex_policy(self.regex_policy).scheme_map(self.schemes.clone());
+        builder.add_parsed(rule)?;
+        Ok(builder.compiled_patterns())
+    }
+
+    /// Stages the removal of all rules with the given text, returning whether any rule was found.
+    ///
+    /// Rules staged by [`add_rule`](Self::add_rule) can be removed as well. Matching is not affected until the
+    /// changes are committed.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use gfwlist::GfwList;
+    /// let mut gfw_list = GfwList::from("||blocked-site.com\n@@||exception.com").unwrap();
+    /// assert!(gfw_list.remove_rule("@@||exception.com"));
+    /// assert!(!gfw_list.remove_rule("||missing.com"));
+    /// gfw_list.commit().unwrap();
+    /// assert_eq!(gfw_list.len(), 1);
+    /// ```
+    pub fn remove_rule(&mut self, text: &str) -> bool {
+        let rules = self.staged.get_or_insert_with(|| self.rules.clone());
+        let len = rules.len();
+        rules.retain(|rule| rule.text() != text);
+        rules.len() != len
+    }
+
+    /// Returns whether there are staged changes that have not been committed.
+    pub fn has_staged_changes(&self) -> bool {
+        self.staged.as_ref().is_some_and(|staged| staged != &self.rules)
+    }
+
+    /// Discards all staged changes.
+    pub fn discard_changes(&mut self) {
+        self.staged = None;
+    }
+
+    /// Recompiles the automata with the staged changes applied.
+    ///
+    /// Nothing is rebuilt if there are no staged changes. The automaton of positive or negative rules is only rebuilt
+    /// if rules of that polarity changed, and regexes are only compiled if they are new, so small changes are cheap to
+    /// commit. If the build fails, the list and the staged changes are
+    /// left untouched. Hit counts are carried over to the new rules by [`RuleId`].
+    pub fn commit(&mut self) -> Result<(), BuildError> {
+        let Some(rules) = self.staged.take_if(|staged| staged != &self.rules) else {
+            self.staged = None;
+            return Ok(());
+        };
+        match self.rebuild(&rules) {
+            Ok(list) => {
+                list.carry_hit_counts(self);
+                *self = list;
+                Ok(())
             }
-            let (patterns, rules) = if line_str.starts_with("@@") {
-                line_str = &line_str[2..];
-                (&mut negative_patterns, &mut negative_rules)
-            } else {
-                (&mut positive_patterns, &mut positive_rules)
-            };
-            let line = line_str.as_bytes();
-            let mut needle: Vec<u8> = vec![];
-            if line[0] == b'.' {
-                append_host_path(&mut needle, &line[1..]);
-            } else if line[0] != b'|' {
-                needle.push(constants::BEGIN_OF_HOST);
-                append_host_path(&mut needle, line);
-            } else if line.get(1) == Some(&b'|') {
-                append_host_path(&mut needle, &line[2..]);
-            } else {
-                append_url::<false>(&mut needle, &line_str[1..])
-                    .map_err(|e| BuildError::Syntax(line_index, SyntaxError::Url(e)))?;
+            Err(err) => {
+                self.staged = Some(rules);
+                Err(err)
             }
-            patterns.push(needle);
-            rules.push(line_str.to_string());
-        }
-        Ok(GfwList {
-            positive_ac: AhoCorasick::new(positive_patterns).map_err(BuildError::AhoCorasick)?,
-            negative_ac: AhoCorasick::new(negative_patterns).map_err(BuildError::AhoCorasick)?,
-            positive_rules,
-            regex_patterns,
-        })
+        }
+    }
+
+    /// Compiles rules with the settings of this list. Hit counters are reset.
+    fn rebuild(&self, rules: &[Rule]) -> Result<Self, BuildError> {
+        let mut builder = GfwListBuilder::new();
+        builder.reuse = Some(patch::Reuse::new(self));
+        builder.regex_policy(self.regex_policy);
+        builder.regex_prefilter(self.regex_prefilter.is_some());
+        builder.ignore_query(self.ignore_query);
+        builder.scheme_map(self.schemes.clone());
+        for section in &self.disabled_sections {
+            builder.disable_section(section.as_str());
+        }
+        for (index, rule) in rules.iter().enumerate() {
+            builder.add_parsed(rule.clone()).map_err(|error| BuildError::Syntax {
+                line: index + 1,
+                offset: 0,
+                error,
+            })?;
+        }
+        let mut list = builder.build()?;
+        list.set_hit_counting(self.hits.is_some());
+        list.expectations = self.expectations.clone();
+        list.metadata = self.metadata.clone();
+        Ok(list)
+    }
+
+    /// Iterates over the active rules in source order, with their classification.
+    ///
+    /// Rules of disabled sections are left out.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use gfwlist::{GfwList, RuleKind};
+    /// let gfw_list = GfwList::from("||blocked-site.com\n@@||exception.com\n/video/").unwrap();
+    /// let rules: Vec<_> = gfw_list.rules().collect();
+    /// assert_eq!(rules, [
+    ///     (RuleKind::Positive, "||blocked-site.com"),
+    ///     (RuleKind::Negative, "@@||exception.com"),
+    ///     (RuleKind::Regex, "/video/"),
+    /// ]);
+    /// ```
+    pub fn rules(&self) -> impl Iterator<Item = (RuleKind, &str)> {
+        (self.rules.iter())
+            .filter(|rule| {
+                rule.section()
+                    .is_none_or(|section| !self.disabled_sections.contains(section))
+            })
+            .map(|rule| (rule.kind(), rule.text()))
+    }
+
+    /// Serializes the list back into GFW list text, with every rule in its canonical form (see [`Rule`]'s `Display`).
+    ///
+    /// Rules are emitted in list order, including rules of disabled sections, and section headers are written where
+    /// the section changes. Parsing the output yields the same rules and sections. Comments and the original spelling
+    /// of the rules are not preserved.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use gfwlist::GfwList;
+    /// let gfw_list = GfwList::from(".example.com\n!---- News ----\n@@||NEWS.com").unwrap();
+    /// assert_eq!(gfw_list.to_source(), "||example.com\n!---- News ----\n@@||NEWS.com\n");
+    /// ```
+    pub fn to_source(&self) -> String {
+        self.to_string()
+    }
+
+    /// Iterates over the compiled rules in source order.
+    pub(crate) fn iter_rules(&self) -> impl Iterator<Item = &Rule> {
+        self.rules.iter()
     }
 
     /// Tests whether a URL matches any rule in the GfwList.
@@ -187,20 +1073,307 @@ impl GfwList {
     /// assert_eq!(gfw_list.test("http://allowed-site.com/page").unwrap(), None);
     /// ```
     pub fn test(&self, input: &str) -> Result<Option<&str>, url::ParseError> {
-        for (regex, rule) in &self.regex_patterns {
-            if regex.is_match(input) {
-                return Ok(Some(rule));
-            }
+        Ok(self.decide(input)?.blocked_by().map(Rule::text))
+    }
+
+    /// Decides whether a URL is blocked, allowed by a negative rule, or not matched at all.
+    ///
+    /// The steps are the same as for [`GfwList::test`], which only reports [`Decision::Blocked`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use gfwlist::{Decision, GfwList};
+    /// let gfw_list = GfwList::from("||blocked-site.com\n@@||exception.blocked-site.com").unwrap();
+    /// let Decision::Allowed(rule) = gfw_list.decide("http://exception.blocked-site.com").unwrap() else {
+    ///     unreachable!();
+    /// };
+    /// assert_eq!(rule.text(), "@@||exception.blocked-site.com");
+    /// assert!(gfw_list.decide("http://www.blocked-site.com").unwrap().is_blocked());
+    /// assert_eq!(gfw_list.decide("http://allowed-site.com").unwrap(), Decision::NoMatch);
+    /// ```
+    pub fn decide(&self, input: &str) -> Result<Decision<'_>, url::ParseError> {
+        let url = Url::parse(input);
+        if let Some(verdict) = self.test_important(input, url.as_ref().ok())? {
+            return Ok(verdict.resolve(self));
         }
+        if let Some(verdict) = self.test_regex(input) {
+            return Ok(verdict.resolve(self));
+        }
+        Ok(self.test_automata(&url?)?.resolve(self))
+    }
+
+    /// Tests whether an already parsed URL matches any rule, see [`GfwList::test`].
+    ///
+    /// Regex rules are tested against the serialization of the URL, which is normalized by the parser. For example,
+    /// `http://example.com` is serialized as `http://example.com/`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use gfwlist::GfwList;
+    /// # use url::Url;
+    /// let gfw_list = GfwList::from("||blocked-site.com").unwrap();
+    /// let url = Url::parse("http://blocked-site.com/page").unwrap();
+    /// assert_eq!(gfw_list.test_url(&url).unwrap(), Some("||blocked-site.com"));
+    /// ```
+    pub fn test_url(&self, url: &Url) -> Result<Option<&str>, url::ParseError> {
+        Ok(self.decide_url(url)?.blocked_by().map(Rule::text))
+    }
+
+    /// Decides whether an already parsed URL is blocked, see [`GfwList::decide`] and [`GfwList::test_url`].
+    ///
+    /// Fails with [`url::ParseError::EmptyHost`] if the URL has no host.
+    pub fn decide_url(&self, url: &Url) -> Result<Decision<'_>, url::ParseError> {
+        if let Some(verdict) = self.test_important(url.as_str(), Some(url))? {
+            return Ok(verdict.resolve(self));
+        }
+        if let Some(verdict) = self.test_regex(url.as_str()) {
+            return Ok(verdict.resolve(self));
+        }
+        Ok(self.test_automata(url)?.resolve(self))
+    }
+
+    /// Tests whether a URL given by its components matches any rule, see [`GfwList::test`].
+    ///
+    /// This is useful for callers that never materialize a URL, such as TLS proxies that know the SNI host. The
+    /// components are not parsed: the scheme and host are matched case-insensitively, and an empty path is treated as
+    /// `/`. Regex rules are tested against `scheme://host/path`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use gfwlist::GfwList;
+    /// let gfw_list = GfwList::from("|https://blocked-site.com/private").unwrap();
+    /// let rule = gfw_list.test_parts("https", "blocked-site.com", "/private/page").unwrap();
+    /// assert_eq!(rule, Some("|https://blocked-site.com/private"));
+    /// assert_eq!(gfw_list.test_parts("http", "blocked-site.com", "/private/page").unwrap(), None);
+    /// ```
+    pub fn test_parts(&self, scheme: &str, host: &str, path: &str) -> Result<Option<&str>, url::ParseError> {
+        Ok(self.decide_parts(scheme, host, path)?.blocked_by().map(Rule::text))
+    }
+
+    /// Decides whether a URL given by its components is blocked, see [`GfwList::test_parts`].
+    ///
+    /// Fails with [`url::ParseError::EmptyHost`] if the host is empty.
+    pub fn decide_parts(&self, scheme: &str, host: &str, path: &str) -> Result<Decision<'_>, url::ParseError> {
+        if host.is_empty() {
+            return Err(url::ParseError::EmptyHost);
+        }
+        let scheme = scheme.to_ascii_lowercase();
+        let scheme = self.schemes.canonical(&scheme);
+        let host = host.to_ascii_lowercase();
+        let (host, port) = port::split_port(&host);
+        let host = ip::normalize_host(host);
+        let path = if path.is_empty() { "/" } else { path };
+        let path = match self.ignore_query {
+            true => path.split(['?', '#']).next().unwrap_or(path),
+            false => path,
+        };
+        let input = (!self.regex_patterns.is_empty()).then(|| {
+            let port = port.map(|port| format!(":{port}")).unwrap_or_default();
+            format!("{scheme}://{host}{port}{path}")
+        });
+        let mut haystack: Vec<u8> = vec![];
+        encode::append_parts(&mut haystack, scheme, &host, path);
+        let port = port.or_else(|| port::default_port(scheme));
+        let without_context = |index| self.applies_without_context(index);
+        if let Some(verdict) = self.important_verdict(input.as_deref(), &haystack, port, without_context) {
+            return Ok(verdict.resolve(self));
+        }
+        if let Some(verdict) = input.and_then(|input| self.test_regex(&input)) {
+            return Ok(verdict.resolve(self));
+        }
+        let verdict = self.with_ip_rules(self.test_haystack(&haystack, port), ip::host_ip(&host));
+        Ok(verdict.resolve(self))
+    }
+
+    /// Tests whether a bare host name is blocked, see [`GfwList::decide_host`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use gfwlist::GfwList;
+    /// let gfw_list = GfwList::from("||blocked-site.com\n@@||exception.blocked-site.com").unwrap();
+    /// assert_eq!(gfw_list.test_host("www.blocked-site.com"), Some("||blocked-site.com"));
+    /// assert_eq!(gfw_list.test_host("exception.blocked-site.com"), None);
+    /// ```
+    pub fn test_host(&self, host: &str) -> Option<&str> {
+        self.decide_host(host).blocked_by().map(Rule::text)
+    }
+
+    /// Decides whether a bare host name is blocked, for callers such as DNS forwarders that have no URL.
+    ///
+    /// Only rules that depend on the host alone are evaluated: domain rules (`||example.com`, `.example.com`) and
+    /// exact host rules (`example.com`) without a path. URL rules and regular expressions are skipped. The host is
+    /// expected in its ASCII form and is matched case-insensitively, ignoring a trailing dot. IPv6 addresses may be
+    /// given with or without brackets.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use gfwlist::GfwList;
+    /// let gfw_list = GfwList::from("||[2001:4860:4860::8888]\n2001:db8:0::1").unwrap();
+    /// assert!(gfw_list.decide_host("2001:4860:4860:0:0:0:0:8888").is_blocked());
+    /// assert!(gfw_list.decide_host("[2001:db8::1]").is_blocked());
+    /// assert!(gfw_list.test("http://[2001:DB8::1]:8080/").unwrap().is_some());
+    /// ```
+    pub fn decide_host(&self, host: &str) -> Decision<'_> {
+        let host = host.strip_suffix('.').unwrap_or(host).to_ascii_lowercase();
+        let host = ip::normalize_host(&host);
         let mut haystack: Vec<u8> = vec![];
-        append_url::<true>(&mut haystack, input)?;
-        if self.negative_ac.find(&haystack).is_some() {
+        encode::append_bare_host(&mut haystack, &host);
+        let without_context = |index| self.applies_without_context(index);
+        if let Some(verdict) = self.important_verdict(None, &haystack, None, without_context) {
+            return verdict.resolve(self);
+        }
+        (self.with_ip_rules(self.test_haystack(&haystack, None), ip::host_ip(&host))).resolve(self)
+    }
+
+    /// Finds every rule matching a URL, for debugging lists.
+    ///
+    /// Unlike [`GfwList::decide`], this does not stop at the first match and does not count hits.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use gfwlist::GfwList;
+    /// let gfw_list = GfwList::from("||example.com\n@@||www.example.com\n/\\/video/\n||www.example.com").unwrap();
+    /// let matches = gfw_list.test_all("http://www.example.com/video").unwrap();
+    /// let rules: Vec<_> = matches.iter().map(|rule| rule.text()).collect();
+    /// assert_eq!(rules, ["/\\/video/", "@@||www.example.com", "||www.example.com", "||example.com"]);
+    /// assert!(matches.decision().is_blocked());
+    /// ```
+    pub fn test_all(&self, input: &str) -> Result<Matches<'_>, url::ParseError> {
+        let url = Url::parse(input)?;
+        let url = self.matched_url(&url);
+        let mut haystack: Vec<u8> = vec![];
+        append_parsed_url::<true>(&mut haystack, &url, true, &self.schemes)?;
+        let regex_input = self.schemes.apply(self.matched_input(input));
+        let mut matches: Vec<(usize, String)> = (self.regex_patterns.iter())
+            .filter_map(|(regex, index)| Some((*index, regex.find(&regex_input)?.as_str().to_string())))
+            .collect();
+        let ip = ip::url_ip(&url);
+        for negative in [true, false] {
+            let automata_matches = (self.automata.matches(negative, &haystack))
+                .map(|(index, range)| (index, encode::display(&haystack[range])));
+            // IP rules are overridden by the automata rules of their polarity, see `with_ip_rules`
+            let ip_matches = (self.ip_rules.iter())
+                .filter(|(network, index)| {
+                    self.rules[*index].is_negative() == negative && ip.is_some_and(|ip| network.contains(ip))
+                })
+                .map(|(_, index)| (*index, ip.map(|ip| ip.to_string()).unwrap_or_default()));
+            for (index, fragment) in automata_matches.chain(ip_matches) {
+                if self.port_matches(index, url.port_or_known_default())
+                    && !matches.iter().any(|(other, _)| *other == index)
+                {
+                    matches.push((index, fragment));
+                }
+            }
+        }
+        let (indices, fragments): (Vec<_>, _) = matches.into_iter().unzip();
+        Ok(Matches {
+            url: input.to_string(),
+            rules: indices.into_iter().map(|index| &self.rules[index]).collect(),
+            fragments,
+        })
+    }
+
+    /// Returns the verdict of the first `$important` rule matching a URL, for lookups without a request context, see
+    /// [`TestOptions::context`].
+    pub(crate) fn test_important(&self, input: &str, url: Option<&Url>) -> Result<Option<Verdict>, url::ParseError> {
+        if self.important_rules.is_empty() {
             return Ok(None);
         }
-        if let Some(match_) = self.positive_ac.find(&haystack) {
-            return Ok(Some(&self.positive_rules[match_.pattern().as_usize()]));
+        let mut haystack: Vec<u8> = vec![];
+        let mut port = None;
+        if let Some(url) = url {
+            let url = &*self.matched_url(url);
+            append_parsed_url::<true>(&mut haystack, url, true, &self.schemes)?;
+            port = url.port_or_known_default();
+        }
+        let input = self.schemes.apply(self.matched_input(input));
+        let without_context = |index| self.applies_without_context(index);
+        Ok(self.important_verdict(Some(&input), &haystack, port, without_context))
+    }
+
+    /// Returns the verdict of the first regex rule matching the input, where negative regexes come first.
+    pub(crate) fn test_regex(&self, input: &str) -> Option<Verdict> {
+        let input = &*self.schemes.apply(self.matched_input(input));
+        let index = match &self.regex_prefilter {
+            Some(prefilter) => {
+                let candidates = prefilter.candidates(input);
+                (self.regex_patterns.iter().zip(candidates))
+                    .find(|((regex, index), candidate)| {
+                        *candidate && self.applies_without_context(*index) && regex.is_match(input)
+                    })
+                    .map(|((_, index), _)| *index)
+            }
+            None => (self.regex_patterns.iter())
+                .find(|(regex, index)| self.applies_without_context(*index) && regex.is_match(input))
+                .map(|(_, index)| *index),
+        }?;
+        Some(self.regex_verdict(index))
+    }
+
+    /// Returns the verdict of a matching regex rule.
+    pub(crate) fn regex_verdict(&self, index: usize) -> Verdict {
+        match self.rules[index].is_negative() {
+            true => Verdict::Allowed(index),
+            false => Verdict::Blocked(index),
+        }
+    }
+
+    /// Matches the URL against the negative and positive automata.
+    pub(crate) fn test_automata(&self, url: &Url) -> Result<Verdict, url::ParseError> {
+        let url = &*self.matched_url(url);
+        let mut haystack: Vec<u8> = vec![];
+        append_parsed_url::<true>(&mut haystack, url, true, &self.schemes)?;
+        let port = url.port_or_known_default();
+        Ok(self.with_ip_rules(self.test_haystack(&haystack, port), ip::url_ip(url)))
+    }
+
+    /// Returns the part of a URL that regexes are tested against, which leaves out the query string and fragment if
+    /// the query is ignored.
+    pub(crate) fn matched_input<'i>(&self, input: &'i str) -> &'i str {
+        match self.ignore_query {
+            true => input.split(['?', '#']).next().unwrap_or(input),
+            false => input,
+        }
+    }
+
+    /// Returns the URL that the automata are matched against, which has no query string if the query is ignored.
+    fn matched_url<'u>(&self, url: &'u Url) -> Cow<'u, Url> {
+        match self.ignore_query && url.query().is_some() {
+            true => {
+                let mut url = url.clone();
+                url.set_query(None);
+                Cow::Owned(url)
+            }
+            false => Cow::Borrowed(url),
         }
-        Ok(None)
+    }
+
+    /// Matches an encoded haystack on a port against the negative and positive automata.
+    fn test_haystack(&self, haystack: &[u8], port: Option<u16>) -> Verdict {
+        if self.rule_ports.is_empty() && self.contextual_rules.is_empty() {
+            return self.automata.find(haystack).into();
+        }
+        let first = |negative| {
+            (self.automata.matches(negative, haystack))
+                .map(|(index, _)| index)
+                .find(|index| self.port_matches(*index, port) && self.applies_without_context(*index))
+        };
+        match (first(true), first(false)) {
+            (Some(index), _) => Verdict::Allowed(index),
+            (None, Some(index)) => Verdict::Blocked(index),
+            (None, None) => Verdict::NoMatch,
+        }
+    }
+
+    /// Returns whether a rule may match without a request context, which rules with `$dnstype` do not.
+    pub(crate) fn applies_without_context(&self, index: usize) -> bool {
+        self.contextual_rules.binary_search(&index).is_err()
     }
 
     /// Returns the number of rules in the GfwList.
@@ -217,7 +1390,7 @@ impl GfwList {
     /// assert_eq!(gfw_list.len(), 2);
     /// ```
     pub fn len(&self) -> usize {
-        self.positive_ac.patterns_len() + self.negative_ac.patterns_len() + self.regex_patterns.len()
+        self.rules.len()
     }
 
     /// Checks if the GfwList is empty.
@@ -238,11 +1411,334 @@ impl GfwList {
     }
 }
 
+/// Formats the list like [`GfwList::to_source`].
+impl fmt::Display for GfwList {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut section = None;
+        for rule in &self.rules {
+            if rule.section() != section {
+                section = rule.section();
+                if let Some(name) = section {
+                    writeln!(f, "!---- {name} ----")?;
+                }
+            }
+            writeln!(f, "{rule}")?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for GfwList {
+    type Err = BuildError;
+
+    /// Parses a list, like [`GfwList::from`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use gfwlist::GfwList;
+    /// let gfw_list: GfwList = "||blocked-site.com".parse().unwrap();
+    /// assert_eq!(gfw_list.test("http://blocked-site.com").unwrap(), Some("||blocked-site.com"));
+    /// ```
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        GfwList::from(input)
+    }
+}
+
+/// Clones the list without recompiling it.
+///
+/// The automata and regular expressions are reference-counted internally, so cloning mostly copies the rules. The
+/// clone starts with a snapshot of the hit counters, which are then counted separately. To share one list with its
+/// counters between threads instead, wrap it in an [`Arc`](std::sync::Arc).
+///
+/// # Examples
+///
+/// ```
+/// # use gfwlist::GfwList;
+/// let gfw_list = GfwList::from("||blocked-site.com").unwrap();
+/// let clone = gfw_list.clone();
+/// std::thread::spawn(move || assert!(clone.test("http://blocked-site.com").unwrap().is_some()))
+///     .join()
+///     .unwrap();
+/// ```
+impl Clone for GfwList {
+    fn clone(&self) -> Self {
+        GfwList {
+            automata: self.automata.clone(),
+            rules: self.rules.clone(),
+            regex_patterns: self.regex_patterns.clone(),
+            regex_prefilter: self.regex_prefilter.clone(),
+            important_rules: self.important_rules.clone(),
+            contextual_rules: self.contextual_rules.clone(),
+            rule_ports: self.rule_ports.clone(),
+            ip_rules: self.ip_rules.clone(),
+            staged: self.staged.clone(),
+            disabled_sections: self.disabled_sections.clone(),
+            regex_policy: self.regex_policy,
+            ignore_query: self.ignore_query,
+            hits: self.hits.clone(),
+            expectations: self.expectations.clone(),
+            metadata: self.metadata.clone(),
+            schemes: self.schemes.clone(),
+            domain_index: self.domain_index.clone(),
+        }
+    }
+}
+
+impl TryFrom<&str> for GfwList {
+    type Error = BuildError;
+
+    fn try_from(input: &str) -> Result<Self, Self::Error> {
+        GfwList::from(input)
+    }
+}
+
 // https://github.com/gfwlist/gfwlist/wiki/Syntax
 #[cfg(test)]
 mod test {
     use super::*;
 
+    #[test]
+    fn parse_and_try_from() {
+        assert_eq!("||a.com".parse::<GfwList>().unwrap().len(), 1);
+        assert!(matches!(
+            GfwList::try_from("/("),
+            Err(BuildError::Syntax { line: 1, .. })
+        ));
+    }
+
+    #[test]
+    fn clone_counts_separately() {
+        let mut gfw = GfwList::from("||a.com\n@@||b.a.com\n/c\\.com/").unwrap();
+        gfw.set_hit_counting(true);
+        gfw.test("http://a.com").unwrap();
+        let clone = gfw.clone();
+        clone.test("http://a.com").unwrap();
+        assert_eq!(clone.test("http://c.com").unwrap(), Some("/c\\.com/"));
+        assert_eq!(
+            clone.decide("http://b.a.com").unwrap().allowed_by().map(Rule::text),
+            Some("@@||b.a.com")
+        );
+        assert_eq!(gfw.hit_counts().map(|(_, hits)| hits).collect::<Vec<_>>(), [1, 0, 0]);
+        assert_eq!(clone.hit_counts().map(|(_, hits)| hits).collect::<Vec<_>>(), [2, 1, 1]);
+    }
+
+    #[test]
+    fn from_file() {
+        let path = std::env::temp_dir().join(format!("gfwlist-from-file-{}.txt", std::process::id()));
+        fs::write(&path, "||a.com\n").unwrap();
+        assert_eq!(GfwList::from_file(&path).unwrap().len(), 1);
+        fs::remove_file(&path).unwrap();
+        fs::write(&path, "W0F1dG9Qcm94eSAwLjIuOV0KfHxibG9ja2VkLXNp\ndGUuY29tCg==\n").unwrap();
+        assert_eq!(GfwList::from_file(&path).unwrap().len(), 1);
+        fs::remove_file(&path).unwrap();
+        let error = GfwList::from_file(&path).unwrap_err();
+        assert!(matches!(error, BuildError::Io(_)));
+        assert_eq!(error.code(), ErrorCode::Io);
+    }
+
+    #[test]
+    fn source_round_trip() {
+        let source = "||a.com\n!---- S ----\n|http://b.com/x\n/c/\n!---- T ----\n@@d.com";
+        let gfw = GfwList::from(source).unwrap();
+        let reparsed = GfwList::from(&gfw.to_source()).unwrap();
+        assert!(GfwList::diff(&gfw, &reparsed).is_empty());
+        let sections = |list: &GfwList| {
+            list.iter_rules()
+                .map(|rule| rule.section().map(str::to_string))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(sections(&gfw), sections(&reparsed));
+    }
+
+    #[test]
+    fn decision_display() {
+        let gfw = GfwList::from("||a.com\n@@||b.a.com").unwrap();
+        assert_eq!(
+            gfw.decide("http://a.com").unwrap().to_string(),
+            "blocked by ||a.com (line 1)"
+        );
+        assert_eq!(
+            gfw.decide("http://b.a.com").unwrap().to_string(),
+            "allowed by @@||b.a.com (line 2)"
+        );
+        assert_eq!(gfw.decide("http://c.com").unwrap().to_string(), "not matched");
+        let rule = Rule::parse("||a.com").unwrap().unwrap();
+        assert_eq!(Decision::Blocked(&rule).to_string(), "blocked by ||a.com");
+    }
+
+    // `GfwList` is shared between threads by reference, see `GfwList::build_shared`
+    const _: () = {
+        const fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<GfwList>();
+        assert_send_sync::<GfwListBuilder>();
+        assert_send_sync::<live::LiveGfwList>();
+        assert_send_sync::<cache::CachedGfwList>();
+    };
+
+    #[test]
+    fn shared_between_threads() {
+        let gfw = GfwList::build_shared("||a.com\n@@||b.a.com").unwrap();
+        let workers: Vec<_> = (0..4)
+            .map(|index| {
+                let gfw = gfw.clone();
+                std::thread::spawn(move || {
+                    let url = if index % 2 == 0 {
+                        "http://a.com"
+                    } else {
+                        "http://b.a.com"
+                    };
+                    gfw.test(url).unwrap().map(str::to_string)
+                })
+            })
+            .collect();
+        let results: Vec<_> = workers.into_iter().map(|worker| worker.join().unwrap()).collect();
+        assert_eq!(
+            results,
+            [Some("||a.com".to_string()), None, Some("||a.com".to_string()), None]
+        );
+    }
+
+    #[test]
+    fn error_is_owned() {
+        fn assert_error<E: std::error::Error + Send + Sync + 'static>() {}
+        assert_error::<BuildError>();
+        assert_error::<SyntaxError>();
+
+        fn build(input: String) -> Result<GfwList, Box<dyn std::error::Error>> {
+            Ok(GfwList::from(&input)?)
+        }
+        let error = build("/invalid".to_string()).unwrap_err();
+        assert_eq!(error.to_string(), "syntax error at line 1: invalid rule syntax");
+    }
+
+    #[test]
+    fn syntax_error_position() {
+        let error = GfwList::from("||example.com\r\n! comment\n\n/invalid").unwrap_err();
+        assert!(matches!(
+            error,
+            BuildError::Syntax {
+                line: 4,
+                offset: 26,
+                error: SyntaxError::Rule,
+            }
+        ));
+    }
+
+    #[test]
+    fn test_all_priority() {
+        let gfw = GfwList::from(
+            "||example.com\n||www.example.com\nwww.example.com/a\n@@|http://www.example.com/a/b\nIP-CIDR,8.8.8.0/24\n\
+             @@IP-CIDR,8.8.8.8/32\n8.8.8.4",
+        )
+        .unwrap();
+        for url in [
+            "http://www.example.com/a/b",
+            "https://www.example.com/a/b",
+            "http://example.com",
+            "http://8.8.8.8/",
+            "http://8.8.8.4/",
+            "http://8.8.8.1/",
+        ] {
+            assert_eq!(gfw.test_all(url).unwrap().decision(), gfw.decide(url).unwrap());
+        }
+        assert_eq!(gfw.test_all("https://www.example.com/a/b").unwrap().len(), 3);
+        assert!(gfw.test_all("http://other.com").unwrap().is_empty());
+        assert_eq!(gfw.test_all("http://8.8.8.8/").unwrap().len(), 2);
+    }
+
+    #[test]
+    fn idna_hosts() {
+        let gfw = GfwList::from("||bücher.de\n.例子.测试\n@@|http://www.Bücher.de/shop").unwrap();
+        assert_eq!(gfw.test("http://www.bücher.de/").unwrap(), Some("||bücher.de"));
+        assert_eq!(gfw.test("http://xn--bcher-kva.de/").unwrap(), Some("||bücher.de"));
+        assert!(gfw.decide("http://www.bücher.de/shop").unwrap().allowed_by().is_some());
+        assert!(gfw.test("https://www.例子.测试/").unwrap().is_some());
+        assert!(gfw.test_host("xn--fsqu00a.xn--0zwm56d").is_some());
+    }
+
+    #[test]
+    fn host_only() {
+        let gfw = GfwList::from("||a.com\nb.com\n||c.com/path\n|http://d.com\n/e\\.com/").unwrap();
+        assert!(gfw.test_host("www.A.com.").is_some());
+        assert!(gfw.test_host("b.com").is_some());
+        assert!(gfw.test_host("www.b.com").is_none());
+        assert!(gfw.test_host("c.com").is_none());
+        assert!(gfw.test_host("d.com").is_none());
+        assert!(gfw.test_host("e.com").is_none());
+    }
+
+    #[test]
+    fn separators() {
+        let gfw = GfwList::from("||a.com^\n|http://b.com^ad^\nc.com/x^y\n@@||www.a.com^static^").unwrap();
+        assert!(gfw.test_host("www.a.com").is_some());
+        assert!(gfw.test("http://a.com.cn/").unwrap().is_none());
+        assert!(gfw.test("http://b.com/ad").unwrap().is_some());
+        assert!(gfw.test("http://b.com/ad/1").unwrap().is_some());
+        assert!(gfw.test("http://b.com/ads").unwrap().is_none());
+        assert!(gfw.test("http://c.com/x:y").unwrap().is_some());
+        assert!(gfw.test("http://c.com/x-y").unwrap().is_none());
+        assert!(gfw.test("http://www.a.com/static/app.js").unwrap().is_none());
+        assert!(GfwList::from("||a.com/^b^c^d").is_err());
+    }
+
+    #[test]
+    fn negative_regexes() {
+        let gfw = GfwList::from("/video/\n@@/\\.cn\\//\n||example.com\n@@/static/").unwrap();
+        assert_eq!(gfw.test("http://video.example.com/").unwrap(), Some("/video/"));
+        assert_eq!(gfw.test("http://video.example.cn/").unwrap(), None);
+        assert!(
+            gfw.decide("http://example.com/static/a.js")
+                .unwrap()
+                .allowed_by()
+                .is_some()
+        );
+        let matches = gfw.test_all("http://example.cn/video").unwrap();
+        assert_eq!(matches.iter().next().unwrap().text(), "@@/\\.cn\\//");
+        assert!(GfwList::from("@@/abc").is_err());
+    }
+
+    #[test]
+    fn wildcards() {
+        let gfw = GfwList::from("|http://*.example.com/ads/*\n||cdn*.net/img\n@@||*.example.com/ads/ok^\n||plain.org")
+            .unwrap();
+        assert!(gfw.test("http://www.example.com/ads/banner").unwrap().is_some());
+        assert!(gfw.test("http://example.com/ads/banner").unwrap().is_none());
+        assert!(gfw.test("https://www.example.com/ads/banner").unwrap().is_none());
+        assert!(gfw.test("http://a.example.com/ads/ok/1").unwrap().is_none());
+        assert!(gfw.test("http://cdn1.net/img/a.png").unwrap().is_some());
+        assert!(gfw.test("http://www.cdn-x.net/img").unwrap().is_some());
+        assert!(gfw.test("http://cdn1.net/video").unwrap().is_none());
+        assert!(gfw.test("http://plain.org").unwrap().is_some());
+        let matches = gfw.test_all("http://a.example.com/ads/ok").unwrap();
+        assert_eq!(
+            matches.iter().map(Rule::text).collect::<Vec<_>>(),
+            ["@@||*.example.com/ads/ok^", "|http://*.example.com/ads/*"]
+        );
+        assert_eq!(gfw.expand_rule("||cdn*.net").unwrap(), [".cdn*.net/"]);
+    }
+
+    #[test]
+    fn parts_match_urls() {
+        let gfw = GfwList::from("||a.com/x\n|http://b.com\nc.com\n@@||www.c.com\n/d\\.com\\/y/").unwrap();
+        for (scheme, host, path) in [
+            ("http", "www.a.com", "/x/1"),
+            ("http", "b.com", ""),
+            ("https", "b.com", "/"),
+            ("HTTP", "C.com", "/"),
+            ("http", "www.c.com", "/"),
+            ("http", "d.com", "/y"),
+        ] {
+            let url = format!("{scheme}://{host}{path}");
+            assert_eq!(
+                gfw.test_parts(scheme, host, path).unwrap(),
+                gfw.test(&url).unwrap(),
+                "{url}"
+            );
+        }
+        assert!(gfw.test_parts("http", "", "/").is_err());
+    }
+
     #[test]
     fn test_1() {
         let gfw = GfwList::from("|http://example.com").unwrap();
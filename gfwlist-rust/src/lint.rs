@@ -0,0 +1,163 @@
+//! Checks for mistakes in GFW lists.
+//!
+//! [`GfwList::lint`] reports lines that are invalid, duplicated, redundant or can never take effect. Whether a rule
+//! can take effect is decided on the URLs generated for it by [`corpus::generate`](crate::corpus::generate), so
+//! shadowing is detected for the common cases, but not proven. Rules that match broadly are reported as well, see
+//! [`risk::estimate`](crate::risk::estimate).
+
+use std::collections::HashMap;
+use std::fmt;
+
+use crate::optimize::covering_rules;
+use crate::{Decision, GfwList, GfwListBuilder, Rule, SyntaxError, Verdict, corpus, risk};
+
+/// A problem found by [`GfwList::lint`].
+#[derive(Debug)]
+pub struct Lint {
+    /// The 1-based line number of the problem
+    pub line: usize,
+    /// The text of the line
+    pub text: String,
+    /// The kind of problem
+    pub kind: LintKind,
+}
+
+/// The kinds of problems found by [`GfwList::lint`].
+///
+/// Line numbers refer to the other rule involved in the problem.
+#[derive(Debug)]
+pub enum LintKind {
+    /// The line cannot be parsed
+    Syntax(SyntaxError),
+    /// The rule is equivalent to the rule on an earlier line
+    Duplicate(usize),
+    /// Every URL matched by the rule is also matched by a rule with the same polarity
+    Covered(usize),
+    /// The rule never takes effect because a rule with higher precedence always decides first
+    Shadowed(usize),
+    /// The rule matches broadly and may block more than intended, with the given [risk score](crate::risk::Risk)
+    Broad(u8),
+}
+
+impl fmt::Display for Lint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.kind)
+    }
+}
+
+impl fmt::Display for LintKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LintKind::Syntax(error) => write!(f, "{error}"),
+            LintKind::Duplicate(line) => write!(f, "duplicate of the rule on line {line}"),
+            LintKind::Covered(line) => write!(f, "redundant, already covered by the rule on line {line}"),
+            LintKind::Shadowed(line) => {
+                write!(
+                    f,
+                    "can never take effect, because the rule on line {line} always matches first"
+                )
+            }
+            LintKind::Broad(score) => write!(f, "broad rule with a risk score of {score}/100, review what it matches"),
+        }
+    }
+}
+
+fn line_of(rule: &Rule) -> usize {
+    rule.origin().map_or(0, |origin| origin.line)
+}
+
+impl GfwList {
+    /// Checks a GFW list for problems, sorted by line.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use gfwlist::{GfwList, lint::LintKind};
+    /// let lints = GfwList::lint("||example.com\n||www.example.com\n/example\\.org/\n@@||example.org\n.example.com");
+    /// let lints: Vec<_> = lints.iter().map(|lint| lint.to_string()).collect();
+    /// assert_eq!(lints, [
+    ///     "line 2: redundant, already covered by the rule on line 1",
+    ///     "line 4: can never take effect, because the rule on line 3 always matches first",
+    ///     "line 5: duplicate of the rule on line 1",
+    /// ]);
+    /// ```
+    pub fn lint(input: &str) -> Vec<Lint> {
+        let mut builder = GfwListBuilder::new();
+        let mut lints: Vec<Lint> = (builder.add_rules_lenient(input).into_iter())
+            .map(|diagnostic| Lint {
+                line: diagnostic.line,
+                text: diagnostic.text,
+                kind: LintKind::Syntax(diagnostic.error),
+            })
+            .collect();
+        let Ok(list) = builder.build() else {
+            return lints;
+        };
+
+        let mut first_lines = HashMap::new();
+        let covering = covering_rules(&list.rules, |_| true);
+        for (rule, covering) in list.rules.iter().zip(covering) {
+            let first_line = *first_lines.entry(rule.id()).or_insert(line_of(rule));
+            let kind = if first_line != line_of(rule) {
+                LintKind::Duplicate(first_line)
+            } else if let Some(index) = covering {
+                LintKind::Covered(line_of(&list.rules[index]))
+            } else if let Some(line) = list.shadowing_line(rule) {
+                LintKind::Shadowed(line)
+            } else if let risk = risk::estimate(rule)
+                && risk.is_high()
+            {
+                LintKind::Broad(risk.score)
+            } else {
+                continue;
+            };
+            lints.push(Lint {
+                line: line_of(rule),
+                text: rule.text().to_string(),
+                kind,
+            });
+        }
+        lints.sort_by_key(|lint| lint.line);
+        lints
+    }
+
+    /// Returns the line of a rule that decides all URLs generated for a rule before it.
+    fn shadowing_line(&self, rule: &Rule) -> Option<usize> {
+        let urls = corpus::generate(rule).matching;
+        let mut shadowing = None;
+        for url in &urls {
+            let index = if rule.is_negative() {
+                // regex rules are tested before negative rules
+                match self.test_regex(url)? {
+                    Verdict::Blocked(index) | Verdict::Allowed(index) => index,
+                    Verdict::NoMatch => return None,
+                }
+            } else {
+                match self.decide(url).ok()? {
+                    Decision::Allowed(allowing) => self.rules.iter().position(|other| other == allowing)?,
+                    _ => return None,
+                }
+            };
+            shadowing.get_or_insert(index);
+        }
+        shadowing.map(|index| line_of(&self.rules[index]))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn syntax_and_allowlisted_rules() {
+        let lints = GfwList::lint("||www.a.com\n@@||a.com\n/abc\n||b.com");
+        assert_eq!(lints.len(), 2);
+        assert!(matches!(lints[0].kind, LintKind::Shadowed(2)));
+        assert_eq!(lints[0].text, "||www.a.com");
+        assert!(matches!(lints[1].kind, LintKind::Syntax(SyntaxError::Rule)));
+        assert_eq!(lints[1].line, 3);
+        let lints = GfwList::lint("||a.com\n||a.com$important\n||b.com$dnstype=A\n||b.com\n||a.com$important");
+        assert_eq!(lints.len(), 1);
+        assert!(matches!(lints[0].kind, LintKind::Duplicate(2)));
+    }
+}
@@ -0,0 +1,94 @@
+//! Literal fragments required by regular expressions.
+
+use aho_corasick::AhoCorasick;
+use regex::Regex;
+use regex_syntax::hir::{Hir, HirKind};
+
+/// Returns literal fragments that every match of the regular expression contains.
+///
+/// The analysis is conservative: alternations, classes and optional repetitions contribute no fragments, so an
+/// empty result means that nothing is known about the matches.
+pub(crate) fn required_literals(pattern: &str) -> Result<Vec<String>, regex::Error> {
+    let hir = regex_syntax::parse(pattern).map_err(|error| regex::Error::Syntax(error.to_string()))?;
+    let mut literals = vec![];
+    collect(&hir, &mut literals);
+    Ok(literals)
+}
+
+fn collect(hir: &Hir, literals: &mut Vec<String>) {
+    match hir.kind() {
+        HirKind::Literal(literal) => literals.extend(String::from_utf8(literal.0.to_vec()).ok()),
+        HirKind::Capture(capture) => collect(&capture.sub, literals),
+        HirKind::Repetition(repetition) if repetition.min > 0 => collect(&repetition.sub, literals),
+        HirKind::Concat(subs) => subs.iter().for_each(|sub| collect(sub, literals)),
+        _ => {}
+    }
+}
+
+/// An Aho-Corasick prefilter that skips regular expressions whose required literals are absent from the input.
+#[derive(Debug, Clone)]
+pub(crate) struct RegexPrefilter {
+    /// The longest required literal of each filtered regex
+    literals: AhoCorasick,
+    /// The position of the regex of each literal
+    positions: Vec<usize>,
+    /// Whether each regex must be run for every input
+    unfiltered: Vec<bool>,
+}
+
+impl RegexPrefilter {
+    pub(crate) fn new<'a>(regexes: impl IntoIterator<Item = &'a Regex>) -> Result<Self, aho_corasick::BuildError> {
+        let mut literals = vec![];
+        let mut positions = vec![];
+        let mut unfiltered = vec![];
+        for (position, regex) in regexes.into_iter().enumerate() {
+            let literal = required_literals(regex.as_str())
+                .unwrap_or_default()
+                .into_iter()
+                .max_by_key(String::len)
+                .filter(|literal| !literal.is_empty());
+            unfiltered.push(literal.is_none());
+            if let Some(literal) = literal {
+                literals.push(literal);
+                positions.push(position);
+            }
+        }
+        Ok(RegexPrefilter {
+            literals: AhoCorasick::new(literals)?,
+            positions,
+            unfiltered,
+        })
+    }
+
+    /// Returns whether each regex may match the input.
+    pub(crate) fn candidates(&self, input: &str) -> Vec<bool> {
+        let mut candidates = self.unfiltered.clone();
+        for match_ in self.literals.find_overlapping_iter(input) {
+            candidates[self.positions[match_.pattern().as_usize()]] = true;
+        }
+        candidates
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn literals() {
+        assert_eq!(
+            required_literals(r"^https?:\/\/([^\/]+\.)*google\.(com|net)\/search").unwrap(),
+            ["http", "://", "google.", "/search"]
+        );
+        assert!(required_literals("a|b").unwrap().is_empty());
+        assert!(required_literals("(").is_err());
+    }
+
+    #[test]
+    fn prefilter() {
+        let regexes = [Regex::new(r"google\.com").unwrap(), Regex::new("a|b").unwrap()];
+        let prefilter = RegexPrefilter::new(&regexes).unwrap();
+        assert_eq!(prefilter.candidates("http://example.com/"), [false, true]);
+        assert_eq!(prefilter.candidates("http://www.google.com/"), [true, true]);
+    }
+}
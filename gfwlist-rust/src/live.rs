@@ -0,0 +1,236 @@
+//! A shared list that applies incremental updates with background rebuilds.
+//!
+//! [`GfwList::commit`] recompiles every automaton with changed rules, so committing after every change gets expensive
+//! for lists that are updated often. [`LiveGfwList`] queues validated changes instead and, once a
+//! [`CompactionPolicy`] threshold is reached, rebuilds the list on a background thread. Readers keep matching against
+//! the previous snapshot until the new one is swapped in atomically.
+
+use std::sync::{Arc, Mutex, PoisonError, RwLock, Weak};
+use std::{mem, thread};
+
+use crate::{BuildError, GfwList, SyntaxError};
+
+/// When a [`LiveGfwList`] rebuilds its automata.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct CompactionPolicy {
+    /// The number of queued changes that starts a background rebuild, or 0 to only rebuild on
+    /// [`compact`](LiveGfwList::compact)
+    pub max_pending: usize,
+}
+
+impl Default for CompactionPolicy {
+    fn default() -> Self {
+        CompactionPolicy { max_pending: 64 }
+    }
+}
+
+#[derive(Debug)]
+enum Change {
+    Add(String),
+    Remove(String),
+}
+
+#[derive(Debug, Default)]
+struct Queue {
+    changes: Vec<Change>,
+    building: bool,
+    error: Option<BuildError>,
+}
+
+#[derive(Debug)]
+struct Shared {
+    current: RwLock<Arc<GfwList>>,
+    queue: Mutex<Queue>,
+    /// Held while building, so that rebuilds apply to the latest snapshot in order
+    build: Mutex<()>,
+    policy: CompactionPolicy,
+}
+
+/// A [`GfwList`] shared between threads, updated with background rebuilds.
+///
+/// Cloning a `LiveGfwList` is cheap and yields a handle to the same list.
+///
+/// # Examples
+///
+/// ```
+/// # use gfwlist::{GfwList, live::{CompactionPolicy, LiveGfwList}};
+/// let live = LiveGfwList::new(GfwList::from("||blocked-site.com").unwrap(), CompactionPolicy::default());
+/// live.add_rule("||user-site.com").unwrap();
+/// assert_eq!(live.pending(), 1);
+/// assert_eq!(live.snapshot().test("http://user-site.com").unwrap(), None);
+/// live.compact().unwrap();
+/// assert_eq!(live.snapshot().test("http://user-site.com").unwrap(), Some("||user-site.com"));
+/// ```
+#[derive(Debug, Clone)]
+pub struct LiveGfwList {
+    shared: Arc<Shared>,
+}
+
+impl LiveGfwList {
+    /// Wraps a list. Staged changes of the list are committed by the first rebuild.
+    pub fn new(list: GfwList, policy: CompactionPolicy) -> Self {
+        LiveGfwList {
+            shared: Arc::new(Shared {
+                current: RwLock::new(Arc::new(list)),
+                queue: Mutex::default(),
+                build: Mutex::default(),
+                policy,
+            }),
+        }
+    }
+
+    /// Returns a handle that does not keep the list alive, for subscribers such as metrics exporters that should stop
+    /// once the list is dropped.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use gfwlist::{GfwList, live::{CompactionPolicy, LiveGfwList}};
+    /// let live = LiveGfwList::new(GfwList::from("||blocked-site.com").unwrap(), CompactionPolicy::default());
+    /// let subscriber = live.downgrade();
+    /// assert_eq!(subscriber.snapshot().map(|list| list.len()), Some(1));
+    /// drop(live);
+    /// assert!(subscriber.snapshot().is_none());
+    /// ```
+    pub fn downgrade(&self) -> WeakLiveGfwList {
+        WeakLiveGfwList {
+            shared: Arc::downgrade(&self.shared),
+        }
+    }
+
+    /// Returns the current snapshot of the list.
+    ///
+    /// The snapshot is not affected by later rebuilds, so it can be used for a consistent series of lookups.
+    pub fn snapshot(&self) -> Arc<GfwList> {
+        self.shared
+            .current
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone()
+    }
+
+    /// Queues a rule to be added, like [`GfwList::add_rule`].
+    ///
+    /// The rule is validated immediately. Empty lines and comments are ignored.
+    pub fn add_rule(&self, line: &str) -> Result<(), SyntaxError> {
+        if self.snapshot().validate_rule(line)?.is_some() {
+            self.push(Change::Add(line.to_string()));
+        }
+        Ok(())
+    }
+
+    /// Queues the removal of all rules with the given text, like [`GfwList::remove_rule`].
+    pub fn remove_rule(&self, text: &str) {
+        self.push(Change::Remove(text.to_string()));
+    }
+
+    /// Returns the number of queued changes that are not applied yet.
+    pub fn pending(&self) -> usize {
+        self.shared.queue().changes.len()
+    }
+
+    /// Returns the error of the last failed background rebuild, if any.
+    ///
+    /// The changes of a failed rebuild are queued again.
+    pub fn take_error(&self) -> Option<BuildError> {
+        self.shared.queue().error.take()
+    }
+
+    /// Applies all queued changes now, waiting for a running background rebuild to finish first.
+    pub fn compact(&self) -> Result<(), BuildError> {
+        self.shared.rebuild()
+    }
+
+    fn push(&self, change: Change) {
+        let mut queue = self.shared.queue();
+        queue.changes.push(change);
+        let max_pending = self.shared.policy.max_pending;
+        if max_pending == 0 || queue.changes.len() < max_pending || queue.building {
+            return;
+        }
+        queue.building = true;
+        let shared = self.shared.clone();
+        thread::spawn(move || {
+            let result = shared.rebuild();
+            let mut queue = shared.queue();
+            queue.building = false;
+            queue.error = result.err();
+        });
+    }
+}
+
+/// A handle to a [`LiveGfwList`] that does not keep it alive, see [`LiveGfwList::downgrade`].
+#[derive(Debug, Clone)]
+pub struct WeakLiveGfwList {
+    shared: Weak<Shared>,
+}
+
+impl WeakLiveGfwList {
+    /// Returns a strong handle to the list, if it is still alive.
+    pub fn upgrade(&self) -> Option<LiveGfwList> {
+        self.shared.upgrade().map(|shared| LiveGfwList { shared })
+    }
+
+    /// Returns the current snapshot of the list, if it is still alive.
+    pub fn snapshot(&self) -> Option<Arc<GfwList>> {
+        self.upgrade().map(|live| live.snapshot())
+    }
+}
+
+impl Shared {
+    fn queue(&self) -> std::sync::MutexGuard<'_, Queue> {
+        self.queue.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn rebuild(&self) -> Result<(), BuildError> {
+        let _build = self.build.lock().unwrap_or_else(PoisonError::into_inner);
+        let changes = mem::take(&mut self.queue().changes);
+        if changes.is_empty() {
+            return Ok(());
+        }
+        let current = self.current.read().unwrap_or_else(PoisonError::into_inner).clone();
+        let mut list = GfwList::clone(&current);
+        let result = changes.iter().try_for_each(|change| match change {
+            // validated when queued, with the same settings
+            Change::Add(line) => list.add_rule(line).map_err(|error| BuildError::Syntax {
+                line: 1,
+                offset: 0,
+                error,
+            }),
+            Change::Remove(text) => {
+                list.remove_rule(text);
+                Ok(())
+            }
+        });
+        if let Err(error) = result.and_then(|()| list.commit()) {
+            let mut queue = self.queue();
+            let newer = mem::replace(&mut queue.changes, changes);
+            queue.changes.extend(newer);
+            return Err(error);
+        }
+        // hits counted on the previous snapshot during the rebuild are carried over as well
+        list.carry_hit_counts(&current);
+        *self.current.write().unwrap_or_else(PoisonError::into_inner) = Arc::new(list);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn background_rebuild() {
+        let live = LiveGfwList::new(GfwList::from("||a.com").unwrap(), CompactionPolicy { max_pending: 2 });
+        live.add_rule("||b.com").unwrap();
+        live.remove_rule("||a.com");
+        assert!(live.add_rule("/(/").is_err());
+        // waits for the background rebuild started by the second change
+        live.compact().unwrap();
+        assert!(live.take_error().is_none());
+        assert_eq!(live.pending(), 0);
+        let snapshot = live.snapshot();
+        assert_eq!(snapshot.test("http://b.com").unwrap(), Some("||b.com"));
+        assert_eq!(snapshot.test("http://a.com").unwrap(), None);
+    }
+}
@@ -0,0 +1,116 @@
+//! The header and metadata comments at the top of a list.
+//!
+//! Lists commonly start like this:
+//!
+//! ```text
+//! [AutoProxy 0.2.9]
+//! ! Title: GFWList4LL
+//! ! Last Modified: Sat, 06 Jul 2024 03:15:14 -0400
+//! ! Expires: 6h
+//! ```
+//!
+//! The values are kept as written, except for [`ListMetadata::expires_after`], which interprets the expiry for
+//! updaters. Only the first occurrence of each field counts.
+
+use std::time::Duration;
+
+use crate::GfwList;
+
+/// The header and metadata comments of a list, see [`GfwList::metadata`].
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct ListMetadata {
+    /// The version of the `[AutoProxy 0.2.9]` header, such as `0.2.9`
+    pub version: Option<String>,
+    /// The value of the `! Title:` comment
+    pub title: Option<String>,
+    /// The value of the `! Last Modified:` comment
+    pub last_modified: Option<String>,
+    /// The value of the `! Expires:` comment, such as `6h` or `4 days`
+    pub expires: Option<String>,
+}
+
+impl ListMetadata {
+    /// Records the header or metadata comment on a line, if it sets a field that is not set yet.
+    pub(crate) fn parse_line(&mut self, text: &str) {
+        let text = text.trim();
+        if let Some(version) = text.strip_prefix("[AutoProxy").and_then(|rest| rest.strip_suffix(']')) {
+            self.version.get_or_insert_with(|| version.trim().to_string());
+            return;
+        }
+        let Some((key, value)) = text.strip_prefix('!').and_then(|comment| comment.split_once(':')) else {
+            return;
+        };
+        let field = match key.trim().to_ascii_lowercase().as_str() {
+            "title" => &mut self.title,
+            "last modified" => &mut self.last_modified,
+            "expires" => &mut self.expires,
+            _ => return,
+        };
+        field.get_or_insert_with(|| value.trim().to_string());
+    }
+
+    /// Returns whether no field is set.
+    pub fn is_empty(&self) -> bool {
+        *self == ListMetadata::default()
+    }
+
+    /// Interprets the `! Expires:` value as the time after which the list should be downloaded again.
+    ///
+    /// Accepts a number followed by a unit in days or hours, such as `6h`, `4 days` or `1 day (update frequency)`.
+    /// Numbers without a unit are days, as in Adblock Plus.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use std::time::Duration;
+    /// # use gfwlist::GfwList;
+    /// let gfw_list = GfwList::from("[AutoProxy 0.2.9]\n! Title: Example\n! Expires: 6h\n||blocked-site.com").unwrap();
+    /// let metadata = gfw_list.metadata();
+    /// assert_eq!(metadata.version.as_deref(), Some("0.2.9"));
+    /// assert_eq!(metadata.title.as_deref(), Some("Example"));
+    /// assert_eq!(metadata.expires_after(), Some(Duration::from_secs(6 * 3600)));
+    /// ```
+    pub fn expires_after(&self) -> Option<Duration> {
+        let expires = self.expires.as_deref()?.trim();
+        let digits = expires.find(|c: char| !c.is_ascii_digit()).unwrap_or(expires.len());
+        let count: u64 = expires[..digits].parse().ok()?;
+        let unit = expires[digits..].trim_start();
+        let hours = match unit.chars().next() {
+            None | Some('d' | 'D' | '(') => 24,
+            Some('h' | 'H') => 1,
+            Some(_) => return None,
+        };
+        Some(Duration::from_secs(count.checked_mul(hours * 3600)?))
+    }
+}
+
+impl GfwList {
+    /// Returns the header and metadata comments of the list.
+    pub fn metadata(&self) -> &ListMetadata {
+        &self.metadata
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn first_occurrence() {
+        let mut metadata = ListMetadata::default();
+        for line in [
+            "[AutoProxy 0.2.9]",
+            "! Last Modified: today",
+            "!TITLE:a",
+            "! Title: b",
+            "! Expires: 4 days",
+        ] {
+            metadata.parse_line(line);
+        }
+        assert_eq!(metadata.title.as_deref(), Some("a"));
+        assert_eq!(metadata.last_modified.as_deref(), Some("today"));
+        assert_eq!(metadata.expires_after(), Some(Duration::from_secs(4 * 86400)));
+        metadata.expires = Some("soon".into());
+        assert_eq!(metadata.expires_after(), None);
+    }
+}
@@ -0,0 +1,171 @@
+//! Explanations of why rules do not match a URL, for users wondering why a rule "doesn't work".
+//!
+//! [`GfwList::near_misses`] looks at the rules about the registrable domain of a URL that do not match it, and reports
+//! for each the first component that prevents the match. Rules whose host matches the URL come first, since they are
+//! the ones most likely meant to match it.
+
+use std::fmt;
+
+use url::Url;
+
+use crate::host::is_under;
+use crate::ip::normalize_host;
+use crate::{GfwList, Pattern, Rule};
+
+/// The component of a URL that keeps a rule from matching it, see [`GfwList::near_misses`].
+///
+/// Variants are ordered from the nearest miss to the farthest.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Mismatch {
+    /// The rule only matches another scheme, like `|https://example.com` for `http://example.com`
+    Scheme,
+    /// The rule only matches another port, like `||example.com:8443` for `https://example.com`
+    Port,
+    /// The path does not start with the path of the rule
+    Path,
+    /// The query does not start with the query of the rule
+    Query,
+    /// The rule only matches its exact host, like `example.com` for `www.example.com`
+    Subdomain,
+    /// The host ends with the host of the rule in the middle of a label, like `sample.example.com` for
+    /// `||le.example.com`
+    LabelBoundary,
+    /// The rule is about another host of the same registrable domain
+    Host,
+}
+
+impl fmt::Display for Mismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Mismatch::Scheme => "the rule only matches another scheme",
+            Mismatch::Port => "the rule only matches another port",
+            Mismatch::Path => "the path does not start with the path of the rule",
+            Mismatch::Query => "the query does not start with the query of the rule",
+            Mismatch::Subdomain => "the rule does not cover subdomains",
+            Mismatch::LabelBoundary => "the host of the rule does not start at a label boundary",
+            Mismatch::Host => "the rule is about another host",
+        })
+    }
+}
+
+/// A rule that does not match a URL, with the reason, see [`GfwList::near_misses`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NearMiss<'a> {
+    /// The rule that does not match
+    pub rule: &'a Rule,
+    /// The first component that keeps the rule from matching
+    pub mismatch: Mismatch,
+}
+
+/// Describes the miss, such as `||example.com/ads: the path does not start with the path of the rule`.
+impl fmt::Display for NearMiss<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.rule.text(), self.mismatch)
+    }
+}
+
+/// Returns the component of the URL that keeps a host, domain or URL rule from matching it.
+fn mismatch(pattern: &Pattern, url: &Url, host: &str) -> Option<Mismatch> {
+    let rule_host = normalize_host(&pattern.host()?.to_ascii_lowercase()).into_owned();
+    if let Pattern::Url(text) = pattern
+        && let Some((scheme, _)) = text.split_once("://")
+        && !scheme.contains('*')
+        && !scheme.eq_ignore_ascii_case(url.scheme())
+    {
+        return Some(Mismatch::Scheme);
+    }
+    if !rule_host.contains('*') {
+        let covered = match pattern {
+            Pattern::Domain(_) => is_under(host, &rule_host),
+            _ => host == rule_host,
+        };
+        if !covered {
+            return Some(match pattern {
+                Pattern::Domain(_) if host.ends_with(rule_host.as_str()) => Mismatch::LabelBoundary,
+                Pattern::Domain(_) => Mismatch::Host,
+                _ if is_under(host, &rule_host) => Mismatch::Subdomain,
+                _ => Mismatch::Host,
+            });
+        }
+    }
+    if let Some(port) = pattern.port()
+        && url.port_or_known_default() != Some(port)
+    {
+        return Some(Mismatch::Port);
+    }
+    match pattern.path() {
+        Some(path) if path.contains('?') => Some(Mismatch::Query),
+        _ => Some(Mismatch::Path),
+    }
+}
+
+impl GfwList {
+    /// Explains why the rules about the registrable domain of a URL do not match it.
+    ///
+    /// Each host, domain and URL rule about a host with the registrable domain of the URL, as indexed for
+    /// [`rules_for_domain`](Self::rules_for_domain), that does not match the URL is reported with the first component
+    /// that prevents the match, nearest misses first and in source order otherwise. Regex and IP rules are left out,
+    /// since their misses cannot be attributed to a component. Rules that match, even if overridden by other rules, are
+    /// left out as well; see [`test_all`](Self::test_all) for those.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use gfwlist::{GfwList, Mismatch};
+    /// let gfw_list = GfwList::from("example.com\n||example.com/ads\n|https://www.example.com").unwrap();
+    /// let misses = gfw_list.near_misses("http://www.example.com/video").unwrap();
+    /// let misses: Vec<_> = misses.iter().map(|miss| (miss.rule.text(), miss.mismatch)).collect();
+    /// assert_eq!(misses, [
+    ///     ("|https://www.example.com", Mismatch::Scheme),
+    ///     ("||example.com/ads", Mismatch::Path),
+    ///     ("example.com", Mismatch::Subdomain),
+    /// ]);
+    /// ```
+    pub fn near_misses(&self, input: &str) -> Result<Vec<NearMiss<'_>>, url::ParseError> {
+        let url = Url::parse(input)?;
+        let Some(host) = url.host_str() else {
+            return Ok(vec![]);
+        };
+        let host = normalize_host(host).to_ascii_lowercase();
+        let matches = self.test_all(input)?;
+        let mut misses: Vec<NearMiss> = (self.rules_for_base_domain(&host, false).into_iter())
+            .filter(|rule| !matches.iter().any(|matched| std::ptr::eq(matched, *rule)))
+            .filter_map(|rule| {
+                let mismatch = mismatch(rule.pattern(), &url, &host)?;
+                Some(NearMiss { rule, mismatch })
+            })
+            .collect();
+        misses.sort_by_key(|miss| miss.mismatch);
+        Ok(misses)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn mismatches() {
+        let gfw = GfwList::from(
+            "||example.com:8443\n||example.com/search?q=ads\n||mail.example.com\n@@||www.example.com\n/example/",
+        )
+        .unwrap();
+        let misses = gfw.near_misses("https://www.example.com/search?q=news").unwrap();
+        let misses: Vec<_> = misses.iter().map(|miss| (miss.rule.text(), miss.mismatch)).collect();
+        assert_eq!(
+            misses,
+            [
+                ("||example.com:8443", Mismatch::Port),
+                ("||example.com/search?q=ads", Mismatch::Query),
+                ("||mail.example.com", Mismatch::Host),
+            ]
+        );
+        let gfw = GfwList::from("||le.example.com\nexample.org").unwrap();
+        let misses = gfw.near_misses("http://sample.example.com/").unwrap();
+        assert_eq!(
+            misses[0].to_string(),
+            "||le.example.com: the host of the rule does not start at a label boundary"
+        );
+        assert!(gfw.near_misses("data:text/plain,example.com").unwrap().is_empty());
+    }
+}
@@ -0,0 +1,223 @@
+//! Detection of bad list updates from the decisions they make.
+//!
+//! Sanity checks on the size of a new list do not catch an update that adds a single overly broad rule, such as
+//! `||com`. Such an update shows up in the decisions instead: the share of blocked requests jumps right after the new
+//! list is swapped in. A [`DecisionMonitor`] tracks the block rate in windows of a fixed number of decisions, and
+//! compares the windows after a swap with the last window before it.
+
+use std::fmt;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Mutex, PoisonError};
+
+use crate::Decision;
+
+/// When a [`DecisionMonitor`] raises an alert.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MonitorPolicy {
+    /// The number of decisions in a window
+    pub window: u64,
+    /// The number of windows after a swap that are compared with the window before it
+    pub watch_windows: usize,
+    /// The increase of the block rate, as a fraction of all decisions, that raises an alert
+    pub max_increase: f64,
+}
+
+impl Default for MonitorPolicy {
+    fn default() -> Self {
+        MonitorPolicy {
+            window: 1000,
+            watch_windows: 10,
+            max_increase: 0.2,
+        }
+    }
+}
+
+/// A spike of the block rate after a list swap, see [`DecisionMonitor`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Alert {
+    /// The block rate of the last window before the swap
+    pub baseline: f64,
+    /// The block rate of the window that raised the alert
+    pub observed: f64,
+    /// The 1-based index of the window after the swap
+    pub window: usize,
+}
+
+impl fmt::Display for Alert {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "block rate rose from {:.1}% to {:.1}% in window {} after a list swap",
+            self.baseline * 100.0,
+            self.observed * 100.0,
+            self.window,
+        )
+    }
+}
+
+#[derive(Debug, Default)]
+struct State {
+    /// The block rate of the last full window
+    last_rate: Option<f64>,
+    /// The baseline of the current swap, and the number of windows watched so far
+    watching: Option<(f64, usize)>,
+}
+
+/// Tracks the block rate of decisions and calls back when it spikes after a list swap.
+///
+/// Decisions are counted with atomic increments, so recording is cheap and can happen on every lookup. The callback
+/// runs on the thread that records the last decision of a window.
+///
+/// # Examples
+///
+/// ```
+/// # use std::sync::{Arc, Mutex};
+/// # use gfwlist::GfwList;
+/// # use gfwlist::monitor::{DecisionMonitor, MonitorPolicy};
+/// let alerts = Arc::new(Mutex::new(vec![]));
+/// let sink = alerts.clone();
+/// let policy = MonitorPolicy { window: 4, ..MonitorPolicy::default() };
+/// let monitor = DecisionMonitor::new(policy, move |alert| sink.lock().unwrap().push(*alert));
+///
+/// let urls = ["http://a.com", "http://b.com", "http://c.com", "http://d.com"];
+/// let old = GfwList::from("||a.com").unwrap();
+/// for url in urls {
+///     monitor.record(&old.decide(url).unwrap());
+/// }
+/// // the update accidentally blocks every `.com` domain
+/// let new = GfwList::from("||a.com\n||com").unwrap();
+/// monitor.list_swapped();
+/// for url in urls {
+///     monitor.record(&new.decide(url).unwrap());
+/// }
+/// let alerts = alerts.lock().unwrap();
+/// assert_eq!((alerts[0].baseline, alerts[0].observed), (0.25, 1.0));
+/// ```
+pub struct DecisionMonitor {
+    policy: MonitorPolicy,
+    decisions: AtomicU64,
+    blocked: AtomicU64,
+    state: Mutex<State>,
+    on_alert: Box<dyn Fn(&Alert) + Send + Sync>,
+}
+
+impl fmt::Debug for DecisionMonitor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DecisionMonitor")
+            .field("policy", &self.policy)
+            .field("decisions", &self.decisions)
+            .field("blocked", &self.blocked)
+            .field("state", &self.state)
+            .finish_non_exhaustive()
+    }
+}
+
+impl DecisionMonitor {
+    /// Creates a monitor that calls `on_alert` for every watched window whose block rate is too high.
+    pub fn new(policy: MonitorPolicy, on_alert: impl Fn(&Alert) + Send + Sync + 'static) -> Self {
+        DecisionMonitor {
+            policy,
+            decisions: AtomicU64::new(0),
+            blocked: AtomicU64::new(0),
+            state: Mutex::default(),
+            on_alert: Box::new(on_alert),
+        }
+    }
+
+    /// Records a decision.
+    pub fn record(&self, decision: &Decision) {
+        self.record_blocked(decision.is_blocked());
+    }
+
+    /// Records whether a request was blocked, for callers that do not keep the [`Decision`].
+    pub fn record_blocked(&self, blocked: bool) {
+        if blocked {
+            self.blocked.fetch_add(1, Ordering::Relaxed);
+        }
+        let decisions = self.decisions.fetch_add(1, Ordering::Relaxed) + 1;
+        if decisions == self.policy.window.max(1) {
+            self.close_window();
+        }
+    }
+
+    /// Starts watching the block rate, comparing the next windows with the last full window.
+    ///
+    /// Call this whenever a new list is swapped in, such as after
+    /// [`LiveGfwList::compact`](crate::live::LiveGfwList::compact). Decisions of the current window are discarded,
+    /// since they may mix both lists. No alerts are raised if no full window was recorded before the swap.
+    pub fn list_swapped(&self) {
+        let mut state = self.state();
+        self.decisions.store(0, Ordering::Relaxed);
+        self.blocked.store(0, Ordering::Relaxed);
+        state.watching = state.last_rate.map(|rate| (rate, 0));
+    }
+
+    /// Returns the block rate of the last full window, if any.
+    pub fn block_rate(&self) -> Option<f64> {
+        self.state().last_rate
+    }
+
+    fn state(&self) -> std::sync::MutexGuard<'_, State> {
+        self.state.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn close_window(&self) {
+        let mut state = self.state();
+        // decisions recorded while the lock was taken count towards the next window
+        let decisions = self.decisions.swap(0, Ordering::Relaxed);
+        let blocked = self.blocked.swap(0, Ordering::Relaxed);
+        let rate = blocked as f64 / decisions.max(1) as f64;
+        let alert = match &mut state.watching {
+            Some((baseline, windows)) => {
+                *windows += 1;
+                let alert = Alert {
+                    baseline: *baseline,
+                    observed: rate,
+                    window: *windows,
+                };
+                if *windows >= self.policy.watch_windows {
+                    state.watching = None;
+                }
+                Some(alert).filter(|alert| alert.observed - alert.baseline > self.policy.max_increase)
+            }
+            None => None,
+        };
+        state.last_rate = Some(rate);
+        drop(state);
+        if let Some(alert) = alert {
+            (self.on_alert)(&alert);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::Arc;
+
+    use super::*;
+
+    #[test]
+    fn watched_windows() {
+        let alerts = Arc::new(Mutex::new(vec![]));
+        let sink = alerts.clone();
+        let policy = MonitorPolicy {
+            window: 2,
+            watch_windows: 2,
+            max_increase: 0.5,
+        };
+        let monitor = DecisionMonitor::new(policy, move |alert: &Alert| sink.lock().unwrap().push(alert.window));
+        // no baseline yet
+        monitor.list_swapped();
+        for blocked in [true, true, false, false] {
+            monitor.record_blocked(blocked);
+        }
+        assert_eq!(monitor.block_rate(), Some(0.0));
+        monitor.list_swapped();
+        for blocked in [true, false, true, true, true, true, true, true] {
+            monitor.record_blocked(blocked);
+        }
+        // the third window after the swap is no longer watched
+        assert_eq!(*alerts.lock().unwrap(), [2]);
+        assert_eq!(monitor.block_rate(), Some(1.0));
+    }
+}
@@ -0,0 +1,162 @@
+//! Removal of redundant rules.
+//!
+//! Upstream lists often contain `||example.com` together with rules such as `||www.example.com` or
+//! `example.com/path` that it already covers. Since a URL is blocked if any positive rule matches, and allowed if any
+//! negative rule matches, a rule covered by another rule of the same polarity never changes a decision and can be
+//! removed to reduce the size of the automata. Only domain and host rules with the same [options](Rule::options) are
+//! compared; URL and regex rules are always kept.
+
+use std::collections::HashMap;
+
+use crate::{BuildError, GfwList, Pattern, Rule, RuleOptions};
+
+/// The hosts and paths matched by a domain or host rule.
+struct Coverage {
+    negative: bool,
+    subdomains: bool,
+    options: Option<RuleOptions>,
+    host: String,
+    /// The path prefix, with a trailing slash
+    path: String,
+}
+
+impl Coverage {
+    fn new(rule: &Rule) -> Option<Self> {
+        let subdomains = match rule.pattern() {
+            Pattern::Domain(_) => true,
+            Pattern::Host(_) => false,
+            _ => return None,
+        };
+        let host = rule
+            .pattern()
+            .host()
+            .filter(|host| !host.is_empty())?
+            .to_ascii_lowercase();
+        let mut path = rule.pattern().path().unwrap_or_default();
+        if !path.ends_with('/') {
+            path.push('/');
+        }
+        Some(Coverage {
+            negative: rule.is_negative(),
+            subdomains,
+            options: rule.options().cloned(),
+            host,
+            path,
+        })
+    }
+
+    /// Returns whether every URL matched by `other` is matched by `self`.
+    fn covers(&self, other: &Coverage) -> bool {
+        let host_covered = if self.subdomains {
+            other.host == self.host || (other.host.strip_suffix(&self.host)).is_some_and(|prefix| prefix.ends_with('.'))
+        } else {
+            !other.subdomains && other.host == self.host
+        };
+        self.negative == other.negative
+            && self.options == other.options
+            && host_covered
+            && other.path.starts_with(&self.path)
+    }
+}
+
+/// Removes the rules covered by other rules of the same polarity, keeping the order of the remaining rules.
+///
+/// Of several equivalent rules, such as `.example.com` and `||example.com`, the first one is kept.
+///
+/// # Examples
+///
+/// ```
+/// # use gfwlist::{Rule, optimize::minimize};
+/// let input = "||www.example.com\n||example.com\nexample.com/path\n@@||a.example.com\n.example.com";
+/// let rules: Vec<Rule> = input.lines().map(|line| Rule::parse(line).unwrap().unwrap()).collect();
+/// let texts: Vec<_> = minimize(&rules).iter().map(|rule| rule.text().to_string()).collect();
+/// assert_eq!(texts, ["||example.com", "@@||a.example.com"]);
+/// ```
+pub fn minimize(rules: &[Rule]) -> Vec<Rule> {
+    minimize_with(rules, |_| true)
+}
+
+/// Like [`minimize`], but only rules for which `is_active` returns `true` may cover other rules.
+fn minimize_with(rules: &[Rule], is_active: impl Fn(&Rule) -> bool) -> Vec<Rule> {
+    (rules.iter().zip(covering_rules(rules, is_active)))
+        .filter(|(_, covering)| covering.is_none())
+        .map(|(rule, _)| rule.clone())
+        .collect()
+}
+
+/// Finds, for each rule, the index of an active rule that makes it redundant.
+pub(crate) fn covering_rules(rules: &[Rule], is_active: impl Fn(&Rule) -> bool) -> Vec<Option<usize>> {
+    let coverages: Vec<Option<Coverage>> = rules.iter().map(Coverage::new).collect();
+    // index the active rules by host, so that only the suffixes of a host need to be checked
+    let mut by_host: HashMap<&str, Vec<usize>> = HashMap::new();
+    for (index, coverage) in coverages.iter().enumerate() {
+        if let Some(coverage) = coverage
+            && is_active(&rules[index])
+        {
+            by_host.entry(coverage.host.as_str()).or_default().push(index);
+        }
+    }
+    let covering = |index: usize, coverage: &Coverage| {
+        let host = coverage.host.as_str();
+        let suffixes = std::iter::once(host).chain(host.match_indices('.').map(|(pos, _)| &host[pos + 1..]));
+        suffixes
+            .filter_map(|suffix| by_host.get(suffix))
+            .flatten()
+            .copied()
+            .find(|&other| {
+                let Some(other_coverage) = &coverages[other] else {
+                    return false;
+                };
+                // of two equivalent rules, the first one is kept
+                other != index && other_coverage.covers(coverage) && (!coverage.covers(other_coverage) || other < index)
+            })
+    };
+    (coverages.iter().enumerate())
+        .map(|(index, coverage)| coverage.as_ref().and_then(|coverage| covering(index, coverage)))
+        .collect()
+}
+
+impl GfwList {
+    /// Returns a copy of the list without redundant rules, see [`minimize`].
+    ///
+    /// Rules of disabled sections are kept, and do not cover other rules.
+    pub fn optimize(&self) -> Result<GfwList, BuildError> {
+        let is_active = |rule: &Rule| {
+            rule.section()
+                .is_none_or(|section| !self.disabled_sections.contains(section))
+        };
+        let rules = minimize_with(&self.rules, is_active);
+        self.rebuild(&rules)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn same_decisions() {
+        let input = "||example.com\n||www.example.com/a\nexample.com\n|http://example.com/b\n@@||x.example.com\n\
+                     @@x.example.com/c\nexample.org/a\nexample.org/a/b\nwww.example.org/a\n/regex/";
+        let gfw = GfwList::from(input).unwrap();
+        let optimized = gfw.optimize().unwrap();
+        assert_eq!(optimized.len(), 6);
+        for url in [
+            "http://example.com/",
+            "http://www.example.com/a",
+            "http://x.example.com/c",
+            "http://example.org/a/b",
+            "http://www.example.org/a",
+            "http://example.org/",
+            "http://regex.com/",
+        ] {
+            assert_eq!(
+                gfw.decide(url).unwrap().is_blocked(),
+                optimized.decide(url).unwrap().is_blocked(),
+                "{url}"
+            );
+        }
+        let gfw = GfwList::from("||a.com\n||a.com$important\n||b.com$dnstype=A\n||b.com\n||b.com$dnstype=A").unwrap();
+        assert_eq!(gfw.optimize().unwrap().len(), 4);
+    }
+}
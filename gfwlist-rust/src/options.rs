@@ -0,0 +1,279 @@
+use std::time::Instant;
+
+use url::Url;
+
+use crate::host::is_under;
+use crate::ip::url_ip;
+use crate::{Confidence, Decision, GfwList, MatchError, RequestContext, Rule, RuleOptions, Verdict, append_parsed_url};
+
+/// Per-call overrides for [`GfwList::test_with_options`].
+///
+/// This lets one compiled list serve several policies without being rebuilt.
+#[derive(Debug, Clone)]
+pub struct TestOptions {
+    /// Whether rules see the query string of the URL, and regex rules also its fragment, which is the default unless
+    /// the list [ignores queries](crate::GfwListBuilder::ignore_query)
+    pub include_query: bool,
+    /// The scheme assumed for inputs without one, such as `example.com/path`
+    pub default_scheme: Option<String>,
+    /// The time after which matching fails with [`MatchError::Timeout`]
+    pub deadline: Option<Instant>,
+    /// If set, only rules with one of these tags take part, where the tags of a rule are its
+    /// [section](Rule::section) and the name of its [source](crate::Origin::source)
+    pub tags: Option<Vec<String>>,
+    /// If set, the `$options` of rules are evaluated against it, see [`RequestContext`], and otherwise rules with
+    /// `$dnstype` do not match, while rules with `$important` take precedence over all others either way
+    pub context: Option<RequestContext>,
+    /// If set, keyword rules with [`Confidence::Low`](crate::Confidence::Low) only take part for hosts under one of
+    /// these domains, which confirm their matches, so that an empty list leaves out keyword rules altogether
+    pub keyword_suffixes: Option<Vec<String>>,
+}
+
+impl Default for TestOptions {
+    fn default() -> Self {
+        TestOptions {
+            include_query: true,
+            default_scheme: None,
+            deadline: None,
+            tags: None,
+            context: None,
+            keyword_suffixes: None,
+        }
+    }
+}
+
+impl TestOptions {
+    fn check_deadline(&self) -> Result<(), MatchError> {
+        match self.deadline {
+            Some(deadline) if Instant::now() >= deadline => Err(MatchError::Timeout),
+            _ => Ok(()),
+        }
+    }
+
+    fn is_selected(&self, rule: &Rule, host: Option<&str>) -> bool {
+        if let (Some(context), Some(host)) = (&self.context, host)
+            && !context.allows(rule, host)
+        {
+            return false;
+        }
+        if self.context.is_none() && rule.options().is_some_and(RuleOptions::needs_context) {
+            return false;
+        }
+        if let Some(suffixes) = &self.keyword_suffixes
+            && rule.confidence() == Confidence::Low
+            && !host.is_some_and(|host| suffixes.iter().any(|suffix| is_under(host, suffix)))
+        {
+            return false;
+        }
+        let Some(tags) = &self.tags else {
+            return true;
+        };
+        tags.iter().any(|tag| rule.has_tag(tag))
+    }
+}
+
+impl GfwList {
+    /// Tests whether a URL matches any rule, with per-call options, see [`GfwList::test`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use gfwlist::{GfwList, TestOptions};
+    /// let gfw_list = GfwList::from("||blocked-site.com\n!---- Ads ----\n/[?&]utm_source=/").unwrap();
+    /// let options = TestOptions {
+    ///     default_scheme: Some("https".into()),
+    ///     include_query: false,
+    ///     ..Default::default()
+    /// };
+    /// assert_eq!(gfw_list.test_with_options("blocked-site.com/", &options).unwrap(), Some("||blocked-site.com"));
+    /// assert_eq!(gfw_list.test_with_options("example.com/?utm_source=a", &options).unwrap(), None);
+    ///
+    /// let options = TestOptions {
+    ///     tags: Some(vec!["Ads".into()]),
+    ///     ..Default::default()
+    /// };
+    /// assert_eq!(gfw_list.test_with_options("https://blocked-site.com/", &options).unwrap(), None);
+    /// ```
+    pub fn test_with_options(&self, input: &str, options: &TestOptions) -> Result<Option<&str>, MatchError> {
+        Ok(self.decide_with_options(input, options)?.blocked_by().map(Rule::text))
+    }
+
+    /// Decides whether a URL is blocked, with per-call options, see [`GfwList::decide`].
+    pub fn decide_with_options(&self, input: &str, options: &TestOptions) -> Result<Decision<'_>, MatchError> {
+        let with_scheme;
+        let mut input = input;
+        if let Some(scheme) = &options.default_scheme
+            && !input.contains("://")
+        {
+            with_scheme = format!("{scheme}://{input}");
+            input = &with_scheme;
+        }
+        input = match options.include_query {
+            true => self.matched_input(input),
+            false => input.split(['?', '#']).next().unwrap_or(input),
+        };
+        let input = &*self.schemes.apply(input);
+        let url = Url::parse(input);
+        let host = url.as_ref().ok().and_then(Url::host_str);
+        if !self.important_rules.is_empty() {
+            let mut haystack: Vec<u8> = vec![];
+            if let Ok(url) = &url {
+                append_parsed_url::<true>(&mut haystack, url, true, &self.schemes)?;
+            }
+            let port = url.as_ref().ok().and_then(Url::port_or_known_default);
+            let selected = |index: usize| options.is_selected(&self.rules[index], host);
+            if let Some(verdict) = self.important_verdict(Some(input), &haystack, port, selected) {
+                return Ok(verdict.resolve(self));
+            }
+        }
+
+        for (regex, index) in &self.regex_patterns {
+            options.check_deadline()?;
+            if options.is_selected(&self.rules[*index], host) && regex.is_match(input) {
+                return Ok(self.regex_verdict(*index).resolve(self));
+            }
+        }
+        options.check_deadline()?;
+        let url = url?;
+        if options.tags.is_none() && options.context.is_none() && options.keyword_suffixes.is_none() {
+            return Ok(self.test_automata(&url)?.resolve(self));
+        }
+
+        let mut haystack: Vec<u8> = vec![];
+        append_parsed_url::<true>(&mut haystack, &url, true, &self.schemes)?;
+        let ip = url_ip(&url);
+        let first_selected = |negative| {
+            let ip_rules = (self.ip_rules.iter())
+                .filter(|(network, index)| {
+                    self.rules[*index].is_negative() == negative && ip.is_some_and(|ip| network.contains(ip))
+                })
+                .map(|(_, index)| *index);
+            (self.automata.matches(negative, &haystack))
+                .map(|(index, _)| index)
+                .chain(ip_rules)
+                .filter(|index| self.port_matches(*index, url.port_or_known_default()))
+                .find(|index| options.is_selected(&self.rules[*index], url.host_str()))
+        };
+        let verdict = if let Some(index) = first_selected(true) {
+            Verdict::Allowed(index)
+        } else if let Some(index) = first_selected(false) {
+            Verdict::Blocked(index)
+        } else {
+            Verdict::NoMatch
+        };
+        Ok(verdict.resolve(self))
+    }
+
+    /// Returns the verdict of the first selected `$important` rule matching an encoded URL or host, where important
+    /// exceptions come first, as in AdGuard Home. Regex rules are only tested if there is an input.
+    pub(crate) fn important_verdict(
+        &self,
+        input: Option<&str>,
+        haystack: &[u8],
+        port: Option<u16>,
+        selected: impl Fn(usize) -> bool,
+    ) -> Option<Verdict> {
+        if self.important_rules.is_empty() {
+            return None;
+        }
+        let selected = |index: &usize| {
+            self.important_rules.binary_search(index).is_ok() && self.port_matches(*index, port) && selected(*index)
+        };
+        for negative in [true, false] {
+            let regex_match = || {
+                (self.regex_patterns.iter())
+                    .filter(|(_, index)| self.rules[*index].is_negative() == negative && selected(index))
+                    .find(|(regex, _)| input.is_some_and(|input| regex.is_match(input)))
+                    .map(|(_, index)| *index)
+            };
+            let automata_match = || {
+                (self.automata.matches(negative, haystack))
+                    .map(|(index, _)| index)
+                    .find(selected)
+            };
+            if let Some(index) = regex_match().or_else(automata_match) {
+                return Some(match negative {
+                    true => Verdict::Allowed(index),
+                    false => Verdict::Blocked(index),
+                });
+            }
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+
+    use super::*;
+
+    #[test]
+    fn deadline() {
+        let gfw = GfwList::from("||example.com").unwrap();
+        let options = TestOptions {
+            deadline: Some(Instant::now() - Duration::from_secs(1)),
+            ..Default::default()
+        };
+        assert!(matches!(
+            gfw.test_with_options("http://example.com", &options),
+            Err(MatchError::Timeout)
+        ));
+        assert!(
+            gfw.test_with_options("http://example.com", &TestOptions::default())
+                .unwrap()
+                .is_some()
+        );
+    }
+
+    #[test]
+    fn keyword_suffixes() {
+        let gfw = GfwList::from("/^https?:\\/\\/[^\\/]*google/\n||google.com\n@@/ad/").unwrap();
+        let options = TestOptions {
+            keyword_suffixes: Some(vec![".cn".into()]),
+            ..Default::default()
+        };
+        let test = |url| gfw.test_with_options(url, &options).unwrap();
+        assert_eq!(test("https://www.google.com/ad"), Some("||google.com"));
+        assert_eq!(test("https://googleapis.net/"), None);
+        assert!(test("https://google.com.cn/").is_some());
+        assert!(gfw.test("https://googleapis.net/").unwrap().is_some());
+    }
+
+    #[test]
+    fn without_context() {
+        let gfw = GfwList::from("||a.com\n@@||b.a.com\n||b.a.com$important\n||c.com$dnstype=AAAA").unwrap();
+        let cached = crate::cache::CachedGfwList::new(gfw.clone(), 16);
+        let important = Some("||b.a.com$important");
+        assert_eq!(gfw.test("http://b.a.com/").unwrap(), important);
+        assert_eq!(cached.test("http://b.a.com/").unwrap(), important);
+        assert_eq!(gfw.test_host("b.a.com"), important);
+        assert_eq!(gfw.test_parts("http", "b.a.com", "/").unwrap(), important);
+        assert_eq!(
+            gfw.test_with_options("http://b.a.com/", &TestOptions::default())
+                .unwrap(),
+            important
+        );
+
+        assert_eq!(gfw.test("http://c.com/").unwrap(), None);
+        assert_eq!(cached.test("http://c.com/").unwrap(), None);
+        assert_eq!(gfw.test_host("c.com"), None);
+        assert_eq!(
+            gfw.test_with_options("http://c.com/", &TestOptions::default()).unwrap(),
+            None
+        );
+        let options = |dns_type: &str| TestOptions {
+            context: Some(RequestContext {
+                dns_type: Some(dns_type.into()),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        assert_eq!(gfw.test_with_options("http://c.com/", &options("A")).unwrap(), None);
+        assert!(
+            gfw.test_with_options("http://c.com/", &options("AAAA"))
+                .unwrap()
+                .is_some()
+        );
+    }
+}
@@ -0,0 +1,25 @@
+//! Parallel batch matching, enabled by the `rayon` feature.
+
+use rayon::prelude::*;
+
+use crate::GfwList;
+
+impl GfwList {
+    /// Tests many URLs in parallel on the rayon thread pool, see [`GfwList::test`].
+    ///
+    /// Results are returned in the order of the inputs.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use gfwlist::GfwList;
+    /// let gfw_list = GfwList::from("||blocked-site.com").unwrap();
+    /// let results = gfw_list.test_many(&["http://blocked-site.com", "http://example.com", "invalid"]);
+    /// assert_eq!(results[0], Ok(Some("||blocked-site.com")));
+    /// assert_eq!(results[1], Ok(None));
+    /// assert!(results[2].is_err());
+    /// ```
+    pub fn test_many<S: AsRef<str> + Sync>(&self, inputs: &[S]) -> Vec<Result<Option<&str>, url::ParseError>> {
+        inputs.par_iter().map(|input| self.test(input.as_ref())).collect()
+    }
+}
@@ -0,0 +1,215 @@
+//! Patching compiled lists with small changes instead of rebuilding them, see [`GfwList::commit`].
+//!
+//! Building the Aho-Corasick automata dominates the cost of a commit. When the rules that go into the automaton of one
+//! polarity are unchanged, apart from their positions in the list, that automaton is taken over from the current list
+//! with its rules renumbered. A change to positive rules thus only rebuilds the positive automaton, and the other way
+//! round. Compiled regexes are reused by their source as well.
+
+use std::collections::HashMap;
+
+use gfwlist_core::{Automata, AutomataBuilder, PatternError};
+use regex::Regex;
+
+use crate::rule::parse_lines;
+use crate::{BuildError, GfwList, Rule};
+
+/// The compiled structures of a list that a build may take over.
+#[derive(Debug)]
+pub(crate) struct Reuse {
+    automata: Automata,
+    regexes: HashMap<String, Regex>,
+    /// The positive and the negative side, in this order
+    sides: [Side; 2],
+}
+
+/// The automaton side of one polarity, while the patterns added for it still match the rules it was built from.
+#[derive(Debug)]
+struct Side {
+    /// The rules that went into the side, with their indices in the current list
+    expected: Vec<(usize, Rule)>,
+    /// The patterns added so far, which match the first expected rules, with their indices in the new list
+    deferred: Vec<(Vec<u8>, bool, usize)>,
+    /// Whether the patterns diverged from the expected rules, so that the side is rebuilt
+    diverged: bool,
+}
+
+impl Reuse {
+    /// Collects the structures of a list for reuse.
+    pub(crate) fn new(list: &GfwList) -> Self {
+        let mut sides = [false, true].map(|_| Side {
+            expected: vec![],
+            deferred: vec![],
+            diverged: false,
+        });
+        for (negative, side) in [false, true].into_iter().zip(&mut sides) {
+            let mut rules = list.automata.pattern_rules(negative).to_vec();
+            // patterns expanded from separator placeholders share their rule
+            rules.dedup();
+            side.expected = (rules.into_iter())
+                .map(|index| (index, list.rules[index].clone()))
+                .collect();
+        }
+        Self {
+            automata: list.automata.clone(),
+            regexes: (list.regex_patterns.iter())
+                .map(|(regex, _)| (regex.as_str().to_string(), regex.clone()))
+                .collect(),
+            sides,
+        }
+    }
+
+    /// Returns the compiled regex with the source, if any.
+    pub(crate) fn regex(&self, source: &str) -> Option<Regex> {
+        self.regexes.get(source).cloned()
+    }
+
+    /// Adds a pattern, or a literal fragment of an approximated regex, unless the side may still be reused.
+    pub(crate) fn add(
+        &mut self,
+        automata: &mut AutomataBuilder,
+        needle: Vec<u8>,
+        literal: bool,
+        rule: &Rule,
+        index: usize,
+    ) -> Result<(), PatternError> {
+        let side = &mut self.sides[rule.is_negative() as usize];
+        if !side.diverged {
+            if side
+                .expected
+                .get(side.deferred.len())
+                .is_some_and(|(_, expected)| expected == rule)
+            {
+                if !literal {
+                    AutomataBuilder::check_pattern(&needle)?;
+                }
+                side.deferred.push((needle, literal, index));
+                return Ok(());
+            }
+            side.diverge(automata, rule.is_negative());
+        }
+        match literal {
+            true => automata.add_literal(needle, rule.is_negative(), index),
+            false => automata.add_pattern(needle, rule.is_negative(), index)?,
+        };
+        Ok(())
+    }
+
+    /// Adds the deferred patterns of sides that cannot be reused, before the automata are built.
+    pub(crate) fn finish(&mut self, automata: &mut AutomataBuilder) {
+        for (negative, side) in [false, true].into_iter().zip(&mut self.sides) {
+            if !side.diverged && side.deferred.len() < side.expected.len() {
+                side.diverge(automata, negative);
+            }
+        }
+    }
+
+    /// Takes over the sides that can be reused into the built automata.
+    pub(crate) fn apply(&self, automata: &mut Automata) {
+        for (negative, side) in [false, true].into_iter().zip(&self.sides) {
+            if side.diverged {
+                continue;
+            }
+            let map: HashMap<usize, usize> = (side.expected.iter().zip(&side.deferred))
+                .map(|((old, _), (_, _, new))| (*old, *new))
+                .collect();
+            automata.reuse_side(negative, &self.automata, |rule| map[&rule]);
+        }
+    }
+
+    /// Returns whether the side of the polarity is taken over.
+    #[cfg(test)]
+    fn reused(&self, negative: bool) -> bool {
+        !self.sides[negative as usize].diverged
+    }
+}
+
+impl Side {
+    fn diverge(&mut self, automata: &mut AutomataBuilder, negative: bool) {
+        self.diverged = true;
+        for (needle, literal, index) in self.deferred.drain(..) {
+            match literal {
+                true => automata.add_literal(needle, negative, index),
+                // checked when deferred
+                false => automata.add_pattern(needle, negative, index).unwrap(),
+            };
+        }
+    }
+}
+
+impl GfwList {
+    /// Stages a new version of the list, such as an update of its subscription, to replace all rules by the next
+    /// [`commit`](Self::commit).
+    ///
+    /// Small updates are cheap to commit: only the automata of the polarities they touch are rebuilt. Unlike
+    /// [`GfwList::from`], the input is parsed line by line without evaluating directives or collecting expectations
+    /// and metadata, which are kept from the current list. If a line cannot be parsed, nothing is staged, while rules
+    /// that fail to compile make the commit fail.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use gfwlist::GfwList;
+    /// let mut gfw_list = GfwList::from("||a.com\n||b.com\n@@||c.b.com").unwrap();
+    /// gfw_list.stage_update("||a.com\n||b.com\n||d.com\n@@||c.b.com").unwrap();
+    /// gfw_list.commit().unwrap();
+    /// assert_eq!(gfw_list.test("https://d.com").unwrap(), Some("||d.com"));
+    /// assert_eq!(gfw_list.test("https://c.b.com").unwrap(), None);
+    /// ```
+    pub fn stage_update(&mut self, input: &str) -> Result<(), BuildError> {
+        let mut rules = vec![];
+        for (line, offset, _, rule) in parse_lines(input, &[], None) {
+            match rule {
+                Ok(rule) => rules.extend(rule),
+                Err(error) => return Err(BuildError::Syntax { line, offset, error }),
+            }
+        }
+        self.staged = Some(rules);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{GfwListBuilder, RegexPolicy};
+
+    #[test]
+    fn reuse_sides() {
+        let source = "||a.com\n@@||b.a.com\n/^https?:\\/\\/c\\.com\\//\n||b.com/path\n@@|http://d.b.com";
+        let update = "||new.com\n||a.com\n@@||b.a.com\n/^https?:\\/\\/c\\.com\\//\n||b.com/path\n@@|http://d.b.com";
+        for policy in [RegexPolicy::Compile, RegexPolicy::Approximate] {
+            let mut builder = GfwListBuilder::new();
+            builder.regex_policy(policy).add_rules(source).unwrap();
+            let mut list = builder.build().unwrap();
+            let mut builder = GfwListBuilder::new();
+            builder.regex_policy(policy).add_rules(update).unwrap();
+            let expected = builder.build().unwrap();
+
+            list.stage_update(update).unwrap();
+            list.commit().unwrap();
+            for url in [
+                "https://new.com",
+                "http://b.a.com",
+                "https://c.com/",
+                "http://b.com/path",
+                "http://d.b.com",
+            ] {
+                assert_eq!(list.test(url).unwrap(), expected.test(url).unwrap(), "{url}");
+            }
+        }
+
+        let list = GfwList::from(source).unwrap();
+        let mut builder = GfwListBuilder::new();
+        builder.reuse = Some(Reuse::new(&list));
+        builder.add_rules(update).unwrap();
+        let reuse = builder.reuse.as_ref().unwrap();
+        assert!(!reuse.reused(false) && reuse.reused(true));
+        let gfw = builder.build().unwrap();
+        assert_eq!(gfw.test("http://d.b.com").unwrap(), None);
+        assert_eq!(gfw.test("http://new.com").unwrap(), Some("||new.com"));
+        let mut gfw = GfwList::from("").unwrap();
+        assert!(gfw.stage_update("||a.com\n/abc").is_err());
+        gfw.stage_update("||a.com\n/(/").unwrap();
+        assert!(gfw.commit().is_err() && gfw.has_staged_changes());
+    }
+}
@@ -0,0 +1,79 @@
+//! Port-specific rules such as `|http://example.com:8080` or `||example.com:2053^`, which only match URLs on that port.
+//!
+//! The automata match hosts without their ports. A rule with a port is encoded like the same rule without it, and its
+//! matches are checked against the effective port of the URL, which is its explicit port or the default port of its
+//! scheme, see [`Pattern::port`](crate::Pattern::port).
+
+use std::borrow::Cow;
+
+use crate::GfwList;
+
+/// Splits the port off a host like `example.com:8080` or `[2001:db8::1]:8080`.
+///
+/// IPv6 addresses without brackets are returned unchanged, since their last group cannot be told from a port.
+pub(crate) fn split_port(host: &str) -> (&str, Option<u16>) {
+    let Some((name, port)) = host.rsplit_once(':') else {
+        return (host, None);
+    };
+    if name.contains(':') && !name.ends_with(']') || !port.bytes().all(|byte| byte.is_ascii_digit()) {
+        return (host, None);
+    }
+    match port.parse() {
+        Ok(port) => (name, Some(port)),
+        Err(_) => (host, None),
+    }
+}
+
+/// Removes the port from the host of a domain or host pattern like `example.com:8080/path`, whose host ends at the
+/// first `/` or `?`.
+pub(crate) fn strip_port(input: &str) -> Cow<'_, str> {
+    let (host, path) = input.split_at(input.find(['/', '?']).unwrap_or(input.len()));
+    match split_port(host) {
+        (host, Some(_)) => Cow::Owned(format!("{host}{path}")),
+        (_, None) => Cow::Borrowed(input),
+    }
+}
+
+/// Returns the default port of a scheme, as assumed by [`url::Url::port_or_known_default`].
+pub(crate) fn default_port(scheme: &str) -> Option<u16> {
+    match scheme {
+        "http" | "ws" => Some(80),
+        "https" | "wss" => Some(443),
+        "ftp" => Some(21),
+        _ => None,
+    }
+}
+
+impl GfwList {
+    /// Returns whether a rule applies on the port, which is `None` if it is not known, such as for bare hosts.
+    pub(crate) fn port_matches(&self, index: usize, port: Option<u16>) -> bool {
+        match self.rule_ports.binary_search_by_key(&index, |&(index, _)| index) {
+            Ok(pos) => port == Some(self.rule_ports[pos].1),
+            Err(_) => true,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn ports() {
+        assert_eq!(split_port("example.com:8080"), ("example.com", Some(8080)));
+        assert_eq!(split_port("[2001:db8::1]:443"), ("[2001:db8::1]", Some(443)));
+        assert_eq!(split_port("2001:db8::1"), ("2001:db8::1", None));
+        assert_eq!(split_port("example.com:99999"), ("example.com:99999", None));
+        assert_eq!(strip_port("example.com:2053/path:1"), "example.com/path:1");
+        let gfw = GfwList::from("||example.com:2053^\n|http://example.org:8080\n@@|https://example.com:8443/").unwrap();
+        assert_eq!(
+            gfw.test("https://www.example.com:2053/").unwrap(),
+            Some("||example.com:2053^")
+        );
+        assert_eq!(gfw.test("https://www.example.com/").unwrap(), None);
+        assert!(gfw.test("http://example.org:8080/").unwrap().is_some());
+        assert!(gfw.test("http://example.org/").unwrap().is_none());
+        assert!(gfw.test_parts("http", "example.org:8080", "/").unwrap().is_some());
+        assert!(gfw.test_host("example.com").is_none());
+    }
+}
@@ -0,0 +1,320 @@
+//! Adblock Plus `$options` and the request context they are evaluated against.
+//!
+//! Rules may end with options like `||ads.com^$script,domain=example.com|~shop.example.com,third-party`. Lists are
+//! parsed with their options stripped from the pattern, so that such rules match like the rest. The constraints are
+//! only evaluated when a [`RequestContext`] is given through [`TestOptions::context`](crate::TestOptions::context),
+//! and only for the parts of the context that are known. Rules with `$dnstype` never match without a context, and
+//! `$important` rules take precedence over all others either way.
+
+use std::fmt;
+
+use crate::Rule;
+
+/// The resource types of Adblock Plus, which restrict a rule to requests of these types.
+const RESOURCE_TYPES: &[&str] = &[
+    "document",
+    "font",
+    "image",
+    "media",
+    "object",
+    "other",
+    "ping",
+    "popup",
+    "script",
+    "stylesheet",
+    "subdocument",
+    "webrtc",
+    "websocket",
+    "xmlhttprequest",
+];
+
+/// Options of Adblock Plus, uBlock Origin and AdGuard that are recognized but not evaluated, and kept in
+/// [`RuleOptions::ignored`].
+const IGNORED_OPTIONS: &[&str] = &[
+    "all",
+    "badfilter",
+    "client",
+    "collapse",
+    "csp",
+    "ctag",
+    "denyallow",
+    "dnsrewrite",
+    "elemhide",
+    "genericblock",
+    "generichide",
+    "header",
+    "match-case",
+    "redirect",
+    "redirect-rule",
+    "removeparam",
+    "rewrite",
+    "sitekey",
+];
+
+/// The `$options` of a rule, see [`Rule::options`].
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct RuleOptions {
+    /// `$domain=example.com`: the document domains, including subdomains, that the rule is restricted to
+    pub domains: Vec<String>,
+    /// `$domain=~example.com`: the document domains, including subdomains, that the rule does not apply to
+    pub excluded_domains: Vec<String>,
+    /// `$third-party` or `$~third-party`: whether the rule only applies to third-party or first-party requests, where
+    /// requests are first-party if the last two labels of the host match those of the document domain
+    pub third_party: Option<bool>,
+    /// `$script,image`: the resource types that the rule is restricted to
+    pub resource_types: Vec<String>,
+    /// `$~script`: the resource types that the rule does not apply to
+    pub excluded_resource_types: Vec<String>,
+    /// `$important`: whether the rule takes precedence over exceptions that are not important, as in AdGuard Home
+    pub important: bool,
+    /// `$dnstype=A|AAAA`: the DNS record types, in uppercase, that the rule is restricted to
+    pub dns_types: Vec<String>,
+    /// `$dnstype=~A`: the DNS record types, in uppercase, that the rule does not apply to
+    pub excluded_dns_types: Vec<String>,
+    /// Options that are kept but not evaluated, such as `csp=...`
+    pub ignored: Vec<String>,
+}
+
+impl RuleOptions {
+    /// Parses the text after `$`, returning `None` unless it is a list of known options.
+    fn parse(text: &str) -> Option<Self> {
+        let mut options = RuleOptions::default();
+        for item in text.split(',') {
+            let (name, value) = match item.split_once('=') {
+                Some((name, value)) => (name, Some(value)),
+                None => (item, None),
+            };
+            let (inverse, bare) = match name.strip_prefix('~') {
+                Some(bare) => (true, bare),
+                None => (false, name),
+            };
+            let valid = |c: char| c.is_ascii_alphanumeric() || c == '-' || c == '_';
+            if !bare.starts_with(|c: char| c.is_ascii_alphabetic()) || !bare.chars().all(valid) {
+                return None;
+            }
+            let bare = bare.to_ascii_lowercase();
+            match (bare.as_str(), value) {
+                ("domain", Some(value)) => {
+                    for domain in value.split('|') {
+                        let (excluded, domain) = match domain.strip_prefix('~') {
+                            Some(domain) => (true, domain),
+                            None => (false, domain),
+                        };
+                        if domain.is_empty() {
+                            return None;
+                        }
+                        match excluded {
+                            true => options.excluded_domains.push(domain.to_ascii_lowercase()),
+                            false => options.domains.push(domain.to_ascii_lowercase()),
+                        }
+                    }
+                }
+                ("dnstype", Some(value)) if !inverse => {
+                    for kind in value.split('|') {
+                        let (excluded, kind) = match kind.strip_prefix('~') {
+                            Some(kind) => (true, kind),
+                            None => (false, kind),
+                        };
+                        if kind.is_empty() {
+                            return None;
+                        }
+                        match excluded {
+                            true => options.excluded_dns_types.push(kind.to_ascii_uppercase()),
+                            false => options.dns_types.push(kind.to_ascii_uppercase()),
+                        }
+                    }
+                }
+                ("important", None) if !inverse => options.important = true,
+                ("third-party", None) => options.third_party = Some(!inverse),
+                (kind, None) if RESOURCE_TYPES.contains(&kind) => match inverse {
+                    true => options.excluded_resource_types.push(bare),
+                    false => options.resource_types.push(bare),
+                },
+                (name, _) if IGNORED_OPTIONS.contains(&name) => options.ignored.push(item.to_string()),
+                _ => return None,
+            }
+        }
+        Some(options)
+    }
+
+    /// Returns whether the rule only applies with a [`RequestContext`], which is the case for `$dnstype`, since a URL
+    /// does not imply a record type.
+    pub(crate) fn needs_context(&self) -> bool {
+        !self.dns_types.is_empty() || !self.excluded_dns_types.is_empty()
+    }
+
+    /// Returns whether the rule applies to a request for the host in the given context.
+    pub fn applies_to(&self, host: &str, context: &RequestContext) -> bool {
+        if let Some(document) = context.document_domain.as_deref() {
+            let document = document.to_ascii_lowercase();
+            if !self.domains.is_empty() && !self.domains.iter().any(|domain| is_subdomain(&document, domain)) {
+                return false;
+            }
+            if self
+                .excluded_domains
+                .iter()
+                .any(|domain| is_subdomain(&document, domain))
+            {
+                return false;
+            }
+            if let Some(third_party) = self.third_party
+                && third_party == (base_domain(host) == base_domain(&document))
+            {
+                return false;
+            }
+        }
+        if let Some(kind) = context.resource_type.as_deref() {
+            if !self.resource_types.is_empty() && !self.resource_types.iter().any(|other| other == kind) {
+                return false;
+            }
+            if self.excluded_resource_types.iter().any(|other| other == kind) {
+                return false;
+            }
+        }
+        if let Some(kind) = context.dns_type.as_deref() {
+            if !self.dns_types.is_empty() && !self.dns_types.iter().any(|other| other.eq_ignore_ascii_case(kind)) {
+                return false;
+            }
+            if self
+                .excluded_dns_types
+                .iter()
+                .any(|other| other.eq_ignore_ascii_case(kind))
+            {
+                return false;
+            }
+        }
+        true
+    }
+}
+
+impl fmt::Display for RuleOptions {
+    /// Formats the options in a canonical order, without the leading `$`.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut items: Vec<String> = vec![];
+        items.extend(self.resource_types.iter().cloned());
+        items.extend(self.excluded_resource_types.iter().map(|kind| format!("~{kind}")));
+        if let Some(third_party) = self.third_party {
+            items.push(if third_party { "third-party" } else { "~third-party" }.to_string());
+        }
+        if !self.domains.is_empty() || !self.excluded_domains.is_empty() {
+            let domains: Vec<String> = (self.domains.iter().cloned())
+                .chain(self.excluded_domains.iter().map(|domain| format!("~{domain}")))
+                .collect();
+            items.push(format!("domain={}", domains.join("|")));
+        }
+        if self.important {
+            items.push("important".to_string());
+        }
+        if !self.dns_types.is_empty() || !self.excluded_dns_types.is_empty() {
+            let kinds: Vec<String> = (self.dns_types.iter().cloned())
+                .chain(self.excluded_dns_types.iter().map(|kind| format!("~{kind}")))
+                .collect();
+            items.push(format!("dnstype={}", kinds.join("|")));
+        }
+        items.extend(self.ignored.iter().cloned());
+        f.write_str(&items.join(","))
+    }
+}
+
+/// Returns whether the host is the domain or one of its subdomains.
+fn is_subdomain(host: &str, domain: &str) -> bool {
+    host.strip_suffix(domain)
+        .is_some_and(|rest| rest.is_empty() || rest.ends_with('.'))
+}
+
+/// Returns the last two labels of a host, which approximate its registrable domain without a public suffix list.
+pub(crate) fn base_domain(host: &str) -> &str {
+    match host.rmatch_indices('.').nth(1) {
+        Some((pos, _)) => &host[pos + 1..],
+        None => host,
+    }
+}
+
+/// Splits the `$options` off a line, if the text after its last `$` is a list of known options. Otherwise the `$` is
+/// part of the pattern, as in `|http://example.com/cgi$bin`.
+pub(crate) fn split_options(line: &str) -> (&str, Option<RuleOptions>) {
+    let Some(pos) = line.rfind('$') else {
+        return (line, None);
+    };
+    let body = &line[..pos];
+    // `$` may end a regular expression, as in `/example\.com$/`
+    if line.strip_prefix("@@").unwrap_or(line).starts_with('/') && !body.ends_with('/') {
+        return (line, None);
+    }
+    match RuleOptions::parse(&line[pos + 1..]) {
+        Some(options) => (body, Some(options)),
+        None => (line, None),
+    }
+}
+
+/// What is known about a request beyond its URL, for evaluating rule options.
+///
+/// # Examples
+///
+/// ```
+/// # use gfwlist::{GfwList, RequestContext, TestOptions};
+/// let gfw_list = GfwList::from("||ads.com^$third-party,script").unwrap();
+/// let options = |document: &str, kind: &str| TestOptions {
+///     context: Some(RequestContext {
+///         document_domain: Some(document.into()),
+///         resource_type: Some(kind.into()),
+///         ..Default::default()
+///     }),
+///     ..Default::default()
+/// };
+/// let url = "https://cdn.ads.com/a.js";
+/// assert!(gfw_list.test_with_options(url, &options("example.com", "script")).unwrap().is_some());
+/// assert!(gfw_list.test_with_options(url, &options("www.ads.com", "script")).unwrap().is_none());
+/// assert!(gfw_list.test_with_options(url, &options("example.com", "image")).unwrap().is_none());
+/// // without a context, options are not evaluated
+/// assert!(gfw_list.test(url).unwrap().is_some());
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct RequestContext {
+    /// The domain of the document that made the request, which `$domain` and `$third-party` are evaluated against
+    pub document_domain: Option<String>,
+    /// The resource type of the request, such as `script` or `image`
+    pub resource_type: Option<String>,
+    /// The record type of a DNS query, such as `A` or `AAAA`, which `$dnstype` is evaluated against
+    pub dns_type: Option<String>,
+}
+
+impl RequestContext {
+    /// Returns whether the options of a rule, if any, allow it to match a request for the host.
+    pub(crate) fn allows(&self, rule: &Rule, host: &str) -> bool {
+        (rule.options()).is_none_or(|options| options.applies_to(host, self))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parse_options() {
+        let (body, options) = split_options("||a.com^$~image,domain=B.com|~c.b.com,important,dnstype=aaaa|~A,csp");
+        let options = options.unwrap();
+        assert_eq!(body, "||a.com^");
+        assert_eq!(
+            options.to_string(),
+            "~image,domain=b.com|~c.b.com,important,dnstype=AAAA|~A,csp"
+        );
+        assert_eq!(options.excluded_resource_types, ["image"]);
+        assert_eq!(
+            (options.domains, options.excluded_domains),
+            (vec!["b.com".into()], vec!["c.b.com".into()])
+        );
+        assert!(options.important);
+        assert_eq!(
+            (options.dns_types, options.excluded_dns_types),
+            (vec!["AAAA".into()], vec!["A".into()])
+        );
+        assert_eq!(options.ignored, ["csp"]);
+        assert_eq!(split_options("/a\\.com$/").1, None);
+        assert_eq!(split_options("|http://a.com/?x=$1").1, None);
+        assert_eq!(split_options("/a\\.com/$script").0, "/a\\.com/");
+        assert_eq!(split_options("|http://a.com/cgi$bin"), ("|http://a.com/cgi$bin", None));
+        let gfw = crate::GfwList::from("|http://a.com/cgi$bin").unwrap();
+        assert!(gfw.test("http://a.com/cgi$bin").unwrap().is_some());
+    }
+}
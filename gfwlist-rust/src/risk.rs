@@ -0,0 +1,176 @@
+//! Estimates of how broadly rules match, for reviewing contributions to shared lists.
+//!
+//! [`estimate`] scores a rule from 0 to 100 by the factors that make it likely to match more than intended: hosts with
+//! few labels, wildcards, short keywords and wide networks raise the score, while path and port constraints lower it.
+//! The score is a heuristic for review, not a measure of how many sites a rule matches. [`GfwList::lint`] reports rules
+//! scoring at least [`HIGH`] as [`LintKind::Broad`](crate::lint::LintKind::Broad).
+//!
+//! [`GfwList::lint`]: crate::GfwList::lint
+
+use std::net::IpAddr;
+
+use crate::literal::required_literals;
+use crate::{Confidence, Pattern, Rule};
+
+/// The score from which a rule counts as high-risk
+pub const HIGH: u8 = 70;
+
+/// The estimated breadth of a rule, see [`estimate`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Risk {
+    /// The score from 0, for rules that match a single page, to 100, for rules that may match almost anything
+    pub score: u8,
+    /// The factors that raised or lowered the score
+    pub factors: Vec<Factor>,
+}
+
+impl Risk {
+    /// Returns whether the score is at least [`HIGH`].
+    pub fn is_high(&self) -> bool {
+        self.score >= HIGH
+    }
+}
+
+/// A factor of a [`Risk`] estimate.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Factor {
+    /// The host has a single label, like `||com`, so the rule covers a whole top-level domain
+    ShallowHost,
+    /// The host contains a wildcard
+    WildcardHost,
+    /// The rule is a keyword of this many characters that may occur anywhere in the host or URL, see
+    /// [`Rule::confidence`]
+    Keyword(usize),
+    /// The regex requires no literal text of at least a few characters
+    NoLiteral,
+    /// The network has this short a prefix, like `IP-CIDR,10.0.0.0/8`
+    WideNetwork(u8),
+    /// The rule only matches some paths, which narrows it
+    PathConstraint,
+    /// The rule only matches one port, which narrows it
+    PortConstraint,
+}
+
+/// The length from which a literal fragment of a regex counts as specific
+const MIN_LITERAL_LEN: usize = 4;
+
+/// Estimates how broadly a rule matches.
+///
+/// # Examples
+///
+/// ```
+/// # use gfwlist::{Rule, risk::{self, Factor}};
+/// let estimate = |line| risk::estimate(&Rule::parse(line).unwrap().unwrap());
+/// assert!(!estimate("||google.com").is_high());
+/// assert!(estimate("||com").is_high());
+/// let risk = estimate("/^https?:\\/\\/[^\\/]*ad/");
+/// assert!(risk.is_high());
+/// assert_eq!(risk.factors, [Factor::Keyword(2)]);
+/// assert!(estimate("||com/ads/tracker.js").score < estimate("||com").score);
+/// ```
+pub fn estimate(rule: &Rule) -> Risk {
+    let mut factors = vec![];
+    let pattern = rule.pattern();
+    let mut score: u32 = match pattern {
+        Pattern::Regex(source) => {
+            let fragment = (required_literals(source).unwrap_or_default().iter())
+                .flat_map(|literal| literal.split([':', '/']))
+                .filter(|fragment| !matches!(*fragment, "http" | "https"))
+                .map(str::len)
+                .max()
+                .unwrap_or_default();
+            match rule.confidence() {
+                _ if fragment == 0 => {
+                    factors.push(Factor::NoLiteral);
+                    100
+                }
+                Confidence::Low => {
+                    factors.push(Factor::Keyword(fragment));
+                    match fragment {
+                        0..=3 => 100,
+                        4..=5 => 80,
+                        6..=8 => 60,
+                        _ => 40,
+                    }
+                }
+                Confidence::High if fragment < MIN_LITERAL_LEN => {
+                    factors.push(Factor::NoLiteral);
+                    80
+                }
+                Confidence::High => 20,
+            }
+        }
+        Pattern::Cidr(network) => {
+            let (addr, prefix) = network.split_once('/').unwrap_or((network, ""));
+            let prefix: u8 = prefix.parse().unwrap_or_default();
+            // IPv6 networks are usually allocated with twice as many bits as IPv4 networks
+            let bits = match addr.parse::<IpAddr>() {
+                Ok(IpAddr::V6(_)) => prefix / 2,
+                _ => prefix,
+            };
+            if bits <= 16 {
+                factors.push(Factor::WideNetwork(prefix));
+            }
+            match bits {
+                0..=8 => 100,
+                9..=16 => 70,
+                17..=24 => 30,
+                _ => 5,
+            }
+        }
+        Pattern::Domain(_) | Pattern::Host(_) | Pattern::Url(_) => {
+            let host = pattern.host().unwrap_or_default();
+            let shallow = !host.contains('.') && !host.starts_with('[');
+            if shallow {
+                factors.push(Factor::ShallowHost);
+            }
+            let mut score = match (pattern, shallow) {
+                (Pattern::Domain(_), true) => 90,
+                (Pattern::Domain(_), false) if host.matches('.').count() == 1 => 30,
+                (Pattern::Domain(_), false) => 15,
+                (_, true) => 60,
+                (_, false) => 10,
+            };
+            if host.contains('*') {
+                factors.push(Factor::WildcardHost);
+                score += 40;
+            }
+            score
+        }
+    };
+    if pattern.path().is_some() {
+        factors.push(Factor::PathConstraint);
+        score /= 2;
+    }
+    if pattern.port().is_some() {
+        factors.push(Factor::PortConstraint);
+        score /= 2;
+    }
+    Risk {
+        score: score.min(100) as u8,
+        factors,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::GfwList;
+    use crate::lint::LintKind;
+
+    #[test]
+    fn scores_and_lints() {
+        let score = |line| estimate(&Rule::parse(line).unwrap().unwrap()).score;
+        assert!(score("||*.com") > score("||example.com"));
+        assert!(score("||example.com") > score("||www.example.com"));
+        assert!(score("/google/") < score("/goo/"));
+        assert!(score("IP-CIDR,10.0.0.0/8") >= HIGH && score("IP-CIDR6,2001:db8::/64") < HIGH);
+        assert!(score("|http://example.com:8080/ads") < score("|http://example.com"));
+        let lints = GfwList::lint("||example.com\n||cn\n/.*/");
+        assert!(matches!(lints[0].kind, LintKind::Broad(90)));
+        assert_eq!(
+            lints[1].to_string(),
+            "line 3: broad rule with a risk score of 100/100, review what it matches"
+        );
+    }
+}
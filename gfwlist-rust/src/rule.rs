@@ -0,0 +1,885 @@
+use std::borrow::Cow;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::ops::Deref;
+use std::sync::Arc;
+
+use gfwlist_core::separator;
+
+use crate::ip::{canonical_network, normalize_host, normalize_pattern_host};
+use crate::port::{split_port, strip_port};
+use crate::request::{RuleOptions, split_options};
+use crate::{SyntaxError, base64, numbered_lines};
+
+/// The matching part of a rule, without its polarity.
+///
+/// Patterns other than regular expressions may contain the Adblock Plus separator placeholder `^`, which matches a
+/// character such as `/` or `:`, or the end of the URL, and the wildcard `*`, which matches any sequence of
+/// characters.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Pattern {
+    /// `/pattern/`: a regular expression tested against the full URL, which may be negative like `@@/pattern/`
+    Regex(String),
+    /// `||example.com/path` or `.example.com/path`: the host or any of its subdomains, followed by an optional path
+    /// prefix
+    Domain(String),
+    /// `example.com/path`: exactly the host, followed by an optional path prefix
+    Host(String),
+    /// `|http://example.com/path`: a URL prefix, including the scheme
+    Url(String),
+    /// `IP-CIDR,8.8.8.0/24` or `IP-CIDR6,2001:db8::/32`: a network in canonical CIDR notation, which matches hosts
+    /// that are IP addresses in it
+    Cidr(String),
+}
+
+impl Pattern {
+    /// Returns the host part of the pattern, if it has one.
+    ///
+    /// For [`Pattern::Url`], the URL is parsed and its host is returned. Regular expressions have no host. A `^`
+    /// separator ending the host is not part of it.
+    pub fn host(&self) -> Option<String> {
+        match self {
+            Pattern::Regex(_) | Pattern::Cidr(_) => None,
+            Pattern::Domain(s) | Pattern::Host(s) => {
+                Some(normalize_host(split_port(split_host_path(s).0).0).into_owned())
+            }
+            Pattern::Url(s) => url::Url::parse(&separator::normalize_url(s))
+                .ok()?
+                .host_str()
+                .map(str::to_string),
+        }
+    }
+
+    /// Returns the port the pattern is restricted to, like `8080` for `|http://example.com:8080` or
+    /// `||example.com:8080^`, if any.
+    ///
+    /// Such patterns only match URLs on the port, where URLs without an explicit port are on the default port of their
+    /// scheme. Default ports written in URL patterns, like in `|https://example.com:443`, are dropped, as they follow
+    /// from the scheme.
+    pub fn port(&self) -> Option<u16> {
+        match self {
+            Pattern::Regex(_) | Pattern::Cidr(_) => None,
+            Pattern::Domain(s) | Pattern::Host(s) => split_port(split_host_path(s).0).1,
+            Pattern::Url(s) => url::Url::parse(&separator::normalize_url(s)).ok()?.port(),
+        }
+    }
+
+    /// Returns the path part of the pattern together with its query, like `/search?q=`, if it constrains either.
+    pub fn path(&self) -> Option<String> {
+        match self {
+            Pattern::Regex(_) | Pattern::Cidr(_) => None,
+            Pattern::Domain(s) | Pattern::Host(s) => match split_host_path(s).1 {
+                "" | "^" => None,
+                path => Some(match path.strip_prefix('^') {
+                    Some(rest) => format!("/{rest}"),
+                    None if path.starts_with('?') => format!("/{path}"),
+                    None => path.to_string(),
+                }),
+            },
+            Pattern::Url(s) => {
+                let s = separator::normalize_url(s);
+                let url = url::Url::parse(&s).ok()?;
+                match url.query() {
+                    Some(query) => Some(format!("{}?{query}", url.path())),
+                    None => Some(url.path().to_string()).filter(|p| p != "/" || s.ends_with('/')),
+                }
+            }
+        }
+    }
+}
+
+/// Matches the scheme of a URL, see [`Rule::to_regex`]
+const URL_SCHEME: &str = r"[a-z][a-z0-9+.\-]*";
+/// Matches the optional port of a URL
+const URL_PORT: &str = r"(?::\d+)?";
+/// Matches the end of a path segment, which is a `/` or the end of the path
+const SEGMENT_END: &str = "(?:[/?#]|$)";
+
+/// Compiles the port of a pattern, where patterns without a port match any port.
+fn port_regex(port: Option<u16>) -> String {
+    match port {
+        Some(port) => format!(":{port}"),
+        None => URL_PORT.to_string(),
+    }
+}
+
+/// Compiles the host of a pattern, where wildcards stay inside the host.
+fn host_regex(host: &str) -> String {
+    (host.split('*')).map(regex::escape).collect::<Vec<_>>().join("[^/?#]*")
+}
+
+/// Compiles the path of a pattern, which matches a prefix of the path of URLs.
+///
+/// Paths are encoded with a trailing `/` unless they end with a wildcard or a separator placeholder, and so are the
+/// paths of URLs. So a `/` or `^` that ends the pattern also matches the end of the path, see
+/// [`separator`](gfwlist_core::separator). A query follows the terminated path and matches a prefix of the query.
+fn path_regex(path: &str) -> String {
+    if let Some((path, query)) = path.split_once('?') {
+        let query = (query.split('*')).map(regex::escape).collect::<Vec<_>>().join("[^#]*");
+        let path = path.strip_suffix('/').unwrap_or(path);
+        return format!("{}/?\\?{query}", segments_regex(path, false));
+    }
+    if path.is_empty() || path == "/" {
+        return "/".to_string();
+    }
+    let mut output = segments_regex(path, true);
+    if !path.ends_with(['/', '^', '*']) {
+        output.push_str(SEGMENT_END);
+    }
+    output
+}
+
+/// Compiles the segments of a path, where a `/` or `^` ending a terminal path also matches the end of the path.
+fn segments_regex(path: &str, terminal: bool) -> String {
+    let separators = regex::escape(std::str::from_utf8(separator::SEPARATORS).unwrap_or_default());
+    let mut output = String::new();
+    let mut chars = path.chars().peekable();
+    while let Some(c) = chars.next() {
+        let last = terminal && chars.peek().is_none();
+        match c {
+            '*' => output.push_str("[^?#]*"),
+            '^' if last => output.push_str(&format!("(?:[{separators}?#]|$)")),
+            '^' => output.push_str(&format!("[{separators}]")),
+            '/' if last => output.push_str(SEGMENT_END),
+            c => output.push_str(&regex::escape(c.encode_utf8(&mut [0; 4]))),
+        }
+    }
+    output
+}
+
+/// Splits a domain or host pattern at the first `/`, the `^` separator ending the host or the `?` starting the query.
+fn split_host_path(input: &str) -> (&str, &str) {
+    let pos = input.find(['/', '^', '?']).unwrap_or(input.len());
+    input.split_at(pos)
+}
+
+/// Where a rule comes from.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Origin {
+    /// The name of the source, if the rule was loaded from a named source
+    pub source: Option<Arc<str>>,
+    /// The 1-based line number of the rule in its source
+    pub line: usize,
+}
+
+impl Origin {
+    /// Creates the origin of a rule at a line of an unnamed input.
+    pub fn line(line: usize) -> Self {
+        Origin { source: None, line }
+    }
+}
+
+impl fmt::Display for Origin {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.source {
+            Some(source) => write!(f, "{source}:{}", self.line),
+            None => write!(f, "line {}", self.line),
+        }
+    }
+}
+
+/// The classification of a compiled rule.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum RuleKind {
+    /// A rule that blocks matching URLs
+    Positive,
+    /// An `@@` rule that allows matching URLs
+    Negative,
+    /// A regular expression tested against the full URL
+    Regex,
+}
+
+/// How precisely a rule singles out what it matches, see [`Rule::confidence`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Confidence {
+    /// The rule is anchored at the start of the host or at a label of it, like `||example.com`
+    High,
+    /// The rule is a keyword that may occur anywhere in the host or URL, like `DOMAIN-KEYWORD,google`, and is prone to
+    /// matching unrelated sites
+    Low,
+}
+
+/// A stable identifier of a rule, derived from its normalized pattern, polarity and options.
+///
+/// The identifier is the 64-bit FNV-1a hash of the canonical form of the rule (see [`Rule::new`]), with the host of
+/// domain and host patterns lowercased, followed by `$` and its [options](Rule::options) if it has any. It does not
+/// depend on the position of the rule, so it can be used to correlate statistics across list versions and processes. It
+/// is formatted as 16 hexadecimal digits.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct RuleId(pub u64);
+
+impl fmt::Display for RuleId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:016x}", self.0)
+    }
+}
+
+/// The text of a rule: owned while building, and a span of the text arena of its [`GfwList`](crate::GfwList) once
+/// built.
+#[derive(Clone)]
+enum Text {
+    Owned(String),
+    Arena { arena: Arc<str>, start: u32, end: u32 },
+}
+
+impl Deref for Text {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        match self {
+            Text::Owned(text) => text,
+            Text::Arena { arena, start, end } => &arena[*start as usize..*end as usize],
+        }
+    }
+}
+
+impl fmt::Debug for Text {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&**self, f)
+    }
+}
+
+/// Moves the texts of the rules into a single shared allocation, so that a large list does not keep one allocation
+/// per rule text. Nothing is moved if the texts add up to more than 4 GiB.
+pub(crate) fn move_to_arena(rules: &mut [Rule]) {
+    let len: usize = rules.iter().map(|rule| rule.text.len()).sum();
+    if u32::try_from(len).is_err() {
+        return;
+    }
+    let mut buffer = String::with_capacity(len);
+    let mut spans = Vec::with_capacity(rules.len());
+    for rule in rules.iter() {
+        let start = buffer.len() as u32;
+        buffer.push_str(&rule.text);
+        spans.push((start, buffer.len() as u32));
+    }
+    let arena: Arc<str> = buffer.into();
+    for (rule, (start, end)) in rules.iter_mut().zip(spans) {
+        rule.text = Text::Arena {
+            arena: arena.clone(),
+            start,
+            end,
+        };
+    }
+}
+
+/// A single parsed rule of a GFW list.
+///
+/// A rule keeps the text it was parsed from, so that matches can be reported exactly as written in the source. It
+/// may also know its [`Origin`] and the section of the list it belongs to, which are not taken into account when
+/// comparing rules.
+#[derive(Debug, Clone)]
+pub struct Rule {
+    text: Text,
+    negative: bool,
+    pattern: Pattern,
+    options: Option<Arc<RuleOptions>>,
+    origin: Option<Origin>,
+    section: Option<Arc<str>>,
+}
+
+impl PartialEq for Rule {
+    fn eq(&self, other: &Self) -> bool {
+        *self.text == *other.text && self.negative == other.negative && self.pattern == other.pattern
+    }
+}
+
+impl Eq for Rule {}
+
+impl Hash for Rule {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        (*self.text).hash(state);
+        self.negative.hash(state);
+        self.pattern.hash(state);
+    }
+}
+
+impl Rule {
+    /// Creates a rule from its parts, generating the canonical text for it.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use gfwlist::{Pattern, Rule};
+    /// let rule = Rule::new(true, Pattern::Domain("example.com".into()));
+    /// assert_eq!(rule.text(), "@@||example.com");
+    /// ```
+    pub fn new(negative: bool, pattern: Pattern) -> Self {
+        let mut rule = Rule {
+            text: Text::Owned(String::new()),
+            negative,
+            pattern,
+            options: None,
+            origin: None,
+            section: None,
+        };
+        rule.text = Text::Owned(rule.to_string());
+        rule
+    }
+
+    /// Parses a single line of a GFW list.
+    ///
+    /// Returns `Ok(None)` for empty lines and comments. Adblock Plus `$options` at the end of the line are split off,
+    /// see [`Rule::options`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use gfwlist::{Pattern, Rule};
+    /// let rule = Rule::parse(".example.com").unwrap().unwrap();
+    /// assert_eq!(rule.pattern(), &Pattern::Domain("example.com".into()));
+    /// assert!(Rule::parse("! comment").unwrap().is_none());
+    /// ```
+    pub fn parse(line: &str) -> Result<Option<Self>, SyntaxError> {
+        // skip empty lines, comments and the `[AutoProxy 0.2.9]` header
+        if line.is_empty() || line.starts_with('!') || (line.starts_with("[AutoProxy") && line.ends_with(']')) {
+            return Ok(None);
+        }
+        let text = line;
+        let (line, options) = split_options(line);
+        let options = options.map(Arc::new);
+        let (negative, body) = match line.strip_prefix("@@") {
+            Some(body) => (true, body),
+            None => (false, line),
+        };
+        let cidr = body.strip_prefix("IP-CIDR,").or_else(|| body.strip_prefix("IP-CIDR6,"));
+        let pattern = if let Some(value) = cidr {
+            // options such as `no-resolve` may follow, like in Clash rules
+            let value = value.split(',').next().unwrap_or_default();
+            Pattern::Cidr(canonical_network(value).ok_or(SyntaxError::Rule)?)
+        } else if body.starts_with('/') {
+            if body.len() == 1 || !body.ends_with('/') {
+                return Err(SyntaxError::Rule);
+            }
+            Pattern::Regex(body[1..body.len() - 1].to_string())
+        } else if let Some(body) = body.strip_prefix("||") {
+            Pattern::Domain(body.to_string())
+        } else if let Some(body) = body.strip_prefix('|') {
+            Pattern::Url(body.to_string())
+        } else if let Some(body) = body.strip_prefix('.') {
+            Pattern::Domain(body.to_string())
+        } else if !body.is_empty() {
+            Pattern::Host(body.to_string())
+        } else {
+            return Err(SyntaxError::Rule);
+        };
+        Ok(Some(Rule {
+            text: Text::Owned(text.to_string()),
+            negative,
+            pattern,
+            options,
+            origin: None,
+            section: None,
+        }))
+    }
+
+    /// Returns the text of the rule as written in the source.
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    /// Returns whether the rule is a negative (`@@`, whitelist) rule.
+    pub fn is_negative(&self) -> bool {
+        self.negative
+    }
+
+    /// Returns the classification of the rule.
+    pub fn kind(&self) -> RuleKind {
+        match (&self.pattern, self.negative) {
+            (Pattern::Regex(_), _) => RuleKind::Regex,
+            (_, true) => RuleKind::Negative,
+            (_, false) => RuleKind::Positive,
+        }
+    }
+
+    /// Returns the pattern of the rule.
+    pub fn pattern(&self) -> &Pattern {
+        &self.pattern
+    }
+
+    /// Compiles the pattern of the rule into an equivalent regular expression, for tools that only match regexes and
+    /// to spell out what exactly a rule matches.
+    ///
+    /// The regex is anchored at the start of the URL and expects it as serialized by [`url::Url`], with a lowercase
+    /// scheme and host and a path of at least `/`. It matches the URLs the rule matches on its own, without
+    /// [scheme maps](crate::SchemeMap) or [options](Rule::options). Regex rules return their own expression. IP rules,
+    /// which match addresses rather than URLs, and URL rules that are not valid URLs return `None`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use gfwlist::Rule;
+    /// let rule = Rule::parse("||example.com/ads").unwrap().unwrap();
+    /// let regex = rule.to_regex().unwrap();
+    /// assert_eq!(regex, r"^[a-z][a-z0-9+.\-]*://(?:[^/?#]*\.)?example\.com(?::\d+)?/ads(?:[/?#]|$)");
+    /// let regex = regex::Regex::new(&regex).unwrap();
+    /// assert!(regex.is_match("https://www.example.com/ads?id=1"));
+    /// assert!(!regex.is_match("https://www.example.com/adsense"));
+    /// ```
+    pub fn to_regex(&self) -> Option<String> {
+        Some(match &self.pattern {
+            Pattern::Regex(source) => source.clone(),
+            Pattern::Cidr(_) => return None,
+            Pattern::Domain(s) | Pattern::Host(s) => {
+                let s = separator::normalize_host_path(s);
+                let port = port_regex(self.pattern.port());
+                let s = normalize_pattern_host(&strip_port(&s)).into_owned();
+                let (host, path) = s.split_at(s.find(['/', '?']).unwrap_or(s.len()));
+                let subdomains = match self.pattern {
+                    Pattern::Domain(_) => r"(?:[^/?#]*\.)?",
+                    _ => "",
+                };
+                format!(
+                    "^{URL_SCHEME}://{subdomains}{}{port}{}",
+                    host_regex(host),
+                    path_regex(path)
+                )
+            }
+            Pattern::Url(s) => {
+                let s = separator::normalize_url(s);
+                let url = url::Url::parse(&s).ok()?;
+                let start = format!("^{}://{}", regex::escape(url.scheme()), host_regex(url.host_str()?));
+                // like in the encoding of URL patterns, a bare `/` path only constrains the URL if it was written
+                let path = match url.query() {
+                    Some(query) => format!("{}?{query}", url.path()),
+                    None => url.path().to_string(),
+                };
+                match (path.as_str(), url.port()) {
+                    ("/", None) if !s.ends_with('/') => start,
+                    // the host is still matched as a prefix
+                    ("/", Some(port)) if !s.ends_with('/') => format!("{start}[^/?#:]*:{port}/"),
+                    (path, port) => format!("{start}{}{}", port_regex(port), path_regex(path)),
+                }
+            }
+        })
+    }
+
+    /// Returns the `$options` of the rule, if it has any.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use gfwlist::{Pattern, Rule};
+    /// let rule = Rule::parse("||ads.com^$script,domain=example.com").unwrap().unwrap();
+    /// assert_eq!(rule.pattern(), &Pattern::Domain("ads.com^".into()));
+    /// assert_eq!(rule.options().unwrap().resource_types, ["script"]);
+    /// ```
+    pub fn options(&self) -> Option<&RuleOptions> {
+        self.options.as_deref()
+    }
+
+    /// Returns the stable identifier of the rule, which covers its normalized pattern, whether it is negative and its
+    /// options.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use gfwlist::Rule;
+    /// let rule = Rule::parse(".Example.com").unwrap().unwrap();
+    /// assert_eq!(rule.id(), Rule::parse("||example.com").unwrap().unwrap().id());
+    /// assert_ne!(rule.id(), Rule::parse("@@||example.com").unwrap().unwrap().id());
+    /// assert_ne!(rule.id(), Rule::parse("||example.com$important").unwrap().unwrap().id());
+    /// ```
+    pub fn id(&self) -> RuleId {
+        let pattern = match &self.pattern {
+            Pattern::Domain(s) | Pattern::Host(s) => {
+                let (host, path) = split_host_path(s);
+                let normalized = format!("{}{path}", host.to_ascii_lowercase());
+                match self.pattern {
+                    Pattern::Domain(_) => Pattern::Domain(normalized),
+                    _ => Pattern::Host(normalized),
+                }
+            }
+            pattern => pattern.clone(),
+        };
+        let mut canonical = Rule::new(self.negative, pattern).text.to_string();
+        if let Some(options) = &self.options {
+            canonical = format!("{canonical}${options}");
+        }
+        let mut hash: u64 = 0xcbf29ce484222325;
+        for &byte in canonical.as_bytes() {
+            hash ^= byte as u64;
+            hash = hash.wrapping_mul(0x100000001b3);
+        }
+        RuleId(hash)
+    }
+
+    /// Returns where the rule comes from, if known.
+    pub fn origin(&self) -> Option<&Origin> {
+        self.origin.as_ref()
+    }
+
+    /// Sets where the rule comes from.
+    pub fn with_origin(mut self, origin: Origin) -> Self {
+        self.origin = Some(origin);
+        self
+    }
+
+    /// Returns the name of the section the rule belongs to, if any.
+    ///
+    /// Sections are started by comments such as `!---------- News ----------` or `!##### Whitelist Start #####`.
+    pub fn section(&self) -> Option<&str> {
+        self.section.as_deref()
+    }
+
+    /// Returns how precisely the rule singles out what it matches.
+    ///
+    /// Keyword rules have [`Confidence::Low`]: regexes that are not anchored at the start of the URL or whose host part
+    /// may start anywhere in the host, such as those imported from `DOMAIN-KEYWORD` rules, and rules whose host
+    /// contains a wildcard. All other rules have [`Confidence::High`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use gfwlist::{Confidence, Rule};
+    /// let confidence = |line| Rule::parse(line).unwrap().unwrap().confidence();
+    /// assert_eq!(confidence("||google.com"), Confidence::High);
+    /// assert_eq!(confidence("/^https?:\\/\\/[^\\/]+\\.google\\.com/"), Confidence::High);
+    /// assert_eq!(confidence("/google/"), Confidence::Low);
+    /// assert_eq!(confidence("/^https?:\\/\\/[^\\/]*google/"), Confidence::Low);
+    /// assert_eq!(confidence("||*google*.com"), Confidence::Low);
+    /// ```
+    pub fn confidence(&self) -> Confidence {
+        let is_keyword = match &self.pattern {
+            Pattern::Regex(source) => {
+                // the host part starts with a run of any characters, unless a `.` follows, which starts a label
+                !source.starts_with('^')
+                    || ["\\/\\/[^\\/]*", "\\/\\/[^\\/]+", "\\/\\/.*", "\\/\\/.+"]
+                        .iter()
+                        .any(|start| source.contains(start) && !source.contains(&format!("{start}\\.")))
+            }
+            Pattern::Cidr(_) => false,
+            pattern => pattern.host().is_some_and(|host| host.contains('*')),
+        };
+        match is_keyword {
+            true => Confidence::Low,
+            false => Confidence::High,
+        }
+    }
+
+    /// Returns whether the rule has a tag, which is its section or the name of its source.
+    pub(crate) fn has_tag(&self, tag: &str) -> bool {
+        self.section() == Some(tag) || self.origin().and_then(|origin| origin.source.as_deref()) == Some(tag)
+    }
+
+    /// Sets the section the rule belongs to.
+    pub fn with_section(mut self, section: impl Into<Arc<str>>) -> Self {
+        self.section = Some(section.into());
+        self
+    }
+
+    /// Overrides the polarity of the rule, adding or removing the `@@` prefix of its text.
+    ///
+    /// If the text has no `@@` prefix to remove, as with rules of a custom [`RuleParser`], the canonical text is
+    /// generated instead.
+    pub fn with_negative(mut self, negative: bool) -> Self {
+        if self.negative != negative {
+            self.negative = negative;
+            let text = match negative {
+                true => Some(format!("@@{}", &*self.text)),
+                false => self.text.strip_prefix("@@").map(str::to_string),
+            };
+            self.text = Text::Owned(text.unwrap_or_else(|| self.to_string()));
+        }
+        self
+    }
+}
+
+/// Returns the section name of a header comment such as `!---------- News ----------`.
+pub(crate) fn section_header(line: &str) -> Option<&str> {
+    let body = line.strip_prefix('!')?;
+    let delimiter = body.chars().next().filter(|c| matches!(c, '-' | '#' | '='))?;
+    if !body.starts_with(&delimiter.to_string().repeat(3)) {
+        return None;
+    }
+    Some(body.trim_matches(|c: char| c == delimiter || c.is_whitespace())).filter(|name| !name.is_empty())
+}
+
+/// A line syntax beyond the AutoProxy syntax, registered with
+/// [`GfwListBuilder::add_parser`](crate::GfwListBuilder::add_parser).
+///
+/// Parsers are tried in the order they were added, and lines that none of them accepts are parsed with
+/// [`Rule::parse`]. Rules keep the line they were parsed from as their [text](Rule::text), so that matches are reported
+/// as written in the list. Closures taking a line are parsers as well.
+pub trait RuleParser: Send + Sync {
+    /// Parses a line into a rule, returning `None` if the line is not in this syntax.
+    fn parse(&self, line: &str) -> Option<Result<Rule, SyntaxError>>;
+}
+
+impl<F> RuleParser for F
+where
+    F: Fn(&str) -> Option<Result<Rule, SyntaxError>> + Send + Sync,
+{
+    fn parse(&self, line: &str) -> Option<Result<Rule, SyntaxError>> {
+        self(line)
+    }
+}
+
+impl fmt::Debug for dyn RuleParser {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("RuleParser")
+    }
+}
+
+/// Parses a line with the first parser that accepts it, falling back to [`Rule::parse`].
+pub(crate) fn parse_line(parsers: &[Arc<dyn RuleParser>], line: &str) -> Result<Option<Rule>, SyntaxError> {
+    for parser in parsers {
+        if let Some(result) = parser.parse(line) {
+            return result.map(|rule| {
+                Some(Rule {
+                    text: Text::Owned(line.to_string()),
+                    ..rule
+                })
+            });
+        }
+    }
+    Rule::parse(line)
+}
+
+/// The directive starting an inline base64 chunk, see [`parse_lines`]
+const BASE64_BEGIN: &str = "!#include-base64-begin";
+/// The directive ending an inline base64 chunk
+const BASE64_END: &str = "!#include-base64-end";
+
+/// Splits the input into numbered lines, replacing inline base64 chunks with the lines they encode.
+///
+/// A chunk consists of the lines between `!#include-base64-begin` and `!#include-base64-end`, or the end of the input.
+/// Decoded lines have the line number and offset of the begin directive. A chunk that is not valid base64-encoded
+/// UTF-8 is returned as an error at the begin directive.
+fn expand_base64_chunks(input: &str) -> Vec<(usize, usize, Result<Cow<'_, str>, SyntaxError>)> {
+    let mut output = vec![];
+    let mut lines = numbered_lines(input);
+    while let Some((line, offset, text)) = lines.next() {
+        if text.trim_end() != BASE64_BEGIN {
+            output.push((line, offset, Ok(Cow::Borrowed(text))));
+            continue;
+        }
+        let chunk: String = (lines.by_ref())
+            .map(|(_, _, text)| text)
+            .take_while(|text| text.trim_end() != BASE64_END)
+            .collect();
+        let decoded = base64::decode(&chunk).and_then(|bytes| String::from_utf8(bytes).ok());
+        match decoded {
+            Some(decoded) => {
+                for text in decoded.lines() {
+                    output.push((line, offset, Ok(Cow::Owned(text.to_string()))));
+                }
+            }
+            None => output.push((line, offset, Err(SyntaxError::Base64))),
+        }
+    }
+    output
+}
+
+/// Parses the lines of a GFW list, attaching origins and sections to the rules.
+///
+/// Yields the line number, byte offset and text of each line together with the parse result. Inline base64 chunks
+/// are decoded, see [`expand_base64_chunks`]. Lines are parsed with the given parsers first, see [`parse_line`]. Lines
+/// longer than `max_line_length` are not parsed, and fail with [`SyntaxError::LineTooLong`].
+pub(crate) fn parse_lines<'a>(
+    input: &'a str,
+    parsers: &'a [Arc<dyn RuleParser>],
+    max_line_length: Option<usize>,
+) -> impl Iterator<Item = (usize, usize, Cow<'a, str>, Result<Option<Rule>, SyntaxError>)> {
+    let mut section: Option<Arc<str>> = None;
+    expand_base64_chunks(input)
+        .into_iter()
+        .map(move |(line, offset, text)| {
+            let text = match text {
+                Ok(text) => text,
+                Err(error) => return (line, offset, Cow::Borrowed(BASE64_BEGIN), Err(error)),
+            };
+            if let Some(limit) = max_line_length
+                && text.len() > limit
+            {
+                let length = text.len();
+                return (line, offset, text, Err(SyntaxError::LineTooLong { length, limit }));
+            }
+            if let Some(name) = section_header(&text) {
+                section = Some(name.into());
+            }
+            let result = parse_line(parsers, &text).map(|rule| {
+                rule.map(|rule| Rule {
+                    origin: Some(Origin::line(line)),
+                    section: section.clone(),
+                    ..rule
+                })
+            });
+            (line, offset, text, result)
+        })
+}
+
+impl fmt::Display for Rule {
+    /// Formats the rule in its canonical form, which may differ from [`Rule::text`].
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.negative {
+            f.write_str("@@")?;
+        }
+        match &self.pattern {
+            Pattern::Regex(s) => write!(f, "/{s}/"),
+            Pattern::Domain(s) => write!(f, "||{s}"),
+            Pattern::Host(s) => f.write_str(s),
+            Pattern::Url(s) => write!(f, "|{s}"),
+            Pattern::Cidr(s) if s.contains(':') => write!(f, "IP-CIDR6,{s}"),
+            Pattern::Cidr(s) => write!(f, "IP-CIDR,{s}"),
+        }?;
+        match &self.options {
+            Some(options) => write!(f, "${options}"),
+            None => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parse_round_trip() {
+        for line in [
+            "||example.com",
+            "@@|http://example.com/page",
+            "example.com/path",
+            "/^https?:\\/\\/a/",
+            "@@/^https?:\\/\\/b/",
+        ] {
+            let rule = Rule::parse(line).unwrap().unwrap();
+            assert_eq!(rule.to_string(), line);
+        }
+        let rule = Rule::parse("@@.example.com").unwrap().unwrap();
+        assert_eq!(rule.to_string(), "@@||example.com");
+        assert_eq!(rule.text(), "@@.example.com");
+    }
+
+    #[test]
+    fn to_regex() {
+        let rules = [
+            "||example.com",
+            "example.com/a/",
+            "||example.org^ads^",
+            "|http://example.net",
+            "|https://example.net/a*b",
+            "||*.cdn.com/x",
+            "||[2001:db8::1]",
+            "||example.com:8443^",
+            "|https://example.net:8443",
+            "||example.com/search?q=",
+            "|http://example.org/?id=*&x",
+        ];
+        let urls = [
+            "http://example.com/",
+            "https://www.example.com:8443/path?q",
+            "http://notexample.com/",
+            "http://example.com/a",
+            "http://example.com/ab",
+            "http://example.com/a/b",
+            "http://example.org/ads?",
+            "http://example.org/ads/1",
+            "http://example.org/adsense",
+            "http://example.net.evil.com/",
+            "https://example.net/a/c/b/d",
+            "https://example.net/ac",
+            "http://a.b.cdn.com/x/",
+            "http://cdn.com/x",
+            "http://[2001:db8::1]/",
+            "http://[2001:db8::1:0]/",
+            "https://example.net:8443/a",
+            "https://example.net.cdn:8443/",
+            "http://example.com/search?q=abc",
+            "http://example.com/search/?q=",
+            "http://example.com/search?x=1&q=",
+            "http://example.org/?id=1&x=2",
+            "http://example.org/?x&id=1&x=2",
+        ];
+        for rule in rules {
+            let list = crate::GfwList::from(rule).unwrap();
+            let regex = Rule::parse(rule).unwrap().unwrap().to_regex().unwrap();
+            let regex = regex::Regex::new(&regex).unwrap();
+            for url in urls {
+                let expected = list.test(url).unwrap().is_some();
+                assert_eq!(regex.is_match(url), expected, "{rule} on {url}");
+            }
+        }
+        assert_eq!(Rule::parse("IP-CIDR,10.0.0.0/8").unwrap().unwrap().to_regex(), None);
+    }
+
+    #[test]
+    fn arena_texts() {
+        let mut rules: Vec<_> = ["||a.com", "@@.b.com", "/c/"]
+            .into_iter()
+            .map(|line| Rule::parse(line).unwrap().unwrap())
+            .collect();
+        let owned = rules.clone();
+        move_to_arena(&mut rules);
+        assert_eq!(rules, owned);
+        assert!(matches!(&rules[1].text, Text::Arena { start: 7, end: 15, .. }));
+        assert_eq!(rules[1].clone().with_negative(false).text(), ".b.com");
+
+        let parser = |line: &str| {
+            let (action, domain) = line.split_once(',')?;
+            Some(Ok(Rule::new(action != "proxy", Pattern::Domain(domain.into()))))
+        };
+        let parsers: [Arc<dyn RuleParser>; 1] = [Arc::new(parser)];
+        for line in ["direct,cdn.blocked-site.com", "直连,a.com"] {
+            let rule = parse_line(&parsers, line).unwrap().unwrap();
+            assert_eq!(rule.text(), line);
+            let rule = rule.with_negative(false);
+            assert_eq!(rule.text(), Rule::new(false, rule.pattern().clone()).text());
+        }
+    }
+
+    #[test]
+    fn parse_invalid() {
+        assert!(Rule::parse("/").is_err());
+        assert!(Rule::parse("/abc").is_err());
+        assert!(Rule::parse("@@").is_err());
+        assert!(Rule::parse("[AutoProxy 0.2.9]").unwrap().is_none());
+    }
+
+    #[test]
+    fn base64_chunks() {
+        // "||b.com\n@@||c.b.com"
+        let input = "||a.com\n!#include-base64-begin\nfHxiLmNvbQpAQHx8\nYy5iLmNvbQ==\n!#include-base64-end\n/d/\n!#\
+                     include-base64-begin\n*";
+        let lines: Vec<_> = parse_lines(input, &[], None)
+            .map(|(line, _, text, result)| (line, text.into_owned(), result.is_ok()))
+            .collect();
+        assert_eq!(lines.len(), 5);
+        assert_eq!(lines[1], (2, "||b.com".to_string(), true));
+        assert_eq!(lines[2], (2, "@@||c.b.com".to_string(), true));
+        assert_eq!(lines[3], (6, "/d/".to_string(), true));
+        assert_eq!(lines[4], (7, BASE64_BEGIN.to_string(), false));
+    }
+
+    #[test]
+    fn sections() {
+        assert_eq!(
+            section_header("!---------------------News---------------------"),
+            Some("News")
+        );
+        assert_eq!(
+            section_header("!################ Whitelist Start ################"),
+            Some("Whitelist Start")
+        );
+        assert_eq!(section_header("!-------------------------------------"), None);
+        assert_eq!(section_header("! Title: GFWList"), None);
+        let rules: Vec<_> = parse_lines("||a.com\n!---- News ----\n||b.com", &[], None)
+            .filter_map(|(_, _, _, result)| result.unwrap())
+            .map(|rule| rule.section().map(str::to_string))
+            .collect();
+        assert_eq!(rules, [None, Some("News".to_string())]);
+    }
+
+    #[test]
+    fn host_and_path() {
+        let rule = Rule::parse("||example.com/a/b").unwrap().unwrap();
+        assert_eq!(rule.pattern().host().as_deref(), Some("example.com"));
+        assert_eq!(rule.pattern().path().as_deref(), Some("/a/b"));
+        let rule = Rule::parse("|https://example.com").unwrap().unwrap();
+        assert_eq!(rule.pattern().host().as_deref(), Some("example.com"));
+        assert_eq!(rule.pattern().path(), None);
+        let rule = Rule::parse("||example.com?q=").unwrap().unwrap();
+        assert_eq!(rule.pattern().host().as_deref(), Some("example.com"));
+        assert_eq!(rule.pattern().path().as_deref(), Some("/?q="));
+    }
+}
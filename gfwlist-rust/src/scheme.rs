@@ -0,0 +1,86 @@
+//! Equivalent schemes for matching.
+//!
+//! Rules anchored to a scheme, such as `|http://example.com`, only match URLs with that scheme. A [`SchemeMap`] makes
+//! other schemes equivalent to it, so that for example WebSocket upgrades to `ws://example.com` are matched like
+//! `http://example.com`. The map applies to URLs and to the scheme of URL rules alike, and regular expressions see
+//! URLs with the mapped scheme.
+
+use std::borrow::Cow;
+
+/// A table mapping schemes to the scheme they are matched as.
+///
+/// # Examples
+///
+/// ```
+/// # use gfwlist::{GfwListBuilder, SchemeMap};
+/// let mut builder = GfwListBuilder::new();
+/// builder.scheme_map(SchemeMap::web_sockets());
+/// builder.add_rules("|https://blocked-site.com/chat\n/^http:\\/\\/example\\.com/").unwrap();
+/// let gfw = builder.build().unwrap();
+/// assert!(gfw.test("wss://blocked-site.com/chat").unwrap().is_some());
+/// assert!(gfw.test("ws://example.com/").unwrap().is_some());
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SchemeMap {
+    aliases: Vec<(String, String)>,
+}
+
+impl SchemeMap {
+    /// Creates an empty map, under which every scheme is only equivalent to itself.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates a map treating `ws` as `http` and `wss` as `https`.
+    pub fn web_sockets() -> Self {
+        let mut map = Self::new();
+        map.alias("ws", "http").alias("wss", "https");
+        map
+    }
+
+    /// Matches a scheme as another scheme, such as `ftp` as `http`. Schemes are case-insensitive.
+    pub fn alias(&mut self, scheme: &str, canonical: &str) -> &mut Self {
+        let scheme = scheme.to_ascii_lowercase();
+        self.aliases.retain(|(other, _)| *other != scheme);
+        self.aliases.push((scheme, canonical.to_ascii_lowercase()));
+        self
+    }
+
+    /// Returns the scheme a scheme is matched as. The input is expected in lowercase.
+    pub fn canonical<'a>(&'a self, scheme: &'a str) -> &'a str {
+        (self.aliases.iter())
+            .find(|(alias, _)| alias == scheme)
+            .map_or(scheme, |(_, canonical)| canonical)
+    }
+
+    /// Returns whether the map has no aliases.
+    pub fn is_empty(&self) -> bool {
+        self.aliases.is_empty()
+    }
+
+    /// Replaces the scheme of a URL string with its canonical scheme.
+    pub(crate) fn apply<'i>(&self, input: &'i str) -> Cow<'i, str> {
+        if let Some((scheme, rest)) = input.split_once(':')
+            && let canonical = self.canonical(&scheme.to_ascii_lowercase())
+            && !canonical.eq_ignore_ascii_case(scheme)
+        {
+            return Cow::Owned(format!("{canonical}:{rest}"));
+        }
+        Cow::Borrowed(input)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn apply() {
+        let mut map = SchemeMap::web_sockets();
+        map.alias("FTP", "http");
+        assert_eq!(map.apply("WSS://example.com/"), "https://example.com/");
+        assert_eq!(map.apply("ftp://example.com/"), "http://example.com/");
+        assert!(matches!(map.apply("http://example.com/"), Cow::Borrowed(_)));
+        assert!(matches!(map.apply("example.com"), Cow::Borrowed(_)));
+    }
+}
@@ -0,0 +1,433 @@
+//! Compiling one matcher from several sources of rules.
+//!
+//! Real-world setups combine an upstream GFW list with domain lists, Clash rule providers and personal rules. A
+//! [`Source`] describes one of them, optionally overriding the polarity of all its rules, and [`compile`] merges them
+//! into a single [`GfwList`]. Every compiled rule remembers its [`Origin`](crate::Origin), including the source name.
+
+use std::net::IpAddr;
+use std::sync::Arc;
+
+use crate::format::{Import, adguard_home, clash, dnsmasq, domain_list, hosts, quantumult_x, surge};
+use crate::rule::parse_lines;
+use crate::{BuildError, GfwList, GfwListBuilder, Origin, Pattern, Rule};
+
+/// The format of a [`Source`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SourceFormat {
+    /// A GFW list in AutoProxy syntax
+    AutoProxy,
+    /// One domain per line, matched together with its subdomains, with `#` comments
+    Domains,
+    /// A Clash rule provider, see [`format::clash`](crate::format::clash)
+    ClashProvider,
+    /// A blocklist in the format of the hosts file, see [`format::hosts`](crate::format::hosts)
+    Hosts,
+    /// A dnsmasq configuration fragment, see [`format::dnsmasq`](crate::format::dnsmasq)
+    Dnsmasq,
+    /// A domain list of domain-list-community with all its entries, see
+    /// [`format::domain_list`](crate::format::domain_list)
+    DomainList,
+    /// A Surge or Shadowrocket ruleset, see [`format::surge`](crate::format::surge)
+    Surge,
+    /// An AdGuard Home DNS blocklist, see [`format::adguard_home`](crate::format::adguard_home)
+    AdGuardHome,
+    /// A Quantumult X filter resource, see [`format::quantumult_x`](crate::format::quantumult_x)
+    QuantumultX,
+}
+
+/// The number of significant lines [`SourceFormat::detect`] looks at
+const DETECT_LINES: usize = 100;
+
+/// The types of Clash entries that only occur in Clash rules, unlike `IP-CIDR` entries
+const CLASH_TYPES: &[&str] = &[
+    "DOMAIN",
+    "DOMAIN-SUFFIX",
+    "DOMAIN-KEYWORD",
+    "DOMAIN-REGEX",
+    "GEOSITE",
+    "GEOIP",
+];
+
+/// The prefixes of domain-list-community entries
+const DOMAIN_LIST_PREFIXES: &[&str] = &["domain:", "full:", "keyword:", "regexp:", "include:"];
+
+impl SourceFormat {
+    /// Guesses the format of a list from the syntax of its first lines.
+    ///
+    /// Detects AutoProxy lists, including Adblock Plus syntax, Clash rule providers and rules, hosts files, dnsmasq
+    /// fragments and domain-list-community lists. Each line votes for the format its syntax is specific to, and the
+    /// format with most votes wins. Lists without specific syntax, such as plain domains, are taken as
+    /// [`AutoProxy`](Self::AutoProxy) lists.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use gfwlist::source::SourceFormat;
+    /// assert_eq!(SourceFormat::detect("[AutoProxy 0.2.9]\n||example.com"), SourceFormat::AutoProxy);
+    /// assert_eq!(SourceFormat::detect("payload:\n  - DOMAIN-SUFFIX,example.com"), SourceFormat::ClashProvider);
+    /// assert_eq!(SourceFormat::detect("# ads\n0.0.0.0 ads.example.com"), SourceFormat::Hosts);
+    /// assert_eq!(SourceFormat::detect("server=/example.cn/114.114.114.114"), SourceFormat::Dnsmasq);
+    /// assert_eq!(SourceFormat::detect("example.com\nfull:www.example.org"), SourceFormat::DomainList);
+    /// ```
+    pub fn detect(input: &str) -> SourceFormat {
+        const CANDIDATES: [SourceFormat; 5] = [
+            SourceFormat::AutoProxy,
+            SourceFormat::ClashProvider,
+            SourceFormat::Hosts,
+            SourceFormat::Dnsmasq,
+            SourceFormat::DomainList,
+        ];
+        let mut votes = [0; CANDIDATES.len()];
+        let lines = (input.lines().map(str::trim))
+            .filter(|line| !line.is_empty() && !line.starts_with(['#', '!']))
+            .take(DETECT_LINES);
+        for line in lines {
+            if line.starts_with("[AutoProxy") {
+                return SourceFormat::AutoProxy;
+            }
+            if let Some(format) = Self::detect_line(line) {
+                votes[CANDIDATES
+                    .iter()
+                    .position(|candidate| *candidate == format)
+                    .unwrap_or(0)] += 1;
+            }
+        }
+        // ties go to the earlier candidate
+        let (index, _) = (votes.iter().enumerate()).fold((0, 0), |best, (index, &count)| match count > best.1 {
+            true => (index, count),
+            false => best,
+        });
+        CANDIDATES[index]
+    }
+
+    /// Returns the format whose syntax a line is specific to, if any.
+    fn detect_line(line: &str) -> Option<SourceFormat> {
+        let entry = line.strip_prefix("- ").unwrap_or(line).trim_matches(['\'', '"']);
+        if matches!(line, "payload:" | "rules:")
+            || (entry.split_once(',')).is_some_and(|(kind, _)| CLASH_TYPES.contains(&kind))
+        {
+            return Some(SourceFormat::ClashProvider);
+        }
+        if let Some((directive, _)) = line.split_once("=/")
+            && directive.bytes().all(|byte| byte.is_ascii_lowercase() || byte == b'-')
+        {
+            return Some(SourceFormat::Dnsmasq);
+        }
+        let mut fields = line.split_whitespace();
+        if fields.next().is_some_and(|addr| addr.parse::<IpAddr>().is_ok()) && fields.next().is_some() {
+            return Some(SourceFormat::Hosts);
+        }
+        if DOMAIN_LIST_PREFIXES.iter().any(|prefix| line.starts_with(prefix)) {
+            return Some(SourceFormat::DomainList);
+        }
+        if line.starts_with(['|', '/', '.']) || line.starts_with("@@") || line.contains(['^', '$']) {
+            return Some(SourceFormat::AutoProxy);
+        }
+        None
+    }
+}
+
+/// A named source of rules.
+#[derive(Debug, Clone)]
+pub struct Source<'a> {
+    /// The name of the source, used in origins and errors
+    pub name: &'a str,
+    /// The format of the content
+    pub format: SourceFormat,
+    /// The content of the source
+    pub content: &'a str,
+    /// Overrides the polarity of all rules: `Some(true)` turns them into negative (whitelist) rules, `Some(false)`
+    /// into positive rules
+    pub negative: Option<bool>,
+}
+
+impl<'a> Source<'a> {
+    /// Creates a source that keeps the polarity of its rules.
+    pub fn new(name: &'a str, format: SourceFormat, content: &'a str) -> Self {
+        Source {
+            name,
+            format,
+            content,
+            negative: None,
+        }
+    }
+
+    /// Overrides the polarity of all rules of the source.
+    pub fn with_negative(mut self, negative: bool) -> Self {
+        self.negative = Some(negative);
+        self
+    }
+
+    /// Parses the source into rules, with origins referring to the source.
+    ///
+    /// Entries that cannot be represented are reported as dropped.
+    pub fn import(&self) -> Result<Import, BuildError> {
+        let mut import = match self.format {
+            SourceFormat::AutoProxy => {
+                let mut import = Import::default();
+                for (line, offset, _, result) in parse_lines(self.content, &[], None) {
+                    let rule = result.map_err(|error| self.error(BuildError::Syntax { line, offset, error }))?;
+                    import.rules.extend(rule);
+                }
+                import
+            }
+            SourceFormat::Domains => {
+                let mut import = Import::default();
+                for (line_index, line) in self.content.lines().enumerate() {
+                    let line = line.split('#').next().unwrap_or_default().trim();
+                    if !line.is_empty() {
+                        let rule = Rule::new(false, Pattern::Domain(line.to_string()));
+                        import.rules.push(rule.with_origin(Origin::line(line_index + 1)));
+                    }
+                }
+                import
+            }
+            SourceFormat::ClashProvider => clash::import(self.content),
+            SourceFormat::Hosts => hosts::import(self.content),
+            SourceFormat::Dnsmasq => dnsmasq::import(self.content),
+            SourceFormat::DomainList => domain_list::import(self.content, &[]),
+            SourceFormat::Surge => surge::import(self.content),
+            SourceFormat::AdGuardHome => adguard_home::import(self.content),
+            SourceFormat::QuantumultX => quantumult_x::import(self.content),
+        };
+        let name: Arc<str> = self.name.into();
+        let rules = std::mem::take(&mut import.rules);
+        for mut rule in rules {
+            if let Some(negative) = self.negative {
+                rule = rule.with_negative(negative);
+            }
+            let line = rule.origin().map_or(0, |origin| origin.line);
+            let origin = Origin {
+                source: Some(name.clone()),
+                line,
+            };
+            import.rules.push(rule.with_origin(origin));
+        }
+        Ok(import)
+    }
+
+    fn error(&self, error: BuildError) -> BuildError {
+        BuildError::Source(self.name.to_string(), Box::new(error))
+    }
+}
+
+impl GfwListBuilder {
+    /// Adds all rules of a source, returning the entries that were dropped.
+    pub fn add_source(&mut self, source: &Source) -> Result<Vec<(usize, String)>, BuildError> {
+        let import = source.import()?;
+        for rule in import.rules {
+            let line = rule.origin().map_or(0, |origin| origin.line);
+            self.add_parsed(rule)
+                .map_err(|error| source.error(BuildError::Syntax { line, offset: 0, error }))?;
+        }
+        Ok(import.dropped)
+    }
+}
+
+/// Compiles several sources into a single [`GfwList`].
+///
+/// Entries that cannot be represented are skipped; use [`Source::import`] or [`GfwListBuilder::add_source`] to
+/// inspect them.
+///
+/// # Examples
+///
+/// ```
+/// # use gfwlist::source::{Source, SourceFormat, compile};
+/// let gfw = compile(&[
+///     Source::new("gfwlist.txt", SourceFormat::AutoProxy, "||blocked-site.com"),
+///     Source::new("direct.txt", SourceFormat::Domains, "# direct\nexception.com\n").with_negative(true),
+/// ])
+/// .unwrap();
+/// assert_eq!(gfw.test("http://blocked-site.com").unwrap(), Some("||blocked-site.com"));
+/// assert_eq!(gfw.test("http://exception.com").unwrap(), None);
+/// ```
+pub fn compile(sources: &[Source]) -> Result<GfwList, BuildError> {
+    let mut builder = GfwListBuilder::new();
+    for source in sources {
+        builder.add_source(source)?;
+    }
+    builder.build()
+}
+
+/// A set of owned sources that can be enabled and disabled at runtime.
+///
+/// Each call to [`Profile::compile`] rebuilds a [`GfwList`] from the enabled sources, which makes it easy to bisect
+/// which source introduced a bad rule.
+///
+/// # Examples
+///
+/// ```
+/// # use gfwlist::source::{Profile, Source, SourceFormat};
+/// let mut profile = Profile::new();
+/// profile.add(&Source::new("upstream", SourceFormat::AutoProxy, "||blocked-site.com"));
+/// profile.add(&Source::new("user", SourceFormat::Domains, "user-site.com"));
+/// assert_eq!(profile.compile().unwrap().len(), 2);
+///
+/// assert!(profile.set_enabled("user", false));
+/// let gfw = profile.compile().unwrap();
+/// assert_eq!(gfw.test("http://user-site.com").unwrap(), None);
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct Profile {
+    sources: Vec<ProfileSource>,
+}
+
+#[derive(Debug, Clone)]
+struct ProfileSource {
+    name: String,
+    format: SourceFormat,
+    content: String,
+    negative: Option<bool>,
+    enabled: bool,
+}
+
+impl Profile {
+    /// Creates an empty profile.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a copy of a source, enabled. A source with the same name is replaced.
+    pub fn add(&mut self, source: &Source) -> &mut Self {
+        let source = ProfileSource {
+            name: source.name.to_string(),
+            format: source.format,
+            content: source.content.to_string(),
+            negative: source.negative,
+            enabled: true,
+        };
+        match self.sources.iter_mut().find(|s| s.name == source.name) {
+            Some(existing) => *existing = source,
+            None => self.sources.push(source),
+        }
+        self
+    }
+
+    /// Removes a source, returning whether it existed.
+    pub fn remove(&mut self, name: &str) -> bool {
+        let len = self.sources.len();
+        self.sources.retain(|s| s.name != name);
+        self.sources.len() != len
+    }
+
+    /// Enables or disables a source, returning whether it exists.
+    pub fn set_enabled(&mut self, name: &str, enabled: bool) -> bool {
+        match self.sources.iter_mut().find(|s| s.name == name) {
+            Some(source) => {
+                source.enabled = enabled;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Returns whether a source is enabled, or `None` if it does not exist.
+    pub fn is_enabled(&self, name: &str) -> Option<bool> {
+        self.sources.iter().find(|s| s.name == name).map(|s| s.enabled)
+    }
+
+    /// Iterates over the names of all sources in insertion order, together with whether they are enabled.
+    pub fn sources(&self) -> impl Iterator<Item = (&str, bool)> {
+        self.sources.iter().map(|s| (s.name.as_str(), s.enabled))
+    }
+
+    /// Compiles the enabled sources into a [`GfwList`].
+    pub fn compile(&self) -> Result<GfwList, BuildError> {
+        let sources: Vec<Source> = (self.sources.iter())
+            .filter(|s| s.enabled)
+            .map(|s| Source {
+                name: &s.name,
+                format: s.format,
+                content: &s.content,
+                negative: s.negative,
+            })
+            .collect();
+        compile(&sources)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::SyntaxError;
+
+    #[test]
+    fn provenance() {
+        let source = Source::new(
+            "clash.yaml",
+            SourceFormat::ClashProvider,
+            "payload:\n  - DOMAIN-KEYWORD,ads\n  - DOMAIN,example.com\n",
+        );
+        let import = source.clone().with_negative(true).import().unwrap();
+        assert!(import.dropped.is_empty());
+        assert_eq!(import.rules[0].text(), "@@/^[^:]+:\\/\\/[^\\/]*ads/");
+        let rule = &import.rules[1];
+        assert_eq!(rule.text(), "@@example.com");
+        assert_eq!(rule.origin().unwrap().to_string(), "clash.yaml:3");
+    }
+
+    #[test]
+    fn detect_formats() {
+        let detect = SourceFormat::detect;
+        assert_eq!(detect(""), SourceFormat::AutoProxy);
+        assert_eq!(detect("example.com\nexample.org"), SourceFormat::AutoProxy);
+        assert_eq!(
+            detect("! comment\n||example.com^\n@@||example.org"),
+            SourceFormat::AutoProxy
+        );
+        assert_eq!(
+            detect("DOMAIN-SUFFIX,example.com\nIP-CIDR,10.0.0.0/8"),
+            SourceFormat::ClashProvider
+        );
+        assert_eq!(
+            detect("rules:\n  - 'DOMAIN,example.com,Proxy'"),
+            SourceFormat::ClashProvider
+        );
+        assert_eq!(
+            detect("127.0.0.1 localhost\n::1 localhost\n0.0.0.0 ads.com"),
+            SourceFormat::Hosts
+        );
+        assert_eq!(
+            detect("# cn\nipset=/example.cn/chnroute\ncache-size=1000"),
+            SourceFormat::Dnsmasq
+        );
+        assert_eq!(
+            detect("# v2ray\nkeyword:ads @ads\nexample.com"),
+            SourceFormat::DomainList
+        );
+        // a stray line in another syntax is outvoted
+        assert_eq!(detect("||a.com\n||b.com\ndomain:c.com"), SourceFormat::AutoProxy);
+    }
+
+    #[test]
+    fn source_errors() {
+        let error = compile(&[Source::new("user.txt", SourceFormat::AutoProxy, "||ok.com\n/bad")]).unwrap_err();
+        let BuildError::Source(name, error) = error else {
+            panic!("unexpected error: {error}");
+        };
+        assert_eq!(name, "user.txt");
+        assert!(matches!(
+            *error,
+            BuildError::Syntax {
+                line: 2,
+                error: SyntaxError::Rule,
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn profile_sources() {
+        let mut profile = Profile::new();
+        profile.add(&Source::new("a", SourceFormat::Domains, "a.com"));
+        profile.add(&Source::new("b", SourceFormat::Domains, "b.com"));
+        profile.add(&Source::new("a", SourceFormat::Domains, "a.com\nc.com"));
+        assert!(profile.set_enabled("b", false));
+        assert!(!profile.set_enabled("missing", false));
+        assert_eq!(profile.sources().collect::<Vec<_>>(), [("a", true), ("b", false)]);
+        assert_eq!(profile.compile().unwrap().len(), 2);
+        assert!(profile.remove("a"));
+        assert_eq!(profile.is_enabled("a"), None);
+        assert!(profile.compile().unwrap().is_empty());
+    }
+}
@@ -0,0 +1,471 @@
+//! Hit counters and per-section statistics.
+//!
+//! Upstream GFW lists are organized in sections started by comments such as `!---------- News ----------`. Each
+//! parsed rule remembers its section (see [`Rule::section`]), so that whole sections can be reported on and
+//! disabled. Hit counting is opt-in, since it adds an atomic increment and a clock read to every match.
+//!
+//! Counters live in memory. To keep them across restarts, or to add up the counters of several processes, take a
+//! [`HitCounts`] snapshot with [`GfwList::hit_snapshot`], save it, and add it back with
+//! [`GfwList::merge_hit_counts`] after loading the list again. Snapshots are keyed by [`RuleId`], so they also apply
+//! to newer versions of the list.
+//!
+//! # Layout
+//!
+//! [`HitCounts::to_bytes`] serializes a snapshot as the magic `GFWH`, a version byte (`1`), the number of entries as
+//! a little-endian `u64`, and the entries sorted by rule identifier, each being the identifier and the count as
+//! little-endian `u64`s.
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::path::Path;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use std::{fs, io};
+
+use crate::{BuildError, GfwList, Rule, RuleId};
+
+const MAGIC: &[u8; 4] = b"GFWH";
+const VERSION: u8 = 1;
+const HEADER_LEN: usize = 13;
+const ENTRY_LEN: usize = 16;
+
+/// Hit counts keyed by rule identifier, which can be saved, loaded and added up.
+///
+/// # Examples
+///
+/// ```
+/// # use gfwlist::GfwList;
+/// # use gfwlist::stats::HitCounts;
+/// let mut gfw_list = GfwList::from("||blocked-site.com").unwrap();
+/// gfw_list.set_hit_counting(true);
+/// gfw_list.test("http://blocked-site.com").unwrap();
+/// let saved = gfw_list.hit_snapshot().to_bytes();
+///
+/// // after a restart
+/// let mut gfw_list = GfwList::from("||blocked-site.com\n||new-site.com").unwrap();
+/// gfw_list.set_hit_counting(true);
+/// gfw_list.merge_hit_counts(&HitCounts::from_bytes(&saved).unwrap());
+/// gfw_list.test("http://blocked-site.com").unwrap();
+/// let hits: Vec<_> = gfw_list.hit_counts().map(|(rule, hits)| (rule.text(), hits)).collect();
+/// assert_eq!(hits, [("||blocked-site.com", 2), ("||new-site.com", 0)]);
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct HitCounts {
+    counts: BTreeMap<RuleId, u64>,
+}
+
+impl HitCounts {
+    /// Creates an empty snapshot.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the count of a rule, which is zero for unknown rules.
+    pub fn get(&self, id: RuleId) -> u64 {
+        self.counts.get(&id).copied().unwrap_or(0)
+    }
+
+    /// Adds hits to the count of a rule.
+    pub fn add(&mut self, id: RuleId, hits: u64) {
+        let count = self.counts.entry(id).or_default();
+        *count = count.saturating_add(hits);
+    }
+
+    /// Adds the counts of another snapshot, such as one of another process.
+    pub fn merge(&mut self, other: &HitCounts) {
+        for (id, hits) in other.iter() {
+            self.add(id, hits);
+        }
+    }
+
+    /// Iterates over the rules with their counts, ordered by rule identifier.
+    pub fn iter(&self) -> impl Iterator<Item = (RuleId, u64)> {
+        self.counts.iter().map(|(id, hits)| (*id, *hits))
+    }
+
+    /// Returns the number of rules in the snapshot.
+    pub fn len(&self) -> usize {
+        self.counts.len()
+    }
+
+    /// Checks if the snapshot has no rules.
+    pub fn is_empty(&self) -> bool {
+        self.counts.is_empty()
+    }
+
+    /// Serializes the snapshot in the layout documented in the [module docs](self).
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut output = Vec::with_capacity(HEADER_LEN + self.counts.len() * ENTRY_LEN);
+        output.extend(MAGIC);
+        output.push(VERSION);
+        output.extend((self.counts.len() as u64).to_le_bytes());
+        for (id, hits) in self.iter() {
+            output.extend(id.0.to_le_bytes());
+            output.extend(hits.to_le_bytes());
+        }
+        output
+    }
+
+    /// Deserializes a snapshot produced by [`HitCounts::to_bytes`], returning `None` if the input is malformed.
+    pub fn from_bytes(input: &[u8]) -> Option<Self> {
+        let header = input.get(..HEADER_LEN)?;
+        if &header[..4] != MAGIC || header[4] != VERSION {
+            return None;
+        }
+        let len = u64::from_le_bytes(header[5..].try_into().ok()?);
+        let entries = &input[HEADER_LEN..];
+        if entries.len() as u64 != len.checked_mul(ENTRY_LEN as u64)? {
+            return None;
+        }
+        let mut counts = HitCounts::new();
+        for entry in entries.chunks_exact(ENTRY_LEN) {
+            let id = u64::from_le_bytes(entry[..8].try_into().ok()?);
+            counts.add(RuleId(id), u64::from_le_bytes(entry[8..].try_into().ok()?));
+        }
+        Some(counts)
+    }
+
+    /// Writes the snapshot to a file, replacing it atomically so that a crash never leaves a truncated file behind.
+    pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let path = path.as_ref();
+        let mut temp = path.as_os_str().to_owned();
+        temp.push(".tmp");
+        fs::write(&temp, self.to_bytes())?;
+        fs::rename(&temp, path)
+    }
+
+    /// Reads a snapshot written by [`HitCounts::save`].
+    ///
+    /// Malformed files are reported as [`io::ErrorKind::InvalidData`].
+    pub fn load(path: impl AsRef<Path>) -> io::Result<Self> {
+        let input = fs::read(path)?;
+        HitCounts::from_bytes(&input).ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "invalid hit counts"))
+    }
+}
+
+/// The hit counter of a rule.
+#[derive(Debug, Default)]
+pub(crate) struct HitCounter {
+    count: AtomicU64,
+    /// The time of the last hit in milliseconds since the Unix epoch, or zero if there was none
+    last_hit: AtomicU64,
+}
+
+impl HitCounter {
+    fn count(&self) -> u64 {
+        self.count.load(Ordering::Relaxed)
+    }
+
+    fn last_hit(&self) -> Option<SystemTime> {
+        match self.last_hit.load(Ordering::Relaxed) {
+            0 => None,
+            millis => Some(UNIX_EPOCH + Duration::from_millis(millis)),
+        }
+    }
+
+    fn reset(&self) {
+        self.count.store(0, Ordering::Relaxed);
+        self.last_hit.store(0, Ordering::Relaxed);
+    }
+}
+
+impl Clone for HitCounter {
+    fn clone(&self) -> Self {
+        HitCounter {
+            count: AtomicU64::new(self.count()),
+            last_hit: AtomicU64::new(self.last_hit.load(Ordering::Relaxed)),
+        }
+    }
+}
+
+/// Statistics of one section of a [`GfwList`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SectionStats {
+    /// The name of the section, or `None` for rules before the first section header
+    pub name: Option<String>,
+    /// The number of rules in the section
+    pub rules: usize,
+    /// The number of matches by rules of the section, or zero if hit counting is disabled
+    pub hits: u64,
+    /// Whether the rules of the section are matched
+    pub enabled: bool,
+}
+
+impl GfwList {
+    /// Enables or disables counting how often each rule decides a match.
+    ///
+    /// Enabling hit counting starts all counters from zero.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use gfwlist::GfwList;
+    /// let mut gfw_list = GfwList::from("||blocked-site.com\n@@||exception.com").unwrap();
+    /// gfw_list.set_hit_counting(true);
+    /// gfw_list.test("http://blocked-site.com").unwrap();
+    /// gfw_list.test("http://www.blocked-site.com").unwrap();
+    /// let hits: Vec<_> = gfw_list.hit_counts().map(|(rule, hits)| (rule.text(), hits)).collect();
+    /// assert_eq!(hits, [("||blocked-site.com", 2), ("@@||exception.com", 0)]);
+    /// ```
+    pub fn set_hit_counting(&mut self, enabled: bool) {
+        if enabled != self.hits.is_some() {
+            self.hits = enabled.then(|| self.rules.iter().map(|_| HitCounter::default()).collect());
+        }
+    }
+
+    /// Returns whether hit counting is enabled.
+    pub fn is_hit_counting(&self) -> bool {
+        self.hits.is_some()
+    }
+
+    /// Iterates over the rules in source order together with their hit counts.
+    ///
+    /// Yields nothing if hit counting is disabled. Use [`Rule::id`] to correlate the counts across list versions.
+    pub fn hit_counts(&self) -> impl Iterator<Item = (&Rule, u64)> {
+        (self.hits.iter())
+            .flat_map(|hits| self.rules.iter().zip(hits.iter()))
+            .map(|(rule, counter)| (rule, counter.count()))
+    }
+
+    /// Iterates over the rules in source order together with the time of their last hit, if any.
+    ///
+    /// Yields nothing if hit counting is disabled. Times are kept in milliseconds and are not part of [`HitCounts`]
+    /// snapshots.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use std::time::{Duration, SystemTime};
+    /// # use gfwlist::GfwList;
+    /// let mut gfw_list = GfwList::from("||blocked-site.com\n||other-site.com").unwrap();
+    /// gfw_list.set_hit_counting(true);
+    /// let before = SystemTime::now() - Duration::from_millis(1);
+    /// gfw_list.test("http://blocked-site.com").unwrap();
+    /// let last_hits: Vec<_> = gfw_list.last_hits().map(|(_, time)| time).collect();
+    /// assert!(last_hits[0].unwrap().duration_since(before).is_ok() && last_hits[1].is_none());
+    /// ```
+    pub fn last_hits(&self) -> impl Iterator<Item = (&Rule, Option<SystemTime>)> {
+        (self.hits.iter())
+            .flat_map(|hits| self.rules.iter().zip(hits.iter()))
+            .map(|(rule, counter)| (rule, counter.last_hit()))
+    }
+
+    /// Resets all hit counters to zero and forgets the times of the last hits.
+    pub fn reset_hit_counts(&self) {
+        for counter in self.hits.iter().flat_map(|hits| hits.iter()) {
+            counter.reset();
+        }
+    }
+
+    /// Takes a snapshot of the hit counters, keyed by [`RuleId`]. The counts of duplicate rules are added up.
+    ///
+    /// The snapshot is empty if hit counting is disabled.
+    pub fn hit_snapshot(&self) -> HitCounts {
+        let mut counts = HitCounts::new();
+        for (rule, hits) in self.hit_counts() {
+            counts.add(rule.id(), hits);
+        }
+        counts
+    }
+
+    /// Adds the counts of a snapshot to the hit counters of the rules with the same [`RuleId`].
+    ///
+    /// Counts of rules that are no longer in the list are ignored, and so is the whole snapshot if hit counting is
+    /// disabled. For duplicate rules, the counts are added to the first one.
+    pub fn merge_hit_counts(&self, counts: &HitCounts) {
+        let Some(hits) = &self.hits else {
+            return;
+        };
+        let mut merged = HashSet::new();
+        for (rule, counter) in self.rules.iter().zip(hits.iter()) {
+            let id = rule.id();
+            if merged.insert(id) {
+                counter.count.fetch_add(counts.get(id), Ordering::Relaxed);
+            }
+        }
+    }
+
+    /// Copies the hit counters of a previous version of the list, matching rules by [`RuleId`](crate::RuleId).
+    pub(crate) fn carry_hit_counts(&self, previous: &GfwList) {
+        let mut counters: HashMap<_, _> = (previous.hits.iter())
+            .flat_map(|hits| previous.rules.iter().zip(hits.iter()))
+            .map(|(rule, counter)| (rule.id(), counter.clone()))
+            .collect();
+        if let Some(hits) = &self.hits {
+            for (rule, counter) in self.rules.iter().zip(hits.iter()) {
+                let previous = counters.remove(&rule.id()).unwrap_or_default();
+                counter.count.store(previous.count(), Ordering::Relaxed);
+                counter
+                    .last_hit
+                    .store(previous.last_hit.into_inner(), Ordering::Relaxed);
+            }
+        }
+    }
+
+    /// Counts a hit for the rule at an index of [`iter_rules`](Self::iter_rules).
+    pub(crate) fn record_hit(&self, index: usize) {
+        if let Some(hits) = &self.hits {
+            hits[index].count.fetch_add(1, Ordering::Relaxed);
+            let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default();
+            hits[index].last_hit.store(now.as_millis() as u64, Ordering::Relaxed);
+        }
+    }
+
+    /// Returns an estimate of the heap memory used by the list, in bytes.
+    ///
+    /// The estimate covers the rules and the automata. Compiled regular expressions are only counted by the length of
+    /// their source.
+    pub fn memory_usage(&self) -> usize {
+        rules_memory_usage(&self.rules)
+            + self.automata.memory_usage()
+            + self.regex_patterns.len() * size_of::<usize>()
+            + self
+                .hits
+                .as_ref()
+                .map_or(0, |hits| hits.len() * size_of::<HitCounter>())
+    }
+
+    /// Returns the statistics of each section, in the order the sections appear in the list.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use gfwlist::GfwList;
+    /// let gfw_list = GfwList::from("||a.com\n!---------- News ----------\n||b.com\n||c.com").unwrap();
+    /// let sections: Vec<_> = gfw_list.section_stats().into_iter().map(|s| (s.name, s.rules)).collect();
+    /// assert_eq!(sections, [(None, 1), (Some("News".to_string()), 2)]);
+    /// ```
+    pub fn section_stats(&self) -> Vec<SectionStats> {
+        let mut sections: Vec<SectionStats> = vec![];
+        for (index, rule) in self.rules.iter().enumerate() {
+            let name = rule.section();
+            let position = match sections.iter().position(|stats| stats.name.as_deref() == name) {
+                Some(position) => position,
+                None => {
+                    sections.push(SectionStats {
+                        name: name.map(str::to_string),
+                        rules: 0,
+                        hits: 0,
+                        enabled: name.is_none_or(|name| !self.disabled_sections.contains(name)),
+                    });
+                    sections.len() - 1
+                }
+            };
+            let stats = &mut sections[position];
+            stats.rules += 1;
+            if let Some(hits) = &self.hits {
+                stats.hits += hits[index].count();
+            }
+        }
+        sections
+    }
+
+    /// Enables or disables all rules of a section, recompiling the automata if needed.
+    ///
+    /// Returns whether the list has a section with this name. Hit counters and staged changes are kept. Disabled
+    /// sections stay disabled when staged changes are committed.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use gfwlist::GfwList;
+    /// let mut gfw_list = GfwList::from("!---------- News ----------\n||news.com").unwrap();
+    /// assert!(gfw_list.set_section_enabled("News", false).unwrap());
+    /// assert_eq!(gfw_list.test("http://news.com").unwrap(), None);
+    /// assert!(!gfw_list.set_section_enabled("Missing", false).unwrap());
+    /// ```
+    pub fn set_section_enabled(&mut self, name: &str, enabled: bool) -> Result<bool, BuildError> {
+        if !self.rules.iter().any(|rule| rule.section() == Some(name)) {
+            return Ok(false);
+        }
+        let changed = if enabled {
+            self.disabled_sections.remove(name)
+        } else {
+            self.disabled_sections.insert(name.to_string())
+        };
+        if !changed {
+            return Ok(true);
+        }
+        match self.rebuild(&self.rules) {
+            Ok(mut list) => {
+                list.hits = self.hits.take();
+                list.staged = self.staged.take();
+                *self = list;
+                Ok(true)
+            }
+            Err(err) => {
+                if enabled {
+                    self.disabled_sections.insert(name.to_string());
+                } else {
+                    self.disabled_sections.remove(name);
+                }
+                Err(err)
+            }
+        }
+    }
+}
+
+/// Returns an estimate of the heap memory used by rules, in bytes.
+pub(crate) fn rules_memory_usage(rules: &[Rule]) -> usize {
+    (rules.iter())
+        .map(|rule| size_of::<Rule>() + 2 * rule.text().len() + rule.section().map_or(0, str::len))
+        .sum()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn section_hits() {
+        let mut gfw = GfwList::from("||a.com\n!---- News ----\n||news.com\n@@||ok.news.com\n/video/").unwrap();
+        gfw.set_hit_counting(true);
+        assert!(gfw.test("http://news.com").unwrap().is_some());
+        assert!(gfw.test("http://ok.news.com").unwrap().is_none());
+        assert!(gfw.test("http://a.com/video").unwrap().is_some());
+        gfw.set_section_enabled("News", false).unwrap();
+        assert!(gfw.test("http://news.com").unwrap().is_none());
+        assert!(gfw.test("http://a.com/video").unwrap().is_some());
+
+        let sections = gfw.section_stats();
+        assert_eq!(sections.len(), 2);
+        assert_eq!((sections[0].rules, sections[0].hits, sections[0].enabled), (1, 1, true));
+        assert_eq!(
+            (sections[1].rules, sections[1].hits, sections[1].enabled),
+            (3, 3, false)
+        );
+
+        gfw.set_section_enabled("News", true).unwrap();
+        assert!(gfw.test("http://news.com").unwrap().is_some());
+        gfw.remove_rule("||a.com");
+        gfw.commit().unwrap();
+        let hits: Vec<_> = gfw.hit_counts().map(|(rule, hits)| (rule.text(), hits)).collect();
+        assert_eq!(hits, [("||news.com", 2), ("@@||ok.news.com", 1), ("/video/", 1)]);
+        gfw.reset_hit_counts();
+        assert!(gfw.hit_counts().all(|(_, hits)| hits == 0));
+    }
+
+    #[test]
+    fn persisted_hits() {
+        let mut gfw = GfwList::from("||a.com\n||b.com\n||a.com").unwrap();
+        gfw.set_hit_counting(true);
+        gfw.test("http://a.com").unwrap();
+        gfw.test("http://b.com").unwrap();
+        let snapshot = gfw.hit_snapshot();
+        assert_eq!(snapshot.len(), 2);
+
+        let path = std::env::temp_dir().join(format!("gfwlist-hits-{}", std::process::id()));
+        snapshot.save(&path).unwrap();
+        let mut loaded = HitCounts::load(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(loaded, snapshot);
+        loaded.merge(&snapshot);
+        gfw.reset_hit_counts();
+        gfw.merge_hit_counts(&loaded);
+        let hits: Vec<_> = gfw.hit_counts().map(|(_, hits)| hits).collect();
+        assert_eq!(hits, [2, 2, 0]);
+
+        let bytes = snapshot.to_bytes();
+        assert!(HitCounts::from_bytes(&bytes[..bytes.len() - 1]).is_none());
+        assert!(HitCounts::from_bytes(b"GFWB").is_none());
+    }
+}
@@ -0,0 +1,38 @@
+//! Logging of skipped lines, in its own test binary since it installs the global logger.
+#![cfg(feature = "log")]
+
+use std::sync::Mutex;
+
+use gfwlist::{GfwListBuilder, RegexPolicy};
+
+struct Capture(Mutex<Vec<String>>);
+
+impl log::Log for Capture {
+    fn enabled(&self, _: &log::Metadata) -> bool {
+        true
+    }
+
+    fn log(&self, record: &log::Record) {
+        self.0.lock().unwrap().push(record.args().to_string());
+    }
+
+    fn flush(&self) {}
+}
+
+static CAPTURE: Capture = Capture(Mutex::new(vec![]));
+
+#[test]
+fn logged_lines() {
+    log::set_logger(&CAPTURE).unwrap();
+    log::set_max_level(log::LevelFilter::Warn);
+
+    let mut builder = GfwListBuilder::new();
+    builder.regex_policy(RegexPolicy::Skip);
+    let input = format!("||a.com\n/b/\n{}", "/\n".repeat(GfwListBuilder::MAX_LOGGED_LINES + 1));
+    builder.add_rules_lenient(&input);
+    let logged = CAPTURE.0.lock().unwrap();
+    assert_eq!(logged.len(), GfwListBuilder::MAX_LOGGED_LINES + 1);
+    assert_eq!(logged[0], "skipped line 2: regex rule skipped by the regex policy");
+    assert!(logged[1].starts_with("skipped line 3: "));
+    assert_eq!(logged.last().unwrap(), "skipped 2 more lines");
+}